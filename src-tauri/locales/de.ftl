@@ -0,0 +1,99 @@
+## Fehler, die Befehle zurückgeben
+
+error-not-found = Datei nicht gefunden: { $file }
+error-permission-denied = Zugriff verweigert: { $file }
+error-encrypted = PDF ist verschlüsselt: { $file }
+error-extraction-not-permitted = PDF erlaubt keine Textextraktion: { $file }
+error-no-extractable-text = Aus diesem PDF kann kein Text extrahiert werden. Mögliche Ursachen: nicht unterstützte Textkodierung (häufig bei Adobe-PDFs), gescannte Bilder ohne OCR oder Verschlüsselung. Datei: { $file }
+error-invalid-query = Ungültige Suchanfrage: { $message }
+error-zotero = Zotero-Fehler: { $message }
+error-internal = Interner Fehler beim Durchsuchen von { $file }: { $message }
+error-outside-scope = { $file } liegt außerhalb der Ordner, auf die die App zugreifen darf
+
+hint-not-found = Prüfen Sie, ob die Datei oder der Ordner noch existiert und nicht verschoben wurde.
+hint-permission-denied = Gewähren Sie der App Zugriff auf diesen Ort oder wählen Sie einen anderen Ordner.
+hint-encrypted = Das PDF ist passwortgeschützt. Entfernen Sie den Schutz oder geben Sie das Passwort ein und versuchen Sie es erneut.
+hint-extraction-not-permitted = Die Berechtigungen des PDFs verbieten das Kopieren seines Textes. Aktivieren Sie „Extraktionsberechtigungen ignorieren“, um es trotzdem zu durchsuchen, sofern Sie dazu berechtigt sind.
+hint-no-extractable-text = Das PDF ist möglicherweise ein Scan ohne Textebene. Aktivieren Sie OCR (erfordert Tesseract) und suchen Sie erneut.
+hint-invalid-query = Prüfen Sie die Syntax des regulären Ausdrucks oder schalten Sie den Regex-Modus aus.
+hint-internal = Dies ist ein Programmfehler. Bitte melden Sie ihn, wenn möglich mit der Datei.
+hint-zotero = Prüfen Sie die Einstellung für das Zotero-Datenverzeichnis. Ist die Datenbank gesperrt, hilft es oft, Zotero zu schließen.
+hint-outside-scope = Wählen Sie den Ordner über „Durchsuchen“ aus oder ziehen Sie die Datei ins Fenster und versuchen Sie es erneut.
+
+## Hinweise, warum eine Suchanfrage nichts gefunden hat
+
+note-removes-everything = Die Normalisierung entfernt jedes Zeichen dieser Suchanfrage, daher kann sie nichts finden
+note-whitespace-regex = Leerraum wird vor dem Abgleich aus dem Text entfernt, daher passt \s nie. Gleichen Sie mit dem Rohtext ab, um das Muster wie geschrieben zu verwenden
+note-rewritten-regex = Die Normalisierung hat diesen regulären Ausdruck umgeschrieben, was Muster wie [a-z] verändert. Gleichen Sie mit dem Rohtext ab, um das Muster wie geschrieben zu verwenden
+note-ignored-characters = Die Normalisierung ignoriert { $characters } in der Suchanfrage
+note-changed-characters = Die Normalisierung hat Zeichen der Suchanfrage verändert, etwa Akzente oder Ligaturen
+
+## Markdown-Export
+
+export-title = PDF-Suchergebnisse
+export-total-matches = Gefundene Treffer insgesamt: { $count }
+export-files-with-matches = Dateien mit Treffern: { $count }
+export-file = Datei
+export-filename = Dateiname
+export-modified = Geändert
+export-matches-in-file = Treffer in dieser Datei
+export-match = Treffer { $number } (Seite { $page })
+export-page = Seite
+export-pdf-page = PDF-Seite { $page }
+export-context = Kontext
+export-citekey = Zitierschlüssel
+export-open-in-zotero = In Zotero öffnen
+export-no-corpus = Außerhalb der benannten Korpora
+excerpt-bookmark = S. { $page } — „{ $match }“
+
+## Zusammenfassung zum Teilen
+
+share-summary = { $matches } Treffer in { $documents } Dokumenten
+share-document = { $matches } Treffer, S. { $pages }
+
+## Zusammenfassung je Suchanfrage
+
+summary-query = Suchanfrage
+summary-documents = Dokumente
+summary-hits = Treffer
+summary-top-documents = Dokumente mit den meisten Treffern
+summary-years = Jahre
+summary-no-year = o. J.
+chart-hits-by-year = Gefundene Dokumente nach Jahr
+chart-hits-per-document = Treffer pro Dokument
+chart-query-cooccurrence = Dokumente mit Treffern beider Suchanfragen
+
+## Prüfbericht der Suche
+
+audit-title = Prüfbericht der Suche
+audit-search-date = Suchdatum
+audit-note = Hinweis
+audit-interrupted = Die Suche wurde unterbrochen; nur die bis dahin durchsuchten Dokumente sind enthalten.
+audit-sources = Quellen
+audit-queries = Suchanfragen
+audit-query = Suchanfrage
+audit-type = Typ
+audit-regex = Regex
+audit-weight = Gewicht
+audit-documents-matched = Dokumente mit Treffern
+audit-yes = ja
+audit-no = nein
+audit-parameters = Parameter
+audit-document-flow = Dokumentenfluss
+audit-files-found = Gefundene PDFs
+audit-excluded-modified-date = Ausgeschlossen nach Änderungsdatum
+audit-excluded-date-added = Ausgeschlossen nach Hinzufügedatum
+audit-excluded-screening = Ausgeschlossen durch Screening
+audit-excluded-subset = Außerhalb der Korpus-Teilmenge
+audit-excluded-native-index = Ausgeschlossen durch den Suchindex des Systems
+audit-excluded-reviewer = Ausgeschlossen durch Prüfer
+audit-collapsed-copies = Zusammengefasste identische Kopien
+audit-files-searched = Durchsuchte Dokumente
+audit-excluded-filter-queries = Ausgeschlossen durch Filteranfragen
+audit-excluded-min-matches = Ausgeschlossen durch Mindesttreffer pro Datei
+audit-files-with-errors = Nicht durchsuchbar
+audit-files-with-matches = Dokumente mit Treffern
+audit-removed-by-reviewer = Vom Prüfer aus den Ergebnissen entfernt
+audit-reviewer-exclusions = Vom Prüfer ausgeschlossene Dokumente
+audit-matches-removed = { $count } Treffer entfernt
+audit-not-searched = Nicht durchsuchte Dokumente
@@ -0,0 +1,99 @@
+## Errors returned by commands
+
+error-not-found = File not found: { $file }
+error-permission-denied = Permission denied: { $file }
+error-encrypted = PDF is encrypted: { $file }
+error-extraction-not-permitted = PDF does not permit text extraction: { $file }
+error-no-extractable-text = Unable to extract text from this PDF. This may be due to: unsupported text encoding (common in Adobe PDFs), scanned images without OCR, or encryption. File: { $file }
+error-invalid-query = Invalid query: { $message }
+error-zotero = Zotero error: { $message }
+error-internal = Internal error while searching { $file }: { $message }
+error-outside-scope = { $file } is outside the folders the app has been given access to
+
+hint-not-found = Check that the file or folder still exists and hasn't been moved.
+hint-permission-denied = Grant the app access to this location or choose a different folder.
+hint-encrypted = The PDF is password protected. Remove the protection or enter the password and try again.
+hint-extraction-not-permitted = The PDF's permissions forbid copying its text. Enable "Ignore extraction permissions" to search it anyway, if you're entitled to.
+hint-no-extractable-text = The PDF may be a scan without a text layer. Turn on OCR (requires Tesseract) and search again.
+hint-invalid-query = Check the regular expression syntax, or turn off regex mode.
+hint-internal = This is a bug. Please report it, including the file if you can share it.
+hint-zotero = Check the Zotero data directory setting. Closing Zotero can help if its database is locked.
+hint-outside-scope = Choose the folder with Browse or drop the file onto the window, then try again.
+
+## Notes explaining why a query found nothing
+
+note-removes-everything = Normalization removes every character of this query, so it can't match anything
+note-whitespace-regex = Whitespace is stripped from the text before matching, so \s never matches. Match against the raw text to use the pattern as written
+note-rewritten-regex = Normalization rewrote this regex, which changes patterns like [a-z]. Match against the raw text to use the pattern as written
+note-ignored-characters = Normalization ignores { $characters } in the query
+note-changed-characters = Normalization changed characters in the query, such as accents or ligatures
+
+## Markdown export
+
+export-title = PDF Search Results
+export-total-matches = Total matches found: { $count }
+export-files-with-matches = Files with matches: { $count }
+export-file = File
+export-filename = Filename
+export-modified = Modified
+export-matches-in-file = Matches in this file
+export-match = Match { $number } (Page { $page })
+export-page = Page
+export-pdf-page = PDF page { $page }
+export-context = Context
+export-citekey = Citekey
+export-open-in-zotero = Open in Zotero
+export-no-corpus = Outside the named corpora
+excerpt-bookmark = p. { $page } — “{ $match }”
+
+## Share summary
+
+share-summary = { $matches } matches in { $documents } documents
+share-document = { $matches } matches, p. { $pages }
+
+## Query summary
+
+summary-query = Query
+summary-documents = Documents
+summary-hits = Hits
+summary-top-documents = Top documents
+summary-years = Years
+summary-no-year = n.d.
+chart-hits-by-year = Matched documents by year
+chart-hits-per-document = Hits per document
+chart-query-cooccurrence = Documents matched by both queries
+
+## Search audit report
+
+audit-title = Search Audit Report
+audit-search-date = Search date
+audit-note = Note
+audit-interrupted = The search was interrupted; only the documents scanned before then are included.
+audit-sources = Sources
+audit-queries = Queries
+audit-query = Query
+audit-type = Type
+audit-regex = Regex
+audit-weight = Weight
+audit-documents-matched = Documents matched
+audit-yes = yes
+audit-no = no
+audit-parameters = Parameters
+audit-document-flow = Document Flow
+audit-files-found = PDFs found
+audit-excluded-modified-date = Excluded by modification date
+audit-excluded-date-added = Excluded by date added
+audit-excluded-screening = Excluded by screening
+audit-excluded-subset = Outside the corpus subset
+audit-excluded-native-index = Excluded by platform search index
+audit-excluded-reviewer = Excluded by reviewer
+audit-collapsed-copies = Identical copies collapsed
+audit-files-searched = Documents scanned
+audit-excluded-filter-queries = Excluded by filter queries
+audit-excluded-min-matches = Excluded by minimum matches per file
+audit-files-with-errors = Could not be searched
+audit-files-with-matches = Documents with matches
+audit-removed-by-reviewer = Removed from the results by reviewer
+audit-reviewer-exclusions = Documents Excluded by Reviewer
+audit-matches-removed = { $count } matches removed
+audit-not-searched = Documents Not Searched
@@ -0,0 +1,99 @@
+## Errores devueltos por los comandos
+
+error-not-found = Archivo no encontrado: { $file }
+error-permission-denied = Permiso denegado: { $file }
+error-encrypted = El PDF está cifrado: { $file }
+error-extraction-not-permitted = El PDF no permite extraer texto: { $file }
+error-no-extractable-text = No se puede extraer texto de este PDF. Puede deberse a: codificación de texto no compatible (habitual en PDF de Adobe), imágenes escaneadas sin OCR o cifrado. Archivo: { $file }
+error-invalid-query = Consulta no válida: { $message }
+error-zotero = Error de Zotero: { $message }
+error-internal = Error interno al buscar en { $file }: { $message }
+error-outside-scope = { $file } está fuera de las carpetas a las que la aplicación tiene acceso
+
+hint-not-found = Compruebe que el archivo o la carpeta todavía existe y no se ha movido.
+hint-permission-denied = Conceda a la aplicación acceso a esta ubicación o elija otra carpeta.
+hint-encrypted = El PDF está protegido con contraseña. Quite la protección o introduzca la contraseña e inténtelo de nuevo.
+hint-extraction-not-permitted = Los permisos del PDF prohíben copiar su texto. Active «Ignorar permisos de extracción» para buscar en él de todos modos, si tiene derecho a hacerlo.
+hint-no-extractable-text = Puede que el PDF sea un escaneo sin capa de texto. Active el OCR (requiere Tesseract) y vuelva a buscar.
+hint-invalid-query = Revise la sintaxis de la expresión regular o desactive el modo regex.
+hint-internal = Es un error del programa. Por favor, infórmenos, incluido el archivo si puede compartirlo.
+hint-zotero = Revise el ajuste del directorio de datos de Zotero. Si su base de datos está bloqueada, cerrar Zotero puede ayudar.
+hint-outside-scope = Elija la carpeta con Examinar o arrastre el archivo a la ventana y vuelva a intentarlo.
+
+## Notas que explican por qué una consulta no encontró nada
+
+note-removes-everything = La normalización elimina todos los caracteres de esta consulta, así que no puede coincidir con nada
+note-whitespace-regex = Los espacios se eliminan del texto antes de comparar, así que \s nunca coincide. Compare con el texto sin procesar para usar el patrón tal como está escrito
+note-rewritten-regex = La normalización reescribió esta expresión regular, lo que cambia patrones como [a-z]. Compare con el texto sin procesar para usar el patrón tal como está escrito
+note-ignored-characters = La normalización ignora { $characters } en la consulta
+note-changed-characters = La normalización cambió caracteres de la consulta, como acentos o ligaduras
+
+## Exportación a Markdown
+
+export-title = Resultados de búsqueda en PDF
+export-total-matches = Coincidencias encontradas en total: { $count }
+export-files-with-matches = Archivos con coincidencias: { $count }
+export-file = Archivo
+export-filename = Nombre de archivo
+export-modified = Modificado
+export-matches-in-file = Coincidencias en este archivo
+export-match = Coincidencia { $number } (página { $page })
+export-page = Página
+export-pdf-page = página { $page } del PDF
+export-context = Contexto
+export-citekey = Clave de cita
+export-open-in-zotero = Abrir en Zotero
+export-no-corpus = Fuera de los corpus con nombre
+excerpt-bookmark = p. { $page } — «{ $match }»
+
+## Resumen para compartir
+
+share-summary = { $matches } coincidencias en { $documents } documentos
+share-document = { $matches } coincidencias, p. { $pages }
+
+## Resumen por consulta
+
+summary-query = Consulta
+summary-documents = Documentos
+summary-hits = Coincidencias
+summary-top-documents = Documentos con más coincidencias
+summary-years = Años
+summary-no-year = s. f.
+chart-hits-by-year = Documentos encontrados por año
+chart-hits-per-document = Coincidencias por documento
+chart-query-cooccurrence = Documentos con coincidencias de ambas consultas
+
+## Informe de auditoría de la búsqueda
+
+audit-title = Informe de auditoría de la búsqueda
+audit-search-date = Fecha de búsqueda
+audit-note = Nota
+audit-interrupted = La búsqueda se interrumpió; solo se incluyen los documentos examinados hasta entonces.
+audit-sources = Fuentes
+audit-queries = Consultas
+audit-query = Consulta
+audit-type = Tipo
+audit-regex = Regex
+audit-weight = Peso
+audit-documents-matched = Documentos con coincidencias
+audit-yes = sí
+audit-no = no
+audit-parameters = Parámetros
+audit-document-flow = Flujo de documentos
+audit-files-found = PDF encontrados
+audit-excluded-modified-date = Excluidos por fecha de modificación
+audit-excluded-date-added = Excluidos por fecha de incorporación
+audit-excluded-screening = Excluidos por cribado
+audit-excluded-subset = Fuera del subconjunto del corpus
+audit-excluded-native-index = Excluidos por el índice de búsqueda del sistema
+audit-excluded-reviewer = Excluidos por el revisor
+audit-collapsed-copies = Copias idénticas agrupadas
+audit-files-searched = Documentos examinados
+audit-excluded-filter-queries = Excluidos por consultas de filtro
+audit-excluded-min-matches = Excluidos por mínimo de coincidencias por archivo
+audit-files-with-errors = No se pudieron examinar
+audit-files-with-matches = Documentos con coincidencias
+audit-removed-by-reviewer = Eliminados de los resultados por el revisor
+audit-reviewer-exclusions = Documentos excluidos por el revisor
+audit-matches-removed = { $count } coincidencias eliminadas
+audit-not-searched = Documentos no examinados
@@ -0,0 +1,164 @@
+use crate::index::Index;
+use crate::pdf_search::{time_file, FileTiming, QueryItem, SearchParams};
+use crate::walk::{walk_pdfs, WalkOptions};
+use anyhow::Result;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// Same as the search window uses
+const CONTEXT_WORDS: usize = 100;
+
+/// Throughput of one way of getting a corpus's text
+#[derive(Debug, Serialize)]
+pub struct BackendResult {
+    pub backend: String,
+    pub files: usize,
+    pub failed_files: usize,
+    pub pages: usize,
+    pub matches: usize,
+    pub elapsed_ms: u64,
+    // Summed over the worker threads, so they can exceed `elapsed_ms`
+    pub extraction_ms: u64,
+    pub matching_ms: u64,
+    pub pages_per_sec: f64,
+    pub matches_per_sec: f64,
+    // Files whose text came from the index's cache
+    pub cache_hits: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BenchmarkReport {
+    pub platform: String,
+    pub threads: usize,
+    pub files: usize,
+    pub backends: Vec<BackendResult>,
+    // The results as a plain-text table
+    pub report: String,
+}
+
+/// Search the PDFs under `directory` with each available extraction backend and
+/// compare their throughput. lopdf is always run; with an index, its text cache is
+/// run twice, first filling it for files it doesn't hold yet, then warm.
+pub fn run_benchmark(
+    directory: &Path,
+    queries: Vec<QueryItem>,
+    walk_options: &WalkOptions,
+    index: Option<&Index>,
+    max_files: Option<usize>,
+) -> Result<BenchmarkReport> {
+    let (mut pdf_files, _) = walk_pdfs(directory, walk_options)?;
+    if let Some(max_files) = max_files {
+        pdf_files.truncate(max_files);
+    }
+
+    let params = SearchParams {
+        queries,
+        directory: directory.to_string_lossy().to_string(),
+        context_words: CONTEXT_WORDS,
+        ..Default::default()
+    };
+
+    let mut backends = vec![run_backend("lopdf", &pdf_files, &params, None)];
+    if let Some(index) = index {
+        backends.push(run_backend("text cache (first pass)", &pdf_files, &params, Some(index)));
+        backends.push(run_backend("text cache (warm)", &pdf_files, &params, Some(index)));
+    }
+
+    let platform = format!("{} {}", std::env::consts::OS, std::env::consts::ARCH);
+    let threads = rayon::current_num_threads();
+    let report = format_report(&platform, threads, pdf_files.len(), &backends);
+    eprintln!("{}", report);
+
+    Ok(BenchmarkReport {
+        platform,
+        threads,
+        files: pdf_files.len(),
+        backends,
+        report,
+    })
+}
+
+fn run_backend(name: &str, pdf_files: &[PathBuf], params: &SearchParams, index: Option<&Index>) -> BackendResult {
+    let started = Instant::now();
+    let timings: Vec<Result<FileTiming>> = pdf_files
+        .par_iter()
+        .map(|pdf_path| time_file(pdf_path, params, index))
+        .collect();
+    let elapsed = started.elapsed();
+
+    let mut result = BackendResult {
+        backend: name.to_string(),
+        files: pdf_files.len(),
+        failed_files: 0,
+        pages: 0,
+        matches: 0,
+        elapsed_ms: elapsed.as_millis() as u64,
+        extraction_ms: 0,
+        matching_ms: 0,
+        pages_per_sec: 0.0,
+        matches_per_sec: 0.0,
+        cache_hits: 0,
+    };
+    let mut extraction = Duration::ZERO;
+    let mut matching = Duration::ZERO;
+    for timing in timings {
+        match timing {
+            Ok(timing) => {
+                result.pages += timing.pages;
+                result.matches += timing.matches;
+                extraction += timing.extraction;
+                matching += timing.matching;
+                if timing.from_cache {
+                    result.cache_hits += 1;
+                }
+            }
+            Err(_) => result.failed_files += 1,
+        }
+    }
+
+    result.extraction_ms = extraction.as_millis() as u64;
+    result.matching_ms = matching.as_millis() as u64;
+    let seconds = elapsed.as_secs_f64().max(f64::EPSILON);
+    result.pages_per_sec = result.pages as f64 / seconds;
+    result.matches_per_sec = result.matches as f64 / seconds;
+    result
+}
+
+fn format_report(platform: &str, threads: usize, files: usize, backends: &[BackendResult]) -> String {
+    let mut report = String::new();
+    let _ = writeln!(report, "Benchmark on {} with {} threads, {} files", platform, threads, files);
+    let _ = writeln!(
+        report,
+        "{:<24} {:>8} {:>10} {:>10} {:>12} {:>12} {:>10} {:>8}",
+        "Backend", "Pages", "Pages/s", "Matches/s", "Extract ms", "Match ms", "Cache hits", "Failed"
+    );
+    for backend in backends {
+        let _ = writeln!(
+            report,
+            "{:<24} {:>8} {:>10.1} {:>10.1} {:>12} {:>12} {:>10} {:>8}",
+            backend.backend,
+            backend.pages,
+            backend.pages_per_sec,
+            backend.matches_per_sec,
+            backend.extraction_ms,
+            backend.matching_ms,
+            backend.cache_hits,
+            backend.failed_files
+        );
+    }
+
+    // How much the warm cache saves over extracting every file
+    if let (Some(lopdf), Some(warm)) = (backends.first(), backends.get(2)) {
+        if lopdf.pages_per_sec > 0.0 {
+            let _ = writeln!(
+                report,
+                "Warm cache is {:.1}x the speed of lopdf extraction",
+                warm.pages_per_sec / lopdf.pages_per_sec
+            );
+        }
+    }
+    report
+}
@@ -0,0 +1,175 @@
+use crate::export::{bibtex_bibliography, export_to_markdown, group_by_file, page_citation, render_file_group, FileGroup, QuoteStyle};
+use crate::i18n;
+use crate::pdf_metadata::encode_pdf_string;
+use crate::pdf_search::SearchMatch;
+use anyhow::{Context, Result};
+use lopdf::{dictionary, Document, Object, ObjectId};
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashSet};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BundleOptions {
+    // Add a PDF per document containing only the pages with matches, with a
+    // bookmark for each match
+    #[serde(default)]
+    pub include_excerpts: bool,
+    // How matches are set off in their context; the settings' `export_quote_style`
+    #[serde(skip)]
+    pub quote_style: QuoteStyle,
+}
+
+/// Package an export into a single zip for sharing: the combined markdown,
+/// one markdown file per document, a BibTeX bibliography of the Zotero-matched
+/// documents and, optionally, excerpt PDFs of the matched pages
+pub fn write_export_bundle(output_path: &Path, matches: &[SearchMatch], options: &BundleOptions) -> Result<()> {
+    let file = File::create(output_path)
+        .context(format!("Failed to create bundle: {}", output_path.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let zip_options = SimpleFileOptions::default();
+
+    // Rendered once for both results.md and the per-document files
+    let groups = group_by_file(matches);
+    let rendered: Vec<String> = groups.iter().map(|group| render_file_group(group, &options.quote_style)).collect();
+
+    zip.start_file("results.md", zip_options)?;
+    zip.write_all(export_to_markdown(matches.len(), &rendered).as_bytes())?;
+
+    let mut used_names = HashSet::new();
+    for (group, markdown) in groups.iter().zip(&rendered) {
+        let name = unique_name(group, &mut used_names);
+
+        zip.start_file(format!("documents/{}.md", name), zip_options)?;
+        zip.write_all(markdown.as_bytes())?;
+
+        if options.include_excerpts {
+            // A document that can't be excerpted still has its matches in the markdown
+            match excerpt_pdf(group) {
+                Ok(pdf) => {
+                    zip.start_file(format!("excerpts/{}.pdf", name), zip_options)?;
+                    zip.write_all(&pdf)?;
+                }
+                Err(e) => eprintln!("Warning: Failed to excerpt {}: {}", group.item.file_path, e),
+            }
+        }
+    }
+
+    let bibliography = bibtex_bibliography(&groups);
+    if !bibliography.is_empty() {
+        zip.start_file("bibliography.bib", zip_options)?;
+        zip.write_all(bibliography.as_bytes())?;
+    }
+
+    zip.finish()
+        .context(format!("Failed to write bundle: {}", output_path.display()))?;
+    Ok(())
+}
+
+// File-system-safe name for a document's files, unique within the bundle
+fn unique_name(group: &FileGroup, used: &mut HashSet<String>) -> String {
+    let stem = Path::new(&group.item.file_name)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect::<String>();
+
+    let mut name = stem.clone();
+    let mut suffix = 2;
+    while !used.insert(name.clone()) {
+        name = format!("{}_{}", stem, suffix);
+        suffix += 1;
+    }
+    name
+}
+
+// Longest match text shown in a bookmark, in characters
+const BOOKMARK_MATCH_CHARS: usize = 60;
+
+// Copy of the source PDF reduced to the pages that have matches, its outline
+// replaced by one bookmark per match so any reader can step through the hits
+fn excerpt_pdf(group: &FileGroup) -> Result<Vec<u8>> {
+    let mut doc = Document::load(&group.item.file_path)
+        .context(format!("Failed to load PDF: {}", group.item.file_path))?;
+
+    let keep: BTreeSet<u32> = group.matches.iter().map(|m| m.page_number as u32).collect();
+    // Page objects keep their IDs when other pages are deleted, but not their numbers
+    let pages = doc.get_pages();
+    let remove: Vec<u32> = pages.keys().copied().filter(|page| !keep.contains(page)).collect();
+    doc.delete_pages(&remove);
+    add_match_outline(&mut doc, group, |m| pages.get(&(m.page_number as u32)).copied())?;
+    // The source's own outline, now unreferenced, goes with the deleted pages
+    doc.prune_objects();
+
+    let mut pdf = Vec::new();
+    doc.save_to(&mut pdf)?;
+    Ok(pdf)
+}
+
+// Replace the document's outline with a bookmark per match, "p. 14 — “carbon
+// tax”", pointing at the top of the match when its position is known
+fn add_match_outline(
+    doc: &mut Document,
+    group: &FileGroup,
+    page_id: impl Fn(&SearchMatch) -> Option<ObjectId>,
+) -> Result<()> {
+    let outlines_id = doc.new_object_id();
+    let mut items: Vec<ObjectId> = Vec::new();
+    for m in &group.matches {
+        let Some(page) = page_id(m) else {
+            continue;
+        };
+        let top = m
+            .bounding_boxes
+            .as_ref()
+            .and_then(|boxes| boxes.first())
+            .map_or(Object::Null, |rect| Object::Real(rect.y0.max(rect.y1) as f32));
+        let item = dictionary! {
+            "Title" => encode_pdf_string(&bookmark_title(m)),
+            "Parent" => outlines_id,
+            "Dest" => vec![page.into(), "XYZ".into(), Object::Null, top, Object::Null],
+        };
+        items.push(doc.add_object(item));
+    }
+
+    for (position, id) in items.iter().enumerate() {
+        let item = doc.get_object_mut(*id)?.as_dict_mut()?;
+        if position > 0 {
+            item.set("Prev", items[position - 1]);
+        }
+        if let Some(next) = items.get(position + 1) {
+            item.set("Next", *next);
+        }
+    }
+    let mut outlines = dictionary! {
+        "Type" => "Outlines",
+        "Count" => items.len() as i64,
+    };
+    if let (Some(first), Some(last)) = (items.first(), items.last()) {
+        outlines.set("First", *first);
+        outlines.set("Last", *last);
+    }
+    doc.objects.insert(outlines_id, Object::Dictionary(outlines));
+
+    let catalog = doc.catalog_mut()?;
+    catalog.set("Outlines", outlines_id);
+    if !items.is_empty() {
+        catalog.set("PageMode", "UseOutlines");
+    }
+    Ok(())
+}
+
+// The match as the PDF shows it, since normalization may have run its words together
+fn bookmark_title(m: &SearchMatch) -> String {
+    let text = m.source_span.as_ref().map_or(m.matched_text.as_str(), |span| span.text.as_str());
+    let mut text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() > BOOKMARK_MATCH_CHARS {
+        text = text.chars().take(BOOKMARK_MATCH_CHARS).collect::<String>() + "…";
+    }
+    i18n::message("excerpt-bookmark", &[("page", page_citation(m).into()), ("match", text.into())])
+}
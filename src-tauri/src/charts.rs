@@ -0,0 +1,178 @@
+use crate::export::{summarize_queries, QuerySummary};
+use crate::i18n;
+use crate::pdf_search::SearchMatch;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+// The statistics as Vega-Lite specs with their data inline, so the results view
+// and exported reports draw the same charts from the same aggregation, without
+// the frontend counting matches again. The specs leave width and theme to the
+// renderer.
+
+const SCHEMA: &str = "https://vega.github.io/schema/vega-lite/v5.json";
+
+// Documents shown in the hits-per-document chart
+const CHART_DOCUMENTS: usize = 20;
+
+/// Ready-to-render charts of a result set
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryCharts {
+    // Matched documents per publication year, by query
+    pub hits_by_year: Value,
+    // Hits in the documents with the most, stacked by query
+    pub hits_per_document: Value,
+    // Documents matched by each pair of queries
+    pub query_cooccurrence: Value,
+}
+
+pub fn query_charts(matches: &[SearchMatch]) -> QueryCharts {
+    let summaries = summarize_queries(matches);
+    let color = query_colors(matches, &summaries);
+    QueryCharts {
+        hits_by_year: hits_by_year(&summaries, &color),
+        hits_per_document: hits_per_document(matches, &color),
+        query_cooccurrence: query_cooccurrence(matches, &summaries),
+    }
+}
+
+/// The charts as pretty-printed JSON, for the `vega-lite` summary format
+pub fn export_query_charts(matches: &[SearchMatch]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&query_charts(matches))?)
+}
+
+fn text(id: &str) -> String {
+    i18n::message(id, &[])
+}
+
+// Color encoding that gives each query its highlight color, in query order
+fn query_colors(matches: &[SearchMatch], summaries: &[QuerySummary]) -> Value {
+    let domain: Vec<&str> = summaries.iter().map(|summary| summary.query.as_str()).collect();
+    let range: Vec<&str> = domain
+        .iter()
+        .map(|query| {
+            matches
+                .iter()
+                .find(|m| m.query.as_deref().unwrap_or_default() == *query && !m.color.is_empty())
+                .map_or("", |m| m.color.as_str())
+        })
+        .collect();
+    let mut color = json!({ "field": "query", "type": "nominal", "title": text("summary-query") });
+    // Matches from before colors were recorded fall back to Vega's palette
+    if range.iter().all(|color| !color.is_empty()) {
+        color["scale"] = json!({ "domain": domain, "range": range });
+    }
+    color
+}
+
+fn hits_by_year(summaries: &[QuerySummary], color: &Value) -> Value {
+    let no_year = text("summary-no-year");
+    let values: Vec<Value> = summaries
+        .iter()
+        .flat_map(|summary| {
+            summary.years.iter().map(|(year, documents)| {
+                json!({
+                    "query": summary.query,
+                    "year": year.as_deref().unwrap_or(&no_year),
+                    "documents": documents,
+                })
+            })
+        })
+        .collect();
+    // Years sort as text, which keeps four-digit years in order and puts "n.d." last
+    json!({
+        "$schema": SCHEMA,
+        "title": text("chart-hits-by-year"),
+        "data": { "values": values },
+        "mark": "bar",
+        "encoding": {
+            "x": { "field": "year", "type": "ordinal", "title": text("summary-years") },
+            "y": { "field": "documents", "type": "quantitative", "title": text("summary-documents") },
+            "color": color,
+            "xOffset": { "field": "query" },
+        },
+    })
+}
+
+fn hits_per_document(matches: &[SearchMatch], color: &Value) -> Value {
+    // document -> query -> hits, with documents named by citekey or file name
+    let mut hits: BTreeMap<String, BTreeMap<&str, usize>> = BTreeMap::new();
+    for m in matches {
+        let name = m
+            .item
+            .zotero_metadata
+            .as_ref()
+            .map_or(m.item.file_name.clone(), |metadata| metadata.citekey.clone());
+        *hits.entry(name).or_default().entry(m.query.as_deref().unwrap_or_default()).or_default() += 1;
+    }
+    let mut documents: Vec<(String, BTreeMap<&str, usize>)> = hits.into_iter().collect();
+    documents.sort_by_key(|(_, queries)| std::cmp::Reverse(queries.values().sum::<usize>()));
+    documents.truncate(CHART_DOCUMENTS);
+
+    let values: Vec<Value> = documents
+        .iter()
+        .flat_map(|(document, queries)| {
+            queries
+                .iter()
+                .map(move |(query, hits)| json!({ "document": document, "query": query, "hits": hits }))
+        })
+        .collect();
+    json!({
+        "$schema": SCHEMA,
+        "title": text("chart-hits-per-document"),
+        "data": { "values": values },
+        "mark": "bar",
+        "encoding": {
+            "y": {
+                "field": "document",
+                "type": "nominal",
+                "sort": "-x",
+                "title": text("summary-documents"),
+            },
+            "x": { "aggregate": "sum", "field": "hits", "type": "quantitative", "title": text("summary-hits") },
+            "color": color,
+        },
+    })
+}
+
+fn query_cooccurrence(matches: &[SearchMatch], summaries: &[QuerySummary]) -> Value {
+    let mut documents: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for m in matches {
+        documents
+            .entry(m.query.as_deref().unwrap_or_default())
+            .or_default()
+            .insert(&m.item.file_path);
+    }
+
+    let queries: Vec<&str> = summaries.iter().map(|summary| summary.query.as_str()).collect();
+    let mut values = Vec::new();
+    for a in &queries {
+        for b in &queries {
+            let shared = documents[a].intersection(&documents[b]).count();
+            values.push(json!({ "query_a": a, "query_b": b, "documents": shared }));
+        }
+    }
+    // The diagonal is each query's own document count
+    json!({
+        "$schema": SCHEMA,
+        "title": text("chart-query-cooccurrence"),
+        "data": { "values": values },
+        "encoding": {
+            "x": { "field": "query_a", "type": "nominal", "sort": queries, "title": text("summary-query") },
+            "y": { "field": "query_b", "type": "nominal", "sort": queries, "title": text("summary-query") },
+        },
+        "layer": [
+            {
+                "mark": "rect",
+                "encoding": {
+                    "color": { "field": "documents", "type": "quantitative", "title": text("summary-documents") },
+                },
+            },
+            {
+                "mark": "text",
+                "encoding": { "text": { "field": "documents", "type": "quantitative" } },
+            },
+        ],
+    })
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+// Hardware the slow subsystems can run on. OCR is the only one the app has:
+// Tesseract reads on the CPU, spreading each page over OpenMP threads, and builds
+// with OpenCL also hand image processing to the GPU, whichever vendor's driver
+// provides it (Apple's on Macs, NVIDIA's alongside CUDA). There's no Metal or CUDA
+// build of Tesseract, so a GPU device means its OpenCL build.
+
+/// Where a subsystem should run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ComputeDevice {
+    // The GPU when one is usable, else the CPU
+    #[default]
+    Auto,
+    Cpu,
+    Metal,
+    Cuda,
+}
+
+/// What this machine and the installed tools can use, for the settings to offer
+#[derive(Debug, Clone, Serialize)]
+pub struct Capabilities {
+    pub cpu_threads: usize,
+    // Macs all have a Metal GPU
+    pub metal: bool,
+    // Names of the NVIDIA GPUs the driver lists
+    pub cuda_devices: Vec<String>,
+    // Tesseract's version line, when the OCR command runs
+    pub ocr_engine: Option<String>,
+    // Whether that Tesseract was built with OpenCL, and so can use a GPU
+    pub ocr_opencl: bool,
+    // The devices OCR can be set to
+    pub ocr_devices: Vec<ComputeDevice>,
+}
+
+pub fn cpu_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |threads| threads.get())
+}
+
+// The GPUs `nvidia-smi -L` lists, one "GPU 0: NVIDIA ... (UUID: ...)" line each
+fn cuda_devices() -> Vec<String> {
+    let Ok(output) = Command::new("nvidia-smi").arg("-L").output() else {
+        return Vec::new();
+    };
+    if !output.status.success() {
+        return Vec::new();
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once(": ").map(|(_, name)| name))
+        .map(|name| name.split(" (UUID").next().unwrap_or(name).trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Detect the CPU, GPUs and OCR engine. `ocr_command` is Tesseract as the
+/// settings name it; `--version` lists "Found OpenCL" in builds that have it.
+pub fn capabilities(ocr_command: &str) -> Capabilities {
+    let metal = cfg!(target_os = "macos");
+    let cuda_devices = cuda_devices();
+    let version = Command::new(ocr_command)
+        .arg("--version")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        // Older versions print it to stderr
+        .map(|output| format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr)));
+    let ocr_engine = version
+        .as_deref()
+        .and_then(|version| version.lines().find(|line| !line.trim().is_empty()))
+        .map(|line| line.trim().to_string());
+    let ocr_opencl = version.as_deref().is_some_and(|version| version.contains("OpenCL"));
+
+    let mut ocr_devices = vec![ComputeDevice::Auto, ComputeDevice::Cpu];
+    if ocr_opencl && metal {
+        ocr_devices.push(ComputeDevice::Metal);
+    }
+    if ocr_opencl && !cuda_devices.is_empty() {
+        ocr_devices.push(ComputeDevice::Cuda);
+    }
+
+    Capabilities {
+        cpu_threads: cpu_threads(),
+        metal,
+        cuda_devices,
+        ocr_engine,
+        ocr_opencl,
+        ocr_devices,
+    }
+}
@@ -0,0 +1,186 @@
+use crate::export::QuoteStyle;
+use crate::index::{self, Index};
+use crate::pdf_search::SearchParams;
+use crate::saved_search;
+use crate::settings::AppSettings;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+// A team's shared setup in one file: the settings that describe how to search
+// and export, and the saved searches, which serve as query presets. Settings tied
+// to one machine (folders, tool paths, the OCR device, Zotero credentials) and a
+// reviewer's own exclusions stay out, so importing never points the app at paths
+// that don't exist here.
+
+/// Layout of the bundle file; raised when fields change meaning
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// The settings a team shares
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SharedSettings {
+    pub default_context_words: usize,
+    pub quick_search_limit: usize,
+    pub skip_dirs: Vec<String>,
+    pub language: String,
+    pub zotero_cache_ttl_secs: u64,
+    pub zotero_refresh_on_change_only: bool,
+    pub zotero_skip_my_publications: bool,
+    pub ocr_language: String,
+    pub ocr_batch_size: usize,
+    pub export_quote_style: QuoteStyle,
+}
+
+impl Default for SharedSettings {
+    fn default() -> Self {
+        SharedSettings::from(&AppSettings::default())
+    }
+}
+
+impl From<&AppSettings> for SharedSettings {
+    fn from(settings: &AppSettings) -> Self {
+        SharedSettings {
+            default_context_words: settings.default_context_words,
+            quick_search_limit: settings.quick_search_limit,
+            skip_dirs: settings.skip_dirs.clone(),
+            language: settings.language.clone(),
+            zotero_cache_ttl_secs: settings.zotero_cache_ttl_secs,
+            zotero_refresh_on_change_only: settings.zotero_refresh_on_change_only,
+            zotero_skip_my_publications: settings.zotero_skip_my_publications,
+            ocr_language: settings.ocr_language.clone(),
+            ocr_batch_size: settings.ocr_batch_size,
+            export_quote_style: settings.export_quote_style,
+        }
+    }
+}
+
+impl SharedSettings {
+    /// Overwrite the shared settings, keeping this machine's own
+    pub fn apply(&self, settings: &mut AppSettings) {
+        settings.default_context_words = self.default_context_words;
+        settings.quick_search_limit = self.quick_search_limit;
+        settings.skip_dirs = self.skip_dirs.clone();
+        settings.language = self.language.clone();
+        settings.zotero_cache_ttl_secs = self.zotero_cache_ttl_secs;
+        settings.zotero_refresh_on_change_only = self.zotero_refresh_on_change_only;
+        settings.zotero_skip_my_publications = self.zotero_skip_my_publications;
+        settings.ocr_language = self.ocr_language.clone();
+        settings.ocr_batch_size = self.ocr_batch_size;
+        settings.export_quote_style = self.export_quote_style;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundledSearch {
+    pub name: String,
+    pub params: SearchParams,
+}
+
+/// Everything a team member's app is set up from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigBundle {
+    pub bundle_version: u32,
+    pub exported_at: u64,
+    #[serde(default)]
+    pub settings: SharedSettings,
+    #[serde(default)]
+    pub saved_searches: Vec<BundledSearch>,
+}
+
+/// What `import_searches` did with each saved search in a bundle
+#[derive(Debug, Default, Serialize)]
+pub struct ConfigImportReport {
+    pub settings_applied: bool,
+    pub searches_added: Vec<String>,
+    // Already saved here with the same definition
+    pub searches_unchanged: Vec<String>,
+    // Searches that couldn't be imported, with why
+    pub searches_skipped: Vec<SkippedSearch>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkippedSearch {
+    pub name: String,
+    pub reason: String,
+}
+
+/// Write the shared settings and every saved search to `output_path`
+pub fn export_bundle(output_path: &Path, settings: &AppSettings, index: &Index) -> Result<ConfigBundle> {
+    let bundle = ConfigBundle {
+        bundle_version: BUNDLE_VERSION,
+        exported_at: index::unix_now(),
+        settings: SharedSettings::from(settings),
+        saved_searches: saved_search::list_saved_searches(index)?
+            .into_iter()
+            .map(|search| BundledSearch {
+                name: search.name,
+                params: search.params,
+            })
+            .collect(),
+    };
+    fs::write(output_path, serde_json::to_string_pretty(&bundle)?)
+        .context(format!("Failed to write {}", output_path.display()))?;
+    Ok(bundle)
+}
+
+pub fn read_bundle(input_path: &Path) -> Result<ConfigBundle> {
+    let contents = fs::read_to_string(input_path).context(format!("Failed to read {}", input_path.display()))?;
+    let bundle: ConfigBundle =
+        serde_json::from_str(&contents).context(format!("Failed to parse {}", input_path.display()))?;
+    if bundle.bundle_version > BUNDLE_VERSION {
+        eprintln!(
+            "Warning: {} was exported by a newer version of the app (bundle version {}); settings it added are ignored",
+            input_path.display(),
+            bundle.bundle_version
+        );
+    }
+    Ok(bundle)
+}
+
+/// Save the bundle's searches here. Searches rooted at a folder are moved to
+/// `directory` when given, since the team's corpus sits at a different path on
+/// each machine; searches of a Zotero library use this machine's `zotero_path`.
+/// `allowed` vets each search once its paths are set, as searches saved from the
+/// app are. A search already saved under the same name with the same definition
+/// isn't added again.
+pub fn import_searches(
+    index: &Index,
+    searches: &[BundledSearch],
+    directory: Option<&str>,
+    zotero_path: Option<&str>,
+    allowed: impl Fn(&SearchParams) -> Result<()>,
+    report: &mut ConfigImportReport,
+) -> Result<()> {
+    let existing = saved_search::list_saved_searches(index)?;
+    for search in searches {
+        let mut params = search.params.clone();
+        if let Some(directory) = directory.filter(|_| params.source.as_deref() != Some("zotero")) {
+            params.directory = directory.to_string();
+        }
+        if params.zotero_path.as_deref().is_some_and(|path| !path.is_empty()) {
+            params.zotero_path = zotero_path.map(str::to_string);
+        }
+        if let Err(e) = allowed(&params) {
+            report.searches_skipped.push(SkippedSearch {
+                name: search.name.clone(),
+                reason: format!("{:#}", e),
+            });
+            continue;
+        }
+
+        let definition = serde_json::to_value(&params)?;
+        let unchanged = existing
+            .iter()
+            .filter(|saved| saved.name == search.name)
+            .any(|saved| serde_json::to_value(&saved.params).is_ok_and(|saved| saved == definition));
+        if unchanged {
+            report.searches_unchanged.push(search.name.clone());
+            continue;
+        }
+        saved_search::save_search(index, &search.name, &params)?;
+        report.searches_added.push(search.name.clone());
+    }
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use crate::error::AppError;
+use crate::pdf_search::SearchMatch;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+// A search normally reads one folder, `SearchParams.directory`. Named corpora
+// group folders under a name in the settings ("Dissertation PDFs", "Teaching
+// materials"), and one search can read several of them at once
+// (`SearchParams.corpora`). Each match is labeled with the corpus its document
+// is in, so results and exports can be grouped or filtered by it.
+
+/// Folders searched together under one name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamedCorpus {
+    pub name: String,
+    // Filled in from the settings when a search names the corpus without them
+    #[serde(default)]
+    pub directories: Vec<String>,
+}
+
+/// Reject corpora the settings can't keep: unnamed ones, ones without a folder,
+/// and two of the same name
+pub fn check_corpora(corpora: &[NamedCorpus]) -> Result<()> {
+    let mut names = HashSet::new();
+    for corpus in corpora {
+        let name = corpus.name.trim();
+        if name.is_empty() {
+            return Err(AppError::invalid_input("A corpus needs a name").into());
+        }
+        if corpus.directories.iter().all(|directory| directory.trim().is_empty()) {
+            return Err(AppError::invalid_input(format!("Corpus {} has no folders", name)).into());
+        }
+        if !names.insert(name) {
+            return Err(AppError::invalid_input(format!("There's already a corpus named {}", name)).into());
+        }
+    }
+    Ok(())
+}
+
+/// Give the corpora a search names their folders from the `defined` ones. A
+/// corpus given with folders keeps them, as a rerun's saved parameters do.
+pub fn resolve(requested: &mut [NamedCorpus], defined: &[NamedCorpus]) -> Result<()> {
+    for corpus in requested.iter_mut().filter(|corpus| corpus.directories.is_empty()) {
+        let found = defined
+            .iter()
+            .find(|other| other.name.trim() == corpus.name.trim())
+            .ok_or_else(|| AppError::invalid_input(format!("No corpus named {}", corpus.name)))?;
+        corpus.directories = found.directories.clone();
+    }
+    Ok(())
+}
+
+/// The corpus a document is attributed to: the first of the search's corpora
+/// with a folder it's under, so one in several corpora counts for the first listed
+pub fn corpus_of<'a>(corpora: &'a [NamedCorpus], path: &Path) -> Option<&'a str> {
+    corpora
+        .iter()
+        .find(|corpus| corpus.directories.iter().any(|directory| path.starts_with(directory)))
+        .map(|corpus| corpus.name.as_str())
+}
+
+/// Keep the matches from the corpora `names`; all of them when it's empty
+pub fn filter_matches(matches: Vec<SearchMatch>, names: &[String]) -> Vec<SearchMatch> {
+    if names.is_empty() {
+        return matches;
+    }
+    matches
+        .into_iter()
+        .filter(|m| m.corpus.as_ref().is_some_and(|corpus| names.contains(corpus)))
+        .collect()
+}
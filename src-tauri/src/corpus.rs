@@ -0,0 +1,334 @@
+use crate::error::AppError;
+use crate::index::{self, CachedPage, CachedText, Index};
+use crate::pdf_search::{build_zotero_map, ZoteroMap};
+use crate::structure::Block;
+use crate::walk::{walk_pdfs, WalkOptions};
+use crate::zotero_cache;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+const FORMAT_VERSION: u32 = 1;
+const MANIFEST_FILE: &str = "manifest.json";
+const ZOTERO_FILE: &str = "zotero.json";
+
+// Paths in a bundle are relative to the corpus root with `/` separators, so
+// the bundle can be imported under a different root on another OS
+#[derive(Serialize, Deserialize)]
+struct CorpusManifest {
+    format_version: u32,
+    created: u64,
+    // Where the corpus lived on the exporting machine, for reference only
+    root: String,
+    documents: Vec<CorpusDocument>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CorpusDocument {
+    path: String,
+    size: u64,
+    sha256: String,
+    title: Option<String>,
+    // Entry holding the document's pages
+    text_file: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BundlePage {
+    page_number: usize,
+    label: Option<String>,
+    text: String,
+    #[serde(default)]
+    ocr: bool,
+    #[serde(default)]
+    blocks: Option<Vec<Block>>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CorpusSummary {
+    pub documents: usize,
+    // Files under the root whose cached text is out of date, left out of the export
+    pub skipped_stale: usize,
+    // Zotero items in the metadata snapshot, if one was included
+    pub zotero_items: Option<usize>,
+}
+
+/// Write the cached text of every PDF under `root` to a zip that another
+/// machine can import instead of extracting the PDFs itself. Only files the
+/// index has already extracted are included, so search the corpus once first.
+pub fn export_corpus_bundle(
+    index: &Index,
+    root: &Path,
+    output_path: &Path,
+    zotero_path: Option<&Path>,
+) -> Result<CorpusSummary> {
+    let file = File::create(output_path)
+        .context(format!("Failed to create corpus bundle: {}", output_path.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let zip_options = SimpleFileOptions::default();
+    let mut summary = CorpusSummary::default();
+    let mut documents = Vec::new();
+
+    for path in index.cached_text_paths()? {
+        let pdf_path = PathBuf::from(&path);
+        let Ok(relative) = pdf_path.strip_prefix(root) else {
+            continue;
+        };
+        let Some(cached) = index.cached_text(&path)? else {
+            continue;
+        };
+
+        let size = pdf_path.metadata().map(|m| m.len()).ok();
+        if size != Some(cached.size) || index::file_mtime(&pdf_path) != Some(cached.mtime) {
+            summary.skipped_stale += 1;
+            continue;
+        }
+
+        let text_file = format!("text/{:06}.json", documents.len() + 1);
+        let pages: Vec<BundlePage> = cached
+            .pages
+            .into_iter()
+            .map(|page| BundlePage {
+                page_number: page.page_number,
+                label: page.label,
+                text: page.text,
+                ocr: page.ocr,
+                blocks: page.blocks,
+            })
+            .collect();
+        zip.start_file(text_file.as_str(), zip_options)?;
+        zip.write_all(&serde_json::to_vec(&pages)?)?;
+
+        let relative = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        documents.push(CorpusDocument {
+            path: relative,
+            size: cached.size,
+            sha256: cached.sha256,
+            title: cached.title,
+            text_file,
+        });
+    }
+    summary.documents = documents.len();
+
+    if let Some(zotero_path) = zotero_path {
+        let zotero_map = build_zotero_map(zotero_path, zotero_cache::skip_publications())?;
+        summary.zotero_items = Some(zotero_map.len());
+        zip.start_file(ZOTERO_FILE, zip_options)?;
+        zip.write_all(&serde_json::to_vec(&zotero_map)?)?;
+    }
+
+    let manifest = CorpusManifest {
+        format_version: FORMAT_VERSION,
+        created: index::unix_now(),
+        root: root.to_string_lossy().to_string(),
+        documents,
+    };
+    zip.start_file(MANIFEST_FILE, zip_options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+
+    zip.finish()
+        .context(format!("Failed to write corpus bundle: {}", output_path.display()))?;
+    Ok(summary)
+}
+
+/// Load a corpus bundle's text into the index, for the copy of the corpus at `root`.
+/// Imported entries are verified against the local files by hash the first time
+/// they're searched, so a file that differs from the exported one is re-extracted.
+pub fn import_corpus_bundle(index: &Index, bundle_path: &Path, root: &Path) -> Result<CorpusSummary> {
+    let file = File::open(bundle_path)
+        .context(format!("Failed to open corpus bundle: {}", bundle_path.display()))?;
+    let mut zip = ZipArchive::new(file)
+        .context(format!("Not a corpus bundle: {}", bundle_path.display()))?;
+
+    let manifest: CorpusManifest = serde_json::from_slice(&read_entry(&mut zip, MANIFEST_FILE)?)
+        .context("Corpus bundle has an invalid manifest")?;
+    if manifest.format_version > FORMAT_VERSION {
+        return Err(AppError::invalid_input(format!(
+            "Corpus bundle format {} is newer than this version of the app supports",
+            manifest.format_version
+        ))
+        .into());
+    }
+
+    let mut summary = CorpusSummary::default();
+    for document in &manifest.documents {
+        let pages: Vec<BundlePage> = serde_json::from_slice(&read_entry(&mut zip, &document.text_file)?)
+            .context(format!("Corpus bundle has invalid text for {}", document.path))?;
+        let local_path = local_path(root, &document.path);
+
+        let cached = CachedText {
+            size: document.size,
+            // Never a real modification time, so the first use checks the hash
+            mtime: 0,
+            sha256: document.sha256.clone(),
+            title: document.title.clone(),
+            ocr: pages.iter().any(|page| page.ocr),
+            pages: pages
+                .into_iter()
+                .map(|page| CachedPage {
+                    page_number: page.page_number,
+                    label: page.label,
+                    text: page.text,
+                    ocr: page.ocr,
+                    blocks: page.blocks,
+                })
+                .collect(),
+        };
+        index.store_text(&local_path.to_string_lossy(), &cached)?;
+        summary.documents += 1;
+    }
+
+    if zip.by_name(ZOTERO_FILE).is_ok() {
+        let metadata = read_entry(&mut zip, ZOTERO_FILE)?;
+        let zotero_map: ZoteroMap = serde_json::from_slice(&metadata)
+            .context("Corpus bundle has an invalid Zotero snapshot")?;
+        summary.zotero_items = Some(zotero_map.len());
+        index.store_zotero_snapshot(&root.to_string_lossy(), &String::from_utf8_lossy(&metadata))?;
+    }
+
+    Ok(summary)
+}
+
+/// How the files under a root compare with the hashes the index or a bundle recorded
+#[derive(Debug, Default, Serialize)]
+pub struct SourceVerification {
+    pub unchanged: usize,
+    pub modified: Vec<String>,
+    pub missing: Vec<String>,
+    // PDFs on disk that have no recorded hash
+    pub new: Vec<String>,
+}
+
+// A file as it was recorded; `mtime` is None when only the hash is known
+struct RecordedFile {
+    path: PathBuf,
+    size: u64,
+    mtime: Option<u64>,
+    sha256: String,
+}
+
+/// Check the PDFs under `root` against the hashes recorded for them, by the
+/// index's text cache or, with `bundle_path`, by a corpus bundle's manifest
+pub fn verify_sources(
+    index: &Index,
+    root: &Path,
+    bundle_path: Option<&Path>,
+    walk_options: &WalkOptions,
+) -> Result<SourceVerification> {
+    let recorded = match bundle_path {
+        Some(bundle_path) => bundle_files(bundle_path, root)?,
+        None => cached_files(index, root)?,
+    };
+
+    let mut report = SourceVerification::default();
+    let mut known = HashSet::new();
+    for file in recorded {
+        let path_str = file.path.to_string_lossy().to_string();
+        let Ok(metadata) = file.path.metadata() else {
+            report.missing.push(path_str);
+            continue;
+        };
+
+        // Same size and modification time is trusted without hashing
+        let same_mtime = file.mtime.is_some() && file.mtime == index::file_mtime(&file.path);
+        let unchanged = metadata.len() == file.size
+            && (same_mtime || index::file_sha256(&file.path).is_ok_and(|sha256| sha256 == file.sha256));
+        if unchanged {
+            report.unchanged += 1;
+        } else {
+            report.modified.push(path_str);
+        }
+        known.insert(file.path);
+    }
+
+    let (on_disk, _) = walk_pdfs(root, walk_options)?;
+    report.new = on_disk
+        .into_iter()
+        .filter(|path| !known.contains(path))
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+
+    report.modified.sort();
+    report.missing.sort();
+    Ok(report)
+}
+
+fn cached_files(index: &Index, root: &Path) -> Result<Vec<RecordedFile>> {
+    let mut files = Vec::new();
+    for path in archived_files(index, root)? {
+        if let Some(cached) = index.cached_text(&path.to_string_lossy())? {
+            files.push(RecordedFile {
+                path,
+                size: cached.size,
+                // Imported entries carry no real modification time
+                mtime: (cached.mtime != 0).then_some(cached.mtime),
+                sha256: cached.sha256,
+            });
+        }
+    }
+    Ok(files)
+}
+
+fn bundle_files(bundle_path: &Path, root: &Path) -> Result<Vec<RecordedFile>> {
+    let file = File::open(bundle_path)
+        .context(format!("Failed to open corpus bundle: {}", bundle_path.display()))?;
+    let mut zip = ZipArchive::new(file)
+        .context(format!("Not a corpus bundle: {}", bundle_path.display()))?;
+    let manifest: CorpusManifest = serde_json::from_slice(&read_entry(&mut zip, MANIFEST_FILE)?)
+        .context("Corpus bundle has an invalid manifest")?;
+
+    Ok(manifest
+        .documents
+        .into_iter()
+        .map(|document| RecordedFile {
+            path: local_path(root, &document.path),
+            size: document.size,
+            mtime: None,
+            sha256: document.sha256,
+        })
+        .collect())
+}
+
+// Where a bundle's relative path lives under this machine's root
+fn local_path(root: &Path, relative: &str) -> PathBuf {
+    relative.split('/').fold(root.to_path_buf(), |path, part| path.join(part))
+}
+
+fn read_entry(zip: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>> {
+    let mut entry = zip
+        .by_name(name)
+        .context(format!("Corpus bundle is missing {}", name))?;
+    let mut contents = Vec::new();
+    entry.read_to_end(&mut contents)?;
+    Ok(contents)
+}
+
+/// Files under `root` whose text the index holds, present on disk or not
+pub fn archived_files(index: &Index, root: &Path) -> Result<Vec<PathBuf>> {
+    Ok(index
+        .cached_text_paths()?
+        .into_iter()
+        .map(PathBuf::from)
+        .filter(|path| path.starts_with(root))
+        .collect())
+}
+
+/// Zotero metadata imported with a corpus bundle whose root contains `directory`,
+/// for searching an imported corpus without a local Zotero library
+pub fn zotero_snapshot_for(index: &Index, directory: &Path) -> Result<Option<ZoteroMap>> {
+    for (root, metadata) in index.zotero_snapshots()? {
+        if directory.starts_with(&root) {
+            return Ok(Some(serde_json::from_str(&metadata)?));
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,180 @@
+use crate::i18n;
+use crate::settings::AppSettings;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
+
+// Citations in exports use the terms of the cited item's own language, so a
+// German source is cited "S. 12" next to an English one's "p. 12". The terms are
+// those of the CSL locale files (locales-de-DE.xml and so on); the few the exports
+// use are built in for common languages, and a folder of CSL locale files can add
+// or override languages.
+
+/// The localized terms citations use, as CSL locales define them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CitationTerms {
+    // "page" and "editor", short form, singular and plural
+    pub page: String,
+    pub pages: String,
+    pub editor: String,
+    pub editors: String,
+}
+
+impl CitationTerms {
+    fn new(page: &str, pages: &str, editor: &str, editors: &str) -> Self {
+        CitationTerms {
+            page: page.to_string(),
+            pages: pages.to_string(),
+            editor: editor.to_string(),
+            editors: editors.to_string(),
+        }
+    }
+}
+
+// Short forms from the CSL project's locale files
+const BUILT_IN: &[(&str, [&str; 4])] = &[
+    ("en", ["p.", "pp.", "ed.", "eds."]),
+    ("de", ["S.", "S.", "Hrsg.", "Hrsg."]),
+    ("es", ["p.", "pp.", "ed.", "eds."]),
+    ("fr", ["p.", "p.", "éd.", "éd."]),
+    ("it", ["p.", "pp.", "a c. di", "a c. di"]),
+    ("nl", ["p.", "pp.", "red.", "red."]),
+];
+
+// How Zotero's free-text language field commonly names these languages
+const LANGUAGE_NAMES: &[(&str, &str)] = &[
+    ("english", "en"),
+    ("eng", "en"),
+    ("german", "de"),
+    ("deutsch", "de"),
+    ("ger", "de"),
+    ("deu", "de"),
+    ("spanish", "es"),
+    ("español", "es"),
+    ("espanol", "es"),
+    ("spa", "es"),
+    ("french", "fr"),
+    ("français", "fr"),
+    ("francais", "fr"),
+    ("fre", "fr"),
+    ("fra", "fr"),
+    ("italian", "it"),
+    ("italiano", "it"),
+    ("ita", "it"),
+    ("dutch", "nl"),
+    ("nederlands", "nl"),
+    ("dut", "nl"),
+    ("nld", "nl"),
+];
+
+// Terms read from CSL locale files, by lowercase language tag ("de-at")
+static LOADED: RwLock<Option<HashMap<String, CitationTerms>>> = RwLock::new(None);
+
+/// Read the CSL locale files in `AppSettings.csl_locales_dir`, if set. Files
+/// that can't be read are skipped with a warning.
+pub fn configure(settings: &AppSettings) {
+    let loaded = settings.csl_locales_dir.as_deref().map(|dir| load_locales(Path::new(dir)));
+    *LOADED.write().unwrap() = loaded;
+}
+
+fn load_locales(dir: &Path) -> HashMap<String, CitationTerms> {
+    let mut locales = HashMap::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Warning: Failed to read CSL locales from {}: {}", dir.display(), e);
+            return locales;
+        }
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.extension().and_then(|extension| extension.to_str()) != Some("xml") {
+            continue;
+        }
+        match fs::read_to_string(&path).ok().and_then(|xml| parse_locale(&xml)) {
+            Some((language, terms)) => {
+                locales.insert(language, terms);
+            }
+            None => eprintln!("Warning: Not a usable CSL locale file: {}", path.display()),
+        }
+    }
+    locales
+}
+
+// The language and terms of a CSL locale file. Terms it doesn't define come from
+// the built-in terms of its base language, or English.
+fn parse_locale(xml: &str) -> Option<(String, CitationTerms)> {
+    static LANG: OnceLock<Regex> = OnceLock::new();
+    let lang = LANG.get_or_init(|| Regex::new(r#"<locale[^>]*\sxml:lang="([^"]+)""#).unwrap());
+    let language = lang.captures(xml)?[1].to_lowercase();
+
+    let fallback = built_in(base_language(&language)).unwrap_or_else(|| built_in("en").unwrap());
+    let (page, pages) = short_term(xml, "page").unwrap_or((fallback.page, fallback.pages));
+    let (editor, editors) = short_term(xml, "editor").unwrap_or((fallback.editor, fallback.editors));
+    Some((language, CitationTerms { page, pages, editor, editors }))
+}
+
+// Singular and plural of a term's short form, e.g. <term name="page" form="short">
+fn short_term(xml: &str, name: &str) -> Option<(String, String)> {
+    let pattern = format!(r#"(?s)<term\s+name="{}"\s+form="short"\s*>(.*?)</term>"#, regex::escape(name));
+    let body = Regex::new(&pattern).ok()?.captures(xml)?[1].to_string();
+
+    static SINGLE: OnceLock<Regex> = OnceLock::new();
+    static MULTIPLE: OnceLock<Regex> = OnceLock::new();
+    let single = SINGLE.get_or_init(|| Regex::new(r"(?s)<single>(.*?)</single>").unwrap());
+    let multiple = MULTIPLE.get_or_init(|| Regex::new(r"(?s)<multiple>(.*?)</multiple>").unwrap());
+    match (single.captures(&body), multiple.captures(&body)) {
+        (Some(single), Some(multiple)) => Some((single[1].trim().to_string(), multiple[1].trim().to_string())),
+        // A term without number forms is the same in both
+        _ => Some((body.trim().to_string(), body.trim().to_string())),
+    }
+}
+
+fn built_in(language: &str) -> Option<CitationTerms> {
+    BUILT_IN
+        .iter()
+        .find(|(code, _)| *code == language)
+        .map(|(_, [page, pages, editor, editors])| CitationTerms::new(page, pages, editor, editors))
+}
+
+fn base_language(tag: &str) -> &str {
+    tag.split(['-', '_']).next().unwrap_or_default()
+}
+
+// A lowercase language tag for what an item's language field says: "de-DE",
+// "German" and "Deutsch" all give "de-de" or "de"
+fn language_tag(language: &str) -> String {
+    let language = language.trim().to_lowercase();
+    LANGUAGE_NAMES
+        .iter()
+        .find(|(name, _)| *name == language)
+        .map_or(language.replace('_', "-"), |(_, code)| code.to_string())
+}
+
+/// Terms for citing an item in `language`, its Zotero language field. Regional
+/// variants fall back to their base language; items without a language, or in
+/// one with no terms, are cited in the app's language.
+pub fn terms_for(language: Option<&str>) -> CitationTerms {
+    let loaded = LOADED.read().unwrap();
+    let lookup = |tag: &str| {
+        let base = base_language(tag);
+        loaded
+            .as_ref()
+            .and_then(|locales| {
+                // "de" is served by locales-de-DE.xml as well
+                locales.get(tag).or_else(|| {
+                    let mut regional: Vec<_> = locales.iter().filter(|(other, _)| base_language(other) == base).collect();
+                    regional.sort_by_key(|(other, _)| other.as_str());
+                    regional.first().map(|(_, terms)| *terms)
+                })
+            })
+            .cloned()
+            .or_else(|| built_in(base))
+    };
+    language
+        .filter(|language| !language.trim().is_empty())
+        .and_then(|language| lookup(&language_tag(language)))
+        .or_else(|| lookup(&i18n::language()))
+        .unwrap_or_else(|| built_in(i18n::DEFAULT_LANGUAGE).unwrap())
+}
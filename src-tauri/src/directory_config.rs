@@ -0,0 +1,101 @@
+use crate::error::AppError;
+use crate::pdf_search::SearchParams;
+use crate::walk;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Dropped into a corpus directory to override the search settings for searches rooted there
+pub const CONFIG_FILE: &str = ".pdfsearch.toml";
+
+/// A corpus's own search conventions, kept with it so they travel with the files
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DirectoryConfig {
+    pub context_words: Option<usize>,
+    // A corpus of scans can turn OCR on for every search of it
+    pub use_ocr: Option<bool>,
+    // Only the steps listed are changed
+    pub normalization: NormalizationOverrides,
+    // Files and folders under the corpus root to leave out, with `/` separators
+    pub exclude: Vec<String>,
+    // Directory names skipped anywhere below the root, on top of the settings' list
+    pub skip_dirs: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NormalizationOverrides {
+    pub strip_whitespace: Option<bool>,
+    pub strip_hyphens: Option<bool>,
+    pub expand_ligatures: Option<bool>,
+    pub case_fold: Option<bool>,
+    pub strip_diacritics: Option<bool>,
+}
+
+/// The `.pdfsearch.toml` at the root of `directory`, if there is one
+pub fn load_directory_config(directory: &Path) -> Result<Option<DirectoryConfig>> {
+    let path = directory.join(CONFIG_FILE);
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let contents = fs::read_to_string(&path)
+        .context(format!("Failed to read {}", path.display()))?;
+    let config = toml::from_str(&contents)
+        .map_err(|e| AppError::invalid_input(format!("Invalid {}: {}", path.display(), e)))?;
+    Ok(Some(config))
+}
+
+/// Apply the `.pdfsearch.toml` of the directory a search is rooted at. Its values
+/// take precedence over the search's own. Returns the configuration applied.
+pub fn apply_directory_config(params: &mut SearchParams) -> Result<Option<DirectoryConfig>> {
+    // Zotero searches aren't rooted at a directory, and searches of named corpora
+    // at more than one, whose configs could disagree
+    if params.source.as_deref() == Some("zotero") || params.directory.is_empty() || !params.corpora.is_empty() {
+        return Ok(None);
+    }
+    let root = Path::new(&params.directory).to_path_buf();
+    let Some(config) = load_directory_config(&root)? else {
+        return Ok(None);
+    };
+
+    if let Some(context_words) = config.context_words {
+        params.context_words = context_words;
+    }
+    if let Some(use_ocr) = config.use_ocr {
+        params.use_ocr = use_ocr;
+    }
+
+    let overrides = &config.normalization;
+    let normalization = &mut params.normalization;
+    for (value, setting) in [
+        (overrides.strip_whitespace, &mut normalization.strip_whitespace),
+        (overrides.strip_hyphens, &mut normalization.strip_hyphens),
+        (overrides.expand_ligatures, &mut normalization.expand_ligatures),
+        (overrides.case_fold, &mut normalization.case_fold),
+        (overrides.strip_diacritics, &mut normalization.strip_diacritics),
+    ] {
+        if let Some(value) = value {
+            *setting = value;
+        }
+    }
+
+    params.walk.exclude_paths.extend(config.exclude.iter().map(|relative| {
+        let path = relative
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .fold(root.clone(), |path, part| path.join(part));
+        path.to_string_lossy().to_string()
+    }));
+    if !config.skip_dirs.is_empty() {
+        params
+            .walk
+            .skip_dirs
+            .get_or_insert_with(walk::default_skip_dirs)
+            .extend(config.skip_dirs.iter().cloned());
+    }
+
+    Ok(Some(config))
+}
@@ -0,0 +1,223 @@
+use crate::error::AppError;
+use crate::index::{self, Index};
+use crate::walk::{walk_pdfs, WalkOptions};
+use crate::zotero_cache::zotero_linker;
+use anyhow::Result;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Byte-identical copies of a PDF, typically the same paper downloaded twice or
+// exported from Zotero next to the original. Extra copies are moved into a
+// quarantine folder, never deleted, so a wrong call is undone by moving them back.
+
+/// Hidden, so searches of the corpus skip it
+pub const QUARANTINE_DIR: &str = ".pdfsearch-quarantine";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateFile {
+    pub file_path: String,
+    // Inside Zotero's storage folder, or named like one of its attachments
+    pub zotero_linked: bool,
+}
+
+/// Copies of one file. `keep` is the copy to leave in place; the caller may
+/// change it before resolving the group.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub sha256: String,
+    pub size: u64,
+    pub files: Vec<DuplicateFile>,
+    pub keep: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuarantineOutcome {
+    pub file_path: String,
+    pub kept_path: String,
+    pub quarantine_path: String,
+    // "quarantined", "would_quarantine" on a dry run, or "skipped" with the reason in `error`
+    pub status: String,
+    pub error: Option<AppError>,
+}
+
+/// Group the PDFs under `directory` that have identical contents. The copy kept
+/// is the one Zotero links to when there is one, otherwise the oldest.
+pub fn find_duplicates(directory: &Path, zotero_path: Option<&Path>) -> Result<Vec<DuplicateGroup>> {
+    let (pdf_files, _) = walk_pdfs(directory, &WalkOptions::default())?;
+    let by_hash = identical_files(&pdf_files);
+    if by_hash.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let zotero_linked = zotero_linker(zotero_path);
+
+    let mut groups: Vec<DuplicateGroup> = by_hash
+        .into_iter()
+        .map(|((size, sha256), mut paths)| {
+            paths.sort();
+            let keep = paths
+                .iter()
+                .min_by_key(|path| (!zotero_linked(path), index::file_mtime(path).unwrap_or(u64::MAX)))
+                .expect("groups have files")
+                .to_string_lossy()
+                .to_string();
+            DuplicateGroup {
+                sha256,
+                size,
+                files: paths
+                    .iter()
+                    .map(|path| DuplicateFile {
+                        file_path: path.to_string_lossy().to_string(),
+                        zotero_linked: zotero_linked(path),
+                    })
+                    .collect(),
+                keep,
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| a.keep.cmp(&b.keep));
+    Ok(groups)
+}
+
+/// Sets of two or more files with identical contents, by size and SHA-256
+pub fn identical_files(pdf_files: &[PathBuf]) -> HashMap<(u64, String), Vec<PathBuf>> {
+    // Only files sharing a size can be identical, so most are never hashed
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    for path in pdf_files {
+        if let Ok(metadata) = path.metadata() {
+            by_size.entry(metadata.len()).or_default().push(path.clone());
+        }
+    }
+    let candidates: Vec<(u64, PathBuf)> = by_size
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .flat_map(|(size, paths)| paths.into_iter().map(move |path| (size, path)))
+        .collect();
+    if candidates.is_empty() {
+        return HashMap::new();
+    }
+
+    let hashed: Vec<(u64, String, PathBuf)> = candidates
+        .into_par_iter()
+        .filter_map(|(size, path)| match index::file_sha256(&path) {
+            Ok(sha256) => Some((size, sha256, path)),
+            Err(e) => {
+                eprintln!("Warning: Failed to hash {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect();
+    let mut by_hash: HashMap<(u64, String), Vec<PathBuf>> = HashMap::new();
+    for (size, sha256, path) in hashed {
+        by_hash.entry((size, sha256)).or_default().push(path);
+    }
+    by_hash.retain(|_, paths| paths.len() > 1);
+    by_hash
+}
+
+// Where a copy goes: its place under `root`, mirrored inside the quarantine folder
+fn quarantine_path(quarantine: &Path, root: &Path, path: &Path) -> PathBuf {
+    match path.strip_prefix(root) {
+        Ok(relative) => quarantine.join(relative),
+        Err(_) => quarantine.join(path.file_name().unwrap_or_default()),
+    }
+}
+
+// The group may be stale by now; copies are only moved while the kept file
+// is still there with the same contents
+fn check_kept(group: &DuplicateGroup) -> Result<(), AppError> {
+    if !group.files.iter().any(|file| file.file_path == group.keep) {
+        return Err(AppError::invalid_input(format!("{} isn't one of the copies", group.keep)));
+    }
+    let kept = Path::new(&group.keep);
+    let sha256 = index::file_sha256(kept).map_err(|e| AppError::io(e, &group.keep))?;
+    if sha256 != group.sha256 {
+        return Err(AppError::invalid_input(format!(
+            "{} has changed since duplicates were found",
+            kept.display()
+        )));
+    }
+    Ok(())
+}
+
+fn quarantine_file(path: &Path, sha256: &str, target: &Path, dry_run: bool) -> Result<(), AppError> {
+    let current = index::file_sha256(path).map_err(|e| AppError::io(e, &path.to_string_lossy()))?;
+    if current != sha256 {
+        return Err(AppError::invalid_input(format!(
+            "{} has changed since duplicates were found",
+            path.display()
+        )));
+    }
+    if target.exists() {
+        return Err(AppError::invalid_input(format!("{} already exists", target.display())));
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| AppError::io(e, &parent.to_string_lossy()))?;
+    }
+    fs::rename(path, target).map_err(|e| AppError::io(e, &path.to_string_lossy()))
+}
+
+/// Move every copy but the kept one of each group into `quarantine` (by default
+/// `.pdfsearch-quarantine` under `root`), keeping their paths relative to `root`.
+/// Copies Zotero links to stay where they are, since its attachments would
+/// point at nothing; they're reported as skipped. The index forgets the moved
+/// copies and carries their screening decisions over to the kept copy.
+pub fn quarantine_duplicates(
+    index: Option<&Index>,
+    root: &Path,
+    groups: &[DuplicateGroup],
+    quarantine: Option<&Path>,
+    zotero_path: Option<&Path>,
+    dry_run: bool,
+) -> Vec<QuarantineOutcome> {
+    let quarantine = quarantine.map(Path::to_path_buf).unwrap_or_else(|| root.join(QUARANTINE_DIR));
+    // The groups' own flags may be stale, so the library is checked again
+    let zotero_linked = zotero_linker(zotero_path);
+
+    let mut outcomes = Vec::new();
+    for group in groups {
+        let kept = check_kept(group);
+        for file in group.files.iter().filter(|file| file.file_path != group.keep) {
+            let path = Path::new(&file.file_path);
+            let target = quarantine_path(&quarantine, root, path);
+            let result = kept.clone().and_then(|()| {
+                if file.zotero_linked || zotero_linked(path) {
+                    return Err(AppError::invalid_input(format!(
+                        "{} is a Zotero attachment, which would lose its file if moved",
+                        path.display()
+                    )));
+                }
+                quarantine_file(path, &group.sha256, &target, dry_run)
+            });
+
+            if result.is_ok() && !dry_run {
+                if let Some(index) = index {
+                    if let Err(e) = index.forget_duplicate(&file.file_path, &group.keep) {
+                        eprintln!("Warning: Failed to update the index for {}: {}", file.file_path, e);
+                    }
+                }
+            }
+
+            outcomes.push(QuarantineOutcome {
+                file_path: file.file_path.clone(),
+                kept_path: group.keep.clone(),
+                quarantine_path: target.to_string_lossy().to_string(),
+                status: match (&result, dry_run) {
+                    (Err(_), _) => "skipped",
+                    (Ok(()), true) => "would_quarantine",
+                    (Ok(()), false) => "quarantined",
+                }
+                .to_string(),
+                error: result.err(),
+            });
+        }
+    }
+    outcomes
+}
@@ -0,0 +1,155 @@
+use crate::i18n;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::io;
+
+/// Error returned by every command. Serializes as `{ kind, message, file, hint }`
+/// so the frontend can offer a recovery action for the specific failure.
+#[derive(Debug, Clone)]
+pub enum AppError {
+    NotFound { file: String },
+    PermissionDenied { file: String },
+    Encrypted { file: String },
+    ExtractionNotPermitted { file: String },
+    NoExtractableText { file: String },
+    InvalidQuery { message: String },
+    InvalidInput { message: String },
+    Zotero { message: String },
+    Internal { message: String, file: String },
+    OutsideScope { file: String },
+    Io { message: String, file: Option<String> },
+    Other { message: String },
+}
+
+impl AppError {
+    pub fn io(err: io::Error, file: &str) -> Self {
+        match err.kind() {
+            io::ErrorKind::NotFound => AppError::NotFound { file: file.to_string() },
+            io::ErrorKind::PermissionDenied => AppError::PermissionDenied { file: file.to_string() },
+            _ => AppError::Io {
+                message: err.to_string(),
+                file: Some(file.to_string()),
+            },
+        }
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        AppError::InvalidInput { message: message.into() }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::NotFound { .. } => "not_found",
+            AppError::PermissionDenied { .. } => "permission_denied",
+            AppError::Encrypted { .. } => "encrypted",
+            AppError::ExtractionNotPermitted { .. } => "extraction_not_permitted",
+            AppError::NoExtractableText { .. } => "no_extractable_text",
+            AppError::InvalidQuery { .. } => "invalid_query",
+            AppError::InvalidInput { .. } => "invalid_input",
+            AppError::Zotero { .. } => "zotero",
+            AppError::Internal { .. } => "internal",
+            AppError::OutsideScope { .. } => "outside_scope",
+            AppError::Io { .. } => "io",
+            AppError::Other { .. } => "other",
+        }
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        match self {
+            AppError::NotFound { file }
+            | AppError::PermissionDenied { file }
+            | AppError::Encrypted { file }
+            | AppError::ExtractionNotPermitted { file }
+            | AppError::NoExtractableText { file }
+            | AppError::OutsideScope { file }
+            | AppError::Internal { file, .. } => Some(file),
+            AppError::Io { file, .. } => file.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn hint(&self) -> Option<String> {
+        let id = match self {
+            AppError::NotFound { .. } => "hint-not-found",
+            AppError::PermissionDenied { .. } => "hint-permission-denied",
+            AppError::Encrypted { .. } => "hint-encrypted",
+            AppError::ExtractionNotPermitted { .. } => "hint-extraction-not-permitted",
+            AppError::NoExtractableText { .. } => "hint-no-extractable-text",
+            AppError::InvalidQuery { .. } => "hint-invalid-query",
+            AppError::Internal { .. } => "hint-internal",
+            AppError::Zotero { .. } => "hint-zotero",
+            AppError::OutsideScope { .. } => "hint-outside-scope",
+            _ => return None,
+        };
+        Some(i18n::message(id, &[]))
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Messages from lower layers (I/O, regex, Zotero's database) are passed through untranslated
+        let message = match self {
+            AppError::NotFound { file } => i18n::message("error-not-found", &[("file", file.as_str().into())]),
+            AppError::PermissionDenied { file } => {
+                i18n::message("error-permission-denied", &[("file", file.as_str().into())])
+            }
+            AppError::Encrypted { file } => i18n::message("error-encrypted", &[("file", file.as_str().into())]),
+            AppError::ExtractionNotPermitted { file } => {
+                i18n::message("error-extraction-not-permitted", &[("file", file.as_str().into())])
+            }
+            AppError::NoExtractableText { file } => {
+                i18n::message("error-no-extractable-text", &[("file", file.as_str().into())])
+            }
+            AppError::InvalidQuery { message } => {
+                i18n::message("error-invalid-query", &[("message", message.as_str().into())])
+            }
+            AppError::Zotero { message } => i18n::message("error-zotero", &[("message", message.as_str().into())]),
+            AppError::Internal { message, file } => i18n::message(
+                "error-internal",
+                &[("file", file.as_str().into()), ("message", message.as_str().into())],
+            ),
+            AppError::OutsideScope { file } => {
+                i18n::message("error-outside-scope", &[("file", file.as_str().into())])
+            }
+            AppError::InvalidInput { message } | AppError::Io { message, .. } | AppError::Other { message } => {
+                message.clone()
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 4)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("file", &self.file())?;
+        state.serialize_field("hint", &self.hint())?;
+        state.end()
+    }
+}
+
+impl From<anyhow::Error> for AppError {
+    fn from(err: anyhow::Error) -> Self {
+        for cause in err.chain() {
+            if let Some(app_err) = cause.downcast_ref::<AppError>() {
+                return app_err.clone();
+            }
+            if let Some(regex_err) = cause.downcast_ref::<regex::Error>() {
+                return AppError::InvalidQuery { message: regex_err.to_string() };
+            }
+            if cause.is::<io::Error>() {
+                return AppError::Io {
+                    message: format!("{:#}", err),
+                    file: None,
+                };
+            }
+        }
+
+        AppError::Other { message: format!("{:#}", err) }
+    }
+}
@@ -0,0 +1,1413 @@
+use crate::csl_locale;
+use crate::error::AppError;
+use crate::fallback_metadata::FallbackMetadata;
+use crate::i18n;
+use crate::library::LibraryItem;
+use crate::note_apps::percent_encode;
+use crate::page_render::{match_key, MatchKey};
+use crate::screening::ScreeningFilter;
+use crate::pdf_search::{ExcludedDocument, FileError, SearchMatch, SearchParams, SearchStats, ZoteroMetadata};
+use anyhow::{Context, Result};
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How a match is set off in exported context. The default is the long-standing
+/// `...before **match** after...`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuoteStyle {
+    // "..." where the context was cut from longer text
+    pub ellipses: bool,
+    // Wrap the quotation in “curly quotes”
+    pub smart_quotes: bool,
+    // Set the match off from its context with em dashes instead of spaces
+    pub em_dashes: bool,
+    pub markup: MatchMarkup,
+}
+
+/// How the matched text itself is marked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMarkup {
+    // **match**
+    Bold,
+    // <mark>match</mark>, which markdown renderers pass through as HTML
+    Highlight,
+    None,
+}
+
+impl QuoteStyle {
+    const DEFAULT: QuoteStyle = QuoteStyle {
+        ellipses: true,
+        smart_quotes: false,
+        em_dashes: false,
+        markup: MatchMarkup::Bold,
+    };
+
+    /// The match between its context. Empty context has no ellipsis or separator,
+    /// since nothing was cut there.
+    pub fn frame(&self, before: &str, matched: &str, after: &str) -> String {
+        let matched = match self.markup {
+            MatchMarkup::Bold => format!("**{}**", matched),
+            MatchMarkup::Highlight => format!("<mark>{}</mark>", matched),
+            MatchMarkup::None => matched.to_string(),
+        };
+        let separator = if self.em_dashes { " — " } else { " " };
+        let ellipsis = if self.ellipses { "..." } else { "" };
+
+        let mut framed = String::new();
+        if self.smart_quotes {
+            framed.push('“');
+        }
+        if !before.is_empty() {
+            framed.push_str(ellipsis);
+            framed.push_str(before);
+            framed.push_str(separator);
+        }
+        framed.push_str(&matched);
+        if !after.is_empty() {
+            framed.push_str(separator);
+            framed.push_str(after);
+            framed.push_str(ellipsis);
+        }
+        if self.smart_quotes {
+            framed.push('”');
+        }
+        framed
+    }
+}
+
+impl Default for QuoteStyle {
+    fn default() -> Self {
+        QuoteStyle::DEFAULT
+    }
+}
+
+/// All matches from one document, ordered by page and position on the page
+pub struct FileGroup<'a> {
+    pub item: &'a LibraryItem,
+    pub matches: Vec<&'a SearchMatch>,
+}
+
+/// Group matches by document regardless of input order. Documents are sorted by
+/// path; matches within a document by page, then by offset on the page.
+pub fn group_by_file(matches: &[SearchMatch]) -> Vec<FileGroup<'_>> {
+    let mut groups: BTreeMap<&str, Vec<&SearchMatch>> = BTreeMap::new();
+    for m in matches {
+        groups.entry(&m.item.file_path).or_default().push(m);
+    }
+
+    groups
+        .into_values()
+        .map(|mut matches| {
+            // Stable sort keeps discovery order for matches without an offset
+            matches.sort_by_key(|m| (m.page_number, m.offset.unwrap_or(0)));
+            FileGroup {
+                item: &matches[0].item,
+                matches,
+            }
+        })
+        .collect()
+}
+
+/// The page to cite for a match: its printed label when the PDF defines one
+pub fn page_citation(m: &SearchMatch) -> String {
+    m.page_label.clone().unwrap_or_else(|| m.page_number.to_string())
+}
+
+/// The markdown export of `total_matches` matches from documents already
+/// rendered with `render_file_group`, which reads each source file for its
+/// provenance, so callers that also need the documents on their own render once
+pub fn export_to_markdown(total_matches: usize, rendered: &[String]) -> String {
+    let mut markdown = markdown_header(total_matches, rendered.len());
+    for document in rendered {
+        markdown.push_str(document);
+    }
+    markdown
+}
+
+fn markdown_header(total_matches: usize, total_documents: usize) -> String {
+    let mut markdown = format!("# {}\n\n", i18n::message("export-title", &[]));
+    markdown.push_str(&format!("{}\n\n", i18n::message("export-total-matches", &[("count", total_matches.into())])));
+    markdown.push_str(&format!(
+        "{}\n\n",
+        i18n::message("export-files-with-matches", &[("count", total_documents.into())])
+    ));
+    markdown
+}
+
+pub fn render_file_group(group: &FileGroup, style: &QuoteStyle) -> String {
+    let mut markdown = String::new();
+    markdown.push_str(&format!("\n## {}: `{}`\n", i18n::message("export-file", &[]), group.item.file_path));
+    markdown.push_str(&format!("**{}:** {}\n\n", i18n::message("export-filename", &[]), group.item.file_name));
+    if let Some(provenance) = file_provenance(Path::new(&group.item.file_path)) {
+        markdown.push_str(&format!("**SHA-256:** `{}`\n\n", provenance.sha256));
+        markdown.push_str(&format!(
+            "**{}:** {}\n\n",
+            i18n::message("export-modified", &[]),
+            format_timestamp(provenance.modified)
+        ));
+    }
+    markdown.push_str(&format!(
+        "**{}:** {}\n\n",
+        i18n::message("export-matches-in-file", &[]),
+        group.matches.len()
+    ));
+
+    let page_heading = i18n::message("export-page", &[]);
+    for (idx, m) in group.matches.iter().enumerate() {
+        markdown.push_str(&format!(
+            "### {}\n\n",
+            i18n::message("export-match", &[("number", (idx + 1).into()), ("page", page_citation(m).into())])
+        ));
+        match m.page_label.as_deref() {
+            // Cite the printed page, keeping the physical index for navigating the file
+            Some(label) if label != m.page_number.to_string() => {
+                markdown.push_str(&format!(
+                    "**{}:** {} ({})\n\n",
+                    page_heading,
+                    label,
+                    i18n::message("export-pdf-page", &[("page", m.page_number.into())])
+                ));
+            }
+            _ => markdown.push_str(&format!("**{}:** {}\n\n", page_heading, m.page_number)),
+        }
+        markdown.push_str(&format!("**{}:**\n\n", i18n::message("export-context", &[])));
+        markdown.push_str(&style.frame(&m.context_before, &m.matched_text, &m.context_after));
+        markdown.push_str("\n\n");
+        markdown.push_str("---\n\n");
+    }
+
+    markdown
+}
+
+// Characters of context kept on each side of a match in a share summary
+const SHARE_CONTEXT_CHARS: usize = 80;
+
+/// How a document is named in summaries and citations: "Authors (Year). Title"
+/// from Zotero, else the file name. Edited volumes mark their editors in the
+/// item's language: "Name (Hrsg.) (2020). Title".
+pub fn source_heading(file_name: &str, metadata: Option<&ZoteroMetadata>) -> String {
+    match metadata {
+        Some(metadata) => {
+            let title = metadata.title.as_deref().unwrap_or(file_name);
+            let authors = metadata.authors.as_ref().map(|authors| {
+                if only_editors(metadata) {
+                    let terms = csl_locale::terms_for(metadata.language.as_deref());
+                    let term = if authors.contains(", ") { terms.editors } else { terms.editor };
+                    format!("{} ({})", authors, term)
+                } else {
+                    authors.clone()
+                }
+            });
+            heading(authors.as_deref(), metadata.year.as_deref(), title)
+        }
+        None => file_name.to_string(),
+    }
+}
+
+fn heading(authors: Option<&str>, year: Option<&str>, title: &str) -> String {
+    match (authors, year) {
+        (Some(authors), Some(year)) => format!("{} ({}). {}", authors, year, title),
+        (None, Some(year)) => format!("{} ({})", title, year),
+        _ => title.to_string(),
+    }
+}
+
+/// `source_heading` for a document, which without a Zotero item is named by the
+/// title, authors and year guessed from it, when there's a title
+pub fn item_heading(item: &LibraryItem) -> String {
+    match (&item.zotero_metadata, &item.fallback_metadata) {
+        (None, Some(FallbackMetadata { title: Some(title), authors, year, .. })) => {
+            heading(authors.as_deref(), year.as_deref(), title)
+        }
+        (metadata, _) => source_heading(&item.file_name, metadata.as_ref()),
+    }
+}
+
+// Title, authors and year to cite a match's document by: its Zotero item's, or
+// those guessed from the document
+fn citation_field<'a>(item: &'a LibraryItem, field: &str) -> Option<&'a str> {
+    match (&item.zotero_metadata, &item.fallback_metadata) {
+        (Some(metadata), _) => match field {
+            "title" => metadata.title.as_deref(),
+            "authors" => metadata.authors.as_deref(),
+            _ => metadata.year.as_deref(),
+        },
+        (None, Some(fallback)) => match field {
+            "title" => fallback.title.as_deref(),
+            "authors" => fallback.authors.as_deref(),
+            _ => fallback.year.as_deref(),
+        },
+        (None, None) => None,
+    }
+}
+
+// Every creator of the item is an editor, as in an edited volume
+fn only_editors(metadata: &ZoteroMetadata) -> bool {
+    metadata.editors.is_some() && metadata.editors == metadata.authors
+}
+
+/// Short plain-text summary for sending findings to someone: per document its
+/// citation, pages, and the first match with a little context
+pub fn render_share_summary(matches: &[SearchMatch], style: &QuoteStyle) -> String {
+    let groups = group_by_file(matches);
+    let mut summary = format!(
+        "{}\n",
+        i18n::message(
+            "share-summary",
+            &[("matches", matches.len().into()), ("documents", groups.len().into())]
+        )
+    );
+
+    for group in &groups {
+        let first = group.matches[0];
+        let heading = item_heading(group.item);
+
+        let mut pages: Vec<String> = Vec::new();
+        for m in &group.matches {
+            let page = page_citation(m);
+            if !pages.contains(&page) {
+                pages.push(page);
+            }
+        }
+
+        let before_chars = first.context_before.chars().count();
+        let before: String = first
+            .context_before
+            .chars()
+            .skip(before_chars.saturating_sub(SHARE_CONTEXT_CHARS))
+            .collect();
+        let after: String = first.context_after.chars().take(SHARE_CONTEXT_CHARS).collect();
+
+        summary.push_str(&format!("\n{}\n", heading));
+        summary.push_str(&format!(
+            "  {}\n",
+            i18n::message(
+                "share-document",
+                &[("matches", group.matches.len().into()), ("pages", pages.join(", ").into())]
+            )
+        ));
+        // A plain-text summary has no markup, and is always quoted
+        let style = QuoteStyle {
+            markup: MatchMarkup::None,
+            ..*style
+        };
+        let quotation = style.frame(&before, &first.matched_text, &after);
+        if style.smart_quotes {
+            summary.push_str(&format!("  {}\n", quotation));
+        } else {
+            summary.push_str(&format!("  \"{}\"\n", quotation));
+        }
+    }
+
+    summary
+}
+
+/// Fingerprint of a source file when it was exported, so a result set can
+/// later be checked against PDFs that may have changed since
+#[derive(Debug, Clone, Serialize)]
+pub struct FileProvenance {
+    pub sha256: String,
+    pub modified: u64,
+}
+
+pub fn file_provenance(path: &Path) -> Option<FileProvenance> {
+    let sha256 = crate::index::file_sha256(path).ok()?;
+    let modified = crate::index::file_mtime(path)?;
+    Some(FileProvenance { sha256, modified })
+}
+
+fn format_timestamp(secs: u64) -> String {
+    DateTime::from_timestamp(secs as i64, 0)
+        .map(|date| date.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| secs.to_string())
+}
+
+#[derive(Serialize)]
+struct JsonExport<'a> {
+    exported_at: u64,
+    total_matches: usize,
+    documents: Vec<JsonDocument<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonDocument<'a> {
+    #[serde(flatten)]
+    item: &'a LibraryItem,
+    provenance: Option<FileProvenance>,
+    matches: Vec<&'a SearchMatch>,
+}
+
+fn json_document<'a>(group: FileGroup<'a>) -> JsonDocument<'a> {
+    JsonDocument {
+        item: group.item,
+        provenance: file_provenance(Path::new(&group.item.file_path)),
+        matches: group.matches,
+    }
+}
+
+/// Matches grouped by document as JSON, with each source file's provenance
+pub fn export_to_json(matches: &[SearchMatch]) -> Result<String> {
+    let export = JsonExport {
+        exported_at: crate::index::unix_now(),
+        total_matches: matches.len(),
+        documents: group_by_file(matches).into_iter().map(json_document).collect(),
+    };
+    Ok(serde_json::to_string_pretty(&export)?)
+}
+
+/// Options of `export_results`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ExportOptions {
+    // Put a Zotero item's attachments under one heading, and order by citekey
+    pub group_by_citekey: bool,
+    // Cite matches as Pandoc citations, `[@citekey, p. 12]`, where there's a citekey
+    pub pandoc_citations: bool,
+    // Markdown block written per match, with `{placeholder}`s; see `MATCH_TEMPLATE_FIELDS`
+    pub match_template: Option<String>,
+    // Word or LibreOffice document styling DOCX and ODT exports; see `pandoc`
+    pub reference_doc: Option<String>,
+    // Render each match's region of the page as an image beside the export and
+    // embed it, for PDFs whose text extracts badly; see `page_render`
+    pub match_images: bool,
+    // The rendered images, relative to the export's folder
+    #[serde(skip)]
+    pub images: HashMap<MatchKey, String>,
+    // Only the matches from these named corpora; all of them when empty. See `corpora`
+    pub corpora: Vec<String>,
+    // Head the sources of each named corpus with its name, corpora in name order
+    pub group_by_corpus: bool,
+    // How matches are set off in their context; the settings' `export_quote_style`
+    #[serde(skip)]
+    pub quote_style: QuoteStyle,
+}
+
+/// Placeholders a match template can use. `{{` and `}}` write literal braces.
+pub const MATCH_TEMPLATE_FIELDS: &[&str] = &[
+    "quote",
+    "before",
+    "match",
+    "after",
+    "citation",
+    "page",
+    "pdf_page",
+    "page_link",
+    "citekey",
+    "title",
+    "authors",
+    "year",
+    "file_name",
+    "file_path",
+    "zotero_link",
+    "query",
+    "image",
+];
+
+const DEFAULT_MATCH_TEMPLATE: &str = "{image}> {quote}\n>\n> {citation} {page_link}\n\n";
+
+enum TemplatePart {
+    Text(String),
+    Field(String),
+}
+
+// Split a match template into text and placeholders, rejecting unknown placeholders
+fn parse_match_template(template: &str) -> Result<Vec<TemplatePart>> {
+    let invalid = |message: String| AppError::invalid_input(format!("Invalid match template: {}", message));
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(invalid(format!("unclosed {{{}", name)).into()),
+                    }
+                }
+                if !MATCH_TEMPLATE_FIELDS.contains(&name.as_str()) {
+                    return Err(invalid(format!(
+                        "unknown placeholder {{{}}}; available: {}",
+                        name,
+                        MATCH_TEMPLATE_FIELDS.join(", ")
+                    ))
+                    .into());
+                }
+                parts.push(TemplatePart::Text(std::mem::take(&mut text)));
+                parts.push(TemplatePart::Field(name));
+            }
+            '}' => return Err(invalid("unmatched }; write }} for a literal brace".to_string()).into()),
+            c => text.push(c),
+        }
+    }
+    parts.push(TemplatePart::Text(text));
+    Ok(parts)
+}
+
+// A placeholder's value for one match
+fn match_template_field(name: &str, m: &SearchMatch, options: &ExportOptions) -> String {
+    let metadata = m.item.zotero_metadata.as_ref();
+    let page = page_citation(m);
+    match name {
+        "quote" => options.quote_style.frame(&m.context_before, &m.matched_text, &m.context_after),
+        "before" => m.context_before.clone(),
+        "match" => m.matched_text.clone(),
+        "after" => m.context_after.clone(),
+        // Pandoc reads locators in the document's language, so only the plain form
+        // uses the item's own
+        "citation" => match metadata {
+            Some(metadata) if options.pandoc_citations => format!("[@{}, p. {}]", metadata.citekey, page),
+            _ => {
+                let terms = csl_locale::terms_for(metadata.and_then(|metadata| metadata.language.as_deref()));
+                format!("({} {})", terms.page, page)
+            }
+        },
+        "page" => page,
+        "pdf_page" => m.page_number.to_string(),
+        // Opens Zotero's reader at the physical page
+        "page_link" => metadata
+            .and_then(|metadata| metadata.pdf_attachment_key.as_ref())
+            .map(|key| {
+                format!(
+                    "[{}](zotero://open-pdf/library/items/{}?page={})",
+                    i18n::message("export-open-in-zotero", &[]),
+                    key,
+                    m.page_number
+                )
+            })
+            .unwrap_or_default(),
+        "citekey" => metadata.map(|metadata| metadata.citekey.clone()).unwrap_or_default(),
+        "title" => citation_field(&m.item, "title").unwrap_or(&m.item.file_name).to_string(),
+        "authors" | "year" => citation_field(&m.item, name).unwrap_or_default().to_string(),
+        "file_name" => m.item.file_name.clone(),
+        "file_path" => m.item.file_path.clone(),
+        "zotero_link" => m.item.zotero_link.clone().unwrap_or_default(),
+        "query" => m.query.clone().unwrap_or_default(),
+        // A paragraph of its own, or nothing for a match without an image
+        "image" => options
+            .images
+            .get(&match_key(m))
+            .map(|path| format!("![{}, {} {}](<{}>)\n\n", m.item.file_name, i18n::message("export-page", &[]), page_citation(m), path))
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+/// One match rendered through `options.match_template`, or the default block:
+/// the quotation with its citation and a link opening Zotero at the page
+pub fn render_match(m: &SearchMatch, options: &ExportOptions) -> Result<String> {
+    let template = parse_match_template(options.match_template.as_deref().unwrap_or(DEFAULT_MATCH_TEMPLATE))?;
+    Ok(render_match_with(m, &template, options))
+}
+
+fn render_match_with(m: &SearchMatch, template: &[TemplatePart], options: &ExportOptions) -> String {
+    let mut rendered = String::new();
+    for part in template {
+        match part {
+            TemplatePart::Text(text) => rendered.push_str(text),
+            TemplatePart::Field(name) => rendered.push_str(&match_template_field(name, m, options)),
+        }
+    }
+    rendered
+}
+
+/// Markdown for reference managers and note-taking apps: a heading per source
+/// with its authors, year and title, the citekey and a `zotero://` link, then each
+/// match rendered through the match template
+pub fn export_citation_markdown(matches: &[SearchMatch], options: &ExportOptions) -> Result<String> {
+    let template = parse_match_template(options.match_template.as_deref().unwrap_or(DEFAULT_MATCH_TEMPLATE))?;
+
+    let file_groups = group_by_file(matches);
+    let mut sources: Vec<(Option<&ZoteroMetadata>, Vec<&FileGroup>)> = Vec::new();
+    for group in &file_groups {
+        let metadata = group.item.zotero_metadata.as_ref();
+        let existing = sources.iter_mut().find(|(other, _)| {
+            options.group_by_citekey
+                && matches!((metadata, other), (Some(a), Some(b)) if a.citekey == b.citekey)
+        });
+        match existing {
+            Some((_, groups)) => groups.push(group),
+            None => sources.push((metadata, vec![group])),
+        }
+    }
+    if options.group_by_citekey {
+        // Stable, so documents without a citekey keep their path order at the end
+        sources.sort_by_key(|(metadata, _)| metadata.map_or((1, ""), |metadata| (0, metadata.citekey.as_str())));
+    }
+    let corpus_of = |groups: &[&FileGroup]| groups[0].matches[0].corpus.clone();
+    if options.group_by_corpus {
+        // Stable too, with the documents outside every named corpus last
+        sources.sort_by_key(|(_, groups)| corpus_of(groups).map_or((1, String::new()), |corpus| (0, corpus)));
+    }
+
+    let mut markdown = markdown_header(matches.len(), file_groups.len());
+    let mut current_corpus = None;
+    for (metadata, groups) in sources {
+        let item = groups[0].item;
+        if options.group_by_corpus {
+            let corpus = corpus_of(&groups);
+            if current_corpus.as_ref() != Some(&corpus) {
+                let name = corpus.clone().unwrap_or_else(|| i18n::message("export-no-corpus", &[]));
+                markdown.push_str(&format!("\n## {}\n", name));
+                current_corpus = Some(corpus);
+            }
+        }
+        let level = if options.group_by_corpus { "###" } else { "##" };
+        markdown.push_str(&format!("\n{} {}\n\n", level, item_heading(item)));
+        if let Some(metadata) = metadata {
+            markdown.push_str(&format!(
+                "**{}:** @{}  \n[{}]({})\n\n",
+                i18n::message("export-citekey", &[]),
+                metadata.citekey,
+                i18n::message("export-open-in-zotero", &[]),
+                metadata.zotero_link
+            ));
+        }
+        for group in groups {
+            // Tell apart attachments grouped under one item
+            if metadata.is_some() && options.group_by_citekey {
+                markdown.push_str(&format!("**{}:** {}\n\n", i18n::message("export-filename", &[]), group.item.file_name));
+            }
+            for m in &group.matches {
+                markdown.push_str(&render_match_with(m, &template, options));
+            }
+        }
+    }
+
+    Ok(markdown)
+}
+
+/// Every match as a CSV row with its source's citation details and context
+pub fn export_matches_to_csv(matches: &[SearchMatch]) -> String {
+    let header = [
+        "citekey", "authors", "year", "title", "file_name", "file_path", "page", "pdf_page", "query",
+        "context_before", "matched_text", "context_after", "zotero_link", "corpus",
+    ];
+    let mut csv = csv_row(header.into_iter());
+
+    for m in matches {
+        let metadata = m.item.zotero_metadata.as_ref();
+        let page = page_citation(m);
+        let pdf_page = m.page_number.to_string();
+        let row = [
+            metadata.map(|metadata| metadata.citekey.as_str()).unwrap_or_default(),
+            citation_field(&m.item, "authors").unwrap_or_default(),
+            citation_field(&m.item, "year").unwrap_or_default(),
+            citation_field(&m.item, "title").unwrap_or_default(),
+            m.item.file_name.as_str(),
+            m.item.file_path.as_str(),
+            page.as_str(),
+            pdf_page.as_str(),
+            m.query.as_deref().unwrap_or_default(),
+            m.context_before.as_str(),
+            m.matched_text.as_str(),
+            m.context_after.as_str(),
+            m.item.zotero_link.as_deref().unwrap_or_default(),
+            m.corpus.as_deref().unwrap_or_default(),
+        ];
+        csv.push_str(&csv_row(row.into_iter()));
+    }
+
+    csv
+}
+
+/// One BibTeX entry per Zotero item, in document order
+pub fn bibtex_bibliography(groups: &[FileGroup]) -> String {
+    let mut seen = BTreeSet::new();
+    let mut bibliography = String::new();
+
+    for group in groups {
+        let Some(metadata) = group.item.zotero_metadata.as_ref() else {
+            continue;
+        };
+        if !seen.insert(metadata.citekey.as_str()) {
+            continue;
+        }
+
+        bibliography.push_str(&format!("@misc{{{},\n", metadata.citekey));
+        if let Some(ref title) = metadata.title {
+            bibliography.push_str(&format!("  title = {{{}}},\n", title));
+        }
+        if let Some(ref authors) = metadata.authors {
+            let field = if only_editors(metadata) { "editor" } else { "author" };
+            bibliography.push_str(&format!("  {} = {{{}}},\n", field, authors.replace(", ", " and ")));
+        }
+        if let Some(ref year) = metadata.year {
+            bibliography.push_str(&format!("  year = {{{}}},\n", year));
+        }
+        if let Some(ref language) = metadata.language {
+            bibliography.push_str(&format!("  language = {{{}}},\n", language));
+        }
+        bibliography.push_str(&format!("  note = {{{}}},\n", metadata.zotero_link));
+        bibliography.push_str("}\n\n");
+    }
+
+    bibliography
+}
+
+// A file:// URI for a local path, each segment percent-encoded. Windows paths
+// become file:///C:/...
+fn file_uri(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let segments: Vec<String> = path
+        .split('/')
+        .map(|segment| {
+            let drive = segment.len() == 2 && segment.ends_with(':') && segment.as_bytes()[0].is_ascii_alphabetic();
+            if drive { segment.to_string() } else { percent_encode(segment) }
+        })
+        .collect();
+    let path = segments.join("/");
+    if path.starts_with('/') {
+        format!("file://{}", path)
+    } else {
+        format!("file:///{}", path)
+    }
+}
+
+// Stable across exports of the same match, so tools importing twice can tell
+fn annotation_id(m: &SearchMatch) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(m.item.file_path.as_bytes());
+    hasher.update(format!("\0{}\0{:?}\0", m.page_number, m.offset));
+    hasher.update(m.query.as_deref().unwrap_or_default().as_bytes());
+    let digest: String = hasher.finalize().iter().take(16).map(|byte| format!("{:02x}", byte)).collect();
+    format!("urn:pdf-context-search:match:{}", digest)
+}
+
+fn web_annotation(m: &SearchMatch, created: &str) -> serde_json::Value {
+    // The quote as the PDF has it, rather than as normalized for matching. The
+    // context is normalized text, which a viewer wouldn't find on the page, so
+    // there's no prefix or suffix.
+    let exact = m.source_span.as_ref().map_or(m.matched_text.as_str(), |span| span.text.as_str());
+
+    let mut body = Vec::new();
+    if let Some(ref query) = m.query {
+        body.push(json!({ "type": "TextualBody", "value": query, "purpose": "tagging" }));
+    }
+    if let Some(ref zotero_link) = m.item.zotero_link {
+        body.push(json!({ "id": zotero_link, "purpose": "linking" }));
+    }
+
+    json!({
+        "id": annotation_id(m),
+        "type": "Annotation",
+        "motivation": "highlighting",
+        "created": created,
+        "body": body,
+        "target": {
+            "source": file_uri(&m.item.file_path),
+            "format": "application/pdf",
+            // The page (RFC 3778 fragment, from 1), and the quote on it
+            "selector": {
+                "type": "FragmentSelector",
+                "conformsTo": "http://tools.ietf.org/rfc/rfc3778",
+                "value": format!("page={}", m.page_number),
+                "refinedBy": { "type": "TextQuoteSelector", "exact": exact },
+            },
+        },
+    })
+}
+
+/// Matches as a W3C Web Annotation collection: one highlighting annotation per
+/// match, targeting the PDF's page and quoting the matched text, tagged with the
+/// query, for annotation tools such as Hypothesis and Recogito
+pub fn export_web_annotations(matches: &[SearchMatch]) -> Result<String> {
+    let created = DateTime::from_timestamp(crate::index::unix_now() as i64, 0)
+        .map(|date| date.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        .unwrap_or_default();
+    let items: Vec<serde_json::Value> = matches.iter().map(|m| web_annotation(m, &created)).collect();
+    let collection = json!({
+        "@context": "http://www.w3.org/ns/anno.jsonld",
+        "type": "AnnotationCollection",
+        "label": i18n::message("export-title", &[]),
+        "total": items.len(),
+        "first": {
+            "type": "AnnotationPage",
+            "startIndex": 0,
+            "items": items,
+        },
+    });
+    Ok(serde_json::to_string_pretty(&collection)?)
+}
+
+/// Render matches in one of the export formats: "markdown" (citation-aware, see
+/// `export_citation_markdown`), "csv", "json", "bibtex" or "webannotation"
+pub fn export_results(matches: &[SearchMatch], format: &str, options: &ExportOptions) -> Result<String> {
+    match format {
+        "markdown" => export_citation_markdown(matches, options),
+        "csv" => Ok(export_matches_to_csv(matches)),
+        "json" => export_to_json(matches),
+        "bibtex" => Ok(bibtex_bibliography(&group_by_file(matches))),
+        "webannotation" => export_web_annotations(matches),
+        _ => Err(AppError::invalid_input(format!("Unknown export format: {}", format)).into()),
+    }
+}
+
+/// Parameters of `export_selected_documents`
+#[derive(Debug, Clone, Deserialize)]
+pub struct SelectionExportParams {
+    pub search_id: u64,
+    // Documents whose matches are exported; the rest of the search's are left out
+    pub file_paths: Vec<String>,
+    pub output_path: String,
+    // One of the `export_results` formats
+    pub format: String,
+    #[serde(default)]
+    pub options: ExportOptions,
+    pub screening: Option<ScreeningFilter>,
+}
+
+/// The search a selection of its documents was exported from
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHeader<'a> {
+    pub search_id: u64,
+    pub params: &'a SearchParams,
+    // Documents exported, out of the documents the search matched
+    pub documents_selected: usize,
+    pub documents_matched: usize,
+}
+
+/// Put the search a selection came from at the top of an export in `format`, so
+/// the partial export records how its matches were found: YAML front matter for
+/// markdown, a `search` member for the JSON formats, and comment lines for CSV
+/// (`#`) and BibTeX (`%`). The parameters are written as JSON, on one line.
+pub fn with_search_header(exported: String, format: &str, header: &SearchHeader) -> Result<String> {
+    let params = serde_json::to_string(header.params)?;
+    let documents = format!("{} / {}", header.documents_selected, header.documents_matched);
+    let lines = |comment: &str| {
+        format!(
+            "{c} search_id: {}\n{c} documents: {}\n{c} params: {}\n",
+            header.search_id,
+            documents,
+            params,
+            c = comment
+        )
+    };
+    match format {
+        // JSON is also YAML, so the parameters read back as a mapping
+        "markdown" => Ok(format!(
+            "---\nsearch_id: {}\ndocuments: \"{}\"\nparams: {}\n---\n\n{}",
+            header.search_id, documents, params, exported
+        )),
+        "json" | "webannotation" => {
+            let mut value: serde_json::Value = serde_json::from_str(&exported)?;
+            if let Some(object) = value.as_object_mut() {
+                object.insert("search".to_string(), serde_json::to_value(header)?);
+            }
+            Ok(serde_json::to_string_pretty(&value)?)
+        }
+        "csv" => Ok(lines("#") + &exported),
+        "bibtex" => Ok(lines("%") + "\n" + &exported),
+        _ => Err(AppError::invalid_input(format!("Unknown export format: {}", format)).into()),
+    }
+}
+
+/// How much of an export `preview_export` renders
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PreviewOptions {
+    pub max_kb: usize,
+    // Preview a quote style before saving it in the settings; None uses the
+    // settings' `export_quote_style`
+    pub quote_style: Option<QuoteStyle>,
+}
+
+impl Default for PreviewOptions {
+    fn default() -> Self {
+        PreviewOptions {
+            max_kb: 32,
+            quote_style: None,
+        }
+    }
+}
+
+/// The start of an export as it would be written, and the size of the whole
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportPreview {
+    // Cut at `max_kb`, on a character boundary
+    pub text: String,
+    // More of the export follows `text`
+    pub truncated: bool,
+    pub total_matches: usize,
+    pub total_documents: usize,
+    // Documents rendered into the preview, which is all of them for CSV
+    pub documents_rendered: usize,
+}
+
+/// Render the first `max_kb` kilobytes of a "markdown", "json" or "csv" export
+/// without writing anything. Markdown and JSON render one document at a time and
+/// stop once the preview is full, so documents past it aren't read for provenance.
+pub fn preview_export(matches: &[SearchMatch], format: &str, options: &PreviewOptions) -> Result<ExportPreview> {
+    let limit = options.max_kb * 1024;
+    let groups = group_by_file(matches);
+    let total_documents = groups.len();
+    let style = options.quote_style.unwrap_or_default();
+
+    let (mut text, documents_rendered) = match format {
+        "markdown" => {
+            let mut markdown = markdown_header(matches.len(), total_documents);
+            let mut rendered = 0;
+            for group in &groups {
+                if markdown.len() > limit {
+                    break;
+                }
+                markdown.push_str(&render_file_group(group, &style));
+                rendered += 1;
+            }
+            (markdown, rendered)
+        }
+        "json" => {
+            let mut documents = Vec::new();
+            let mut bytes = 0;
+            for group in groups {
+                if bytes > limit {
+                    break;
+                }
+                let document = json_document(group);
+                bytes += serde_json::to_string_pretty(&document)?.len();
+                documents.push(document);
+            }
+            let rendered = documents.len();
+            let export = JsonExport {
+                exported_at: crate::index::unix_now(),
+                total_matches: matches.len(),
+                documents,
+            };
+            (serde_json::to_string_pretty(&export)?, rendered)
+        }
+        "csv" => (export_captures_to_csv(matches), total_documents),
+        _ => return Err(AppError::invalid_input(format!("Unknown export format: {}", format)).into()),
+    };
+
+    let truncated = text.len() > limit || documents_rendered < total_documents;
+    if text.len() > limit {
+        let mut end = limit;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    Ok(ExportPreview {
+        text,
+        truncated,
+        total_matches: matches.len(),
+        total_documents,
+        documents_rendered,
+    })
+}
+
+/// A table of the values captured by regex queries' named groups: one row per
+/// match, one column per group name. Matches without captures are left out.
+pub fn export_captures_to_csv(matches: &[SearchMatch]) -> String {
+    let rows: Vec<&SearchMatch> = matches.iter().filter(|m| !m.captures.is_empty()).collect();
+    let groups: BTreeSet<&str> = rows
+        .iter()
+        .flat_map(|m| m.captures.keys().map(String::as_str))
+        .collect();
+
+    let mut header = vec!["citekey", "file_name", "page", "query", "matched_text"];
+    header.extend(groups.iter().copied());
+    let mut csv = csv_row(header.into_iter());
+
+    for m in rows {
+        let citekey = m.item.zotero_metadata.as_ref().map(|metadata| metadata.citekey.as_str());
+        let page = page_citation(m);
+        let fixed = [
+            citekey.unwrap_or_default(),
+            m.item.file_name.as_str(),
+            page.as_str(),
+            m.query.as_deref().unwrap_or_default(),
+            m.matched_text.as_str(),
+        ];
+        let captured = groups
+            .iter()
+            .map(|group| m.captures.get(*group).map(String::as_str).unwrap_or_default());
+        csv.push_str(&csv_row(fixed.into_iter().chain(captured)));
+    }
+
+    csv
+}
+
+// Documents listed per query in a summary
+const SUMMARY_TOP_DOCUMENTS: usize = 5;
+
+/// How one query fared across a result set
+#[derive(Debug, Clone, Serialize)]
+pub struct QuerySummary {
+    pub query: String,
+    pub documents: usize,
+    pub hits: usize,
+    // Documents with the most hits, most first, named by citekey or file name
+    pub top_documents: Vec<(String, usize)>,
+    // Matched documents per Zotero publication year, oldest first; documents
+    // without a year are counted last, under None
+    pub years: Vec<(Option<String>, usize)>,
+}
+
+// One document's hits for one query
+struct DocumentHits {
+    name: String,
+    year: Option<String>,
+    hits: usize,
+}
+
+/// Per-query totals, top documents and year distribution, with queries in the
+/// order they first appear in the matches
+pub fn summarize_queries(matches: &[SearchMatch]) -> Vec<QuerySummary> {
+    let mut queries: Vec<&str> = Vec::new();
+    let mut by_query: BTreeMap<&str, BTreeMap<&str, DocumentHits>> = BTreeMap::new();
+    for m in matches {
+        let query = m.query.as_deref().unwrap_or_default();
+        if !by_query.contains_key(query) {
+            queries.push(query);
+        }
+        let document = by_query.entry(query).or_default().entry(&m.item.file_path).or_insert_with(|| {
+            let metadata = m.item.zotero_metadata.as_ref();
+            DocumentHits {
+                name: metadata.map_or(m.item.file_name.clone(), |metadata| metadata.citekey.clone()),
+                year: metadata.and_then(|metadata| metadata.year.clone()),
+                hits: 0,
+            }
+        });
+        document.hits += 1;
+    }
+
+    queries
+        .into_iter()
+        .map(|query| {
+            let documents = &by_query[query];
+            let mut year_counts: BTreeMap<Option<&str>, usize> = BTreeMap::new();
+            for document in documents.values() {
+                *year_counts.entry(document.year.as_deref()).or_insert(0) += 1;
+            }
+            let mut years: Vec<(Option<String>, usize)> = year_counts
+                .into_iter()
+                .map(|(year, documents)| (year.map(str::to_string), documents))
+                .collect();
+            years.sort_by_key(|(year, _)| year.is_none());
+            let mut top_documents: Vec<(String, usize)> =
+                documents.values().map(|document| (document.name.clone(), document.hits)).collect();
+            top_documents.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            top_documents.truncate(SUMMARY_TOP_DOCUMENTS);
+
+            QuerySummary {
+                query: query.to_string(),
+                documents: documents.len(),
+                hits: documents.values().map(|document| document.hits).sum(),
+                top_documents,
+                years,
+            }
+        })
+        .collect()
+}
+
+fn format_top_documents(summary: &QuerySummary) -> String {
+    summary
+        .top_documents
+        .iter()
+        .map(|(name, hits)| format!("{} ({})", name, hits))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn format_years(summary: &QuerySummary, no_year: &str) -> String {
+    summary
+        .years
+        .iter()
+        .map(|(year, documents)| format!("{}: {}", year.as_deref().unwrap_or(no_year), documents))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The query summary as a Markdown table, for pasting into notes
+pub fn export_query_summary_markdown(summaries: &[QuerySummary]) -> String {
+    let text = |id: &str| i18n::message(id, &[]);
+    let no_year = text("summary-no-year");
+
+    let mut markdown = format!(
+        "| {} | {} | {} | {} | {} |\n",
+        text("summary-query"),
+        text("summary-documents"),
+        text("summary-hits"),
+        text("summary-top-documents"),
+        text("summary-years")
+    );
+    markdown.push_str("|---|---:|---:|---|---|\n");
+    for summary in summaries {
+        markdown.push_str(&format!(
+            "| `{}` | {} | {} | {} | {} |\n",
+            summary.query.replace('|', "\\|"),
+            summary.documents,
+            summary.hits,
+            format_top_documents(summary).replace('|', "\\|"),
+            format_years(summary, &no_year)
+        ));
+    }
+    markdown
+}
+
+/// The query summary as CSV, one row per query
+pub fn export_query_summary_csv(summaries: &[QuerySummary]) -> String {
+    let mut csv = csv_row(["query", "documents", "hits", "top_documents", "years"].into_iter());
+    for summary in summaries {
+        let documents = summary.documents.to_string();
+        let hits = summary.hits.to_string();
+        let top_documents = format_top_documents(summary);
+        let years = format_years(summary, "n.d.");
+        csv.push_str(&csv_row(
+            [summary.query.as_str(), &documents, &hits, &top_documents, &years].into_iter(),
+        ));
+    }
+    csv
+}
+
+/// The documents that matched one combination of the queries and none of the others
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryOverlap {
+    // "Q1 ∧ Q2 ∧ ¬Q3", with the queries' own text
+    pub label: String,
+    pub matched: Vec<String>,
+    pub unmatched: Vec<String>,
+    pub documents: Vec<OverlapDocument>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OverlapDocument {
+    pub file_path: String,
+    // Citekey, else file name, as in the query summary
+    pub name: String,
+    // Hits for each query in `matched`, in the same order
+    pub hits: Vec<usize>,
+}
+
+/// Group the matched documents by the exact set of queries each matched, for
+/// set algebra over the results. `queries` gives the query order and adds
+/// queries that matched nothing; others follow in the order the matches name
+/// them. Combinations of more queries come first, then those with more documents.
+pub fn query_overlap(matches: &[SearchMatch], queries: &[String]) -> Vec<QueryOverlap> {
+    let mut order: Vec<&str> = queries.iter().map(String::as_str).collect();
+    for m in matches {
+        let query = m.query.as_deref().unwrap_or_default();
+        if !order.contains(&query) {
+            order.push(query);
+        }
+    }
+
+    // document -> (name, hits per query in `order`)
+    let mut documents: BTreeMap<&str, (String, Vec<usize>)> = BTreeMap::new();
+    for m in matches {
+        let (_, hits) = documents.entry(&m.item.file_path).or_insert_with(|| {
+            let name = m
+                .item
+                .zotero_metadata
+                .as_ref()
+                .map_or(m.item.file_name.clone(), |metadata| metadata.citekey.clone());
+            (name, vec![0; order.len()])
+        });
+        let query = m.query.as_deref().unwrap_or_default();
+        if let Some(index) = order.iter().position(|q| *q == query) {
+            hits[index] += 1;
+        }
+    }
+
+    let mut combinations: BTreeMap<Vec<bool>, Vec<OverlapDocument>> = BTreeMap::new();
+    for (file_path, (name, hits)) in documents {
+        let combination: Vec<bool> = hits.iter().map(|hits| *hits > 0).collect();
+        combinations.entry(combination).or_default().push(OverlapDocument {
+            file_path: file_path.to_string(),
+            name,
+            hits: hits.into_iter().filter(|hits| *hits > 0).collect(),
+        });
+    }
+
+    let mut overlaps: Vec<QueryOverlap> = combinations
+        .into_iter()
+        .map(|(combination, documents)| {
+            let (mut matched, mut unmatched) = (Vec::new(), Vec::new());
+            for (query, hit) in order.iter().zip(combination) {
+                if hit {
+                    matched.push(query.to_string());
+                } else {
+                    unmatched.push(query.to_string());
+                }
+            }
+            let label = matched
+                .iter()
+                .cloned()
+                .chain(unmatched.iter().map(|query| format!("¬{}", query)))
+                .collect::<Vec<_>>()
+                .join(" ∧ ");
+            QueryOverlap {
+                label,
+                matched,
+                unmatched,
+                documents,
+            }
+        })
+        .collect();
+    overlaps.sort_by(|a, b| {
+        b.matched
+            .len()
+            .cmp(&a.matched.len())
+            .then_with(|| b.documents.len().cmp(&a.documents.len()))
+            .then_with(|| a.label.cmp(&b.label))
+    });
+    overlaps
+}
+
+// RFC 4180: fields with commas, quotes or line breaks are quoted, quotes doubled
+fn csv_row<'a>(fields: impl Iterator<Item = &'a str>) -> String {
+    let fields: Vec<String> = fields
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect();
+    format!("{}\r\n", fields.join(","))
+}
+
+/// Limits for splitting a large export into several markdown files.
+/// A document's matches are never split across parts.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ChunkOptions {
+    pub max_bytes: Option<usize>,
+    pub max_documents: Option<usize>,
+    // How matches are set off in their context; the settings' `export_quote_style`
+    #[serde(skip)]
+    pub quote_style: QuoteStyle,
+}
+
+// Rendered file groups batched into parts, each within the limits where possible
+fn chunk_file_groups(groups: &[FileGroup], options: &ChunkOptions) -> Vec<Vec<String>> {
+    let mut parts: Vec<Vec<String>> = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    let mut current_bytes = 0;
+
+    for group in groups {
+        let rendered = render_file_group(group, &options.quote_style);
+        let too_big = options
+            .max_bytes
+            .is_some_and(|max| current_bytes + rendered.len() > max);
+        let too_many = options.max_documents.is_some_and(|max| current.len() >= max);
+
+        if !current.is_empty() && (too_big || too_many) {
+            parts.push(std::mem::take(&mut current));
+            current_bytes = 0;
+        }
+        current_bytes += rendered.len();
+        current.push(rendered);
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+
+    parts
+}
+
+/// Write matches as markdown to `output_path`. When the chunk limits split the
+/// export, parts are written next to it as `<name>_001.md`, `<name>_002.md`, ...
+/// and `output_path` becomes an index linking them. Returns every file written.
+pub fn write_markdown_export(
+    output_path: &Path,
+    matches: &[SearchMatch],
+    options: &ChunkOptions,
+) -> Result<Vec<PathBuf>> {
+    let groups = group_by_file(matches);
+    let parts = chunk_file_groups(&groups, options);
+
+    if parts.len() <= 1 {
+        let rendered = parts.concat();
+        fs::write(output_path, export_to_markdown(matches.len(), &rendered))
+            .context(format!("Failed to write export: {}", output_path.display()))?;
+        return Ok(vec![output_path.to_path_buf()]);
+    }
+
+    let stem = output_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let mut index = String::from("# PDF Search Results\n\n");
+    index.push_str(&format!("Total matches found: {}\n\n", matches.len()));
+    index.push_str(&format!("Files with matches: {}\n\n", groups.len()));
+    index.push_str(&format!("Results are split into {} parts:\n\n", parts.len()));
+
+    let mut written = Vec::new();
+    let mut first_document = 0;
+    for (idx, part) in parts.iter().enumerate() {
+        let part_name = format!("{}_{:03}.md", stem, idx + 1);
+        let part_path = output_path.with_file_name(&part_name);
+
+        let mut markdown = format!("# PDF Search Results (part {} of {})\n\n", idx + 1, parts.len());
+        for rendered in part {
+            markdown.push_str(rendered);
+        }
+        fs::write(&part_path, markdown)
+            .context(format!("Failed to write export part: {}", part_path.display()))?;
+
+        let last_document = first_document + part.len();
+        index.push_str(&format!(
+            "- [Part {}]({}): files {}–{}\n",
+            idx + 1,
+            part_name,
+            first_document + 1,
+            last_document
+        ));
+        first_document = last_document;
+        written.push(part_path);
+    }
+
+    fs::write(output_path, index)
+        .context(format!("Failed to write export index: {}", output_path.display()))?;
+    written.insert(0, output_path.to_path_buf());
+    Ok(written)
+}
+
+/// How a result set was produced: what was searched, with which parameters,
+/// and how many documents each stage kept
+#[derive(Clone)]
+pub struct SearchAudit {
+    pub searched_at: u64,
+    pub directories: Vec<String>,
+    pub params: SearchParams,
+    pub stats: SearchStats,
+    pub file_errors: Vec<FileError>,
+    pub partial: bool,
+    // Documents removed from the results after the search
+    pub excluded_documents: Vec<ExcludedDocument>,
+}
+
+/// PRISMA-style audit trail of a search, meant to be filed alongside its results
+pub fn export_audit_report(audit: &SearchAudit) -> String {
+    let stats = &audit.stats;
+    let searched_at = format_timestamp(audit.searched_at);
+    let text = |id: &str| i18n::message(id, &[]);
+
+    let mut markdown = format!("# {}\n\n", text("audit-title"));
+    markdown.push_str(&format!("**{}:** {}\n\n", text("audit-search-date"), searched_at));
+    if audit.partial {
+        markdown.push_str(&format!("**{}:** {}\n\n", text("audit-note"), text("audit-interrupted")));
+    }
+
+    markdown.push_str(&format!("## {}\n\n", text("audit-sources")));
+    for directory in &audit.directories {
+        markdown.push_str(&format!("- `{}`\n", directory));
+    }
+    markdown.push('\n');
+
+    markdown.push_str(&format!("## {}\n\n", text("audit-queries")));
+    markdown.push_str(&format!(
+        "| {} | {} | {} | {} | {} |\n",
+        text("audit-query"),
+        text("audit-type"),
+        text("audit-regex"),
+        text("audit-weight"),
+        text("audit-documents-matched")
+    ));
+    markdown.push_str("|---|---|---|---|---|\n");
+    for query in &audit.params.queries {
+        let documents = stats
+            .query_document_counts
+            .iter()
+            .find(|count| count.query == query.query)
+            .map_or("—".to_string(), |count| count.documents.to_string());
+        markdown.push_str(&format!(
+            "| `{}` | {} | {} | {} | {} |\n",
+            query.query.replace('|', "\\|"),
+            query.query_type,
+            if query.use_regex { text("audit-yes") } else { text("audit-no") },
+            query.weight,
+            documents
+        ));
+    }
+    markdown.push('\n');
+
+    markdown.push_str(&format!("## {}\n\n", text("audit-parameters")));
+    markdown.push_str("```json\n");
+    markdown.push_str(&serde_json::to_string_pretty(&audit.params).unwrap_or_default());
+    markdown.push_str("\n```\n\n");
+
+    markdown.push_str(&format!("## {}\n\n", text("audit-document-flow")));
+    let flow = [
+        ("audit-files-found", stats.files_found),
+        ("audit-excluded-modified-date", stats.excluded_by_modified_date),
+        ("audit-excluded-date-added", stats.excluded_by_date_added),
+        ("audit-excluded-screening", stats.excluded_by_screening),
+        ("audit-excluded-subset", stats.excluded_by_subset),
+        ("audit-excluded-native-index", stats.excluded_by_native_index),
+        ("audit-excluded-reviewer", stats.excluded_by_reviewer),
+        ("audit-collapsed-copies", stats.collapsed_copies),
+        ("audit-files-searched", stats.files_searched),
+        ("audit-excluded-filter-queries", stats.excluded_by_filter_queries),
+        ("audit-excluded-min-matches", stats.excluded_by_min_matches),
+        ("audit-files-with-errors", stats.files_with_errors),
+        ("audit-files-with-matches", stats.files_with_matches),
+    ];
+    for (id, count) in flow {
+        markdown.push_str(&format!("- {}: {}\n", text(id), count));
+    }
+    if !audit.excluded_documents.is_empty() {
+        markdown.push_str(&format!(
+            "- {}: {}\n",
+            text("audit-removed-by-reviewer"),
+            audit.excluded_documents.len()
+        ));
+    }
+    markdown.push('\n');
+
+    let reviewer_exclusions: Vec<&ExcludedDocument> = audit
+        .params
+        .excluded_documents
+        .iter()
+        .chain(&audit.excluded_documents)
+        .collect();
+    if !reviewer_exclusions.is_empty() {
+        markdown.push_str(&format!("## {}\n\n", text("audit-reviewer-exclusions")));
+        for excluded in reviewer_exclusions {
+            markdown.push_str(&format!(
+                "- `{}` ({}): {}",
+                excluded.file_path,
+                format_timestamp(excluded.excluded_at),
+                excluded.reason
+            ));
+            if excluded.matches_removed > 0 {
+                markdown.push_str(&format!(
+                    " ({})",
+                    i18n::message("audit-matches-removed", &[("count", excluded.matches_removed.into())])
+                ));
+            }
+            markdown.push('\n');
+        }
+        markdown.push('\n');
+    }
+
+    if !audit.file_errors.is_empty() {
+        markdown.push_str(&format!("## {}\n\n", text("audit-not-searched")));
+        for file_error in &audit.file_errors {
+            markdown.push_str(&format!("- `{}`: {}\n", file_error.file_path, file_error.error));
+        }
+        markdown.push('\n');
+    }
+
+    markdown
+}
@@ -0,0 +1,239 @@
+use crate::layout::{self, PageLayout};
+use crate::pdf_metadata;
+use lopdf::Document;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::sync::OnceLock;
+
+// Documents outside Zotero are otherwise cited by their file name, which for
+// downloads is often "1-s2.0-S0048733319301799-main.pdf". Most papers say what
+// they are on their first page: the title in the largest type, the authors on
+// the line below it, the year in the citation line or copyright notice. This
+// reads that back as a best guess, falling back to the Info dictionary, for
+// exports to cite the document by.
+
+// Lines of page 1 looked at for the title, so a large drop cap or figure label
+// further down doesn't take its place
+const TITLE_LINES: usize = 40;
+const MAX_TITLE_LINES: usize = 3;
+// Glyph heights within this many points count as the same type size
+const SIZE_TOLERANCE: f64 = 0.5;
+const ABSTRACT_CHARS: usize = 1500;
+
+/// A best guess at a document's title, authors and year, read from the document
+/// itself for exports to cite it by when it has no Zotero item
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FallbackMetadata {
+    pub title: Option<String>,
+    pub authors: Option<String>,
+    pub year: Option<String>,
+    #[serde(rename = "abstract")]
+    pub abstract_text: Option<String>,
+    // Where the title came from: "font_size" (page 1's largest type), "info"
+    // (the Info dictionary) or "first_line" (page 1's first line of several words)
+    pub title_source: Option<String>,
+}
+
+impl FallbackMetadata {
+    fn is_empty(&self) -> bool {
+        self.title.is_none() && self.authors.is_none() && self.year.is_none()
+    }
+}
+
+// A line of page 1 and the size of its largest type
+struct Line {
+    text: String,
+    size: f64,
+}
+
+/// Guess from the document: page 1's layout, then its Info dictionary. None when
+/// nothing better than the file name turns up.
+pub fn guess(doc: &Document) -> Option<FallbackMetadata> {
+    let info = pdf_metadata::read_info(doc);
+    let first_page = doc.get_pages().values().next().copied()?;
+    let layout = layout::page_layout(doc, first_page)?;
+    let lines = sized_lines(&layout);
+
+    let mut metadata = FallbackMetadata::default();
+    if let Some((title, end)) = title_by_size(&lines) {
+        metadata.title = Some(title);
+        metadata.title_source = Some("font_size".to_string());
+        metadata.authors = lines[end..]
+            .iter()
+            .map(|line| line.text.as_str())
+            .find(|line| !line.trim().is_empty())
+            .and_then(authors_line);
+    }
+    fill_from_text(&mut metadata, info.title.as_deref(), info.author.as_deref(), &layout.text);
+    (!metadata.is_empty()).then_some(metadata)
+}
+
+/// Guess from page 1's text alone, as for a document whose text is cached but
+/// whose file isn't there to lay out
+pub fn guess_from_text(info_title: Option<&str>, first_page: &str) -> Option<FallbackMetadata> {
+    let mut metadata = FallbackMetadata::default();
+    fill_from_text(&mut metadata, info_title, None, first_page);
+    (!metadata.is_empty()).then_some(metadata)
+}
+
+fn fill_from_text(metadata: &mut FallbackMetadata, info_title: Option<&str>, info_author: Option<&str>, text: &str) {
+    if metadata.title.is_none() {
+        if let Some(title) = info_title.filter(|title| plausible_title(title)) {
+            metadata.title = Some(title.trim().to_string());
+            metadata.title_source = Some("info".to_string());
+        } else {
+            let lines: Vec<&str> = text.lines().map(str::trim).take(TITLE_LINES).collect();
+            if let Some(position) = lines
+                .iter()
+                .position(|line| line.split_whitespace().count() >= 3 && plausible_title(line))
+            {
+                metadata.title = Some(lines[position].to_string());
+                metadata.title_source = Some("first_line".to_string());
+                metadata.authors = lines[position + 1..]
+                    .iter()
+                    .find(|line| !line.is_empty())
+                    .and_then(|line| authors_line(line));
+            }
+        }
+    }
+    if metadata.authors.is_none() {
+        metadata.authors = info_author.map(str::trim).filter(|author| !author.is_empty()).map(str::to_string);
+    }
+    metadata.year = first_year(text);
+    metadata.abstract_text = abstract_text(text);
+}
+
+// Page 1's lines, each sized by its tallest glyph, which the layout estimates
+// from the font size
+fn sized_lines(layout: &PageLayout) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for text in layout.text.split('\n') {
+        let range = start..start + text.len();
+        start = range.end + 1;
+        lines.push(Line {
+            text: text.trim().to_string(),
+            size: line_size(layout, &range),
+        });
+    }
+    lines
+}
+
+fn line_size(layout: &PageLayout, range: &Range<usize>) -> f64 {
+    let first = layout.glyphs.partition_point(|glyph| glyph.range.start < range.start);
+    layout.glyphs[first..]
+        .iter()
+        .take_while(|glyph| glyph.range.end <= range.end)
+        .map(|glyph| glyph.rect.y1 - glyph.rect.y0)
+        .fold(0.0, f64::max)
+}
+
+// The first run of lines in page 1's largest type, and where it ends
+fn title_by_size(lines: &[Line]) -> Option<(String, usize)> {
+    let candidate = |line: &Line| line.text.chars().filter(|c| c.is_alphabetic()).count() >= 4;
+    let largest = lines
+        .iter()
+        .take(TITLE_LINES)
+        .filter(|line| candidate(line))
+        .map(|line| line.size)
+        .fold(0.0, f64::max);
+    if largest <= 0.0 {
+        return None;
+    }
+    let is_title = |line: &Line| candidate(line) && (line.size - largest).abs() <= SIZE_TOLERANCE;
+    let start = lines.iter().take(TITLE_LINES).position(is_title)?;
+    let end = start
+        + lines[start..]
+            .iter()
+            .take(MAX_TITLE_LINES)
+            .take_while(|line| is_title(line))
+            .count();
+    let title = lines[start..end].iter().map(|line| line.text.as_str()).collect::<Vec<_>>().join(" ");
+    plausible_title(&title).then_some((title, end))
+}
+
+// Rules out the titles authoring tools fill in for themselves
+fn plausible_title(title: &str) -> bool {
+    let title = title.trim();
+    let lower = title.to_lowercase();
+    let tool_generated = [".pdf", ".doc", ".docx", ".tex", ".dvi", ".indd"]
+        .iter()
+        .any(|extension| lower.ends_with(extension))
+        || lower.starts_with("microsoft word")
+        || lower == "untitled"
+        || lower.starts_with("untitled ");
+    !tool_generated && title.chars().filter(|c| c.is_alphabetic()).count() >= 4 && title.chars().count() <= 300
+}
+
+// The line under the title when it reads as a list of names: a few words, most
+// capitalized, without the words of affiliations and addresses. Footnote marks
+// after the names are dropped.
+fn authors_line(line: &str) -> Option<String> {
+    let cleaned: String = line
+        .chars()
+        .filter(|c| !c.is_ascii_digit() && !matches!(c, '*' | '†' | '‡' | '§'))
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    let lower = cleaned.to_lowercase();
+    let not_names = ["abstract", "university", "department", "institute", "journal", "doi", "http", "@", "received"];
+    if cleaned.is_empty() || cleaned.chars().count() > 200 || not_names.iter().any(|word| lower.contains(word)) {
+        return None;
+    }
+    let words: Vec<&str> = cleaned
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|word| word.chars().any(char::is_alphabetic))
+        .collect();
+    let capitalized = words
+        .iter()
+        .filter(|word| word.chars().next().is_some_and(char::is_uppercase))
+        .count();
+    let names = (2..=24).contains(&words.len()) && capitalized * 10 >= words.len() * 7;
+    names.then_some(cleaned)
+}
+
+fn first_year(text: &str) -> Option<String> {
+    static YEAR: OnceLock<Regex> = OnceLock::new();
+    let year = YEAR.get_or_init(|| Regex::new(r"\b(19[5-9]\d|20\d\d)\b").unwrap());
+    let current = chrono::Utc::now().format("%Y").to_string();
+    year.find_iter(text)
+        .map(|year| year.as_str())
+        .find(|year| *year <= current.as_str())
+        .map(str::to_string)
+}
+
+// The paragraph after an "Abstract" heading on page 1, up to the keywords or the
+// introduction
+fn abstract_text(text: &str) -> Option<String> {
+    let mut lines = text.lines().map(str::trim);
+    // "Abstract", "ABSTRACT:", "Abstract. We ...", but not "Abstract syntax ..."
+    let after_heading = |line: &str| {
+        let rest = line.get("abstract".len()..).filter(|_| line.to_lowercase().starts_with("abstract"))?;
+        let heading = rest.is_empty()
+            || rest.starts_with([':', '.', '—', '–', '-'])
+            || line.starts_with("ABSTRACT ");
+        heading.then(|| rest.trim_start_matches([':', '.', '—', '–', '-', ' ']).to_string())
+    };
+    let mut abstract_text = lines.by_ref().find_map(after_heading)?;
+    for line in lines {
+        let lower = line.to_lowercase();
+        let ends = ["keywords", "key words", "introduction", "1 introduction", "1. introduction"]
+            .iter()
+            .any(|start| lower.starts_with(start));
+        if ends || (line.is_empty() && !abstract_text.is_empty()) || abstract_text.len() >= ABSTRACT_CHARS {
+            break;
+        }
+        if !abstract_text.is_empty() {
+            abstract_text.push(' ');
+        }
+        abstract_text.push_str(line);
+    }
+    let abstract_text = abstract_text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if abstract_text.is_empty() {
+        return None;
+    }
+    Some(match abstract_text.char_indices().nth(ABSTRACT_CHARS) {
+        Some((cut, _)) => format!("{}…", abstract_text[..cut].trim_end()),
+        None => abstract_text,
+    })
+}
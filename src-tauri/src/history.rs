@@ -0,0 +1,173 @@
+use crate::pdf_search::{SearchMatch, SearchParams};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HISTORY_FILE: &str = "search_history.json";
+const MAX_HISTORY_ENTRIES: usize = 1000;
+
+// Serializes read-modify-write of the history file across windows
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHistoryEntry {
+    pub timestamp: u64,
+    pub directory: String,
+    pub queries: Vec<HistoryQuery>,
+    pub result_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryQuery {
+    pub query: String,
+    pub use_regex: bool,
+    pub query_type: String,
+    // Matches produced by this query; None for filter queries, which don't produce matches
+    pub hits: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsageCount {
+    pub value: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ZeroHitQuery {
+    pub query: String,
+    pub searches: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchHistoryStats {
+    pub total_searches: usize,
+    pub top_queries: Vec<UsageCount>,
+    pub top_directories: Vec<UsageCount>,
+    pub average_result_count: f64,
+    // Queries that have never produced a match in any search they were part of
+    pub zero_hit_queries: Vec<ZeroHitQuery>,
+}
+
+pub fn load_history(data_dir: &Path) -> Result<Vec<SearchHistoryEntry>> {
+    let path = data_dir.join(HISTORY_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let contents = fs::read_to_string(&path)
+        .context(format!("Failed to read search history: {}", path.display()))?;
+    serde_json::from_str(&contents)
+        .context(format!("Failed to parse search history: {}", path.display()))
+}
+
+/// Matches per query, as `record_search` takes them
+pub fn query_hits(matches: &[SearchMatch]) -> HashMap<String, usize> {
+    let mut hits = HashMap::new();
+    for query in matches.iter().filter_map(|m| m.query.as_ref()) {
+        *hits.entry(query.clone()).or_default() += 1;
+    }
+    hits
+}
+
+/// Append a completed search to the history, keeping only the most recent entries
+pub fn record_search(data_dir: &Path, params: &SearchParams, hits: &HashMap<String, usize>) -> Result<()> {
+    let _guard = HISTORY_LOCK.lock().unwrap();
+
+    let queries = params
+        .queries
+        .iter()
+        .map(|q| HistoryQuery {
+            query: q.query.clone(),
+            use_regex: q.use_regex,
+            query_type: q.query_type.clone(),
+            hits: if q.query_type == "filter" {
+                None
+            } else {
+                Some(hits.get(&q.query).copied().unwrap_or(0))
+            },
+        })
+        .collect();
+
+    let mut history = load_history(data_dir)?;
+    history.push(SearchHistoryEntry {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        directory: params.directory.clone(),
+        queries,
+        result_count: hits.values().sum(),
+    });
+
+    if history.len() > MAX_HISTORY_ENTRIES {
+        history.drain(..history.len() - MAX_HISTORY_ENTRIES);
+    }
+
+    fs::create_dir_all(data_dir)
+        .context(format!("Failed to create data directory: {}", data_dir.display()))?;
+    let path = data_dir.join(HISTORY_FILE);
+    fs::write(&path, serde_json::to_string(&history)?)
+        .context(format!("Failed to write search history: {}", path.display()))
+}
+
+pub fn history_stats(history: &[SearchHistoryEntry], top_n: usize) -> SearchHistoryStats {
+    let mut query_counts: HashMap<&str, usize> = HashMap::new();
+    let mut directory_counts: HashMap<&str, usize> = HashMap::new();
+    // query -> (searches that produced hit counts, total hits)
+    let mut query_hits: HashMap<&str, (usize, usize)> = HashMap::new();
+
+    for entry in history {
+        *directory_counts.entry(&entry.directory).or_default() += 1;
+
+        for q in &entry.queries {
+            *query_counts.entry(&q.query).or_default() += 1;
+
+            if let Some(hits) = q.hits {
+                let stats = query_hits.entry(&q.query).or_default();
+                stats.0 += 1;
+                stats.1 += hits;
+            }
+        }
+    }
+
+    let mut zero_hit_queries: Vec<ZeroHitQuery> = query_hits
+        .into_iter()
+        .filter(|(_, (_, total_hits))| *total_hits == 0)
+        .map(|(query, (searches, _))| ZeroHitQuery {
+            query: query.to_string(),
+            searches,
+        })
+        .collect();
+    zero_hit_queries.sort_by(|a, b| b.searches.cmp(&a.searches).then_with(|| a.query.cmp(&b.query)));
+
+    let average_result_count = if history.is_empty() {
+        0.0
+    } else {
+        history.iter().map(|e| e.result_count).sum::<usize>() as f64 / history.len() as f64
+    };
+
+    SearchHistoryStats {
+        total_searches: history.len(),
+        top_queries: top_counts(query_counts, top_n),
+        top_directories: top_counts(directory_counts, top_n),
+        average_result_count,
+        zero_hit_queries,
+    }
+}
+
+fn top_counts(counts: HashMap<&str, usize>, top_n: usize) -> Vec<UsageCount> {
+    let mut counts: Vec<UsageCount> = counts
+        .into_iter()
+        .map(|(value, count)| UsageCount {
+            value: value.to_string(),
+            count,
+        })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    counts.truncate(top_n);
+    counts
+}
@@ -0,0 +1,90 @@
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use std::sync::{OnceLock, RwLock};
+use unic_langid::LanguageIdentifier;
+
+// Errors, diagnostics and export boilerplate come from these Fluent catalogs,
+// compiled into the binary. English is complete; a message missing from another
+// catalog falls back to it.
+
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+    ("es", include_str!("../locales/es.ftl")),
+];
+
+static BUNDLES: OnceLock<Vec<(&'static str, FluentBundle<FluentResource>)>> = OnceLock::new();
+static LANGUAGE: RwLock<String> = RwLock::new(String::new());
+
+fn bundles() -> &'static [(&'static str, FluentBundle<FluentResource>)] {
+    BUNDLES.get_or_init(|| {
+        CATALOGS
+            .iter()
+            .map(|(language, source)| {
+                let id: LanguageIdentifier = language.parse().expect("catalog languages are valid identifiers");
+                let resource = FluentResource::try_new(source.to_string())
+                    .unwrap_or_else(|(resource, errors)| {
+                        eprintln!("Warning: Errors in the {} message catalog: {:?}", language, errors);
+                        resource
+                    });
+                let mut bundle = FluentBundle::new_concurrent(vec![id]);
+                // Isolation marks around arguments would end up in exported files and paths
+                bundle.set_use_isolating(false);
+                bundle
+                    .add_resource(resource)
+                    .expect("catalogs don't define a message twice");
+                (*language, bundle)
+            })
+            .collect()
+    })
+}
+
+/// Languages that have a catalog
+pub fn available_languages() -> Vec<&'static str> {
+    CATALOGS.iter().map(|(language, _)| *language).collect()
+}
+
+/// Switch the language messages are produced in. Regional variants use their
+/// base language ("de-AT" is German); unknown languages get English.
+pub fn set_language(language: &str) {
+    let base = language.split(['-', '_']).next().unwrap_or_default().to_lowercase();
+    let language = if CATALOGS.iter().any(|(catalog, _)| *catalog == base) {
+        base
+    } else {
+        DEFAULT_LANGUAGE.to_string()
+    };
+    *LANGUAGE.write().unwrap() = language;
+}
+
+/// The language messages are currently produced in
+pub fn language() -> String {
+    let language = LANGUAGE.read().unwrap();
+    if language.is_empty() {
+        DEFAULT_LANGUAGE.to_string()
+    } else {
+        language.clone()
+    }
+}
+
+/// The message `id` in the current language, with `args` substituted
+pub fn message(id: &str, args: &[(&str, FluentValue)]) -> String {
+    let mut fluent_args = FluentArgs::new();
+    for (name, value) in args {
+        fluent_args.set(*name, value.clone());
+    }
+
+    let language = LANGUAGE.read().unwrap().clone();
+    for candidate in [language.as_str(), DEFAULT_LANGUAGE] {
+        let Some((_, bundle)) = bundles().iter().find(|(catalog, _)| *catalog == candidate) else {
+            continue;
+        };
+        let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) else {
+            continue;
+        };
+        let mut errors = Vec::new();
+        return bundle.format_pattern(pattern, Some(&fluent_args), &mut errors).into_owned();
+    }
+    id.to_string()
+}
@@ -0,0 +1,726 @@
+use crate::structure::Block;
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const INDEX_FILE: &str = "index.sqlite";
+
+/// Persistent catalog of every PDF the app has encountered
+pub struct Index {
+    conn: Mutex<Connection>,
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn file_mtime(path: &Path) -> Option<u64> {
+    path.metadata()
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
+/// SHA-256 of a file's contents as lowercase hex
+pub fn file_sha256(path: &Path) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+impl Index {
+    pub fn open(data_dir: &Path) -> Result<Index> {
+        std::fs::create_dir_all(data_dir)
+            .context(format!("Failed to create data directory: {}", data_dir.display()))?;
+        let db_path = data_dir.join(INDEX_FILE);
+        let conn = Connection::open(&db_path)
+            .context(format!("Failed to open index: {}", db_path.display()))?;
+
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS files (
+                path TEXT PRIMARY KEY,
+                size INTEGER NOT NULL,
+                mtime INTEGER NOT NULL,
+                first_seen INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS saved_searches (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                params TEXT NOT NULL,
+                created INTEGER NOT NULL,
+                last_run INTEGER,
+                snapshot TEXT
+            );
+            CREATE TABLE IF NOT EXISTS screening (
+                review TEXT NOT NULL,
+                path TEXT NOT NULL,
+                status TEXT NOT NULL,
+                reason TEXT,
+                updated INTEGER NOT NULL,
+                PRIMARY KEY (review, path)
+            );
+            CREATE TABLE IF NOT EXISTS extracted_files (
+                path TEXT PRIMARY KEY,
+                size INTEGER NOT NULL,
+                mtime INTEGER NOT NULL,
+                sha256 TEXT NOT NULL,
+                title TEXT,
+                ocr INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE TABLE IF NOT EXISTS extracted_pages (
+                path TEXT NOT NULL,
+                page_number INTEGER NOT NULL,
+                label TEXT,
+                text TEXT NOT NULL,
+                ocr INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (path, page_number)
+            );
+            CREATE TABLE IF NOT EXISTS corpus_subsets (
+                name TEXT PRIMARY KEY,
+                created INTEGER NOT NULL,
+                search_id INTEGER NOT NULL,
+                queries TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS corpus_subset_files (
+                name TEXT NOT NULL,
+                path TEXT NOT NULL,
+                PRIMARY KEY (name, path)
+            );
+            CREATE TABLE IF NOT EXISTS fallback_metadata (
+                path TEXT PRIMARY KEY,
+                mtime INTEGER NOT NULL,
+                metadata TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS zotero_snapshots (
+                root TEXT PRIMARY KEY,
+                metadata TEXT NOT NULL
+            );
+            CREATE VIRTUAL TABLE IF NOT EXISTS term_index USING fts5(
+                text, content='', detail=none, tokenize='unicode61 remove_diacritics 2'
+            );
+            CREATE VIRTUAL TABLE IF NOT EXISTS term_vocabulary USING fts5vocab(term_index, 'row');
+            CREATE TABLE IF NOT EXISTS term_index_rows (
+                path TEXT PRIMARY KEY,
+                row INTEGER NOT NULL
+            );",
+        )
+        .context("Failed to initialize index schema")?;
+        // Indexes created before OCR was added
+        add_column(&conn, "extracted_files", "ocr", "INTEGER NOT NULL DEFAULT 0")?;
+        add_column(&conn, "extracted_pages", "ocr", "INTEGER NOT NULL DEFAULT 0")?;
+        // and before tagged PDFs' structure was read
+        add_column(&conn, "extracted_pages", "blocks", "TEXT")?;
+
+        let index = Index {
+            conn: Mutex::new(conn),
+        };
+        index.backfill_term_index().context("Failed to build the term index")?;
+        Ok(index)
+    }
+
+    /// Record files in the catalog, returning when each was first seen. Called
+    /// whenever a directory is walked: when it's indexed, listed or searched.
+    /// Files the index hasn't encountered before are stamped with the current time.
+    pub fn register_files(&self, files: &[PathBuf]) -> Result<HashMap<PathBuf, u64>> {
+        let now = unix_now();
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut first_seen = HashMap::new();
+
+        {
+            let mut select = tx.prepare("SELECT first_seen FROM files WHERE path = ?")?;
+            let mut upsert = tx.prepare(
+                "INSERT INTO files (path, size, mtime, first_seen) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(path) DO UPDATE SET size = excluded.size, mtime = excluded.mtime",
+            )?;
+
+            for path in files {
+                let path_str = path.to_string_lossy();
+                let seen: u64 = select
+                    .query_row([path_str.as_ref()], |row| row.get::<_, i64>(0))
+                    .map(|t| t as u64)
+                    .unwrap_or(now);
+
+                let size = path.metadata().map(|m| m.len()).unwrap_or(0);
+                let mtime = file_mtime(path).unwrap_or(0);
+                upsert.execute(params![path_str.as_ref(), size as i64, mtime as i64, seen as i64])?;
+
+                first_seen.insert(path.clone(), seen);
+            }
+        }
+
+        tx.commit()?;
+        Ok(first_seen)
+    }
+}
+
+/// A search definition stored for re-running, with the results of its last run
+pub struct SavedSearchRow {
+    pub id: i64,
+    pub name: String,
+    // SearchParams as JSON
+    pub params: String,
+    pub created: u64,
+    pub last_run: Option<u64>,
+    // Snapshot of the last run's results as JSON
+    pub snapshot: Option<String>,
+}
+
+impl Index {
+    pub fn save_search(&self, name: &str, params_json: &str) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO saved_searches (name, params, created) VALUES (?1, ?2, ?3)",
+            params![name, params_json, unix_now() as i64],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn saved_searches(&self) -> Result<Vec<SavedSearchRow>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, params, created, last_run, snapshot FROM saved_searches ORDER BY id",
+        )?;
+        let rows = stmt.query_map([], saved_search_from_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn saved_search(&self, id: i64) -> Result<Option<SavedSearchRow>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, params, created, last_run, snapshot FROM saved_searches WHERE id = ?",
+        )?;
+        let mut rows = stmt.query_map([id], saved_search_from_row)?;
+        Ok(rows.next().transpose()?)
+    }
+
+    pub fn record_search_run(&self, id: i64, snapshot_json: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE saved_searches SET last_run = ?1, snapshot = ?2 WHERE id = ?3",
+            params![unix_now() as i64, snapshot_json, id],
+        )?;
+        Ok(())
+    }
+}
+
+fn saved_search_from_row(row: &rusqlite::Row) -> rusqlite::Result<SavedSearchRow> {
+    Ok(SavedSearchRow {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        params: row.get(2)?,
+        created: row.get::<_, i64>(3)? as u64,
+        last_run: row.get::<_, Option<i64>>(4)?.map(|t| t as u64),
+        snapshot: row.get(5)?,
+    })
+}
+
+/// A screening decision on one document within a named review
+pub struct ScreeningRow {
+    pub path: String,
+    pub status: String,
+    pub reason: Option<String>,
+    pub updated: u64,
+}
+
+impl Index {
+    pub fn set_screening(&self, review: &str, path: &str, status: &str, reason: Option<&str>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO screening (review, path, status, reason, updated) VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(review, path) DO UPDATE SET status = excluded.status, reason = excluded.reason, updated = excluded.updated",
+            params![review, path, status, reason, unix_now() as i64],
+        )?;
+        Ok(())
+    }
+
+    pub fn clear_screening(&self, review: &str, path: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM screening WHERE review = ?1 AND path = ?2", params![review, path])?;
+        Ok(())
+    }
+
+    pub fn screening(&self, review: &str) -> Result<Vec<ScreeningRow>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT path, status, reason, updated FROM screening WHERE review = ? ORDER BY path",
+        )?;
+        let rows = stmt.query_map([review], |row| {
+            Ok(ScreeningRow {
+                path: row.get(0)?,
+                status: row.get(1)?,
+                reason: row.get(2)?,
+                updated: row.get::<_, i64>(3)? as u64,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+/// A named set of documents, kept from a result set to search within later
+pub struct SubsetRow {
+    pub name: String,
+    pub created: u64,
+    // The search whose results it was made from
+    pub search_id: u64,
+    // That search's queries as a JSON array
+    pub queries: String,
+    pub documents: usize,
+}
+
+impl Index {
+    /// Store a subset under `name`, replacing any subset of that name
+    pub fn save_subset(&self, name: &str, search_id: u64, queries_json: &str, paths: &[String]) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM corpus_subset_files WHERE name = ?", [name])?;
+        tx.execute(
+            "INSERT OR REPLACE INTO corpus_subsets (name, created, search_id, queries) VALUES (?1, ?2, ?3, ?4)",
+            params![name, unix_now() as i64, search_id as i64, queries_json],
+        )?;
+        {
+            let mut insert = tx.prepare("INSERT OR IGNORE INTO corpus_subset_files (name, path) VALUES (?1, ?2)")?;
+            for path in paths {
+                insert.execute(params![name, path])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn subsets(&self) -> Result<Vec<SubsetRow>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT name, created, search_id, queries,
+                (SELECT COUNT(*) FROM corpus_subset_files WHERE corpus_subset_files.name = corpus_subsets.name)
+             FROM corpus_subsets ORDER BY name",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(SubsetRow {
+                name: row.get(0)?,
+                created: row.get::<_, i64>(1)? as u64,
+                search_id: row.get::<_, i64>(2)? as u64,
+                queries: row.get(3)?,
+                documents: row.get::<_, i64>(4)? as usize,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// The documents of a subset, or None when there's no subset of that name
+    pub fn subset_paths(&self, name: &str) -> Result<Option<HashSet<String>>> {
+        let conn = self.conn.lock().unwrap();
+        let exists = conn
+            .query_row("SELECT 1 FROM corpus_subsets WHERE name = ?", [name], |_| Ok(()))
+            .optional()?;
+        if exists.is_none() {
+            return Ok(None);
+        }
+        let mut stmt = conn.prepare("SELECT path FROM corpus_subset_files WHERE name = ?")?;
+        let rows = stmt.query_map([name], |row| row.get(0))?;
+        Ok(Some(rows.collect::<rusqlite::Result<_>>()?))
+    }
+
+    pub fn delete_subset(&self, name: &str) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM corpus_subset_files WHERE name = ?", [name])?;
+        tx.execute("DELETE FROM corpus_subsets WHERE name = ?", [name])?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+/// Text extracted from a PDF, cached so unchanged files aren't extracted again
+pub struct CachedText {
+    pub size: u64,
+    pub mtime: u64,
+    pub sha256: String,
+    pub title: Option<String>,
+    // Pages without a text layer were OCR'd
+    pub ocr: bool,
+    pub pages: Vec<CachedPage>,
+}
+
+pub struct CachedPage {
+    pub page_number: usize,
+    pub label: Option<String>,
+    pub text: String,
+    // The text was recognized from the page image
+    pub ocr: bool,
+    // Blocks of a tagged page, from its structure tree
+    pub blocks: Option<Vec<Block>>,
+}
+
+impl Index {
+    pub fn cached_text(&self, path: &str) -> Result<Option<CachedText>> {
+        let conn = self.conn.lock().unwrap();
+        let file = conn
+            .query_row(
+                "SELECT size, mtime, sha256, title, ocr FROM extracted_files WHERE path = ?",
+                [path],
+                |row| {
+                    Ok(CachedText {
+                        size: row.get::<_, i64>(0)? as u64,
+                        mtime: row.get::<_, i64>(1)? as u64,
+                        sha256: row.get(2)?,
+                        title: row.get(3)?,
+                        ocr: row.get(4)?,
+                        pages: Vec::new(),
+                    })
+                },
+            )
+            .optional()?;
+        let Some(mut file) = file else {
+            return Ok(None);
+        };
+
+        let mut stmt = conn.prepare(
+            "SELECT page_number, label, text, ocr, blocks FROM extracted_pages WHERE path = ? ORDER BY page_number",
+        )?;
+        let pages = stmt.query_map([path], |row| {
+            Ok(CachedPage {
+                page_number: row.get::<_, i64>(0)? as usize,
+                label: row.get(1)?,
+                text: row.get(2)?,
+                ocr: row.get(3)?,
+                // Blocks that don't parse leave the page unstructured rather than failing the read
+                blocks: row
+                    .get::<_, Option<String>>(4)?
+                    .and_then(|blocks| serde_json::from_str(&blocks).ok()),
+            })
+        })?;
+        file.pages = pages.collect::<rusqlite::Result<_>>()?;
+        Ok(Some(file))
+    }
+
+    /// Replace the cached text of a file
+    pub fn store_text(&self, path: &str, cached: &CachedText) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        forget_text(&tx, path)?;
+        tx.execute(
+            "INSERT OR REPLACE INTO extracted_files (path, size, mtime, sha256, title, ocr)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![path, cached.size as i64, cached.mtime as i64, cached.sha256, cached.title, cached.ocr],
+        )?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO extracted_pages (path, page_number, label, text, ocr, blocks)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for page in &cached.pages {
+                let blocks = page.blocks.as_ref().map(serde_json::to_string).transpose()?;
+                insert.execute(params![path, page.page_number as i64, page.label, page.text, page.ocr, blocks])?;
+            }
+        }
+        index_document_terms(&tx, path)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Add cached documents to the term index that were cached before it existed
+    fn backfill_term_index(&self) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let missing: Vec<String> = {
+            let mut stmt = tx.prepare(
+                "SELECT path FROM extracted_files WHERE path NOT IN (SELECT path FROM term_index_rows)",
+            )?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        for path in &missing {
+            index_document_terms(&tx, path)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Indexed terms starting with `prefix`, with the number of cached documents
+    /// containing each, most common first. Terms are lowercased without diacritics.
+    pub fn terms_with_prefix(&self, prefix: &str, limit: usize) -> Result<Vec<(String, usize)>> {
+        let conn = self.conn.lock().unwrap();
+        // Sorts after every term that starts with the prefix
+        let upper_bound = format!("{}\u{10FFFF}", prefix);
+        let mut stmt = conn.prepare(
+            "SELECT term, doc FROM term_vocabulary WHERE term >= ?1 AND term < ?2
+             ORDER BY doc DESC, term LIMIT ?3",
+        )?;
+        let rows = stmt.query_map(params![prefix, upper_bound, limit as i64], |row| {
+            Ok((row.get(0)?, row.get::<_, i64>(1)? as usize))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Cached documents containing a word starting with each of `prefixes`. The
+    /// prefixes are tokenized like the indexed text, so they're matched without
+    /// regard to case or diacritics; a prefix of several words counts as several.
+    pub fn documents_with_prefixes(&self, prefixes: &[String]) -> Result<HashSet<String>> {
+        // The term index doesn't record positions, so it can't match phrases
+        let fts_query = prefixes
+            .iter()
+            .flat_map(|prefix| prefix.split(|c: char| !c.is_alphanumeric()))
+            .filter(|word| !word.is_empty())
+            .map(|word| format!("\"{}\"*", word))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT path FROM term_index_rows
+             WHERE row IN (SELECT rowid FROM term_index WHERE term_index MATCH ?)",
+        )?;
+        let rows = stmt.query_map([fts_query], |row| row.get(0))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Record that a cached file's contents are unchanged despite a new modification time
+    pub fn touch_cached_text(&self, path: &str, mtime: u64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE extracted_files SET mtime = ?1 WHERE path = ?2",
+            params![mtime as i64, path],
+        )?;
+        Ok(())
+    }
+
+    pub fn cached_text_paths(&self) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT path FROM extracted_files ORDER BY path")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// The metadata guessed for a file as JSON, if it was guessed since the file
+    /// last changed
+    pub fn fallback_metadata(&self, path: &str, mtime: u64) -> Result<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn
+            .query_row(
+                "SELECT metadata FROM fallback_metadata WHERE path = ?1 AND mtime = ?2",
+                params![path, mtime as i64],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    pub fn store_fallback_metadata(&self, path: &str, mtime: u64, metadata_json: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO fallback_metadata (path, mtime, metadata) VALUES (?1, ?2, ?3)",
+            params![path, mtime as i64, metadata_json],
+        )?;
+        Ok(())
+    }
+
+    pub fn store_zotero_snapshot(&self, root: &str, metadata_json: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO zotero_snapshots (root, metadata) VALUES (?1, ?2)",
+            params![root, metadata_json],
+        )?;
+        Ok(())
+    }
+
+    /// Drop a file moved out of the corpus as a duplicate of `kept`. Its screening
+    /// decisions carry over to `kept` in reviews that have none for it yet, and
+    /// `kept` takes its place in corpus subsets.
+    pub fn forget_duplicate(&self, path: &str, kept: &str) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        forget_text(&tx, path)?;
+        tx.execute("DELETE FROM files WHERE path = ?", [path])?;
+
+        tx.execute(
+            "UPDATE OR IGNORE screening SET path = ?2 WHERE path = ?1",
+            params![path, kept],
+        )?;
+        tx.execute("DELETE FROM screening WHERE path = ?", [path])?;
+        tx.execute(
+            "UPDATE OR IGNORE corpus_subset_files SET path = ?2 WHERE path = ?1",
+            params![path, kept],
+        )?;
+        tx.execute("DELETE FROM corpus_subset_files WHERE path = ?", [path])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Move everything kept for a renamed file to its new path: its catalog entry,
+    /// cached text and terms, guessed metadata, screening decisions and subset
+    /// membership. Whatever was kept for a file once at `to` is dropped.
+    pub fn move_file(&self, from: &str, to: &str) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        forget_text(&tx, to)?;
+        for table in [
+            "files",
+            "extracted_files",
+            "extracted_pages",
+            "term_index_rows",
+            "fallback_metadata",
+            "screening",
+            "corpus_subset_files",
+        ] {
+            tx.execute(
+                &format!("UPDATE OR REPLACE {} SET path = ?2 WHERE path = ?1", table),
+                params![from, to],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Every file with cached text, with the size, modification time and hash it was cached at
+    pub fn cached_fingerprints(&self) -> Result<Vec<(String, u64, u64, String)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT path, size, mtime, sha256 FROM extracted_files ORDER BY path")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get(0)?, row.get::<_, i64>(1)? as u64, row.get::<_, i64>(2)? as u64, row.get(3)?))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Every file in the catalog
+    pub fn catalog_paths(&self) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT path FROM files ORDER BY path")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Drop a file's cached text and its terms, so it's extracted again when next searched
+    pub fn forget_cached_text(&self, path: &str) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        forget_text(&tx, path)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Drop a file from the catalog. Screening decisions are kept, since they're the reviewer's.
+    pub fn forget_file(&self, path: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM files WHERE path = ?", [path])?;
+        Ok(())
+    }
+
+    /// Problems SQLite's integrity check finds in the database; empty when it's sound
+    pub fn integrity_problems(&self) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        let problems: Vec<String> = rows.collect::<rusqlite::Result<_>>()?;
+        Ok(problems.into_iter().filter(|problem| problem != "ok").collect())
+    }
+
+    /// Drop pages and term rows whose file has no cached text any more, merge the
+    /// term index into one segment and rewrite the database without its free pages.
+    /// Returns the database's size in bytes before and after.
+    pub fn compact(&self) -> Result<(u64, u64)> {
+        let mut conn = self.conn.lock().unwrap();
+        let before = database_size(&conn)?;
+
+        let tx = conn.transaction()?;
+        let orphans: Vec<String> = {
+            let mut stmt = tx.prepare(
+                "SELECT path FROM term_index_rows WHERE path NOT IN (SELECT path FROM extracted_files)
+                 UNION SELECT path FROM extracted_pages WHERE path NOT IN (SELECT path FROM extracted_files)",
+            )?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        for path in &orphans {
+            forget_text(&tx, path)?;
+        }
+        tx.execute("INSERT INTO term_index (term_index) VALUES ('optimize')", [])?;
+        tx.commit()?;
+
+        conn.execute_batch("VACUUM")?;
+        Ok((before, database_size(&conn)?))
+    }
+
+    /// Snapshots imported with a corpus bundle, as (root, metadata JSON)
+    pub fn zotero_snapshots(&self) -> Result<Vec<(String, String)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT root, metadata FROM zotero_snapshots")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+// Add a column that tables created by an older version lack
+fn add_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let exists = conn
+        .prepare(&format!("SELECT 1 FROM pragma_table_info('{}') WHERE name = ?", table))?
+        .exists([column])?;
+    if !exists {
+        conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))?;
+    }
+    Ok(())
+}
+
+// Remove a file's cached text, taking its terms out of the term index first
+fn forget_text(conn: &Connection, path: &str) -> rusqlite::Result<()> {
+    // A contentless FTS table only forgets a row when given the text it indexed
+    let previous_row: Option<i64> = conn
+        .query_row("SELECT row FROM term_index_rows WHERE path = ?", [path], |row| row.get(0))
+        .optional()?;
+    if let Some(row) = previous_row {
+        conn.execute(
+            "INSERT INTO term_index (term_index, rowid, text) VALUES ('delete', ?1, ?2)",
+            params![row, document_text(conn, path)?],
+        )?;
+    }
+    conn.execute("DELETE FROM term_index_rows WHERE path = ?", [path])?;
+    conn.execute("DELETE FROM extracted_pages WHERE path = ?", [path])?;
+    conn.execute("DELETE FROM extracted_files WHERE path = ?", [path])?;
+    conn.execute("DELETE FROM fallback_metadata WHERE path = ?", [path])?;
+    Ok(())
+}
+
+// Bytes the database file takes up, free pages included
+fn database_size(conn: &Connection) -> rusqlite::Result<u64> {
+    let pages: i64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+    let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    Ok((pages * page_size) as u64)
+}
+
+// All of a cached document's pages, as one text
+fn document_text(conn: &Connection, path: &str) -> rusqlite::Result<String> {
+    let mut stmt = conn.prepare("SELECT text FROM extracted_pages WHERE path = ? ORDER BY page_number")?;
+    let pages = stmt.query_map([path], |row| row.get::<_, String>(0))?;
+    Ok(pages.collect::<rusqlite::Result<Vec<_>>>()?.join("\n"))
+}
+
+// Index a cached document's terms as one row, so the vocabulary counts documents
+fn index_document_terms(conn: &Connection, path: &str) -> rusqlite::Result<()> {
+    conn.execute("INSERT INTO term_index (text) VALUES (?)", [document_text(conn, path)?])?;
+    conn.execute(
+        "INSERT OR REPLACE INTO term_index_rows (path, row) VALUES (?1, ?2)",
+        params![path, conn.last_insert_rowid()],
+    )?;
+    Ok(())
+}
+
+/// Keep only files modified within the last `days` days
+pub fn modified_within(files: Vec<PathBuf>, days: u64) -> Vec<PathBuf> {
+    let cutoff = unix_now().saturating_sub(days * 86_400);
+    files
+        .into_iter()
+        .filter(|path| file_mtime(path).is_some_and(|mtime| mtime >= cutoff))
+        .collect()
+}
@@ -0,0 +1,529 @@
+use lopdf::content::Content;
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+// `Document::extract_text` only returns a page's text. To show where a match is on
+// the page, the content stream is walked again here with the text state tracked
+// (fonts, matrices, spacing), producing the same text along with a box for every
+// character. Glyph widths come from the fonts' /Widths; heights are an estimate
+// from the font size, since font metrics beyond widths aren't read.
+
+// Glyph box above and below the baseline, in text space units (fractions of the font size)
+const ASCENT: f64 = 0.8;
+const DESCENT: f64 = -0.2;
+
+// Width of a glyph whose font doesn't give one, in thousandths of the font size
+const DEFAULT_WIDTH: f64 = 500.0;
+
+/// A rectangle in PDF user space: points from the bottom-left corner of the page
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rect {
+    pub x0: f64,
+    pub y0: f64,
+    pub x1: f64,
+    pub y1: f64,
+}
+
+impl Rect {
+    fn union(&self, other: &Rect) -> Rect {
+        Rect {
+            x0: self.x0.min(other.x0),
+            y0: self.y0.min(other.y0),
+            x1: self.x1.max(other.x1),
+            y1: self.y1.max(other.y1),
+        }
+    }
+
+    // Whether `other` sits on the same line, overlapping at least half of the shorter height
+    fn same_line(&self, other: &Rect) -> bool {
+        let overlap = self.y1.min(other.y1) - self.y0.max(other.y0);
+        let shorter = (self.y1 - self.y0).min(other.y1 - other.y0);
+        overlap > 0.0 && overlap >= shorter / 2.0
+    }
+}
+
+/// Where one character of the page text was drawn
+#[derive(Debug, Clone)]
+pub struct GlyphBox {
+    // Byte range of the character in the page text
+    pub range: Range<usize>,
+    pub rect: Rect,
+}
+
+/// Text shown inside one marked-content sequence, as tagged PDFs use to tie
+/// content to their structure tree
+#[derive(Debug, Clone)]
+pub struct MarkedRun {
+    // Byte range of the run in the page text
+    pub range: Range<usize>,
+    // Marked-content ID of the innermost sequence that has one
+    pub mcid: Option<i64>,
+    // Inside an /Artifact sequence: page furniture such as running heads
+    pub artifact: bool,
+}
+
+/// A page's text as `Document::extract_text` produces it, with character boxes
+pub struct PageLayout {
+    pub text: String,
+    pub glyphs: Vec<GlyphBox>,
+    // The text split where marked-content sequences begin and end
+    pub marked: Vec<MarkedRun>,
+}
+
+// An open BMC/BDC sequence
+struct MarkedContent {
+    mcid: Option<i64>,
+    artifact: bool,
+}
+
+impl PageLayout {
+    // Close the run of text shown since `start` under the sequences in `open`
+    fn end_run(&mut self, start: &mut usize, open: &[MarkedContent]) {
+        if self.text.len() > *start {
+            self.marked.push(MarkedRun {
+                range: *start..self.text.len(),
+                mcid: open.iter().rev().find_map(|marked| marked.mcid),
+                artifact: open.iter().any(|marked| marked.artifact),
+            });
+        }
+        *start = self.text.len();
+    }
+}
+
+// a b c d e f, as in the PDF `cm` operator
+type Matrix = [f64; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+// `m` then `n`, in PDF's row-vector convention
+fn multiply(m: &Matrix, n: &Matrix) -> Matrix {
+    [
+        m[0] * n[0] + m[1] * n[2],
+        m[0] * n[1] + m[1] * n[3],
+        m[2] * n[0] + m[3] * n[2],
+        m[2] * n[1] + m[3] * n[3],
+        m[4] * n[0] + m[5] * n[2] + n[4],
+        m[4] * n[1] + m[5] * n[3] + n[5],
+    ]
+}
+
+fn translation(tx: f64, ty: f64) -> Matrix {
+    [1.0, 0.0, 0.0, 1.0, tx, ty]
+}
+
+fn apply(m: &Matrix, x: f64, y: f64) -> (f64, f64) {
+    (x * m[0] + y * m[2] + m[4], x * m[1] + y * m[3] + m[5])
+}
+
+fn number(object: &Object) -> Option<f64> {
+    match object {
+        Object::Integer(i) => Some(*i as f64),
+        Object::Real(r) => Some(*r as f64),
+        _ => None,
+    }
+}
+
+// What's needed of a font to place its glyphs and decode its text as lopdf does
+struct FontInfo<'a> {
+    encoding: &'a str,
+    // Type0 fonts take two bytes per glyph
+    two_byte: bool,
+    first_char: i64,
+    widths: Vec<f64>,
+    missing_width: f64,
+}
+
+impl<'a> FontInfo<'a> {
+    fn new(doc: &Document, font: &'a Dictionary) -> Self {
+        let resolve = |object: &'a Object| -> Option<Object> {
+            match object {
+                Object::Reference(id) => doc.get_object(*id).ok().cloned(),
+                _ => Some(object.clone()),
+            }
+        };
+        let two_byte = font.get(b"Subtype").and_then(Object::as_name_str).ok() == Some("Type0");
+        let widths = font
+            .get(b"Widths")
+            .ok()
+            .and_then(resolve)
+            .and_then(|widths| widths.as_array().ok().map(|array| array.iter().filter_map(number).collect()))
+            .unwrap_or_default();
+        let missing_width = font
+            .get(b"FontDescriptor")
+            .ok()
+            .and_then(resolve)
+            .and_then(|descriptor| descriptor.as_dict().ok().and_then(|d| d.get(b"MissingWidth").ok().and_then(number)))
+            .unwrap_or(if two_byte { 1000.0 } else { DEFAULT_WIDTH });
+        FontInfo {
+            encoding: font.get_font_encoding(),
+            two_byte,
+            first_char: font.get(b"FirstChar").and_then(Object::as_i64).unwrap_or(0),
+            widths,
+            missing_width,
+        }
+    }
+
+    fn width(&self, code: i64) -> f64 {
+        usize::try_from(code - self.first_char)
+            .ok()
+            .and_then(|index| self.widths.get(index).copied())
+            .unwrap_or(self.missing_width)
+    }
+
+    // lopdf decodes these byte by byte, so each byte's characters can be placed exactly
+    fn single_byte_table(&self) -> bool {
+        !self.two_byte
+            && matches!(
+                self.encoding,
+                "StandardEncoding" | "MacRomanEncoding" | "MacExpertEncoding" | "WinAnsiEncoding"
+            )
+    }
+}
+
+struct TextState<'a> {
+    ctm: Matrix,
+    stack: Vec<Matrix>,
+    tm: Matrix,
+    tlm: Matrix,
+    font: Option<&'a FontInfo<'a>>,
+    encoding: Option<&'a str>,
+    size: f64,
+    char_spacing: f64,
+    word_spacing: f64,
+    scale: f64,
+    leading: f64,
+    rise: f64,
+}
+
+impl TextState<'_> {
+    fn next_line(&mut self, tx: f64, ty: f64) {
+        self.tlm = multiply(&translation(tx, ty), &self.tlm);
+        self.tm = self.tlm;
+    }
+
+    // Box of a glyph `width` thousandths of an em wide at the current position
+    fn glyph_rect(&self, width: f64) -> Rect {
+        let rendering = multiply(
+            &multiply(&[self.size * self.scale, 0.0, 0.0, self.size, 0.0, self.rise], &self.tm),
+            &self.ctm,
+        );
+        let corners = [
+            apply(&rendering, 0.0, DESCENT),
+            apply(&rendering, width / 1000.0, DESCENT),
+            apply(&rendering, 0.0, ASCENT),
+            apply(&rendering, width / 1000.0, ASCENT),
+        ];
+        let mut rect = Rect {
+            x0: f64::MAX,
+            y0: f64::MAX,
+            x1: f64::MIN,
+            y1: f64::MIN,
+        };
+        for (x, y) in corners {
+            rect = rect.union(&Rect { x0: x, y0: y, x1: x, y1: y });
+        }
+        rect
+    }
+
+    fn advance(&mut self, width: f64, is_space: bool) {
+        let spacing = self.char_spacing + if is_space { self.word_spacing } else { 0.0 };
+        let tx = (width / 1000.0 * self.size + spacing) * self.scale;
+        self.tm = multiply(&translation(tx, 0.0), &self.tm);
+    }
+}
+
+/// The page's text and a box per character, or None when the content can't be
+/// read. The text matches `Document::extract_text` for the page.
+pub fn page_layout(doc: &Document, page_id: ObjectId) -> Option<PageLayout> {
+    let fonts: BTreeMap<Vec<u8>, FontInfo> = doc
+        .get_page_fonts(page_id)
+        .into_iter()
+        .map(|(name, font)| (name, FontInfo::new(doc, font)))
+        .collect();
+    let content = Content::decode(&doc.get_page_content(page_id).ok()?).ok()?;
+
+    let mut layout = PageLayout {
+        text: String::new(),
+        glyphs: Vec::new(),
+        marked: Vec::new(),
+    };
+    let mut open_marked: Vec<MarkedContent> = Vec::new();
+    let mut run_start = 0;
+    let mut state = TextState {
+        ctm: IDENTITY,
+        stack: Vec::new(),
+        tm: IDENTITY,
+        tlm: IDENTITY,
+        font: None,
+        encoding: None,
+        size: 0.0,
+        char_spacing: 0.0,
+        word_spacing: 0.0,
+        scale: 1.0,
+        leading: 0.0,
+        rise: 0.0,
+    };
+
+    for operation in &content.operations {
+        let operands = &operation.operands;
+        let numbers: Vec<f64> = operands.iter().filter_map(number).collect();
+        match operation.operator.as_str() {
+            "q" => state.stack.push(state.ctm),
+            "Q" => state.ctm = state.stack.pop().unwrap_or(IDENTITY),
+            "cm" if numbers.len() == 6 => {
+                let m = [numbers[0], numbers[1], numbers[2], numbers[3], numbers[4], numbers[5]];
+                state.ctm = multiply(&m, &state.ctm);
+            }
+            "BT" => {
+                state.tm = IDENTITY;
+                state.tlm = IDENTITY;
+            }
+            "ET" if !layout.text.ends_with('\n') => layout.text.push('\n'),
+            "Tf" => {
+                // extract_text fails on a Tf without a font name, and so does this
+                let name = operands.first()?.as_name().ok()?;
+                state.font = fonts.get(name);
+                state.encoding = state.font.map(|font| font.encoding);
+                state.size = operands.get(1).and_then(number).unwrap_or(state.size);
+            }
+            "Tc" => state.char_spacing = numbers.first().copied().unwrap_or(0.0),
+            "Tw" => state.word_spacing = numbers.first().copied().unwrap_or(0.0),
+            "Tz" => state.scale = numbers.first().copied().unwrap_or(100.0) / 100.0,
+            "TL" => state.leading = numbers.first().copied().unwrap_or(0.0),
+            "Ts" => state.rise = numbers.first().copied().unwrap_or(0.0),
+            "Td" if numbers.len() == 2 => state.next_line(numbers[0], numbers[1]),
+            "TD" if numbers.len() == 2 => {
+                state.leading = -numbers[1];
+                state.next_line(numbers[0], numbers[1]);
+            }
+            "Tm" if numbers.len() == 6 => {
+                state.tlm = [numbers[0], numbers[1], numbers[2], numbers[3], numbers[4], numbers[5]];
+                state.tm = state.tlm;
+            }
+            "T*" => state.next_line(0.0, -state.leading),
+            // extract_text leaves out the text these show, so only the position moves
+            "'" => state.next_line(0.0, -state.leading),
+            "\"" => {
+                if numbers.len() >= 2 {
+                    state.word_spacing = numbers[0];
+                    state.char_spacing = numbers[1];
+                }
+                state.next_line(0.0, -state.leading);
+            }
+            "Tj" | "TJ" => show_text(&mut layout, &mut state, operands),
+            // Properties given by name in the page's /Properties aren't looked up;
+            // MCIDs are written inline
+            "BMC" | "BDC" => {
+                layout.end_run(&mut run_start, &open_marked);
+                let properties = operands.get(1).and_then(|properties| properties.as_dict().ok());
+                open_marked.push(MarkedContent {
+                    mcid: properties.and_then(|properties| properties.get(b"MCID").and_then(Object::as_i64).ok()),
+                    artifact: operands.first().and_then(|tag| tag.as_name().ok()) == Some(b"Artifact".as_slice()),
+                });
+            }
+            "EMC" => {
+                layout.end_run(&mut run_start, &open_marked);
+                open_marked.pop();
+            }
+            _ => {}
+        }
+    }
+    layout.end_run(&mut run_start, &open_marked);
+
+    Some(layout)
+}
+
+// Mirrors extract_text: strings are decoded and appended, arrays are followed by
+// a space, and large negative adjustments stand for a space between words
+fn show_text(layout: &mut PageLayout, state: &mut TextState, operands: &[Object]) {
+    for operand in operands {
+        match operand {
+            Object::String(bytes, _) => show_string(layout, state, bytes),
+            Object::Array(array) => {
+                show_text(layout, state, array);
+                layout.text.push(' ');
+            }
+            Object::Integer(_) | Object::Real(_) => {
+                if matches!(operand, Object::Integer(i) if *i < -100) {
+                    layout.text.push(' ');
+                }
+                let adjustment = number(operand).unwrap_or(0.0);
+                state.tm = multiply(&translation(-adjustment / 1000.0 * state.size * state.scale, 0.0), &state.tm);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn show_string(layout: &mut PageLayout, state: &mut TextState, bytes: &[u8]) {
+    let Some(font) = state.font else {
+        // Without a font there are no widths; the text is kept so offsets still line up
+        layout.text.push_str(&Document::decode_text(state.encoding, bytes));
+        return;
+    };
+
+    if font.single_byte_table() {
+        for &byte in bytes {
+            let width = font.width(byte as i64);
+            let decoded = Document::decode_text(state.encoding, &[byte]);
+            let rect = state.glyph_rect(width);
+            let start = layout.text.len();
+            layout.text.push_str(&decoded);
+            for (offset, c) in decoded.char_indices() {
+                layout.glyphs.push(GlyphBox {
+                    range: start + offset..start + offset + c.len_utf8(),
+                    rect,
+                });
+            }
+            state.advance(width, byte == b' ');
+        }
+        return;
+    }
+
+    // Multi-byte and unknown encodings decode as a whole, so the run's width is
+    // shared out evenly among its characters
+    let code_length = if font.two_byte { 2 } else { 1 };
+    let mut glyph_rects = Vec::new();
+    for code in bytes.chunks(code_length) {
+        let code_value = code.iter().fold(0i64, |value, byte| value << 8 | *byte as i64);
+        let width = font.width(code_value);
+        glyph_rects.push(state.glyph_rect(width));
+        state.advance(width, code == [b' ']);
+    }
+    let Some(run) = glyph_rects.iter().copied().reduce(|a, b| a.union(&b)) else {
+        return;
+    };
+
+    let decoded = Document::decode_text(state.encoding, bytes);
+    let start = layout.text.len();
+    layout.text.push_str(&decoded);
+    let count = decoded.chars().count().max(1) as f64;
+    for (index, (offset, c)) in decoded.char_indices().enumerate() {
+        let x0 = run.x0 + (run.x1 - run.x0) * index as f64 / count;
+        let x1 = run.x0 + (run.x1 - run.x0) * (index + 1) as f64 / count;
+        layout.glyphs.push(GlyphBox {
+            range: start + offset..start + offset + c.len_utf8(),
+            rect: Rect { x0, x1, ..run },
+        });
+    }
+}
+
+/// Boxes covering the characters in `range` of the page text, one per line
+pub fn boxes_for_range(glyphs: &[GlyphBox], range: &Range<usize>) -> Vec<Rect> {
+    let mut boxes: Vec<Rect> = Vec::new();
+    for glyph in glyphs.iter().filter(|glyph| glyph.range.start < range.end && glyph.range.end > range.start) {
+        match boxes.last_mut() {
+            Some(last) if last.same_line(&glyph.rect) => *last = last.union(&glyph.rect),
+            _ => boxes.push(glyph.rect),
+        }
+    }
+    boxes
+}
+
+/// How a page is shown: its visible area in user space and the quarter turns
+/// viewers apply to it. Glyph boxes are in user space, so an overlay on a
+/// rendered page goes through `to_display` to line up on rotated or cropped pages.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PageGeometry {
+    // The crop box, else the media box
+    pub visible: Rect,
+    // Clockwise, as /Rotate: 0, 90, 180 or 270
+    pub rotate: u16,
+    // Size of the page as displayed, in points, after rotating
+    pub width: f64,
+    pub height: f64,
+}
+
+impl PageGeometry {
+    /// `rect` in display space: points from the top-left corner of the page as
+    /// viewers show it, cropped and rotated. None when it lies outside the visible area.
+    pub fn to_display(self, rect: &Rect) -> Option<Rect> {
+        let visible = self.visible;
+        let (w, h) = (visible.x1 - visible.x0, visible.y1 - visible.y0);
+        let corners = [(rect.x0, rect.y0), (rect.x1, rect.y1)].map(|(x, y)| {
+            // From the top-left of the unrotated visible area
+            let (u, v) = (x - visible.x0, visible.y1 - y);
+            match self.rotate {
+                90 => (h - v, u),
+                180 => (w - u, h - v),
+                270 => (v, w - u),
+                _ => (u, v),
+            }
+        });
+        let [(ax, ay), (bx, by)] = corners;
+        let display = Rect {
+            x0: ax.min(bx).max(0.0),
+            y0: ay.min(by).max(0.0),
+            x1: ax.max(bx).min(self.width),
+            y1: ay.max(by).min(self.height),
+        };
+        (display.x1 > display.x0 && display.y1 > display.y0).then_some(display)
+    }
+}
+
+/// The page's visible area and rotation, inherited from the page tree where the
+/// page doesn't set them. None when it has no media box.
+pub fn page_geometry(doc: &Document, page_id: ObjectId) -> Option<PageGeometry> {
+    let mut dict = doc.get_dictionary(page_id).ok()?;
+    let mut crop_box = None;
+    let mut media_box = None;
+    let mut rotate = None;
+    loop {
+        crop_box = crop_box.or_else(|| page_rect(doc, dict, b"CropBox"));
+        media_box = media_box.or_else(|| page_rect(doc, dict, b"MediaBox"));
+        rotate = rotate.or_else(|| match dict.get(b"Rotate").ok()? {
+            Object::Reference(id) => doc.get_object(*id).ok()?.as_i64().ok(),
+            object => object.as_i64().ok(),
+        });
+        match dict.get(b"Parent").and_then(Object::as_reference) {
+            Ok(parent) => dict = doc.get_dictionary(parent).ok()?,
+            Err(_) => break,
+        }
+    }
+    let media_box = media_box?;
+    // A crop box reaching past the media box is clipped to it
+    let visible = match crop_box {
+        Some(crop) => Rect {
+            x0: crop.x0.max(media_box.x0),
+            y0: crop.y0.max(media_box.y0),
+            x1: crop.x1.min(media_box.x1),
+            y1: crop.y1.min(media_box.y1),
+        },
+        None => media_box,
+    };
+    if visible.x1 <= visible.x0 || visible.y1 <= visible.y0 {
+        return None;
+    }
+    // Only multiples of 90 are valid; anything else is ignored, as viewers do
+    let rotate = match rotate.unwrap_or(0).rem_euclid(360) {
+        degrees @ (90 | 180 | 270) => degrees as u16,
+        _ => 0,
+    };
+    let (w, h) = (visible.x1 - visible.x0, visible.y1 - visible.y0);
+    let (width, height) = if rotate % 180 == 0 { (w, h) } else { (h, w) };
+    Some(PageGeometry {
+        visible,
+        rotate,
+        width,
+        height,
+    })
+}
+
+fn page_rect(doc: &Document, dict: &Dictionary, key: &[u8]) -> Option<Rect> {
+    let values = match dict.get(key).ok()? {
+        Object::Reference(id) => doc.get_object(*id).ok()?.as_array().ok()?,
+        object => object.as_array().ok()?,
+    };
+    let numbers: Vec<f64> = values.iter().filter_map(number).collect();
+    if numbers.len() != 4 {
+        return None;
+    }
+    Some(Rect {
+        x0: numbers[0].min(numbers[2]),
+        y0: numbers[1].min(numbers[3]),
+        x1: numbers[0].max(numbers[2]),
+        y1: numbers[1].max(numbers[3]),
+    })
+}
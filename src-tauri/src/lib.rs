@@ -1,32 +1,1810 @@
+mod benchmark;
+mod bundle;
+mod charts;
+mod compute;
+mod config_bundle;
+mod corpora;
+mod corpus;
+mod csl_locale;
+mod directory_config;
+mod duplicates;
+mod error;
+mod export;
+mod fallback_metadata;
+mod history;
+mod i18n;
+mod index;
+mod layout;
+mod library;
+mod maintenance;
+mod memory;
+mod native_index;
+mod note_apps;
+mod ocr;
+mod page_render;
+mod palette;
+mod pandoc;
+mod pdf_metadata;
 mod pdf_search;
+mod platform;
+mod problem_corpus;
+mod rename;
+mod results_store;
+mod sample;
+mod saved_search;
+mod scope;
+mod self_test;
+mod screening;
+mod session;
+mod settings;
+mod spool;
+mod structure;
+mod subset;
+mod vocabulary;
+mod walk;
+mod zotero_api;
+mod zotero_cache;
+mod zotero_fulltext;
 
-use pdf_search::{export_to_markdown, search_pdfs, search_single_pdf, list_pdfs, SearchMatch, SearchParams, PdfListItem, ListPdfsParams};
+use benchmark::BenchmarkReport;
+use bundle::BundleOptions;
+use config_bundle::ConfigImportReport;
+use vocabulary::{CorpusVocabulary, QuerySuggestion, TermSuggestion};
+use corpus::{CorpusSummary, SourceVerification};
+use directory_config::DirectoryConfig;
+use duplicates::{DuplicateGroup, QuarantineOutcome};
+use error::AppError;
+use history::SearchHistoryStats;
+use index::Index;
+use library::LibraryItem;
+use maintenance::{MaintenanceOptions, MaintenanceReport};
+use export::{export_audit_report, export_captures_to_csv, export_to_json, render_share_summary, write_markdown_export, ChunkOptions, ExportOptions, ExportPreview, PreviewOptions, QueryOverlap, QuoteStyle, SearchAudit, SearchHeader, SelectionExportParams};
+use note_apps::{percent_encode, LogseqTarget, ObsidianTarget};
+use pdf_metadata::PdfInfo;
+use palette::QueryRestyle;
+use problem_corpus::ProblemCorpus;
+use platform::PlatformReport;
+use pdf_search::{expand_dropped_paths, preview_pdf_matches, search_pdf_paths, search_pdfs, search_single_pdf, list_pdfs, SearchContext, SearchOutcome, build_zotero_link_report, QueryDocumentCount, QueryItem, SearchMatch, SearchParams, ListPdfsParams, MatchNeighbors, NormalizationOptions, CitedSelection, ExcludedDocument, PageHitCounts, ZoteroLinkReport, ZoteroLinkReportParams, IndexReport, SearchProgress};
+use serde::Serialize;
+use rename::{FileRename, RenameOutcome, RenameSuggestion};
+use results_store::{SavedResults, SavedResultsInfo};
+use saved_search::{RerunResult, SavedSearch};
+use screening::{ScreeningDecision, ScreeningFilter, ScreeningStatus};
+use scope::PathScope;
+use self_test::SelfTestReport;
+use session::{ResultSet, ResultsPage, SearchSessions};
+use spool::ResultSpool;
+use subset::CorpusSubset;
+use settings::AppSettings;
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use walk::WalkOptions;
+use zotero_api::{TagResult, ZoteroApiCredentials};
+use tauri::{AppHandle, DragDropEvent, Emitter, Manager, RunEvent, State, Window, WindowEvent};
+use tauri_plugin_dialog::DialogExt;
+use tauri_plugin_opener::OpenerExt;
 
+fn config_dir(app: &AppHandle) -> Result<PathBuf, AppError> {
+    app.path().app_config_dir().map_err(|e| AppError::Other { message: e.to_string() })
+}
+
+fn data_dir(app: &AppHandle) -> Result<PathBuf, AppError> {
+    app.path().app_data_dir().map_err(|e| AppError::Other { message: e.to_string() })
+}
+
+// Searches still run without the index, just without the features that need it
+fn open_index(app: &AppHandle) -> Option<Index> {
+    match data_dir(app).and_then(|dir| Ok(Index::open(&dir)?)) {
+        Ok(index) => Some(index),
+        Err(e) => {
+            eprintln!("Warning: Failed to open search index: {}", e);
+            None
+        }
+    }
+}
+
+// Walk skip rules come from the settings unless the search overrides them
+fn apply_walk_settings(app: &AppHandle, params: &mut SearchParams) {
+    if params.walk.skip_dirs.is_some() {
+        return;
+    }
+    match config_dir(app).and_then(|dir| Ok(settings::load_settings(&dir)?)) {
+        Ok(settings) => params.walk.skip_dirs = Some(settings.skip_dirs),
+        Err(e) => eprintln!("Warning: Failed to load settings: {}", e),
+    }
+}
+
+// Leave out the documents the reviewer excluded from every search
+fn apply_excluded_documents(app: &AppHandle, params: &mut SearchParams) {
+    match config_dir(app).and_then(|dir| Ok(settings::load_settings(&dir)?)) {
+        Ok(settings) => params.excluded_documents.extend(settings.excluded_documents),
+        Err(e) => eprintln!("Warning: Failed to load settings: {}", e),
+    }
+}
+
+// The named corpora a search gives without their folders take them from the settings
+fn apply_corpora(app: &AppHandle, params: &mut SearchParams) -> Result<(), AppError> {
+    if params.corpora.iter().all(|corpus| !corpus.directories.is_empty()) {
+        return Ok(());
+    }
+    let settings = settings::load_settings(&config_dir(app)?)?;
+    Ok(corpora::resolve(&mut params.corpora, &settings.corpora)?)
+}
+
+// The directory and Zotero library a search reads. A Zotero search reads the
+// library's attachments wherever they are, so only its data directory is checked.
+// A search of named corpora reads their folders instead of the directory.
+fn check_search_scope(scope: &PathScope, params: &SearchParams) -> Result<(), AppError> {
+    if !params.corpora.is_empty() {
+        scope.check_all(params.corpora.iter().flat_map(|corpus| &corpus.directories).map(String::as_str))?;
+    } else if params.source.as_deref() != Some("zotero") {
+        scope.check(&params.directory)?;
+    }
+    match params.zotero_path.as_deref() {
+        Some(zotero_path) if !zotero_path.is_empty() => scope.check(zotero_path),
+        _ => Ok(()),
+    }
+}
+
+// Exports that open the matched PDFs themselves
+fn check_match_scope(scope: &PathScope, matches: &[SearchMatch]) -> Result<(), AppError> {
+    scope.check_all(matches.iter().map(|m| m.item.file_path.as_str()))
+}
+
+// Files a search returned can be opened in the viewer, even those a Zotero
+// library keeps outside its data directory
+fn allow_matched_files(scope: &PathScope, matches: &[SearchMatch]) {
+    let files: HashSet<&str> = matches.iter().map(|m| m.item.file_path.as_str()).collect();
+    for file in files {
+        scope.allow(Path::new(file));
+    }
+}
+
+// The files of a streamed search's matches, which aren't in its outcome
+fn allow_spooled_files(scope: &PathScope, spool: Option<&ResultSpool>) {
+    let Some(spool) = spool else {
+        return;
+    };
+    match spool.file_paths() {
+        Ok(files) => {
+            for file in files {
+                scope.allow(Path::new(&file));
+            }
+        }
+        Err(e) => eprintln!("Warning: Failed to read spooled results: {}", e),
+    }
+}
+
+#[derive(Serialize)]
+struct SearchResponse {
+    search_id: u64,
+    // The corpus's .pdfsearch.toml, when one overrode the search's settings
+    directory_config: Option<DirectoryConfig>,
+    #[serde(flatten)]
+    outcome: SearchOutcome,
+}
+
+#[derive(Clone, Serialize)]
+struct SearchFinishedEvent {
+    search_id: u64,
+    match_count: usize,
+    cancelled: bool,
+    // Partial results were kept and saved; see `SearchOutcome.partial`
+    partial: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct SearchProgressEvent {
+    search_id: u64,
+    files_scanned: usize,
+    total_files: usize,
+    current_file: String,
+    matches_so_far: usize,
+    // Matches found since the previous event, without match IDs; the final
+    // results come with the command's response
+    matches: Vec<SearchMatch>,
+}
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+// Sends a search's progress to the window that started it, at most every
+// PROGRESS_INTERVAL so a search over many small files doesn't flood the webview
+fn progress_emitter(window: &Window, search_id: u64) -> impl Fn(SearchProgress) + Sync {
+    let window = window.clone();
+    let pending: Mutex<(Option<Instant>, Vec<SearchMatch>)> = Mutex::new((None, Vec::new()));
+    move |progress: SearchProgress| {
+        let mut pending = pending.lock().unwrap();
+        pending.1.extend(progress.matches);
+        let due = pending.0.is_none_or(|last| last.elapsed() >= PROGRESS_INTERVAL);
+        if !due && progress.files_scanned < progress.total_files {
+            return;
+        }
+        pending.0 = Some(Instant::now());
+        let _ = window.emit_to(
+            window.label(),
+            "search://progress",
+            SearchProgressEvent {
+                search_id,
+                files_scanned: progress.files_scanned,
+                total_files: progress.total_files,
+                current_file: progress.current_file,
+                matches_so_far: progress.matches_so_far,
+                matches: std::mem::take(&mut pending.1),
+            },
+        );
+    }
+}
+
+// Keep the matches of an interrupted search, since its window may be gone by the
+// time the search winds down. Saved under the search ID like any result set.
+fn persist_partial_results(
+    app: &AppHandle,
+    search_id: u64,
+    params: &SearchParams,
+    outcome: &SearchOutcome,
+    spool: Option<&ResultSpool>,
+) {
+    let matches = match spool {
+        Some(spool) => match spool.all(search_id) {
+            Ok(matches) => matches,
+            Err(e) => {
+                eprintln!("Warning: Failed to save partial results: {}", e);
+                return;
+            }
+        },
+        None => outcome.matches.clone(),
+    };
+    let info = SavedResultsInfo {
+        search_id,
+        name: Some("Interrupted search".to_string()),
+        saved_at: index::unix_now(),
+        match_count: matches.len(),
+        params: params.clone(),
+        partial: true,
+    };
+    if let Err(e) = data_dir(app).and_then(|dir| Ok(results_store::save_results(&dir, info, matches)?)) {
+        eprintln!("Warning: Failed to save partial results: {}", e);
+    }
+}
+
+// Runs off the main thread so the window can still cancel it while it works
+#[tauri::command(async)]
+fn search_pdf_files(
+    window: Window,
+    sessions: State<'_, SearchSessions>,
+    scope: State<'_, PathScope>,
+    mut params: SearchParams,
+) -> Result<SearchResponse, AppError> {
+    apply_corpora(window.app_handle(), &mut params)?;
+    check_search_scope(&scope, &params)?;
+    apply_walk_settings(window.app_handle(), &mut params);
+    apply_excluded_documents(window.app_handle(), &mut params);
+    palette::assign_colors(&mut params.queries, &[]);
+    let directory_config = directory_config::apply_directory_config(&mut params)?;
+    let label = window.label().to_string();
+    let (search_id, cancel) = sessions.begin(&label);
+
+    let index = open_index(window.app_handle());
+    let progress = progress_emitter(&window, search_id);
+    let ctx = SearchContext {
+        cancel: &cancel,
+        index: index.as_ref(),
+        progress: Some(&progress),
+    };
+
+    let searched_at = index::unix_now();
+    let mut result = search_pdfs(params.clone(), &ctx);
+    let mut spool = None;
+    if let Ok(outcome) = &mut result {
+        pdf_search::assign_match_ids(search_id, &mut outcome.matches);
+        spool = outcome.spool.take();
+    }
+    match &result {
+        Ok(outcome) => {
+            allow_matched_files(&scope, &outcome.matches);
+            allow_spooled_files(&scope, spool.as_ref());
+            let hits = match spool {
+                Some(ref spool) => spool.query_counts().unwrap_or_else(|e| {
+                    eprintln!("Warning: Failed to read spooled results: {}", e);
+                    HashMap::new()
+                }),
+                None => history::query_hits(&outcome.matches),
+            };
+            let audit = SearchAudit {
+                searched_at,
+                directories: match params.source.as_deref() {
+                    Some("zotero") => vec![format!("Zotero library: {}", params.zotero_path.clone().unwrap_or_default())],
+                    _ if !params.corpora.is_empty() => params
+                        .corpora
+                        .iter()
+                        .flat_map(|corpus| {
+                            corpus.directories.iter().map(move |directory| format!("{}: {}", corpus.name, directory))
+                        })
+                        .collect(),
+                    Some("archive") => vec![format!("Archived corpus: {}", params.directory)],
+                    _ => vec![params.directory.clone()],
+                },
+                params: params.clone(),
+                stats: outcome.stats.clone(),
+                file_errors: outcome.file_errors.clone(),
+                partial: outcome.partial,
+                excluded_documents: Vec::new(),
+            };
+            if outcome.partial {
+                persist_partial_results(window.app_handle(), search_id, &params, outcome, spool.as_ref());
+            }
+            sessions.finish(&label, search_id, &outcome.matches, spool, audit);
+            if let Err(e) = data_dir(window.app_handle())
+                .and_then(|dir| Ok(history::record_search(&dir, &params, &hits)?))
+            {
+                eprintln!("Warning: Failed to record search history: {}", e);
+            }
+        }
+        Err(_) => sessions.abandon(&label, search_id),
+    }
+
+    // Only the window that started the search is notified
+    let _ = window.emit_to(
+        label.as_str(),
+        "search://finished",
+        SearchFinishedEvent {
+            search_id,
+            match_count: result.as_ref().map(|o| o.matches.len()).unwrap_or(0),
+            cancelled: cancel.load(std::sync::atomic::Ordering::Relaxed),
+            partial: result.as_ref().is_ok_and(|o| o.partial),
+        },
+    );
+
+    Ok(SearchResponse {
+        search_id,
+        directory_config,
+        outcome: result?,
+    })
+}
+
+// Budget of a search-as-you-type query when the frontend doesn't give one
+const LIVE_SEARCH_BUDGET_MS: u64 = 300;
+
+// Live results while a query is typed: the index's cached text only, within
+// `params.latency_budget_ms`. Unlike `search_pdf_files` it leaves the window's
+// result set, history and audit alone, since it runs on every keystroke; the
+// frontend runs the full search when the query is submitted.
+#[tauri::command(async)]
+fn search_as_you_type(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    mut params: SearchParams,
+) -> Result<SearchOutcome, AppError> {
+    apply_corpora(&app, &mut params)?;
+    check_search_scope(&scope, &params)?;
+    apply_excluded_documents(&app, &mut params);
+    palette::assign_colors(&mut params.queries, &[]);
+    directory_config::apply_directory_config(&mut params)?;
+    params.latency_budget_ms.get_or_insert(LIVE_SEARCH_BUDGET_MS);
+
+    let index = open_index(&app);
+    let cancel = AtomicBool::new(false);
+    let ctx = SearchContext {
+        cancel: &cancel,
+        index: index.as_ref(),
+        progress: None,
+    };
+    let outcome = search_pdfs(params, &ctx)?;
+    allow_matched_files(&scope, &outcome.matches);
+    Ok(outcome)
+}
+
+#[derive(Serialize)]
+struct DropSearchResult {
+    drop_session_id: String,
+    files_searched: usize,
+    #[serde(flatten)]
+    outcome: SearchOutcome,
+}
+
+// Search exactly the files and folders dropped onto the window with the current
+// query set; `params.directory` is ignored
+#[tauri::command(async)]
+fn search_dropped_paths(
+    window: Window,
+    sessions: State<'_, SearchSessions>,
+    scope: State<'_, PathScope>,
+    paths: Vec<String>,
+    mut params: SearchParams,
+) -> Result<DropSearchResult, AppError> {
+    scope.check_all(paths.iter().map(String::as_str))?;
+    apply_walk_settings(window.app_handle(), &mut params);
+    apply_excluded_documents(window.app_handle(), &mut params);
+    palette::assign_colors(&mut params.queries, &[]);
+    let label = window.label().to_string();
+    let (search_id, cancel) = sessions.begin(&label);
+
+    let index = open_index(window.app_handle());
+    let progress = progress_emitter(&window, search_id);
+    let ctx = SearchContext {
+        cancel: &cancel,
+        index: index.as_ref(),
+        progress: Some(&progress),
+    };
+
+    let searched_at = index::unix_now();
+    let mut spool = None;
+    let result = expand_dropped_paths(&paths, &params.walk).and_then(|(pdf_files, walk)| {
+        let mut outcome = search_pdf_paths(&pdf_files, &params, &ctx)?;
+        outcome.walk = walk;
+        pdf_search::assign_match_ids(search_id, &mut outcome.matches);
+        spool = outcome.spool.take();
+        Ok((pdf_files.len(), outcome))
+    });
+    match &result {
+        Ok((_, outcome)) => {
+            allow_matched_files(&scope, &outcome.matches);
+            allow_spooled_files(&scope, spool.as_ref());
+            let audit = SearchAudit {
+                searched_at,
+                directories: paths.clone(),
+                params: params.clone(),
+                stats: outcome.stats.clone(),
+                file_errors: outcome.file_errors.clone(),
+                partial: outcome.partial,
+                excluded_documents: Vec::new(),
+            };
+            if outcome.partial {
+                persist_partial_results(window.app_handle(), search_id, &params, outcome, spool.as_ref());
+            }
+            sessions.finish(&label, search_id, &outcome.matches, spool, audit);
+        }
+        Err(_) => sessions.abandon(&label, search_id),
+    }
+
+    let (files_searched, outcome) = result?;
+    Ok(DropSearchResult {
+        drop_session_id: format!("drop-{}", search_id),
+        files_searched,
+        outcome,
+    })
+}
+
+#[tauri::command]
+fn cancel_search(window: Window, sessions: State<'_, SearchSessions>) -> bool {
+    sessions.cancel(window.label())
+}
+
+// Remove a document's matches from the window's result set, recording why for
+// the audit report. With `persist`, it's also left out of future searches.
+// Returns the number of matches removed.
+#[tauri::command]
+fn exclude_document(
+    window: Window,
+    sessions: State<'_, SearchSessions>,
+    file_path: String,
+    reason: String,
+    persist: bool,
+) -> Result<usize, AppError> {
+    let exclusion = ExcludedDocument {
+        file_path,
+        reason,
+        excluded_at: index::unix_now(),
+        matches_removed: 0,
+    };
+    let removed = sessions
+        .exclude_document(window.label(), exclusion.clone())
+        .ok_or_else(|| AppError::invalid_input("No completed search to exclude the document from"))?;
+
+    if persist {
+        let config_dir = config_dir(window.app_handle())?;
+        let mut settings = settings::load_settings(&config_dir)?;
+        settings.excluded_documents.retain(|d| d.file_path != exclusion.file_path);
+        settings.excluded_documents.push(exclusion);
+        settings::save_settings(&config_dir, &settings)?;
+    }
+    Ok(removed)
+}
+
+// Let a persistently excluded document back into searches; false if it wasn't excluded
+#[tauri::command]
+fn restore_excluded_document(app: AppHandle, file_path: String) -> Result<bool, AppError> {
+    let config_dir = config_dir(&app)?;
+    let mut settings = settings::load_settings(&config_dir)?;
+    let before = settings.excluded_documents.len();
+    settings.excluded_documents.retain(|d| d.file_path != file_path);
+    if settings.excluded_documents.len() == before {
+        return Ok(false);
+    }
+    settings::save_settings(&config_dir, &settings)?;
+    Ok(true)
+}
+
+#[tauri::command]
+fn get_search_results(window: Window, sessions: State<'_, SearchSessions>) -> Vec<SearchMatch> {
+    sessions.results(window.label())
+}
+
+// A page of the window's results, which is how a streamed search's matches reach
+// the list; works the same for searches that returned theirs
+#[tauri::command(async)]
+fn get_search_results_page(
+    window: Window,
+    sessions: State<'_, SearchSessions>,
+    offset: usize,
+    limit: usize,
+) -> Result<ResultsPage, AppError> {
+    sessions
+        .results_page(window.label(), offset, limit)
+        .ok_or_else(|| AppError::invalid_input("No completed search to page through"))
+}
+
+// Lightweight search for the global quick-search popup: a single plain query
+// run against the saved default directory with a capped result count
+#[tauri::command(async)]
+fn quick_search(app: AppHandle, scope: State<'_, PathScope>, query: String) -> Result<Vec<SearchMatch>, AppError> {
+    let settings = settings::load_settings(&config_dir(&app)?)?;
+    let directory = settings
+        .default_directory
+        .ok_or_else(|| AppError::invalid_input("No default directory configured for quick search"))?;
+
+    let mut params = SearchParams {
+        queries: vec![QueryItem::plain(query)],
+        directory,
+        context_words: settings.default_context_words,
+        zotero_path: settings.zotero_path,
+        max_results: Some(settings.quick_search_limit),
+        walk: WalkOptions {
+            skip_dirs: Some(settings.skip_dirs),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    palette::assign_colors(&mut params.queries, &[]);
+    directory_config::apply_directory_config(&mut params)?;
+
+    let index = open_index(&app);
+    let cancel = AtomicBool::new(false);
+    let ctx = SearchContext {
+        cancel: &cancel,
+        index: index.as_ref(),
+        progress: None,
+    };
+
+    let matches = search_pdfs(params, &ctx)?.matches;
+    allow_matched_files(&scope, &matches);
+    Ok(matches)
+}
+
+// Persist the window's last completed result set so it survives closing the app
+#[tauri::command(async)]
+fn save_results(
+    app: AppHandle,
+    window: Window,
+    sessions: State<'_, SearchSessions>,
+    name: Option<String>,
+) -> Result<SavedResultsInfo, AppError> {
+    let completed = sessions
+        .completed(window.label())
+        .ok_or_else(|| AppError::invalid_input("No completed search to save"))?;
+
+    let results = completed.results.all()?;
+    let info = SavedResultsInfo {
+        search_id: completed.search_id,
+        name,
+        saved_at: index::unix_now(),
+        match_count: results.len(),
+        params: completed.audit.params,
+        partial: completed.audit.partial,
+    };
+    Ok(results_store::save_results(&data_dir(&app)?, info, results)?)
+}
+
+#[tauri::command(async)]
+fn load_results(app: AppHandle, search_id: u64) -> Result<SavedResults, AppError> {
+    Ok(results_store::load_results(&data_dir(&app)?, search_id)?)
+}
+
+#[tauri::command]
+fn list_saved_results(app: AppHandle) -> Result<Vec<SavedResultsInfo>, AppError> {
+    Ok(results_store::list_saved_results(&data_dir(&app)?)?)
+}
+
+// Recolor or rename queries of search `search_id` without running it again: in
+// the window's results when they're that search's, and in its saved results, so
+// later exports show the change. Returns the search's queries as they now are.
+#[tauri::command(async)]
+fn restyle_search_queries(
+    app: AppHandle,
+    window: Window,
+    sessions: State<'_, SearchSessions>,
+    search_id: u64,
+    changes: Vec<QueryRestyle>,
+) -> Result<Vec<QueryItem>, AppError> {
+    let data_dir = data_dir(&app)?;
+    let (_, mut params) = search_results(&app, window.label(), &sessions, search_id)?;
+    palette::check_restyles(&params.queries, &changes)?;
+
+    sessions.update_completed(
+        window.label(),
+        search_id,
+        |m| palette::restyle_match(m, &changes),
+        |audit| palette::restyle_audit(audit, &changes),
+    )?;
+    if results_store::has_results(&data_dir, search_id) {
+        let mut saved = results_store::load_results(&data_dir, search_id)?;
+        palette::restyle_params(&mut saved.info.params, &changes);
+        for m in &mut saved.matches {
+            palette::restyle_match(m, &changes);
+        }
+        results_store::save_results(&data_dir, saved.info, saved.matches)?;
+    }
+    palette::restyle_params(&mut params, &changes);
+    Ok(params.queries)
+}
+
+// Keep the documents of a result set as a named corpus subset, which later
+// searches take as their scope with `SearchParams.subset`: the saved result set
+// `search_id` when given, otherwise the window's last completed search
+#[tauri::command(async)]
+fn register_corpus_subset(
+    app: AppHandle,
+    window: Window,
+    sessions: State<'_, SearchSessions>,
+    name: String,
+    search_id: Option<u64>,
+) -> Result<CorpusSubset, AppError> {
+    let (search_id, params, matches) = match search_id {
+        Some(search_id) => {
+            let saved = results_store::load_results(&data_dir(&app)?, search_id)?;
+            (search_id, saved.info.params, saved.matches)
+        }
+        None => {
+            let completed = sessions
+                .completed(window.label())
+                .ok_or_else(|| AppError::invalid_input("No completed search to keep as a subset"))?;
+            (completed.search_id, completed.audit.params, completed.results.all()?)
+        }
+    };
+    let queries: Vec<String> = params.queries.into_iter().map(|q| q.query).collect();
+    let index = Index::open(&data_dir(&app)?)?;
+    Ok(subset::register_subset(&index, &name, search_id, &queries, &matches)?)
+}
+
+#[tauri::command]
+fn list_corpus_subsets(app: AppHandle) -> Result<Vec<CorpusSubset>, AppError> {
+    let index = Index::open(&data_dir(&app)?)?;
+    Ok(subset::list_subsets(&index)?)
+}
+
+#[tauri::command]
+fn delete_corpus_subset(app: AppHandle, name: String) -> Result<(), AppError> {
+    let index = Index::open(&data_dir(&app)?)?;
+    Ok(index.delete_subset(&name)?)
+}
+
+#[tauri::command]
+fn save_search(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    name: String,
+    mut params: SearchParams,
+) -> Result<i64, AppError> {
+    apply_corpora(&app, &mut params)?;
+    check_search_scope(&scope, &params)?;
+    let index = Index::open(&data_dir(&app)?)?;
+    Ok(saved_search::save_search(&index, &name, &params)?)
+}
+
+#[tauri::command]
+fn list_saved_searches(app: AppHandle) -> Result<Vec<SavedSearch>, AppError> {
+    let index = Index::open(&data_dir(&app)?)?;
+    Ok(saved_search::list_saved_searches(&index)?)
+}
+
+// Execute a saved search and return only what's new since it last ran
+#[tauri::command(async)]
+fn rerun_search(app: AppHandle, search_id: i64) -> Result<RerunResult, AppError> {
+    let index = Index::open(&data_dir(&app)?)?;
+    let cancel = AtomicBool::new(false);
+    let ctx = SearchContext {
+        cancel: &cancel,
+        index: Some(&index),
+        progress: None,
+    };
+
+    Ok(saved_search::rerun_search(search_id, &ctx)?)
+}
+
+// `status: None` clears the document's decision
+#[tauri::command]
+fn set_screening_status(
+    app: AppHandle,
+    review: String,
+    file_path: String,
+    status: Option<ScreeningStatus>,
+    reason: Option<String>,
+) -> Result<(), AppError> {
+    let index = Index::open(&data_dir(&app)?)?;
+    Ok(screening::set_screening(&index, &review, &file_path, status, reason.as_deref())?)
+}
+
+#[tauri::command]
+fn list_screening_statuses(app: AppHandle, review: String) -> Result<Vec<ScreeningDecision>, AppError> {
+    let index = Index::open(&data_dir(&app)?)?;
+    Ok(screening::list_screening(&index, &review)?)
+}
+
+// Exports take an optional screening filter so screened-out documents can be left out
+fn screen_matches(
+    app: &AppHandle,
+    matches: Vec<SearchMatch>,
+    screening: Option<ScreeningFilter>,
+) -> Result<Vec<SearchMatch>, AppError> {
+    match screening {
+        Some(filter) => {
+            let index = Index::open(&data_dir(app)?)?;
+            Ok(screening::filter_matches(&index, matches, &filter)?)
+        }
+        None => Ok(matches),
+    }
+}
+
+// How exports set matches off in their context, from the settings
+fn export_quote_style(app: &AppHandle) -> Result<QuoteStyle, AppError> {
+    Ok(settings::load_settings(&config_dir(app)?)?.export_quote_style)
+}
+
+#[tauri::command]
+fn get_search_history_stats(app: AppHandle, top_n: Option<usize>) -> Result<SearchHistoryStats, AppError> {
+    let history = history::load_history(&data_dir(&app)?)?;
+    Ok(history::history_stats(&history, top_n.unwrap_or(10)))
+}
+
+#[tauri::command]
+fn get_settings(app: AppHandle) -> Result<AppSettings, AppError> {
+    Ok(settings::load_settings(&config_dir(&app)?)?)
+}
+
+// Folders only become accessible by being chosen in a dialog: the default,
+// Zotero and CSL locale directories and the Pandoc reference document must
+// already be allowed, and the allowed folders can be removed but not added to.
+// External tools must be a program name or an existing file.
+#[tauri::command]
+fn save_settings(app: AppHandle, scope: State<'_, PathScope>, mut settings: AppSettings) -> Result<(), AppError> {
+    scope.check_all(
+        settings
+            .default_directory
+            .iter()
+            .chain(&settings.zotero_path)
+            .chain(&settings.csl_locales_dir)
+            .chain(&settings.pandoc_reference_doc)
+            .map(String::as_str),
+    )?;
+    settings::check_tool_commands(&settings)?;
+    corpora::check_corpora(&settings.corpora)?;
+    scope.check_all(settings.corpora.iter().flat_map(|corpus| &corpus.directories).map(String::as_str))?;
+    let config_dir = config_dir(&app)?;
+    let stored = settings::load_settings(&config_dir)?;
+    settings.allowed_roots.retain(|root| stored.allowed_roots.contains(root));
+
+    settings::save_settings(&config_dir, &settings)?;
+    apply_settings(&app, &scope, &settings);
+    Ok(())
+}
+
+// Hand the settings to the modules that keep their own copy
+fn apply_settings(app: &AppHandle, scope: &PathScope, settings: &AppSettings) {
+    i18n::set_language(&settings.language);
+    zotero_cache::set_policy(settings);
+    ocr::configure(settings);
+    csl_locale::configure(settings);
+    pandoc::configure(settings);
+    page_render::configure(settings);
+    problem_corpus::configure(settings, data_dir(app).ok().as_deref());
+    if platform::MOBILE {
+        scope.set_storage_roots(&storage_roots(app));
+    } else {
+        scope.set_settings_roots(settings);
+    }
+}
+
+// The app's own folders, which are all searches can read on mobile targets
+fn storage_roots(app: &AppHandle) -> Vec<PathBuf> {
+    let paths = app.path();
+    [paths.app_data_dir(), paths.app_local_data_dir(), paths.document_dir()]
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect()
+}
+
+// Write the team-shareable settings and the saved searches to one file
+#[tauri::command]
+fn export_config_bundle(app: AppHandle, scope: State<'_, PathScope>, output_path: String) -> Result<(), AppError> {
+    scope.check(&output_path)?;
+    let settings = settings::load_settings(&config_dir(&app)?)?;
+    let index = Index::open(&data_dir(&app)?)?;
+    config_bundle::export_bundle(Path::new(&output_path), &settings, &index)?;
+    Ok(())
+}
+
+// Set this machine up from a teammate's bundle. Its saved searches are rooted at
+// `directory` when given, and must lie within the folders the app may read.
+#[tauri::command]
+fn import_config_bundle(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    input_path: String,
+    directory: Option<String>,
+    include_settings: Option<bool>,
+) -> Result<ConfigImportReport, AppError> {
+    scope.check(&input_path)?;
+    if let Some(ref directory) = directory {
+        scope.check(directory)?;
+    }
+    let bundle = config_bundle::read_bundle(Path::new(&input_path))?;
+    let config_dir = config_dir(&app)?;
+    let mut settings = settings::load_settings(&config_dir)?;
+    let mut report = ConfigImportReport::default();
+
+    if include_settings.unwrap_or(true) {
+        bundle.settings.apply(&mut settings);
+        settings::save_settings(&config_dir, &settings)?;
+        apply_settings(&app, &scope, &settings);
+        report.settings_applied = true;
+    }
+
+    let index = Index::open(&data_dir(&app)?)?;
+    config_bundle::import_searches(
+        &index,
+        &bundle.saved_searches,
+        directory.as_deref(),
+        settings.zotero_path.as_deref(),
+        |params| Ok(check_search_scope(&scope, params)?),
+        &mut report,
+    )?;
+    Ok(report)
+}
+
+// The CPU threads, GPUs and OCR build found, and the OCR devices they allow, for
+// the performance settings
+#[tauri::command(async)]
+fn compute_capabilities(app: AppHandle) -> Result<compute::Capabilities, AppError> {
+    let settings = settings::load_settings(&config_dir(&app)?)?;
+    Ok(compute::capabilities(&settings.ocr_command))
+}
+
+// Which subsystems this platform has, so the frontend hides what can't work there,
+// such as Zotero's database and OCR on mobile
+#[tauri::command(async)]
+fn platform_capabilities(app: AppHandle) -> PlatformReport {
+    platform::report(&storage_roots(&app))
+}
+
+// PDFs collected because their text couldn't be extracted, for bug reports
+#[tauri::command]
+fn list_problem_pdfs(app: AppHandle) -> Result<ProblemCorpus, AppError> {
+    Ok(problem_corpus::list(&data_dir(&app)?)?)
+}
+
+#[tauri::command]
+fn remove_problem_pdf(app: AppHandle, file_path: String) -> Result<bool, AppError> {
+    Ok(problem_corpus::remove(&data_dir(&app)?, &file_path)?)
+}
+
+#[tauri::command]
+fn clear_problem_pdfs(app: AppHandle) -> Result<(), AppError> {
+    Ok(problem_corpus::clear(&data_dir(&app)?)?)
+}
+
+// Ask for a folder in a native dialog and let commands use it from now on
+#[tauri::command(async)]
+fn choose_directory(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    title: Option<String>,
+) -> Result<Option<String>, AppError> {
+    let mut dialog = app.dialog().file();
+    if let Some(title) = title {
+        dialog = dialog.set_title(title);
+    }
+    let Some(directory) = dialog.blocking_pick_folder() else {
+        return Ok(None);
+    };
+    let directory = directory
+        .as_path()
+        .ok_or_else(|| AppError::invalid_input("The chosen folder isn't a local path"))?
+        .to_string_lossy()
+        .to_string();
+
+    let config_dir = config_dir(&app)?;
+    let mut settings = settings::load_settings(&config_dir)?;
+    if !settings.allowed_roots.contains(&directory) {
+        settings.allowed_roots.push(directory.clone());
+        settings::save_settings(&config_dir, &settings)?;
+    }
+    scope.set_settings_roots(&settings);
+    Ok(Some(directory))
+}
+
+// Ask where to save an export; the chosen file may be written for the rest of the session
+#[tauri::command(async)]
+fn choose_save_path(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    title: Option<String>,
+    file_name: Option<String>,
+) -> Result<Option<String>, AppError> {
+    let mut dialog = app.dialog().file();
+    if let Some(title) = title {
+        dialog = dialog.set_title(title);
+    }
+    if let Some(file_name) = file_name {
+        dialog = dialog.set_file_name(file_name);
+    }
+    let Some(path) = dialog.blocking_save_file() else {
+        return Ok(None);
+    };
+    let path = path
+        .as_path()
+        .ok_or_else(|| AppError::invalid_input("The chosen file isn't a local path"))?
+        .to_path_buf();
+    scope.allow(&path);
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+// Ask for a file to open, such as a corpus bundle; allowed for the rest of the session
+#[tauri::command(async)]
+fn choose_file(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    title: Option<String>,
+    extensions: Option<Vec<String>>,
+) -> Result<Option<String>, AppError> {
+    let mut dialog = app.dialog().file();
+    if let Some(title) = title {
+        dialog = dialog.set_title(title);
+    }
+    if let Some(extensions) = extensions {
+        let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+        dialog = dialog.add_filter(extensions.join(", "), &extensions);
+    }
+    let Some(path) = dialog.blocking_pick_file() else {
+        return Ok(None);
+    };
+    let path = path
+        .as_path()
+        .ok_or_else(|| AppError::invalid_input("The chosen file isn't a local path"))?
+        .to_path_buf();
+    scope.allow(&path);
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+// The .pdfsearch.toml that searches rooted at `directory` would apply
+#[tauri::command]
+fn get_directory_config(scope: State<'_, PathScope>, directory: String) -> Result<Option<DirectoryConfig>, AppError> {
+    scope.check(&directory)?;
+    Ok(directory_config::load_directory_config(Path::new(&directory))?)
+}
+
+// Languages the backend's messages and exports are available in
+#[tauri::command]
+fn list_languages() -> Vec<&'static str> {
+    i18n::available_languages()
+}
+
+#[tauri::command]
+fn search_single_pdf_file(
+    scope: State<'_, PathScope>, mut params: SearchParams,
+) -> Result<Vec<SearchMatch>, AppError> {
+    scope.check(&params.directory)?;
+    palette::assign_colors(&mut params.queries, &[]);
+    Ok(search_single_pdf(params)?)
+}
+
+#[tauri::command(async)]
+fn preview_matches(
+    scope: State<'_, PathScope>,
+    file_path: String,
+    queries: Vec<QueryItem>,
+    limit: Option<usize>,
+) -> Result<Vec<SearchMatch>, AppError> {
+    scope.check(&file_path)?;
+    Ok(preview_pdf_matches(&PathBuf::from(file_path), queries, limit.unwrap_or(5))?)
+}
+
+// The results and parameters of the search a match ID belongs to, from the
+// window's last search or, failing that, from the saved results of that search
+fn lookup_search(
+    app: &AppHandle,
+    window_label: &str,
+    sessions: &SearchSessions,
+    match_id: &str,
+) -> Result<(ResultSet, SearchParams), AppError> {
+    search_results(app, window_label, sessions, pdf_search::match_search_id(match_id)?)
+}
+
+// The results and parameters of search `search_id`, from the window's last search
+// or, failing that, from its saved results. A streamed search's results are
+// only read as far as the caller needs them.
+fn search_results(
+    app: &AppHandle,
+    window_label: &str,
+    sessions: &SearchSessions,
+    search_id: u64,
+) -> Result<(ResultSet, SearchParams), AppError> {
+    match sessions.completed(window_label) {
+        Some(completed) if completed.search_id == search_id => Ok((completed.results, completed.audit.params)),
+        _ => {
+            let saved = results_store::load_results(&data_dir(app)?, search_id)?;
+            Ok((ResultSet::Returned(saved.matches), saved.info.params))
+        }
+    }
+}
+
+fn lookup_match(
+    app: &AppHandle,
+    window_label: &str,
+    sessions: &SearchSessions,
+    match_id: &str,
+) -> Result<(SearchMatch, SearchParams), AppError> {
+    // The window's own search, streamed or not, needn't be read whole
+    if let Some((m, audit)) = sessions.find_match(window_label, match_id) {
+        return Ok((m, audit.params));
+    }
+    let (results, params) = lookup_search(app, window_label, sessions, match_id)?;
+    let m = results
+        .find(match_id)?
+        .ok_or_else(|| AppError::invalid_input(format!("No match with ID {}", match_id)))?;
+    Ok((m, params))
+}
+
+// Widen a match's snippet for "show more" without re-running its search
+#[tauri::command(async)]
+fn expand_context(
+    window: Window,
+    sessions: State<'_, SearchSessions>,
+    match_id: String,
+    extra_words_before: usize,
+    extra_words_after: usize,
+) -> Result<SearchMatch, AppError> {
+    let (m, params) = lookup_match(window.app_handle(), window.label(), &sessions, &match_id)?;
+    let index = open_index(window.app_handle());
+    Ok(pdf_search::expand_context(&m, &params, extra_words_before, extra_words_after, index.as_ref())?)
+}
+
+// The hits before and after a match in its document, for viewer navigation
+#[tauri::command(async)]
+fn get_match_neighbors(
+    window: Window,
+    sessions: State<'_, SearchSessions>,
+    match_id: String,
+) -> Result<MatchNeighbors, AppError> {
+    let (results, _) = lookup_search(window.app_handle(), window.label(), &sessions, &match_id)?;
+    // Only the match's own document is read
+    let m = results
+        .find(&match_id)?
+        .ok_or_else(|| AppError::invalid_input(format!("No match with ID {}", match_id)))?;
+    let in_document = results.documents(&[m.item.file_path])?;
+    Ok(pdf_search::match_neighbors(in_document, &match_id)?)
+}
+
+// Turn a text selection in the viewer into a located, cited quotation, whether
+// or not it was a search hit
+#[tauri::command(async)]
+fn cite_selection(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    file_path: String,
+    selection: String,
+    page_number: Option<usize>,
+) -> Result<CitedSelection, AppError> {
+    scope.check(&file_path)?;
+    let settings = settings::load_settings(&config_dir(&app)?)?;
+    let zotero_map = match settings.zotero_path {
+        Some(zotero_path) => zotero_cache::zotero_map(Path::new(&zotero_path))
+            .map_err(|e| eprintln!("Warning: Failed to load Zotero database: {}", e))
+            .ok(),
+        None => None,
+    };
+    let index = open_index(&app);
+    Ok(pdf_search::cite_selection(
+        Path::new(&file_path),
+        &selection,
+        page_number,
+        zotero_map.as_ref(),
+        index.as_ref(),
+    )?)
+}
+
+#[tauri::command(async)]
+fn read_pdf_metadata(scope: State<'_, PathScope>, file_path: String) -> Result<PdfInfo, AppError> {
+    scope.check(&file_path)?;
+    Ok(pdf_metadata::read_pdf_info(Path::new(&file_path))?)
+}
+
+// Fill in the title, author and keywords of a loose PDF, keeping entries it already
+// has unless `overwrite` is set
+#[tauri::command(async)]
+fn write_pdf_metadata(
+    scope: State<'_, PathScope>,
+    file_path: String,
+    info: PdfInfo,
+    overwrite: Option<bool>,
+) -> Result<PdfInfo, AppError> {
+    scope.check(&file_path)?;
+    Ok(pdf_metadata::write_pdf_info(
+        Path::new(&file_path),
+        &info,
+        overwrite.unwrap_or(false),
+    )?)
+}
+
+// Per-page hit counts of one document, for the viewer's scrollbar heatmap
+#[tauri::command(async)]
+fn page_hit_counts(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    file_path: String,
+    queries: Vec<QueryItem>,
+    normalization: Option<NormalizationOptions>,
+) -> Result<PageHitCounts, AppError> {
+    scope.check(&file_path)?;
+    let index = open_index(&app);
+    Ok(pdf_search::page_hit_counts(
+        Path::new(&file_path),
+        queries,
+        normalization.unwrap_or_default(),
+        index.as_ref(),
+    )?)
+}
+
+// Large exports can be split into parts with `chunking`; returns the paths written
+#[tauri::command(async)]
+fn export_results_to_markdown(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    matches: Vec<SearchMatch>,
+    output_path: String,
+    chunking: Option<ChunkOptions>,
+    screening: Option<ScreeningFilter>,
+) -> Result<Vec<String>, AppError> {
+    scope.check(&output_path)?;
+    // Provenance hashes the source files
+    check_match_scope(&scope, &matches)?;
+    let matches = screen_matches(&app, matches, screening)?;
+    let mut chunking = chunking.unwrap_or_default();
+    chunking.quote_style = export_quote_style(&app)?;
+    let written = write_markdown_export(Path::new(&output_path), &matches, &chunking)?;
+    Ok(written.iter().map(|path| path.to_string_lossy().to_string()).collect())
+}
+
+#[tauri::command(async)]
+fn export_results_to_json(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    matches: Vec<SearchMatch>,
+    output_path: String,
+    screening: Option<ScreeningFilter>,
+) -> Result<(), AppError> {
+    scope.check(&output_path)?;
+    // Provenance hashes the source files
+    check_match_scope(&scope, &matches)?;
+    let matches = screen_matches(&app, matches, screening)?;
+    let json = export_to_json(&matches)?;
+    fs::write(&output_path, json).map_err(|e| AppError::io(e, &output_path))
+}
+
+#[tauri::command]
+fn export_results_to_csv(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    matches: Vec<SearchMatch>,
+    output_path: String,
+    screening: Option<ScreeningFilter>,
+) -> Result<(), AppError> {
+    scope.check(&output_path)?;
+    let matches = screen_matches(&app, matches, screening)?;
+    fs::write(&output_path, export_captures_to_csv(&matches)).map_err(|e| AppError::io(e, &output_path))
+}
+
+// Export in "markdown" (with citations and a per-match template), "csv", "json",
+// "bibtex" or "webannotation"
+#[tauri::command(async)]
+fn export_results(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    matches: Vec<SearchMatch>,
+    output_path: String,
+    format: String,
+    options: Option<ExportOptions>,
+    screening: Option<ScreeningFilter>,
+) -> Result<(), AppError> {
+    scope.check(&output_path)?;
+    // Provenance hashes the source files
+    check_match_scope(&scope, &matches)?;
+    let matches = screen_matches(&app, matches, screening)?;
+    let mut options = options.unwrap_or_default();
+    options.quote_style = export_quote_style(&app)?;
+    let matches = corpora::filter_matches(matches, &options.corpora);
+    if options.match_images && format == "markdown" {
+        options.images = page_render::render_match_images(&matches, Path::new(&output_path))?;
+    }
+    let exported = export::export_results(&matches, &format, &options)?;
+    fs::write(&output_path, exported).map_err(|e| AppError::io(e, &output_path))
+}
+
+// Export the matches of only the selected documents of a search, which is looked
+// up here rather than sent back from the frontend, headed by its parameters
+#[tauri::command(async)]
+fn export_selected_documents(
+    app: AppHandle,
+    window: Window,
+    sessions: State<'_, SearchSessions>,
+    scope: State<'_, PathScope>,
+    params: SelectionExportParams,
+) -> Result<(), AppError> {
+    let output_path = params.output_path;
+    scope.check(&output_path)?;
+    let (results, search_params) = search_results(&app, window.label(), &sessions, params.search_id)?;
+    let documents_matched = results.document_count()?;
+    let matches = results.documents(&params.file_paths)?;
+    if matches.is_empty() {
+        return Err(AppError::invalid_input(format!(
+            "None of the selected documents have matches in search {}",
+            params.search_id
+        )));
+    }
+    check_match_scope(&scope, &matches)?;
+    let matches = screen_matches(&app, matches, params.screening)?;
+
+    let mut options = params.options;
+    options.quote_style = export_quote_style(&app)?;
+    let matches = corpora::filter_matches(matches, &options.corpora);
+    if options.match_images && params.format == "markdown" {
+        options.images = page_render::render_match_images(&matches, Path::new(&output_path))?;
+    }
+    let header = SearchHeader {
+        search_id: params.search_id,
+        params: &search_params,
+        documents_selected: matches.iter().map(|m| &m.item.file_path).collect::<HashSet<_>>().len(),
+        documents_matched,
+    };
+    let exported = export::export_results(&matches, &params.format, &options)?;
+    let exported = export::with_search_header(exported, &params.format, &header)?;
+    fs::write(&output_path, exported).map_err(|e| AppError::io(e, &output_path))
+}
+
+// Export as a "docx" or "odt" document: the citation-aware markdown export converted
+// by Pandoc, styled by `options.reference_doc` or the settings' reference document
+#[tauri::command(async)]
+fn export_results_document(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    matches: Vec<SearchMatch>,
+    output_path: String,
+    format: String,
+    options: Option<ExportOptions>,
+    screening: Option<ScreeningFilter>,
+) -> Result<(), AppError> {
+    scope.check(&output_path)?;
+    check_match_scope(&scope, &matches)?;
+    let mut options = options.unwrap_or_default();
+    options.quote_style = export_quote_style(&app)?;
+    if let Some(reference_doc) = &options.reference_doc {
+        scope.check(reference_doc)?;
+    }
+    let matches = screen_matches(&app, matches, screening)?;
+    let matches = corpora::filter_matches(matches, &options.corpora);
+    // Images are embedded in the document, so their folder goes once it's written
+    if options.match_images {
+        options.images = page_render::render_match_images(&matches, Path::new(&output_path))?;
+    }
+    let markdown = export::export_citation_markdown(&matches, &options)?;
+    let converted = pandoc::convert(&markdown, &format, Path::new(&output_path), options.reference_doc.as_deref());
+    if options.match_images {
+        let _ = fs::remove_dir_all(page_render::images_dir(Path::new(&output_path)));
+    }
+    Ok(converted?)
+}
+
+// Pandoc's version, or null when it isn't installed or bundled, so DOCX and ODT
+// exports can be offered only when they'll work
+#[tauri::command(async)]
+fn pandoc_version() -> Option<String> {
+    pandoc::available()
+}
+
+// The start of a "markdown", "json" or "csv" export and its totals, for showing a
+// live preview while export options change; nothing is written
+#[tauri::command]
+fn preview_export(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    matches: Vec<SearchMatch>,
+    format: String,
+    options: Option<PreviewOptions>,
+    screening: Option<ScreeningFilter>,
+) -> Result<ExportPreview, AppError> {
+    // Provenance hashes the source files
+    check_match_scope(&scope, &matches)?;
+    let matches = screen_matches(&app, matches, screening)?;
+    let mut options = options.unwrap_or_default();
+    if options.quote_style.is_none() {
+        options.quote_style = Some(export_quote_style(&app)?);
+    }
+    Ok(export::preview_export(&matches, &format, &options)?)
+}
+
+// Per-query totals, top documents and years as a "markdown" or "csv" table, or
+// as "vega-lite": a JSON object of chart specs (hits_by_year, hits_per_document,
+// query_cooccurrence) with their data inline. Returns the table or specs, so they
+// can also be copied straight to the clipboard or rendered.
+#[tauri::command]
+fn export_query_summary(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    matches: Vec<SearchMatch>,
+    output_path: Option<String>,
+    format: String,
+    screening: Option<ScreeningFilter>,
+) -> Result<String, AppError> {
+    if let Some(output_path) = &output_path {
+        scope.check(output_path)?;
+    }
+    let matches = screen_matches(&app, matches, screening)?;
+    let table = match format.as_str() {
+        "markdown" => export::export_query_summary_markdown(&export::summarize_queries(&matches)),
+        "csv" => export::export_query_summary_csv(&export::summarize_queries(&matches)),
+        "vega-lite" => charts::export_query_charts(&matches)?,
+        _ => return Err(AppError::invalid_input(format!("Unknown summary format: {}", format))),
+    };
+    if let Some(output_path) = output_path {
+        fs::write(&output_path, &table).map_err(|e| AppError::io(e, &output_path))?;
+    }
+    Ok(table)
+}
+
+// Which combination of the queries each matched document hit, e.g. the documents
+// matching the first two queries but not the third, as labeled document lists.
+// `queries` gives their order and adds any that matched nothing.
+#[tauri::command]
+fn query_overlap(
+    app: AppHandle,
+    matches: Vec<SearchMatch>,
+    queries: Option<Vec<String>>,
+    screening: Option<ScreeningFilter>,
+) -> Result<Vec<QueryOverlap>, AppError> {
+    let matches = screen_matches(&app, matches, screening)?;
+    Ok(export::query_overlap(&matches, &queries.unwrap_or_default()))
+}
+
+// Longest body put in a mailto: link, once percent-encoded; many mail clients
+// truncate or reject longer URLs
+const MAILTO_BODY_LIMIT: usize = 1500;
+
+// The percent-encoded summary, cut to fit `MAILTO_BODY_LIMIT` with a pointer to
+// the full summary. Spaces, punctuation and non-ASCII text take three bytes per
+// UTF-8 byte once encoded.
+fn mailto_body(summary: &str, summary_path: &str) -> String {
+    let encoded = percent_encode(summary);
+    if encoded.len() <= MAILTO_BODY_LIMIT {
+        return encoded;
+    }
+    let tail = percent_encode(&format!("\n...\n\nFull summary: {}", summary_path));
+    let mut body = String::new();
+    for c in summary.chars() {
+        let encoded_char = percent_encode(c.encode_utf8(&mut [0; 4]));
+        if body.len() + encoded_char.len() + tail.len() > MAILTO_BODY_LIMIT {
+            break;
+        }
+        body.push_str(&encoded_char);
+    }
+    body + &tail
+}
+
+#[derive(Serialize)]
+struct ShareResult {
+    // Full summary written to a temporary file, for attaching or sharing from the file manager
+    summary_path: String,
+    mailto_link: Option<String>,
+}
+
+// Hand a compact result summary to the OS: `method: "email"` opens a new message in
+// the default mail client; otherwise the summary file is revealed in the file manager,
+// whose share menu can send it on (desktop webviews have no native share sheet)
 #[tauri::command]
-fn search_pdf_files(params: SearchParams) -> Result<Vec<SearchMatch>, String> {
-    search_pdfs(params).map_err(|e| e.to_string())
+fn share_results(
+    app: AppHandle,
+    matches: Vec<SearchMatch>,
+    method: Option<String>,
+    recipient: Option<String>,
+) -> Result<ShareResult, AppError> {
+    let summary = render_share_summary(&matches, &export_quote_style(&app)?);
+    let summary_path = std::env::temp_dir().join(format!("pdf-search-results-{}.txt", index::unix_now()));
+    fs::write(&summary_path, &summary).map_err(|e| AppError::io(e, &summary_path.to_string_lossy()))?;
+    let summary_path_str = summary_path.to_string_lossy().to_string();
+
+    let opener_error = |e: tauri_plugin_opener::Error| AppError::Other { message: e.to_string() };
+    if method.as_deref() == Some("email") {
+        let mailto_link = format!(
+            "mailto:{}?subject={}&body={}",
+            percent_encode(recipient.as_deref().unwrap_or_default()),
+            percent_encode(&format!("PDF search results ({} matches)", matches.len())),
+            mailto_body(&summary, &summary_path_str)
+        );
+        app.opener().open_url(&mailto_link, None::<&str>).map_err(opener_error)?;
+
+        return Ok(ShareResult {
+            summary_path: summary_path_str,
+            mailto_link: Some(mailto_link),
+        });
+    }
+
+    app.opener().reveal_item_in_dir(&summary_path).map_err(opener_error)?;
+    Ok(ShareResult {
+        summary_path: summary_path_str,
+        mailto_link: None,
+    })
+}
+
+// Send a match's quotation, rendered like a citation-aware markdown export, to a
+// note in Obsidian, or with `open_only` just open the note. Returns the URI opened.
+#[tauri::command(async)]
+fn send_match_to_obsidian(
+    window: Window,
+    sessions: State<'_, SearchSessions>,
+    match_id: String,
+    target: ObsidianTarget,
+    options: Option<ExportOptions>,
+) -> Result<String, AppError> {
+    let content = if target.open_only {
+        String::new()
+    } else {
+        let (m, _) = lookup_match(window.app_handle(), window.label(), &sessions, &match_id)?;
+        let mut options = options.unwrap_or_default();
+        options.quote_style = export_quote_style(window.app_handle())?;
+        export::render_match(&m, &options)?
+    };
+    let uri = note_apps::obsidian_uri(&target, &content)?;
+    window
+        .app_handle()
+        .opener()
+        .open_url(&uri, None::<&str>)
+        .map_err(|e| AppError::Other { message: e.to_string() })?;
+    Ok(uri)
+}
+
+// Append a match's quotation to a Logseq page as a new block, then open the page
+// in Logseq. Returns the URI opened.
+#[tauri::command(async)]
+fn send_match_to_logseq(
+    window: Window,
+    sessions: State<'_, SearchSessions>,
+    scope: State<'_, PathScope>,
+    match_id: String,
+    target: LogseqTarget,
+    options: Option<ExportOptions>,
+) -> Result<String, AppError> {
+    if !target.open_only {
+        scope.check(&target.graph_path)?;
+        let (m, _) = lookup_match(window.app_handle(), window.label(), &sessions, &match_id)?;
+        let mut options = options.unwrap_or_default();
+        options.quote_style = export_quote_style(window.app_handle())?;
+        let content = export::render_match(&m, &options)?;
+        note_apps::append_to_logseq(&target, &content)?;
+    }
+    let uri = note_apps::logseq_uri(&target)?;
+    window
+        .app_handle()
+        .opener()
+        .open_url(&uri, None::<&str>)
+        .map_err(|e| AppError::Other { message: e.to_string() })?;
+    Ok(uri)
+}
+
+// Package the matches, per-document files, bibliography and optional excerpt PDFs into one zip
+#[tauri::command(async)]
+fn export_results_bundle(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    matches: Vec<SearchMatch>,
+    output_path: String,
+    options: Option<BundleOptions>,
+    screening: Option<ScreeningFilter>,
+) -> Result<(), AppError> {
+    scope.check(&output_path)?;
+    check_match_scope(&scope, &matches)?;
+    let matches = screen_matches(&app, matches, screening)?;
+    let mut options = options.unwrap_or_default();
+    options.quote_style = export_quote_style(&app)?;
+    Ok(bundle::write_export_bundle(Path::new(&output_path), &matches, &options)?)
 }
 
+// Write the audit trail of the window's last completed search, to be kept alongside its exported results
 #[tauri::command]
-fn search_single_pdf_file(params: SearchParams) -> Result<Vec<SearchMatch>, String> {
-    search_single_pdf(params).map_err(|e| e.to_string())
+fn export_search_audit(
+    window: Window,
+    sessions: State<'_, SearchSessions>,
+    scope: State<'_, PathScope>,
+    output_path: String,
+) -> Result<(), AppError> {
+    scope.check(&output_path)?;
+    let audit = sessions
+        .audit(window.label())
+        .ok_or_else(|| AppError::invalid_input("No completed search to report on"))?;
+    fs::write(&output_path, export_audit_report(&audit)).map_err(|e| AppError::io(e, &output_path))
 }
 
 #[tauri::command]
-fn export_results_to_markdown(matches: Vec<SearchMatch>, output_path: String) -> Result<(), String> {
-    let markdown = export_to_markdown(&matches);
-    fs::write(&output_path, markdown).map_err(|e| e.to_string())
+fn read_pdf_file(scope: State<'_, PathScope>, file_path: String) -> Result<Vec<u8>, AppError> {
+    scope.check(&file_path)?;
+    fs::read(&file_path).map_err(|e| AppError::io(e, &file_path))
 }
 
 #[tauri::command]
-fn read_pdf_file(file_path: String) -> Result<Vec<u8>, String> {
-    fs::read(&file_path).map_err(|e| format!("Failed to read PDF file: {}", e))
+fn list_pdf_files(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    params: ListPdfsParams,
+) -> Result<Vec<LibraryItem>, AppError> {
+    scope.check(&params.directory)?;
+    if let Some(zotero_path) = &params.zotero_path {
+        scope.check(zotero_path)?;
+    }
+    let index = open_index(&app);
+    Ok(list_pdfs(params, index.as_ref())?)
+}
+
+// Proposed `Author_Year_Title.pdf`-style names for the PDFs under a directory
+#[tauri::command(async)]
+fn suggest_filenames(
+    scope: State<'_, PathScope>,
+    directory: String,
+    pattern: Option<String>,
+    zotero_path: Option<String>,
+) -> Result<Vec<RenameSuggestion>, AppError> {
+    scope.check_all(std::iter::once(directory.as_str()).chain(zotero_path.as_deref()))?;
+    Ok(rename::suggest_filenames(
+        Path::new(&directory),
+        pattern.as_deref(),
+        zotero_path.as_deref().map(Path::new),
+    )?)
+}
+
+// Rename files and move their index entries along. Files the settings' Zotero
+// library links to are skipped.
+#[tauri::command(async)]
+fn apply_renames(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    renames: Vec<FileRename>,
+    dry_run: bool,
+) -> Result<Vec<RenameOutcome>, AppError> {
+    scope.check_all(renames.iter().map(|rename| rename.file_path.as_str()))?;
+    let zotero_path = settings::load_settings(&config_dir(&app)?)?.zotero_path;
+    let index = open_index(&app);
+    Ok(rename::apply_renames(index.as_ref(), &renames, zotero_path.as_deref().map(Path::new), dry_run))
+}
+
+// Groups of byte-identical PDFs under a directory, with the copy to keep chosen
+#[tauri::command(async)]
+fn find_duplicates(
+    scope: State<'_, PathScope>,
+    directory: String,
+    zotero_path: Option<String>,
+) -> Result<Vec<DuplicateGroup>, AppError> {
+    scope.check_all(std::iter::once(directory.as_str()).chain(zotero_path.as_deref()))?;
+    Ok(duplicates::find_duplicates(
+        Path::new(&directory),
+        zotero_path.as_deref().map(Path::new),
+    )?)
+}
+
+// Move the extra copies of each group into a quarantine folder and drop them from
+// the index. Copies the settings' Zotero library links to are skipped.
+#[tauri::command(async)]
+fn quarantine_duplicates(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    directory: String,
+    groups: Vec<DuplicateGroup>,
+    quarantine_dir: Option<String>,
+    dry_run: bool,
+) -> Result<Vec<QuarantineOutcome>, AppError> {
+    let files = groups.iter().flat_map(|group| group.files.iter().map(|file| file.file_path.as_str()));
+    scope.check_all(std::iter::once(directory.as_str()).chain(quarantine_dir.as_deref()).chain(files))?;
+
+    let zotero_path = settings::load_settings(&config_dir(&app)?)?.zotero_path;
+    let index = open_index(&app);
+    Ok(duplicates::quarantine_duplicates(
+        index.as_ref(),
+        Path::new(&directory),
+        &groups,
+        quarantine_dir.as_deref().map(Path::new),
+        zotero_path.as_deref().map(Path::new),
+        dry_run,
+    ))
+}
+
+// Share the extracted text of a corpus so other machines can skip extraction
+#[tauri::command(async)]
+fn export_corpus_bundle(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    root: String,
+    output_path: String,
+    zotero_path: Option<String>,
+) -> Result<CorpusSummary, AppError> {
+    scope.check_all([root.as_str(), output_path.as_str()].into_iter().chain(zotero_path.as_deref()))?;
+    let index = Index::open(&data_dir(&app)?)?;
+    Ok(corpus::export_corpus_bundle(
+        &index,
+        Path::new(&root),
+        Path::new(&output_path),
+        zotero_path.as_deref().map(Path::new),
+    )?)
+}
+
+// `root` is where this machine's copy of the corpus lives
+#[tauri::command(async)]
+fn import_corpus_bundle(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    bundle_path: String,
+    root: String,
+) -> Result<CorpusSummary, AppError> {
+    scope.check_all([bundle_path.as_str(), root.as_str()])?;
+    let index = Index::open(&data_dir(&app)?)?;
+    Ok(corpus::import_corpus_bundle(&index, Path::new(&bundle_path), Path::new(&root))?)
+}
+
+// Report which files under `root` changed since their text was cached, or since `bundle_path` was exported
+#[tauri::command(async)]
+fn verify_sources(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    root: String,
+    bundle_path: Option<String>,
+) -> Result<SourceVerification, AppError> {
+    scope.check_all(std::iter::once(root.as_str()).chain(bundle_path.as_deref()))?;
+    let index = Index::open(&data_dir(&app)?)?;
+    let settings = settings::load_settings(&config_dir(&app)?)?;
+    let walk = WalkOptions {
+        skip_dirs: Some(settings.skip_dirs),
+        ..Default::default()
+    };
+    Ok(corpus::verify_sources(
+        &index,
+        Path::new(&root),
+        bundle_path.as_deref().map(Path::new),
+        &walk,
+    )?)
+}
+
+// Suggest alternatives for the queries of a search that found little, from its
+// `query_document_counts`, using the cached text under `directory`
+#[tauri::command(async)]
+fn suggest_queries(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    directory: String,
+    query_counts: Vec<QueryDocumentCount>,
+) -> Result<Vec<QuerySuggestion>, AppError> {
+    scope.check(&directory)?;
+    let index = Index::open(&data_dir(&app)?)?;
+    let low_yield: Vec<String> = query_counts
+        .into_iter()
+        .filter(|count| count.documents <= vocabulary::LOW_YIELD_DOCUMENTS)
+        .map(|count| count.query)
+        .collect();
+    if low_yield.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(vocabulary::suggest_queries(&index, Path::new(&directory), &low_yield)?)
+}
+
+// Most frequent terms and bigrams in the cached text under `directory`, for
+// autocomplete and exploring a corpus
+#[tauri::command(async)]
+fn get_corpus_vocabulary(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    directory: String,
+    top_n: usize,
+) -> Result<CorpusVocabulary, AppError> {
+    scope.check(&directory)?;
+    let index = Index::open(&data_dir(&app)?)?;
+    Ok(vocabulary::corpus_vocabulary(&index, Path::new(&directory), top_n)?)
+}
+
+// Autocomplete for the query input: indexed terms starting with `prefix`
+#[tauri::command(async)]
+fn suggest_terms(app: AppHandle, prefix: String, limit: usize) -> Result<Vec<TermSuggestion>, AppError> {
+    let index = Index::open(&data_dir(&app)?)?;
+    Ok(vocabulary::suggest_terms(&index, &prefix, limit)?)
+}
+
+// Compare extraction backends' throughput on the PDFs under `directory`
+#[tauri::command(async)]
+fn run_benchmark(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    directory: String,
+    queries: Vec<QueryItem>,
+    max_files: Option<usize>,
+) -> Result<BenchmarkReport, AppError> {
+    scope.check(&directory)?;
+    let index = Index::open(&data_dir(&app)?)?;
+    let settings = settings::load_settings(&config_dir(&app)?)?;
+    let walk = WalkOptions {
+        skip_dirs: Some(settings.skip_dirs),
+        ..Default::default()
+    };
+    Ok(benchmark::run_benchmark(Path::new(&directory), queries, &walk, Some(&index), max_files)?)
+}
+
+// Check that extraction, search and OCR work on this install
+#[tauri::command(async)]
+fn run_self_test() -> Result<SelfTestReport, AppError> {
+    Ok(self_test::run_self_test()?)
+}
+
+fn index_directory(app: &AppHandle, directory: &str, rebuild: bool) -> Result<IndexReport, AppError> {
+    let index = Index::open(&data_dir(app)?)?;
+    let settings = settings::load_settings(&config_dir(app)?)?;
+    let walk = WalkOptions {
+        skip_dirs: Some(settings.skip_dirs),
+        ..Default::default()
+    };
+    let (pdf_files, _) = walk::walk_pdfs(Path::new(directory), &walk)?;
+    // Date-added filters count from here rather than from the first search that finds the file
+    index.register_files(&pdf_files)?;
+    Ok(pdf_search::index_files(&pdf_files, &index, rebuild))
+}
+
+// Extract every PDF under `directory` into the index again
+#[tauri::command(async)]
+fn build_index(app: AppHandle, scope: State<'_, PathScope>, directory: String) -> Result<IndexReport, AppError> {
+    scope.check(&directory)?;
+    index_directory(&app, &directory, true)
+}
+
+// Extract only the PDFs under `directory` that are new or changed since they were indexed
+#[tauri::command(async)]
+fn update_index(app: AppHandle, scope: State<'_, PathScope>, directory: String) -> Result<IndexReport, AppError> {
+    scope.check(&directory)?;
+    index_directory(&app, &directory, false)
+}
+
+// Drop cached text no search can use, verify the rest against the files and compact the index
+#[tauri::command(async)]
+fn maintain_index(app: AppHandle, options: Option<MaintenanceOptions>) -> Result<MaintenanceReport, AppError> {
+    let index = Index::open(&data_dir(&app)?)?;
+    Ok(maintenance::maintain_index(&index, &options.unwrap_or_default())?)
+}
+
+// Apply a tag to every Zotero item whose attachment is among the matches
+#[tauri::command(async)]
+fn tag_matched_items(app: AppHandle, matches: Vec<SearchMatch>, tag: String) -> Result<TagResult, AppError> {
+    let settings = settings::load_settings(&config_dir(&app)?)?;
+    let (Some(user_id), Some(api_key)) = (settings.zotero_user_id, settings.zotero_api_key) else {
+        return Err(AppError::invalid_input(
+            "Tagging requires a Zotero user ID and API key with write access in the settings",
+        ));
+    };
+    let credentials = ZoteroApiCredentials { user_id, api_key };
+
+    let item_keys = zotero_api::matched_item_keys(&matches);
+    Ok(zotero_api::tag_items(&credentials, &item_keys, &tag)?)
 }
 
 #[tauri::command]
-fn list_pdf_files(params: ListPdfsParams) -> Result<Vec<PdfListItem>, String> {
-    list_pdfs(params).map_err(|e| e.to_string())
+fn zotero_link_report(
+    scope: State<'_, PathScope>,
+    params: ZoteroLinkReportParams,
+) -> Result<ZoteroLinkReport, AppError> {
+    scope.check_all([params.directory.as_str(), params.zotero_path.as_str()])?;
+    Ok(build_zotero_link_report(params)?)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -34,13 +1812,117 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .manage(SearchSessions::default())
+        .manage(PathScope::default())
+        .setup(|app| {
+            platform::configure();
+            match config_dir(app.handle()).and_then(|dir| Ok(settings::load_settings(&dir)?)) {
+                Ok(settings) => apply_settings(app.handle(), &app.state::<PathScope>(), &settings),
+                Err(e) => eprintln!("Warning: Failed to load settings: {}", e),
+            }
+            // Lets the UI show when Zotero metadata is out of date
+            let handle = app.handle().clone();
+            zotero_cache::on_freshness(move |freshness| {
+                let _ = handle.emit("zotero://metadata-freshness", freshness);
+            });
+            Ok(())
+        })
+        .on_window_event(|window, event| match event {
+            WindowEvent::Destroyed => window.state::<SearchSessions>().remove(window.label()),
+            // Whatever the user drops onto the window may be searched
+            WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) => {
+                let scope = window.state::<PathScope>();
+                for path in paths {
+                    scope.allow(path);
+                }
+            }
+            _ => {}
+        })
         .invoke_handler(tauri::generate_handler![
             search_pdf_files,
+            search_as_you_type,
+            search_dropped_paths,
+            cancel_search,
+            get_search_results,
+            get_search_results_page,
+            quick_search,
+            save_results,
+            load_results,
+            list_saved_results,
+            restyle_search_queries,
+            register_corpus_subset,
+            list_corpus_subsets,
+            delete_corpus_subset,
+            save_search,
+            list_saved_searches,
+            rerun_search,
+            set_screening_status,
+            list_screening_statuses,
+            get_search_history_stats,
+            get_settings,
+            save_settings,
+            compute_capabilities,
+            platform_capabilities,
+            export_config_bundle,
+            import_config_bundle,
+            list_problem_pdfs,
+            remove_problem_pdf,
+            clear_problem_pdfs,
             search_single_pdf_file,
+            preview_matches,
             export_results_to_markdown,
+            export_results_to_json,
+            export_results_to_csv,
+            export_results,
+            export_selected_documents,
+            export_results_document,
+            pandoc_version,
+            export_results_bundle,
+            share_results,
+            export_search_audit,
             read_pdf_file,
-            list_pdf_files
+            list_pdf_files,
+            zotero_link_report,
+            tag_matched_items,
+            export_corpus_bundle,
+            import_corpus_bundle,
+            verify_sources,
+            run_benchmark,
+            run_self_test,
+            suggest_queries,
+            get_corpus_vocabulary,
+            suggest_terms,
+            page_hit_counts,
+            expand_context,
+            get_match_neighbors,
+            cite_selection,
+            exclude_document,
+            restore_excluded_document,
+            list_languages,
+            get_directory_config,
+            export_query_summary,
+            query_overlap,
+            read_pdf_metadata,
+            write_pdf_metadata,
+            suggest_filenames,
+            apply_renames,
+            find_duplicates,
+            quarantine_duplicates,
+            choose_directory,
+            choose_save_path,
+            choose_file,
+            build_index,
+            update_index,
+            maintain_index,
+            preview_export,
+            send_match_to_obsidian,
+            send_match_to_logseq
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_, event| {
+            if let RunEvent::Exit = event {
+                ocr::shutdown();
+            }
+        });
 }
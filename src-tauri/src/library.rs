@@ -0,0 +1,73 @@
+use crate::fallback_metadata::FallbackMetadata;
+use crate::pdf_search::ZoteroMetadata;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// Searches, listings and exports all deal in documents that come from some library:
+// a folder of PDFs or a Zotero library, and other formats later. What identifies
+// the document and where it came from lives here, so a new source adds a variant
+// rather than another optional field on every match.
+
+/// Which library a document was found through
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemSource {
+    // A file in a searched folder, or one dropped on the app
+    #[default]
+    File,
+    // An attachment listed in the Zotero library
+    Zotero,
+}
+
+/// The document's file format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemFormat {
+    #[default]
+    Pdf,
+}
+
+/// A document in one of the libraries the app searches. Matches and listings
+/// carry one, flattened into their own fields when serialized.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryItem {
+    pub file_path: String,
+    pub file_name: String,
+    #[serde(default)]
+    pub source: ItemSource,
+    #[serde(default)]
+    pub format: ItemFormat,
+    // Link to the Zotero item, when the document matched one in the library
+    pub zotero_link: Option<String>,
+    pub zotero_metadata: Option<ZoteroMetadata>,
+    // Title, authors and year guessed from the document, for documents without a
+    // Zotero item; see `fallback_metadata`
+    #[serde(default)]
+    pub fallback_metadata: Option<FallbackMetadata>,
+    // Paths of identical copies folded into this one by `collapse_duplicates`
+    #[serde(default)]
+    pub other_copies: Vec<String>,
+}
+
+impl LibraryItem {
+    /// The document at `path`, with no Zotero item yet
+    pub fn new(path: &Path, source: ItemSource) -> Self {
+        LibraryItem {
+            file_path: path.to_string_lossy().to_string(),
+            file_name: path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            source,
+            format: ItemFormat::Pdf,
+            zotero_link: None,
+            zotero_metadata: None,
+            fallback_metadata: None,
+            other_copies: Vec::new(),
+        }
+    }
+
+    /// Attach the Zotero item the document matched, and its link
+    pub fn with_zotero(mut self, metadata: Option<ZoteroMetadata>) -> Self {
+        self.zotero_link = metadata.as_ref().map(|metadata| metadata.zotero_link.clone());
+        self.zotero_metadata = metadata;
+        self
+    }
+}
@@ -0,0 +1,127 @@
+use crate::index::{self, Index};
+use anyhow::Result;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+
+// The index only ever grows on its own: text of deleted and replaced files stays
+// cached, the term index accumulates segments, and SQLite keeps freed pages. Over
+// months of use that adds up, so maintenance clears out what no search can use
+// and rewrites the database compactly, checking it along the way.
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MaintenanceOptions {
+    // Keep the cached text and catalog entries of files that are gone, for archive
+    // searches (`source: "archive"`) and imported corpora not present on this machine
+    #[serde(default)]
+    pub keep_missing: bool,
+}
+
+/// What `maintain_index` found and did
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MaintenanceReport {
+    // Files with cached text that were checked against the disk
+    pub files_checked: usize,
+    // Cached text dropped because the file no longer exists
+    pub missing_removed: usize,
+    // Cached text dropped because the file's contents no longer hash the same
+    pub changed_removed: usize,
+    // Files whose modification time changed but whose contents didn't
+    pub mtimes_updated: usize,
+    // Catalog entries dropped because the file no longer exists
+    pub catalog_removed: usize,
+    // What SQLite's integrity check reported, before and after compaction; empty when sound
+    pub integrity_problems: Vec<String>,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    pub bytes_reclaimed: u64,
+}
+
+// What to do with one cached file after looking at the disk
+enum CachedState {
+    Unchanged,
+    Missing,
+    Changed,
+    Touched(u64),
+}
+
+/// Check the index against the files it describes and compact it. Every cached
+/// file is hashed again, so a changed file is caught even when its size and
+/// modification time weren't; this reads the whole corpus and is meant to run
+/// in the background now and then, not before every search.
+pub fn maintain_index(index: &Index, options: &MaintenanceOptions) -> Result<MaintenanceReport> {
+    let mut report = MaintenanceReport {
+        integrity_problems: index.integrity_problems()?,
+        ..Default::default()
+    };
+
+    let cached = index.cached_fingerprints()?;
+    report.files_checked = cached.len();
+    let states: Vec<(String, CachedState)> = cached
+        .into_par_iter()
+        .map(|(path, size, mtime, sha256)| {
+            let file = Path::new(&path);
+            let state = match file.metadata() {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => CachedState::Missing,
+                // Unreadable for now, which isn't a reason to lose its text
+                Err(e) => {
+                    eprintln!("Warning: Failed to read {}: {}", path, e);
+                    CachedState::Unchanged
+                }
+                Ok(metadata) if metadata.len() != size => CachedState::Changed,
+                Ok(_) => match index::file_sha256(file) {
+                    Ok(current) if current != sha256 => CachedState::Changed,
+                    Ok(_) => match index::file_mtime(file) {
+                        Some(current) if current != mtime => CachedState::Touched(current),
+                        _ => CachedState::Unchanged,
+                    },
+                    Err(e) => {
+                        eprintln!("Warning: Failed to hash {}: {}", path, e);
+                        CachedState::Unchanged
+                    }
+                },
+            };
+            (path, state)
+        })
+        .collect();
+
+    for (path, state) in states {
+        match state {
+            CachedState::Unchanged => {}
+            CachedState::Missing if options.keep_missing => {}
+            CachedState::Missing => {
+                index.forget_cached_text(&path)?;
+                report.missing_removed += 1;
+            }
+            CachedState::Changed => {
+                index.forget_cached_text(&path)?;
+                report.changed_removed += 1;
+            }
+            CachedState::Touched(mtime) => {
+                index.touch_cached_text(&path, mtime)?;
+                report.mtimes_updated += 1;
+            }
+        }
+    }
+
+    if !options.keep_missing {
+        for path in index.catalog_paths()? {
+            if !Path::new(&path).exists() {
+                index.forget_file(&path)?;
+                report.catalog_removed += 1;
+            }
+        }
+    }
+
+    let (before, after) = index.compact()?;
+    report.bytes_before = before;
+    report.bytes_after = after;
+    report.bytes_reclaimed = before.saturating_sub(after);
+    for problem in index.integrity_problems()? {
+        if !report.integrity_problems.contains(&problem) {
+            report.integrity_problems.push(problem);
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,189 @@
+use crate::pdf_search::SearchMatch;
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const MB: usize = 1024 * 1024;
+// Results beyond this share of the ceiling go to disk, leaving the rest for extraction
+const RESULT_SHARE: usize = 2;
+const THROTTLE_POLL: Duration = Duration::from_millis(10);
+
+/// What the search's memory tracking saw and how it adapted
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MemoryDiagnostics {
+    pub limit_mb: Option<u64>,
+    // Estimated peak of extraction buffers plus held results
+    pub peak_mb: u64,
+    // Matches written to a temporary store instead of being held in memory
+    pub spilled_matches: usize,
+    // Files whose extraction waited for others to finish to stay under the limit
+    pub throttled_files: usize,
+}
+
+/// Approximate accounting of what a search holds in memory. Extraction is
+/// estimated from file sizes, since a PDF is loaded whole before its text is
+/// pulled out; results from the lengths of their strings.
+pub struct MemoryBudget {
+    limit: Option<usize>,
+    extraction: AtomicUsize,
+    results: AtomicUsize,
+    active: AtomicUsize,
+    peak: AtomicUsize,
+    throttled: AtomicUsize,
+}
+
+/// Extraction memory held for one file; released on drop
+pub struct Reservation<'a> {
+    budget: &'a MemoryBudget,
+    bytes: usize,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        self.budget.extraction.fetch_sub(self.bytes, Ordering::Relaxed);
+        self.budget.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl MemoryBudget {
+    pub fn new(limit_mb: Option<u64>) -> Self {
+        MemoryBudget {
+            limit: limit_mb.map(|mb| mb as usize * MB),
+            extraction: AtomicUsize::new(0),
+            results: AtomicUsize::new(0),
+            active: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            throttled: AtomicUsize::new(0),
+        }
+    }
+
+    fn used(&self) -> usize {
+        self.extraction.load(Ordering::Relaxed) + self.results.load(Ordering::Relaxed)
+    }
+
+    /// Account for extracting a file of `bytes`. Near the limit this waits until
+    /// other extractions finish, which narrows the search's effective parallelism;
+    /// a lone extraction always proceeds so the search can't stall.
+    pub fn reserve(&self, bytes: usize) -> Reservation<'_> {
+        if let Some(limit) = self.limit {
+            let mut waited = false;
+            while self.active.load(Ordering::Relaxed) > 0 && self.used() + bytes > limit {
+                waited = true;
+                std::thread::sleep(THROTTLE_POLL);
+            }
+            if waited {
+                self.throttled.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        self.active.fetch_add(1, Ordering::Relaxed);
+        self.extraction.fetch_add(bytes, Ordering::Relaxed);
+        self.peak.fetch_max(self.used(), Ordering::Relaxed);
+        Reservation { budget: self, bytes }
+    }
+
+    /// Account for results kept in memory. Returns false when they should be
+    /// spilled to disk instead.
+    pub fn hold_results(&self, matches: &[SearchMatch]) -> bool {
+        let bytes: usize = matches.iter().map(estimated_size).sum();
+        if let Some(limit) = self.limit {
+            if self.results.load(Ordering::Relaxed) + bytes > limit / RESULT_SHARE {
+                return false;
+            }
+        }
+        self.results.fetch_add(bytes, Ordering::Relaxed);
+        self.peak.fetch_max(self.used(), Ordering::Relaxed);
+        true
+    }
+
+    pub fn diagnostics(&self, spilled_matches: usize) -> MemoryDiagnostics {
+        MemoryDiagnostics {
+            limit_mb: self.limit.map(|limit| (limit / MB) as u64),
+            peak_mb: self.peak.load(Ordering::Relaxed).div_ceil(MB) as u64,
+            spilled_matches,
+            throttled_files: self.throttled.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn estimated_size(m: &SearchMatch) -> usize {
+    std::mem::size_of::<SearchMatch>()
+        + m.item.file_path.len()
+        + m.item.file_name.len()
+        + m.context_before.len()
+        + m.matched_text.len()
+        + m.context_after.len()
+        + m.item.zotero_metadata.as_ref().map_or(0, |_| 512)
+}
+
+static NEXT_SPILL: AtomicU64 = AtomicU64::new(0);
+
+/// Temporary SQLite store for matches that didn't fit in the memory budget.
+/// Matches are read back per file once extraction is done; the file is
+/// deleted on drop.
+pub struct ResultSpill {
+    conn: Mutex<Connection>,
+    path: PathBuf,
+    count: AtomicUsize,
+}
+
+impl ResultSpill {
+    pub fn create() -> Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "pdf-search-spill-{}-{}.sqlite",
+            std::process::id(),
+            NEXT_SPILL.fetch_add(1, Ordering::Relaxed)
+        ));
+        let conn = Connection::open(&path)
+            .context(format!("Failed to create result spill store: {}", path.display()))?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = OFF;
+             PRAGMA synchronous = OFF;
+             CREATE TABLE spill (file_index INTEGER PRIMARY KEY, matches TEXT NOT NULL);",
+        )?;
+        Ok(ResultSpill {
+            conn: Mutex::new(conn),
+            path,
+            count: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn store(&self, file_index: usize, matches: &[SearchMatch]) -> Result<()> {
+        let json = serde_json::to_string(matches)?;
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO spill (file_index, matches) VALUES (?1, ?2)",
+            params![file_index as i64, json],
+        )?;
+        self.count.fetch_add(matches.len(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn take(&self, file_index: usize) -> Result<Vec<SearchMatch>> {
+        let conn = self.conn.lock().unwrap();
+        let json: String = conn.query_row(
+            "SELECT matches FROM spill WHERE file_index = ?",
+            [file_index as i64],
+            |row| row.get(0),
+        )?;
+        conn.execute("DELETE FROM spill WHERE file_index = ?", [file_index as i64])?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for ResultSpill {
+    fn drop(&mut self) {
+        // Close the connection first; Windows won't delete an open file
+        if let (Ok(conn), Ok(placeholder)) = (self.conn.get_mut(), Connection::open_in_memory()) {
+            drop(std::mem::replace(conn, placeholder));
+        }
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::pdf_search::QueryItem;
+use anyhow::Result;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+// Spotlight on macOS and the Windows Search index already hold the text of most
+// local PDFs. Asking them which files contain a query's words lets a search skip
+// opening files that can't match. Their tokenization differs from ours, so this is
+// an opt-in approximation: files the platform hasn't indexed (network volumes,
+// excluded folders, very recent downloads) are dropped too.
+
+/// Whether the platform has a search index to ask
+pub fn available() -> bool {
+    cfg!(any(windows, target_os = "macos"))
+}
+
+/// Narrow `pdf_files` to those the platform's search index reports as possibly
+/// matching. Every literal filter query's words must be present, and when all
+/// parallel queries are literal, one of them must be. Regex and boolean queries
+/// can't be expressed to the index and are left to the full search.
+pub fn prefilter(pdf_files: Vec<PathBuf>, queries: &[QueryItem], scope: Option<&Path>) -> Result<Vec<PathBuf>> {
+    let (filters, parallels): (Vec<&QueryItem>, Vec<&QueryItem>) =
+        queries.iter().partition(|q| q.query_type == "filter");
+
+    let mut required: Vec<HashSet<String>> = Vec::new();
+    for query in filters.iter().filter(|q| !q.use_regex && !q.boolean) {
+        required.push(files_with_all_words(&query.query, scope)?);
+    }
+    if !parallels.is_empty() && parallels.iter().all(|q| !q.use_regex && !q.boolean) {
+        let mut any = HashSet::new();
+        for query in &parallels {
+            any.extend(files_with_all_words(&query.query, scope)?);
+        }
+        required.push(any);
+    }
+
+    Ok(pdf_files
+        .into_iter()
+        .filter(|path| {
+            let key = path_key(path);
+            required.iter().all(|files| files.contains(&key))
+        })
+        .collect())
+}
+
+// Words are looked up separately, since our matching ignores the whitespace,
+// hyphens and line breaks between them that the index would treat as a phrase boundary
+fn files_with_all_words(query: &str, scope: Option<&Path>) -> Result<HashSet<String>> {
+    let mut files: Option<HashSet<String>> = None;
+    for word in query.split_whitespace() {
+        let found: HashSet<String> = files_containing(word, scope)?.iter().map(|path| path_key(path)).collect();
+        files = Some(match files {
+            Some(files) => files.intersection(&found).cloned().collect(),
+            None => found,
+        });
+    }
+    Ok(files.unwrap_or_default())
+}
+
+// Paths as the platform compares them
+fn path_key(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if cfg!(any(windows, target_os = "macos")) {
+        path.to_lowercase()
+    } else {
+        path.to_string()
+    }
+}
+
+// PDFs whose indexed text contains `word` anywhere, case- and diacritic-insensitively
+#[cfg(target_os = "macos")]
+fn files_containing(word: &str, scope: Option<&Path>) -> Result<Vec<PathBuf>> {
+    use anyhow::Context;
+
+    let escaped = word.replace('\\', "\\\\").replace('"', "\\\"").replace('*', "\\*");
+    let query = format!(
+        "kMDItemContentTypeTree == \"com.adobe.pdf\" && kMDItemTextContent == \"*{}*\"cd",
+        escaped
+    );
+    let mut command = std::process::Command::new("mdfind");
+    if let Some(scope) = scope {
+        command.arg("-onlyin").arg(scope);
+    }
+    let output = command.arg(query).output().context("Failed to run mdfind")?;
+    if !output.status.success() {
+        anyhow::bail!("mdfind failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+// The Windows Search index only supports prefix wildcards, so this finds words
+// that start with `word`
+#[cfg(windows)]
+fn files_containing(word: &str, scope: Option<&Path>) -> Result<Vec<PathBuf>> {
+    use anyhow::Context;
+
+    let term = word.replace('"', "").replace('\'', "''");
+    let mut sql = format!(
+        "SELECT System.ItemPathDisplay FROM SYSTEMINDEX WHERE System.FileExtension = '.pdf' AND CONTAINS(*, '\"{}*\"')",
+        term
+    );
+    if let Some(scope) = scope {
+        let scope = scope.to_string_lossy().replace('\\', "/").replace('\'', "''");
+        sql.push_str(&format!(" AND SCOPE = 'file:{}'", scope));
+    }
+
+    // The index is exposed through OLE DB, which PowerShell reaches through ADO
+    let script = format!(
+        "[Console]::OutputEncoding = [Text.Encoding]::UTF8
+         $connection = New-Object -ComObject ADODB.Connection
+         $connection.Open(\"Provider=Search.CollatorDSO;Extended Properties='Application=Windows';\")
+         $rows = $connection.Execute('{}')
+         while (-not $rows.EOF) {{ $rows.Fields.Item(0).Value; $rows.MoveNext() }}",
+        sql.replace('\'', "''")
+    );
+    let output = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .output()
+        .context("Failed to run PowerShell")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Windows Search query failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn files_containing(_word: &str, _scope: Option<&Path>) -> Result<Vec<PathBuf>> {
+    anyhow::bail!("No platform search index is available on this system")
+}
@@ -0,0 +1,139 @@
+use crate::error::AppError;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// Sending a quote straight to a notes app. Obsidian is driven through its
+// obsidian:// URIs; appending under a heading needs the Advanced URI community
+// plugin, since Obsidian's own URIs can only append to the end of a note.
+// Logseq's URIs can only open pages, so quotes are appended to the page's file in
+// the graph folder, which Logseq picks up, and the page is then opened.
+
+/// Where a quote goes in Obsidian
+#[derive(Debug, Clone, Deserialize)]
+pub struct ObsidianTarget {
+    pub vault: String,
+    // Note path within the vault, with or without ".md"
+    pub file: String,
+    // Append under this heading instead of at the end; needs Advanced URI
+    #[serde(default)]
+    pub heading: Option<String>,
+    // Only open the note (at the heading), without adding the quote
+    #[serde(default)]
+    pub open_only: bool,
+}
+
+/// Where a quote goes in Logseq
+#[derive(Debug, Clone, Deserialize)]
+pub struct LogseqTarget {
+    // The graph's folder; its name is the graph name Logseq's URIs use
+    pub graph_path: String,
+    pub page: String,
+    #[serde(default)]
+    pub open_only: bool,
+}
+
+// Percent-encode everything but RFC 3986 unreserved characters
+pub fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// The obsidian:// URI that opens the target note, or appends `content` to it
+pub fn obsidian_uri(target: &ObsidianTarget, content: &str) -> Result<String> {
+    if target.vault.trim().is_empty() || target.file.trim().is_empty() {
+        return Err(AppError::invalid_input("An Obsidian vault and note are required").into());
+    }
+    let vault = percent_encode(&target.vault);
+    let file = target.file.strip_suffix(".md").unwrap_or(&target.file);
+
+    Ok(match (&target.heading, target.open_only) {
+        (Some(heading), true) => format!(
+            "obsidian://advanced-uri?vault={}&filepath={}&heading={}",
+            vault,
+            percent_encode(&format!("{}.md", file)),
+            percent_encode(heading)
+        ),
+        (Some(heading), false) => format!(
+            "obsidian://advanced-uri?vault={}&filepath={}&heading={}&mode=append&data={}",
+            vault,
+            percent_encode(&format!("{}.md", file)),
+            percent_encode(heading),
+            percent_encode(content)
+        ),
+        (None, true) => format!("obsidian://open?vault={}&file={}", vault, percent_encode(file)),
+        // Creates the note if it doesn't exist yet
+        (None, false) => format!(
+            "obsidian://new?vault={}&file={}&content={}&append=true",
+            vault,
+            percent_encode(file),
+            percent_encode(content)
+        ),
+    })
+}
+
+/// The logseq:// URI that opens the target page
+pub fn logseq_uri(target: &LogseqTarget) -> Result<String> {
+    let graph = Path::new(&target.graph_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| AppError::invalid_input(format!("Not a Logseq graph folder: {}", target.graph_path)))?;
+    Ok(format!("logseq://graph/{}?page={}", percent_encode(&graph), percent_encode(&target.page)))
+}
+
+// The page's file in the graph, named as Logseq names new pages: namespaces
+// ("Project/Notes") joined with "___" and characters file systems reject encoded
+fn logseq_page_path(target: &LogseqTarget) -> PathBuf {
+    let name: String = target
+        .page
+        .replace('/', "___")
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '\\' | '|' | '?' | '*' | '%' => format!("%{:02X}", c as u32),
+            c => c.to_string(),
+        })
+        .collect();
+    Path::new(&target.graph_path).join("pages").join(format!("{}.md", name))
+}
+
+/// Append `content` to the Logseq page as a new top-level block, creating the
+/// page's file if needed. Returns the file written.
+pub fn append_to_logseq(target: &LogseqTarget, content: &str) -> Result<PathBuf> {
+    if target.page.trim().is_empty() {
+        return Err(AppError::invalid_input("A Logseq page is required").into());
+    }
+    if !Path::new(&target.graph_path).join("logseq").is_dir() {
+        return Err(AppError::invalid_input(format!("Not a Logseq graph folder: {}", target.graph_path)).into());
+    }
+
+    // Continuation lines are indented under the block's bullet
+    let mut block = String::new();
+    for (i, line) in content.trim_end().lines().enumerate() {
+        block.push_str(if i == 0 { "- " } else { "  " });
+        block.push_str(line);
+        block.push('\n');
+    }
+
+    let path = logseq_page_path(target);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).context(format!("Failed to create {}", dir.display()))?;
+    }
+    let needs_newline = fs::read(&path).is_ok_and(|existing| !existing.is_empty() && !existing.ends_with(b"\n"));
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .context(format!("Failed to open {}", path.display()))?;
+    if needs_newline {
+        file.write_all(b"\n")?;
+    }
+    file.write_all(block.as_bytes())
+        .context(format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
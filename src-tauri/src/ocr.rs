@@ -0,0 +1,358 @@
+use crate::compute::{self, ComputeDevice};
+use crate::settings::AppSettings;
+use anyhow::{anyhow, Context, Result};
+use flate2::read::ZlibDecoder;
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+// Scanned PDFs have no text layer, only an image per page. With OCR turned on,
+// pages whose text is missing or garbled have their largest image read by the
+// Tesseract command-line tool. lopdf can't render pages, so only images Tesseract
+// reads directly are used: JPEG, JPEG 2000, uncompressed or Flate-compressed
+// grayscale, RGB and 1-bit images, and CCITT fax images. JBIG2 scans aren't read.
+// A document's pages are read a batch at a time, one Tesseract process each.
+
+// Fewer non-space characters than this on a page means there's no real text layer
+const MIN_PAGE_CHARS: usize = 20;
+
+// Below this share of letters and digits, the text is mostly extraction garbage
+const MIN_ALPHANUMERIC_SHARE: f64 = 0.5;
+
+struct OcrConfig {
+    command: String,
+    language: String,
+    device: ComputeDevice,
+    batch_size: usize,
+    // Checked on first use after each configuration: None when the command
+    // doesn't run, else whether it was built with OpenCL
+    available: Option<Option<bool>>,
+}
+
+static CONFIG: RwLock<OcrConfig> = RwLock::new(OcrConfig {
+    command: String::new(),
+    language: String::new(),
+    device: ComputeDevice::Auto,
+    batch_size: 1,
+    available: None,
+});
+
+// How one page's Tesseract is run
+struct OcrRun {
+    command: String,
+    language: String,
+    // Limit on its OpenMP threads, so a batch doesn't oversubscribe the CPU
+    threads: Option<usize>,
+}
+
+static NEXT_IMAGE: AtomicU64 = AtomicU64::new(0);
+
+/// Take the OCR command, language, device and batch size from the settings
+pub fn configure(settings: &AppSettings) {
+    *CONFIG.write().unwrap() = OcrConfig {
+        command: settings.ocr_command.clone(),
+        language: settings.ocr_language.clone(),
+        device: settings.ocr_device,
+        batch_size: settings.ocr_batch_size.max(1),
+        available: None,
+    };
+}
+
+/// How many of a document's pages are read at once
+pub fn batch_size() -> usize {
+    CONFIG.read().unwrap().batch_size
+}
+
+// Page images are written here for Tesseract to read, one process's worth
+fn work_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("pdf-search-ocr-{}", std::process::id()))
+}
+
+/// Remove images left behind by OCR calls that didn't finish
+pub fn shutdown() {
+    let _ = fs::remove_dir_all(work_dir());
+}
+
+// None when the command can't be run, which is only reported the first time
+fn ocr_run() -> Option<OcrRun> {
+    {
+        let config = CONFIG.read().unwrap();
+        match config.available {
+            Some(Some(opencl)) => return Some(run_for(&config, opencl)),
+            Some(None) => return None,
+            None => {}
+        }
+    }
+
+    let mut config = CONFIG.write().unwrap();
+    let available = Command::new(&config.command)
+        .arg("--version")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout).contains("OpenCL")
+                || String::from_utf8_lossy(&output.stderr).contains("OpenCL")
+        });
+    config.available = Some(available);
+    let Some(opencl) = available else {
+        eprintln!("Warning: OCR command not found: {}", config.command);
+        return None;
+    };
+    if matches!(config.device, ComputeDevice::Metal | ComputeDevice::Cuda) && !opencl {
+        eprintln!(
+            "Warning: {} wasn't built with OpenCL, so OCR runs on the CPU",
+            config.command
+        );
+    }
+    Some(run_for(&config, opencl))
+}
+
+fn run_for(config: &OcrConfig, opencl: bool) -> OcrRun {
+    // On the CPU, a batch's processes share the threads; Tesseract built with
+    // OpenCL picks its GPU itself
+    let on_cpu = config.device == ComputeDevice::Cpu || !opencl;
+    OcrRun {
+        command: config.command.clone(),
+        language: config.language.clone(),
+        threads: (on_cpu && config.batch_size > 1).then(|| (compute::cpu_threads() / config.batch_size).max(1)),
+    }
+}
+
+/// Whether the OCR command runs
+pub fn available() -> bool {
+    ocr_run().is_some()
+}
+
+/// Whether a page's extracted text is too thin or garbled to search, as with scans
+pub fn needs_ocr(text: &str) -> bool {
+    !has_text(text) || garbled(text)
+}
+
+/// Whether a page has enough text to be a real text layer
+pub fn has_text(text: &str) -> bool {
+    text.chars().filter(|c| !c.is_whitespace()).take(MIN_PAGE_CHARS).count() == MIN_PAGE_CHARS
+}
+
+/// Whether a page's text is mostly symbols rather than letters and digits, as
+/// when its fonts don't map their glyphs to Unicode
+pub fn garbled(text: &str) -> bool {
+    let chars: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let alphanumeric = chars.iter().filter(|c| c.is_alphanumeric()).count();
+    (alphanumeric as f64) < chars.len() as f64 * MIN_ALPHANUMERIC_SHARE
+}
+
+/// Read the text of a page's largest image. None when the page has no image
+/// Tesseract can read or Tesseract isn't installed; an error when it fails.
+pub fn ocr_page(doc: &Document, page_id: ObjectId) -> Result<Option<String>> {
+    let Some(image) = largest_image(doc, page_id) else {
+        return Ok(None);
+    };
+    let Some((extension, bytes)) = image_file(doc, image)? else {
+        return Ok(None);
+    };
+    let Some(run) = ocr_run() else {
+        return Ok(None);
+    };
+
+    let dir = work_dir();
+    fs::create_dir_all(&dir).context(format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(format!("page-{}.{}", NEXT_IMAGE.fetch_add(1, Ordering::Relaxed), extension));
+    fs::write(&path, bytes).context(format!("Failed to write {}", path.display()))?;
+
+    let mut tesseract = Command::new(&run.command);
+    tesseract.arg(&path).arg("stdout").args(["-l", &run.language]);
+    if let Some(threads) = run.threads {
+        tesseract.env("OMP_THREAD_LIMIT", threads.to_string());
+    }
+    let output = tesseract.output();
+    let _ = fs::remove_file(&path);
+    let output = output.context(format!("Failed to run {}", run.command))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} failed: {}",
+            run.command,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let text = String::from_utf8_lossy(&output.stdout).to_string();
+    Ok(Some(text).filter(|text| !text.trim().is_empty()))
+}
+
+fn resolve<'a>(doc: &'a Document, object: &'a Object) -> Option<&'a Object> {
+    match object {
+        Object::Reference(id) => doc.get_object(*id).ok(),
+        _ => Some(object),
+    }
+}
+
+// The image XObject covering the most pixels among the page's resources
+fn largest_image(doc: &Document, page_id: ObjectId) -> Option<&Stream> {
+    let (page_resources, inherited) = doc.get_page_resources(page_id);
+    let resources = page_resources
+        .into_iter()
+        .chain(inherited.into_iter().filter_map(|id| doc.get_dictionary(id).ok()));
+
+    let mut largest: Option<(i64, &Stream)> = None;
+    for resources in resources {
+        let xobjects = resources.get(b"XObject").ok().and_then(|o| resolve(doc, o));
+        let Some(Object::Dictionary(xobjects)) = xobjects else {
+            continue;
+        };
+        for (_, xobject) in xobjects.iter() {
+            let Some(Object::Stream(stream)) = resolve(doc, xobject) else {
+                continue;
+            };
+            if stream.dict.get(b"Subtype").and_then(Object::as_name_str).ok() != Some("Image") {
+                continue;
+            }
+            let pixels =
+                integer(&stream.dict, b"Width").unwrap_or(0) * integer(&stream.dict, b"Height").unwrap_or(0);
+            if largest.is_none_or(|(most, _)| pixels > most) {
+                largest = Some((pixels, stream));
+            }
+        }
+    }
+    largest.map(|(_, stream)| stream)
+}
+
+fn integer(dict: &Dictionary, key: &[u8]) -> Option<i64> {
+    dict.get(key).and_then(Object::as_i64).ok()
+}
+
+// Components per pixel of a gray or RGB color space, directly or through an ICC profile
+fn color_components(doc: &Document, image: &Stream) -> Option<i64> {
+    match resolve(doc, image.dict.get(b"ColorSpace").ok()?)? {
+        Object::Name(name) if name == b"DeviceGray" => Some(1),
+        Object::Name(name) if name == b"DeviceRGB" => Some(3),
+        Object::Array(array) if array.first().and_then(|o| o.as_name_str().ok()) == Some("ICCBased") => {
+            let Some(Object::Stream(profile)) = resolve(doc, array.get(1)?) else {
+                return None;
+            };
+            integer(&profile.dict, b"N").filter(|n| *n == 1 || *n == 3)
+        }
+        _ => None,
+    }
+}
+
+// The image as a file Tesseract can read: its extension and contents
+fn image_file(doc: &Document, image: &Stream) -> Result<Option<(&'static str, Vec<u8>)>> {
+    let filters = image.filters().unwrap_or_default();
+    let width = integer(&image.dict, b"Width").unwrap_or(0);
+    let height = integer(&image.dict, b"Height").unwrap_or(0);
+    if width <= 0 || height <= 0 {
+        return Ok(None);
+    }
+
+    match filters.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["DCTDecode"] => Ok(Some(("jpg", image.content.clone()))),
+        ["JPXDecode"] => Ok(Some(("jp2", image.content.clone()))),
+        ["CCITTFaxDecode"] => Ok(Some(("tif", ccitt_tiff(image, width, height)))),
+        [] | ["FlateDecode"] => {
+            // PNG-style predictors would have to be undone first
+            let params = image.dict.get(b"DecodeParms").and_then(Object::as_dict).ok();
+            if params.and_then(|p| integer(p, b"Predictor")).is_some_and(|predictor| predictor > 1) {
+                return Ok(None);
+            }
+            let pixels = if filters.is_empty() {
+                image.content.clone()
+            } else {
+                let mut pixels = Vec::new();
+                ZlibDecoder::new(image.content.as_slice())
+                    .read_to_end(&mut pixels)
+                    .context("Failed to decompress page image")?;
+                pixels
+            };
+            Ok(netpbm(doc, image, width, height, pixels).map(|pnm| ("pnm", pnm)))
+        }
+        _ => Ok(None),
+    }
+}
+
+// Uncompressed samples as PGM, PPM or, for 1-bit images, PBM
+fn netpbm(doc: &Document, image: &Stream, width: i64, height: i64, pixels: Vec<u8>) -> Option<Vec<u8>> {
+    let image_mask = image.dict.get(b"ImageMask").and_then(Object::as_bool).unwrap_or(false);
+    let bits = if image_mask { 1 } else { integer(&image.dict, b"BitsPerComponent")? };
+    let components = if image_mask { 1 } else { color_components(doc, image)? };
+    let row_bytes = (width * components * bits + 7) / 8;
+    if (pixels.len() as i64) < row_bytes * height {
+        return None;
+    }
+
+    let (magic, body) = match (bits, components) {
+        // PDF gray has 0 as black and PBM has 1 as black; an image mask paints its 1 bits
+        (1, 1) if image_mask => ("P4", pixels),
+        (1, 1) => ("P4", pixels.iter().map(|byte| !byte).collect()),
+        (8, 1) => ("P5", pixels),
+        (8, 3) => ("P6", pixels),
+        _ => return None,
+    };
+    let header = if magic == "P4" {
+        format!("{}\n{} {}\n", magic, width, height)
+    } else {
+        format!("{}\n{} {}\n255\n", magic, width, height)
+    };
+    let mut file = header.into_bytes();
+    file.extend_from_slice(&body[..(row_bytes * height) as usize]);
+    Some(file)
+}
+
+// Fax-compressed data wrapped in a minimal single-strip TIFF
+fn ccitt_tiff(image: &Stream, width: i64, height: i64) -> Vec<u8> {
+    let params = image.dict.get(b"DecodeParms").and_then(Object::as_dict).ok();
+    let k = params.and_then(|p| integer(p, b"K")).unwrap_or(0);
+    let black_is_1 = params
+        .and_then(|p| p.get(b"BlackIs1").and_then(Object::as_bool).ok())
+        .unwrap_or(false);
+    let rows = params.and_then(|p| integer(p, b"Rows")).unwrap_or(height);
+
+    // Group 4 for K < 0, otherwise Group 3 with 2D coding when K > 0
+    let (compression, t4_options) = if k < 0 { (4, None) } else { (3, Some(u32::from(k > 0))) };
+    let data_offset: u32 = 8;
+    let data = &image.content;
+
+    let mut entries: Vec<(u16, u16, u32)> = vec![
+        (256, 4, width as u32),          // ImageWidth
+        (257, 4, rows as u32),           // ImageLength
+        (258, 3, 1),                     // BitsPerSample
+        (259, 3, compression),           // Compression
+        (262, 3, u32::from(black_is_1)), // PhotometricInterpretation: white is zero unless BlackIs1
+        (273, 4, data_offset),           // StripOffsets
+        (277, 3, 1),                     // SamplesPerPixel
+        (278, 4, rows as u32),           // RowsPerStrip
+        (279, 4, data.len() as u32),     // StripByteCounts
+    ];
+    if let Some(options) = t4_options {
+        entries.push((292, 4, options)); // T4Options
+    }
+    entries.sort_by_key(|(tag, _, _)| *tag);
+
+    // Little-endian header, then the data, then the directory at an even offset
+    let ifd_offset = data_offset + data.len() as u32 + (data.len() as u32 & 1);
+    let mut tiff = Vec::new();
+    tiff.extend_from_slice(b"II");
+    tiff.extend_from_slice(&42u16.to_le_bytes());
+    tiff.extend_from_slice(&ifd_offset.to_le_bytes());
+    tiff.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        tiff.push(0);
+    }
+    tiff.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    for (tag, kind, value) in entries {
+        tiff.extend_from_slice(&tag.to_le_bytes());
+        tiff.extend_from_slice(&kind.to_le_bytes());
+        tiff.extend_from_slice(&1u32.to_le_bytes());
+        if kind == 3 {
+            tiff.extend_from_slice(&(value as u16).to_le_bytes());
+            tiff.extend_from_slice(&[0, 0]);
+        } else {
+            tiff.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    tiff.extend_from_slice(&0u32.to_le_bytes());
+    tiff
+}
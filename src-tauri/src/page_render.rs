@@ -0,0 +1,244 @@
+use crate::layout::{self, Rect};
+use crate::pdf_search::SearchMatch;
+use crate::settings::AppSettings;
+use crate::structure::StructureTree;
+use anyhow::{anyhow, Context, Result};
+use lopdf::{Document, ObjectId};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::RwLock;
+use unicode_normalization::UnicodeNormalization;
+
+// Images of where matches sit on the page, for exports of PDFs whose text
+// extracts badly. lopdf can't render pages, so Poppler's pdftoppm renders the
+// cropped region; the region comes from the match's character boxes (see `layout`).
+
+// Resolution of the images, in dots per inch
+const DPI: f64 = 150.0;
+
+// Space kept around the matched text, in points: a line above and below, and
+// some of the line on either side
+const PADDING_X: f64 = 48.0;
+const PADDING_Y: f64 = 18.0;
+
+struct RenderConfig {
+    command: String,
+    // Checked on first use after each configuration
+    available: Option<bool>,
+}
+
+static CONFIG: RwLock<RenderConfig> = RwLock::new(RenderConfig {
+    command: String::new(),
+    available: None,
+});
+
+/// Take the pdftoppm command from the settings
+pub fn configure(settings: &AppSettings) {
+    *CONFIG.write().unwrap() = RenderConfig {
+        command: settings.pdftoppm_command.clone(),
+        available: None,
+    };
+}
+
+// None when pdftoppm can't be run, which is only reported the first time
+fn command() -> Option<String> {
+    {
+        let config = CONFIG.read().unwrap();
+        match config.available {
+            Some(true) => return Some(config.command.clone()),
+            Some(false) => return None,
+            None => {}
+        }
+    }
+
+    let mut config = CONFIG.write().unwrap();
+    let available = Command::new(&config.command)
+        .arg("-v")
+        .output()
+        .is_ok_and(|output| output.status.success());
+    config.available = Some(available);
+    if !available {
+        eprintln!("Warning: Page render command not found: {}", config.command);
+        return None;
+    }
+    Some(config.command.clone())
+}
+
+/// Identifies a match among an export's, for looking up its image
+pub type MatchKey = (String, usize, Option<usize>, Option<String>);
+
+pub fn match_key(m: &SearchMatch) -> MatchKey {
+    (m.item.file_path.clone(), m.page_number, m.offset, m.query.clone())
+}
+
+/// Render each match's region of its page into `<output stem>_images/` next to
+/// `output_path`. Returns the images' paths relative to the output's folder, by
+/// match; matches that can't be located or rendered have none.
+pub fn render_match_images(matches: &[SearchMatch], output_path: &Path) -> Result<HashMap<MatchKey, String>> {
+    let mut images = HashMap::new();
+    let Some(command) = command() else {
+        return Ok(images);
+    };
+
+    let dir = images_dir(output_path);
+    let folder = dir.file_name().unwrap_or_default().to_string_lossy().to_string();
+    fs::create_dir_all(&dir).context(format!("Failed to create {}", dir.display()))?;
+
+    let mut by_file: BTreeMap<&str, Vec<&SearchMatch>> = BTreeMap::new();
+    for m in matches {
+        by_file.entry(m.item.file_path.as_str()).or_default().push(m);
+    }
+
+    let mut number = 0;
+    for (file_path, file_matches) in by_file {
+        let doc = match load(file_path) {
+            Ok(doc) => doc,
+            Err(e) => {
+                eprintln!("Warning: No match images for {}: {:#}", file_path, e);
+                continue;
+            }
+        };
+        let pages = doc.get_pages();
+        // Tagged pages were searched in their structure's order, which source spans refer to
+        let structure = StructureTree::read(&doc);
+        // Character boxes per page, worked out once for all of its matches
+        let mut layouts = HashMap::new();
+
+        for m in file_matches {
+            let Some(&page_id) = pages.get(&(m.page_number as u32)) else {
+                continue;
+            };
+            let boxes = match m.bounding_boxes {
+                Some(ref boxes) => boxes.clone(),
+                None => {
+                    let page_layout = layouts
+                        .entry(m.page_number)
+                        .or_insert_with(|| {
+                            layout::page_layout(&doc, page_id).map(|page_layout| {
+                                match structure.as_ref().and_then(|structure| structure.arrange(page_id, &page_layout)) {
+                                    Some(tagged) => tagged.layout,
+                                    None => page_layout,
+                                }
+                            })
+                        });
+                    match (page_layout.as_ref(), m.source_span.as_ref()) {
+                        (Some(page_layout), Some(span)) if !m.ocr => {
+                            match source_range(&page_layout.text, span.start..span.end, &span.text) {
+                                Some(range) => layout::boxes_for_range(&page_layout.glyphs, &range),
+                                None => continue,
+                            }
+                        }
+                        _ => continue,
+                    }
+                }
+            };
+            let Some(region) = boxes.into_iter().reduce(|a, b| Rect {
+                x0: a.x0.min(b.x0),
+                y0: a.y0.min(b.y0),
+                x1: a.x1.max(b.x1),
+                y1: a.y1.max(b.y1),
+            }) else {
+                continue;
+            };
+
+            number += 1;
+            let name = format!("match-{:03}", number);
+            match render_region(&command, &doc, page_id, file_path, m.page_number, region, &dir.join(&name)) {
+                Ok(()) => {
+                    images.insert(match_key(m), format!("{}/{}.png", folder, name));
+                }
+                Err(e) => eprintln!("Warning: Failed to render page {} of {}: {:#}", m.page_number, file_path, e),
+            }
+        }
+    }
+
+    Ok(images)
+}
+
+/// The folder beside an export its match images go in
+pub fn images_dir(output_path: &Path) -> PathBuf {
+    let stem = output_path.file_stem().unwrap_or_default().to_string_lossy();
+    output_path.with_file_name(format!("{}_images", stem))
+}
+
+fn load(file_path: &str) -> Result<Document> {
+    let mut doc = Document::load(file_path).context(format!("Failed to load PDF: {}", file_path))?;
+    if doc.is_encrypted() {
+        doc.decrypt("").map_err(|_| anyhow!("The PDF is encrypted"))?;
+    }
+    Ok(doc)
+}
+
+// The byte range in `text` whose NFC form is the UTF-16 range `span` of the
+// text's NFC form, as `SourceSpan` records it; None if it doesn't read `expected`
+fn source_range(text: &str, span: Range<usize>, expected: &str) -> Option<Range<usize>> {
+    // NFC only combines a character with the marks that follow it, so the text is
+    // normalized in runs that each start with a character that isn't a mark
+    let mut utf16 = 0;
+    let mut start = None;
+    let mut run_start = 0;
+    let mut boundaries = text
+        .char_indices()
+        .filter(|(i, c)| *i > 0 && !unicode_normalization::char::is_combining_mark(*c))
+        .map(|(i, _)| i)
+        .chain(std::iter::once(text.len()));
+    loop {
+        if utf16 == span.start && start.is_none() {
+            start = Some(run_start);
+        }
+        if utf16 == span.end {
+            let start = start?;
+            let matched: String = text[start..run_start].nfc().collect();
+            return (matched == expected).then_some(start..run_start);
+        }
+        let run_end = boundaries.next()?;
+        utf16 += text[run_start..run_end].nfc().map(char::len_utf16).sum::<usize>();
+        run_start = run_end;
+    }
+}
+
+// Render `region` of the page, padded and kept within the page, to `<out>.png`
+fn render_region(
+    command: &str,
+    doc: &Document,
+    page_id: ObjectId,
+    file_path: &str,
+    page_number: usize,
+    region: Rect,
+    out: &Path,
+) -> Result<()> {
+    let geometry = layout::page_geometry(doc, page_id).ok_or_else(|| anyhow!("the page has no media box"))?;
+    let region = geometry
+        .to_display(&region)
+        .ok_or_else(|| anyhow!("the match lies outside the page"))?;
+
+    // pdftoppm crops in pixels from the top-left corner of the crop box, turned
+    // by the page's rotation, which is the display space of `PageGeometry`
+    let scale = DPI / 72.0;
+    let x0 = (region.x0 - PADDING_X).max(0.0);
+    let x1 = (region.x1 + PADDING_X).min(geometry.width);
+    let y0 = (region.y0 - PADDING_Y).max(0.0);
+    let y1 = (region.y1 + PADDING_Y).min(geometry.height);
+    let pixels = |points: f64| (points * scale).round().to_string();
+
+    let output = Command::new(command)
+        .args(["-png", "-singlefile", "-cropbox", "-r", &DPI.to_string()])
+        .args(["-f", &page_number.to_string(), "-l", &page_number.to_string()])
+        .args(["-x", &pixels(x0), "-y", &pixels(y0)])
+        .args(["-W", &pixels(x1 - x0), "-H", &pixels(y1 - y0)])
+        .arg(file_path)
+        .arg(out)
+        .output()
+        .context(format!("Failed to run {}", command))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} failed: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use crate::error::AppError;
+use crate::export::SearchAudit;
+use crate::pdf_search::{QueryItem, SearchMatch, SearchParams};
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::HashSet;
+
+// The Okabe-Ito palette, whose colors stay distinguishable under the common forms
+// of color blindness. Its black is left out, since text can't be highlighted with it.
+pub const PALETTE: &[&str] = &[
+    "#E69F00", // orange
+    "#56B4E9", // sky blue
+    "#009E73", // bluish green
+    "#F0E442", // yellow
+    "#0072B2", // blue
+    "#D55E00", // vermillion
+    "#CC79A7", // reddish purple
+];
+
+/// Give every query that has no color one from the palette. A query keeps the
+/// color it had in `previous` (the stored version of the same query set), and
+/// otherwise takes the first palette color no other query uses, cycling through
+/// the palette again once every color is taken.
+pub fn assign_colors(queries: &mut [QueryItem], previous: &[QueryItem]) {
+    for query in queries.iter_mut().filter(|q| q.color.is_empty()) {
+        if let Some(earlier) = previous.iter().find(|p| p.query == query.query && !p.color.is_empty()) {
+            query.color = earlier.color.clone();
+        }
+    }
+
+    let mut used: HashSet<String> = queries
+        .iter()
+        .filter(|q| !q.color.is_empty())
+        .map(|q| q.color.to_lowercase())
+        .collect();
+    let mut cycled = 0;
+    for query in queries.iter_mut().filter(|q| q.color.is_empty()) {
+        let color = PALETTE
+            .iter()
+            .copied()
+            .find(|color| !used.contains(&color.to_lowercase()))
+            .unwrap_or_else(|| {
+                cycled += 1;
+                PALETTE[(cycled - 1) % PALETTE.len()]
+            });
+        used.insert(color.to_lowercase());
+        query.color = color.to_string();
+    }
+}
+
+/// A new color or name for one of a stored search's queries
+#[derive(Debug, Clone, Deserialize)]
+pub struct QueryRestyle {
+    // The query as the search has it now
+    pub query: String,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub rename: Option<String>,
+}
+
+// "#rgb" or "#rrggbb"
+fn is_hex_color(color: &str) -> bool {
+    color
+        .strip_prefix('#')
+        .is_some_and(|hex| matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Check `changes` against the search's queries: each names one of them, colors
+/// are hex, and no two queries end up with the same text
+pub fn check_restyles(queries: &[QueryItem], changes: &[QueryRestyle]) -> Result<()> {
+    for change in changes {
+        if !queries.iter().any(|q| q.query == change.query) {
+            return Err(AppError::invalid_input(format!("The search has no query {}", change.query)).into());
+        }
+        if let Some(color) = change.color.as_deref().filter(|color| !is_hex_color(color)) {
+            return Err(AppError::invalid_input(format!("Not a hex color: {}", color)).into());
+        }
+        if change.rename.as_deref().is_some_and(|name| name.trim().is_empty()) {
+            return Err(AppError::invalid_input("A query can't be renamed to nothing").into());
+        }
+    }
+    let mut names = HashSet::new();
+    for query in queries {
+        if !names.insert(restyled_name(&query.query, changes)) {
+            return Err(AppError::invalid_input(format!(
+                "Two queries would be named {}",
+                restyled_name(&query.query, changes)
+            ))
+            .into());
+        }
+    }
+    Ok(())
+}
+
+fn restyled_name<'a>(query: &'a str, changes: &'a [QueryRestyle]) -> &'a str {
+    changes
+        .iter()
+        .find(|change| change.query == query)
+        .and_then(|change| change.rename.as_deref())
+        .unwrap_or(query)
+}
+
+// Apply the change for `query`, if there is one, to a query's text and color
+fn restyle(query: &mut String, color: &mut String, changes: &[QueryRestyle]) {
+    let Some(change) = changes.iter().find(|change| change.query == *query) else {
+        return;
+    };
+    if let Some(ref new_color) = change.color {
+        *color = new_color.clone();
+    }
+    if let Some(ref rename) = change.rename {
+        *query = rename.clone();
+    }
+}
+
+/// Recolor and rename a search's queries as recorded in its parameters. A
+/// renamed query's text changes with it, so re-running the search looks for
+/// the new text.
+pub fn restyle_params(params: &mut SearchParams, changes: &[QueryRestyle]) {
+    for query in &mut params.queries {
+        restyle(&mut query.query, &mut query.color, changes);
+    }
+}
+
+/// Recolor and rename the queries a match and its context highlights came from
+pub fn restyle_match(m: &mut SearchMatch, changes: &[QueryRestyle]) {
+    if let Some(ref mut query) = m.query {
+        restyle(query, &mut m.color, changes);
+    }
+    for highlight in &mut m.highlights {
+        restyle(&mut highlight.query, &mut highlight.color, changes);
+    }
+}
+
+/// Recolor and rename the queries in a search's audit trail
+pub fn restyle_audit(audit: &mut SearchAudit, changes: &[QueryRestyle]) {
+    restyle_params(&mut audit.params, changes);
+    for count in &mut audit.stats.query_document_counts {
+        let mut color = String::new();
+        restyle(&mut count.query, &mut color, changes);
+    }
+}
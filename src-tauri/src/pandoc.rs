@@ -0,0 +1,114 @@
+use crate::settings::AppSettings;
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+// Word and LibreOffice documents are made from the markdown export by Pandoc,
+// run as a command-line tool: the one set in the settings (on the PATH by
+// default), else a copy bundled next to the app's executable.
+
+struct PandocConfig {
+    command: String,
+    reference_doc: Option<String>,
+    // Command that answered --version, and its version line; looked up on first use
+    resolved: Option<Option<(PathBuf, String)>>,
+}
+
+static CONFIG: RwLock<PandocConfig> = RwLock::new(PandocConfig {
+    command: String::new(),
+    reference_doc: None,
+    resolved: None,
+});
+
+static NEXT_INPUT: AtomicU64 = AtomicU64::new(0);
+
+/// Take the Pandoc command and default reference document from the settings
+pub fn configure(settings: &AppSettings) {
+    *CONFIG.write().unwrap() = PandocConfig {
+        command: settings.pandoc_command.clone(),
+        reference_doc: settings.pandoc_reference_doc.clone(),
+        resolved: None,
+    };
+}
+
+// The first line of `command --version`, when it runs
+fn version(command: &Path) -> Option<String> {
+    let output = Command::new(command).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).lines().next().unwrap_or_default().to_string())
+}
+
+// Pandoc as configured, then as bundled with the app
+fn resolve() -> Option<(PathBuf, String)> {
+    {
+        let config = CONFIG.read().unwrap();
+        if let Some(ref resolved) = config.resolved {
+            return resolved.clone();
+        }
+    }
+
+    let mut config = CONFIG.write().unwrap();
+    let bundled = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(if cfg!(windows) { "pandoc.exe" } else { "pandoc" })));
+    let resolved = std::iter::once(PathBuf::from(&config.command))
+        .chain(bundled)
+        .find_map(|command| version(&command).map(|version| (command, version)));
+    config.resolved = Some(resolved.clone());
+    resolved
+}
+
+/// Pandoc's version line, or None when neither the configured nor a bundled
+/// Pandoc runs
+pub fn available() -> Option<String> {
+    resolve().map(|(_, version)| version)
+}
+
+/// Convert markdown to a "docx" or "odt" document at `output_path`, styled by
+/// `reference_doc` or the one in the settings
+pub fn convert(markdown: &str, format: &str, output_path: &Path, reference_doc: Option<&str>) -> Result<()> {
+    if !matches!(format, "docx" | "odt") {
+        return Err(crate::error::AppError::invalid_input(format!("Unknown document format: {}", format)).into());
+    }
+    let (command, _) = resolve().ok_or_else(|| {
+        anyhow!("Pandoc wasn't found. Install it, or set the Pandoc command in the settings")
+    })?;
+    let reference_doc = reference_doc
+        .map(str::to_string)
+        .or_else(|| CONFIG.read().unwrap().reference_doc.clone());
+
+    let input = std::env::temp_dir().join(format!(
+        "pdf-search-export-{}-{}.md",
+        std::process::id(),
+        NEXT_INPUT.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&input, markdown).context(format!("Failed to write {}", input.display()))?;
+
+    let mut pandoc = Command::new(&command);
+    pandoc
+        .args(["--from", "markdown", "--to", format, "--output"])
+        .arg(output_path)
+        .arg(&input);
+    // Images are linked relative to the output's folder
+    if let Some(dir) = output_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        pandoc.arg("--resource-path").arg(dir);
+    }
+    if let Some(reference_doc) = reference_doc {
+        pandoc.arg(format!("--reference-doc={}", reference_doc));
+    }
+    let output = pandoc.output();
+    let _ = fs::remove_file(&input);
+    let output = output.context(format!("Failed to run {}", command.display()))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Pandoc failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use crate::error::AppError;
+use crate::pdf_search::decode_pdf_string;
+use anyhow::{Context, Result};
+use lopdf::{Dictionary, Document, Object, StringFormat};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// The basic entries of a PDF's Info dictionary
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PdfInfo {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub keywords: Option<String>,
+}
+
+const FIELDS: [&[u8]; 3] = [b"Title", b"Author", b"Keywords"];
+
+impl PdfInfo {
+    fn field(&self, key: &[u8]) -> Option<&str> {
+        let value = match key {
+            b"Title" => &self.title,
+            b"Author" => &self.author,
+            _ => &self.keywords,
+        };
+        value.as_deref().map(str::trim).filter(|value| !value.is_empty())
+    }
+
+    fn set_field(&mut self, key: &[u8], value: Option<String>) {
+        match key {
+            b"Title" => self.title = value,
+            b"Author" => self.author = value,
+            _ => self.keywords = value,
+        }
+    }
+}
+
+fn load(path: &Path) -> Result<Document> {
+    if !path.is_file() {
+        return Err(AppError::NotFound {
+            file: path.to_string_lossy().to_string(),
+        }
+        .into());
+    }
+    Document::load(path).context(format!("Failed to load PDF: {}", path.display()))
+}
+
+// The Info dictionary, wherever the trailer keeps it
+fn info_dictionary(doc: &Document) -> Option<&Dictionary> {
+    let info = doc.trailer.get(b"Info").ok()?;
+    let (_, info) = doc.dereference(info).ok()?;
+    info.as_dict().ok()
+}
+
+/// Title, author and keywords from a loaded PDF's Info dictionary
+pub fn read_info(doc: &Document) -> PdfInfo {
+    let mut info = PdfInfo::default();
+    if let Some(dictionary) = info_dictionary(doc) {
+        for key in FIELDS {
+            let value = dictionary
+                .get(key)
+                .ok()
+                .and_then(|value| doc.dereference(value).ok())
+                .and_then(|(_, value)| value.as_str().ok())
+                .map(|bytes| decode_pdf_string(bytes).trim().to_string())
+                .filter(|value| !value.is_empty());
+            info.set_field(key, value);
+        }
+    }
+    info
+}
+
+/// Title, author and keywords from a PDF's Info dictionary
+pub fn read_pdf_info(path: &Path) -> Result<PdfInfo> {
+    Ok(read_info(&load(path)?))
+}
+
+/// A PDF text string: PDFDocEncoding, which ASCII is a subset of, or UTF-16BE with a byte order mark
+pub fn encode_pdf_string(text: &str) -> Object {
+    if text.is_ascii() {
+        Object::string_literal(text)
+    } else {
+        let mut bytes = vec![0xFE, 0xFF];
+        bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+        Object::String(bytes, StringFormat::Hexadecimal)
+    }
+}
+
+/// Fill in a PDF's title, author and keywords, for loose files that lack them.
+/// Entries the PDF already has are kept unless `overwrite` is set. The file is
+/// rewritten through a temporary copy, so a failed write leaves it intact.
+/// Returns the Info entries the file ends up with.
+pub fn write_pdf_info(path: &Path, info: &PdfInfo, overwrite: bool) -> Result<PdfInfo> {
+    let mut doc = load(path)?;
+    // Rewriting would drop the encryption the rest of the file still depends on
+    if doc.is_encrypted() {
+        return Err(AppError::Encrypted {
+            file: path.to_string_lossy().to_string(),
+        }
+        .into());
+    }
+
+    let existing = read_info(&doc);
+    let mut updates = Vec::new();
+    for key in FIELDS {
+        if let Some(value) = info.field(key) {
+            if overwrite || existing.field(key).is_none() {
+                updates.push((key, value));
+            }
+        }
+    }
+    if updates.is_empty() {
+        return Ok(existing);
+    }
+
+    // Keep the current Info entries (Producer, dates, ...) whether the trailer
+    // holds them directly or by reference, and store the result as a new object
+    let mut dictionary = info_dictionary(&doc).cloned().unwrap_or_default();
+    for (key, value) in updates {
+        dictionary.set(key, encode_pdf_string(value));
+    }
+    let info_id = doc.add_object(dictionary);
+    doc.trailer.set("Info", Object::Reference(info_id));
+
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp_path = path.with_file_name(format!(".{}.metadata-tmp", file_name));
+    doc.save(&temp_path)
+        .context(format!("Failed to write PDF: {}", temp_path.display()))?;
+    if let Err(e) = fs::rename(&temp_path, path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(AppError::io(e, &path.to_string_lossy()).into());
+    }
+
+    Ok(read_info(&doc))
+}
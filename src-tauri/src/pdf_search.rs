@@ -1,12 +1,42 @@
+use crate::corpora::{self, NamedCorpus};
+use crate::corpus;
+use crate::duplicates;
+use crate::error::AppError;
+use crate::export;
+use crate::fallback_metadata::{self, FallbackMetadata};
+use crate::i18n;
+use crate::index::{self, CachedPage, CachedText, Index};
+use crate::layout::{self, GlyphBox, PageGeometry, Rect};
+use crate::library::{ItemSource, LibraryItem};
+use crate::native_index;
+use crate::ocr;
+use crate::platform;
+use crate::problem_corpus::{self, FailureCategory};
+use crate::memory::{MemoryBudget, MemoryDiagnostics, ResultSpill};
+use crate::sample::{sample_matches, SampleParams};
+use crate::screening::{self, ScreeningFilter};
+use crate::spool::ResultSpool;
+use crate::structure::{Block, StructureTree};
+use crate::subset;
 use anyhow::{Context, Result};
-use lopdf::Document;
+use lopdf::{Document, Object, ObjectId};
 use rayon::prelude::*;
-use regex::Regex;
+use regex::{Regex, RegexBuilder};
 use rusqlite::Connection;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize, Serializer};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ops::Range;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+use crate::walk::{walk_pdfs, WalkDiagnostics, WalkOptions};
+use crate::zotero_cache;
+use crate::zotero_fulltext::ZoteroFulltext;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ZoteroMetadata {
@@ -16,25 +46,249 @@ pub struct ZoteroMetadata {
     pub authors: Option<String>,
     pub zotero_link: String,
     pub pdf_attachment_key: Option<String>,
+    // "filename" for exact attachment matches, "heuristic" for fuzzy title matches
+    #[serde(default = "default_match_method")]
+    pub match_method: String,
+    #[serde(default)]
+    pub match_confidence: Option<f64>,
+    #[serde(default)]
+    pub open_pdf_link: Option<String>,
+    // Other PDF attachments of the same parent item (e.g. preprint vs. published version)
+    #[serde(default)]
+    pub sibling_attachments: Vec<ZoteroAttachment>,
+    // The item's language field as entered, e.g. "de", "en-US" or "German"
+    #[serde(default)]
+    pub language: Option<String>,
+    // Creators whose role is editor, also listed in `authors`
+    #[serde(default)]
+    pub editors: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SearchMatch {
-    pub file_path: String,
+pub struct ZoteroAttachment {
+    pub attachment_key: String,
     pub file_name: String,
+    pub title: Option<String>,
+    pub open_pdf_link: String,
+}
+
+/// Zotero metadata by attachment, keyed by where the attachment is stored: its
+/// file in Zotero's storage folder, or a linked file's own path. Zotero's renaming
+/// gives attachments in different storage folders the same file name, so a PDF is
+/// looked up by its path first, and by file name only where one attachment has it,
+/// as for copies of the library's PDFs kept in another folder. Serialized as the
+/// map from path to metadata.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "HashMap<String, ZoteroMetadata>", into = "HashMap<String, ZoteroMetadata>")]
+pub struct ZoteroMap {
+    by_path: HashMap<String, ZoteroMetadata>,
+    // File names of a single attachment, to its path
+    by_file_name: HashMap<String, String>,
+}
+
+impl From<HashMap<String, ZoteroMetadata>> for ZoteroMap {
+    fn from(by_path: HashMap<String, ZoteroMetadata>) -> Self {
+        let mut names: HashMap<String, Option<String>> = HashMap::new();
+        for path in by_path.keys() {
+            names
+                .entry(attachment_file_name(path).to_string())
+                .and_modify(|single| *single = None)
+                .or_insert_with(|| Some(path.clone()));
+        }
+        ZoteroMap {
+            by_file_name: names.into_iter().filter_map(|(name, path)| Some((name, path?))).collect(),
+            by_path,
+        }
+    }
+}
+
+impl From<ZoteroMap> for HashMap<String, ZoteroMetadata> {
+    fn from(map: ZoteroMap) -> Self {
+        map.by_path
+    }
+}
+
+impl ZoteroMap {
+    /// The metadata of the attachment at `path`, or else of the only attachment
+    /// with its file name
+    pub fn get(&self, path: &Path) -> Option<&ZoteroMetadata> {
+        self.by_path.get(path.to_string_lossy().as_ref()).or_else(|| {
+            let file_name = path.file_name()?.to_string_lossy();
+            self.by_path.get(self.by_file_name.get(file_name.as_ref())?)
+        })
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.get(path).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_path.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_path.is_empty()
+    }
+
+    /// Each attachment's path, as the map keys it, and metadata
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ZoteroMetadata)> {
+        self.by_path.iter().map(|(path, metadata)| (path.as_str(), metadata))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &ZoteroMetadata> {
+        self.by_path.values()
+    }
+}
+
+// The file name in an attachment's path, whichever separator it uses
+fn attachment_file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\', ':']).next().unwrap_or(path)
+}
+
+// Where an attachment's file is: in its storage folder for "storage:" paths, as
+// given for linked files. None for files relative to the linked attachment base
+// directory, which lives in the Zotero profile's preferences rather than the data directory.
+fn attachment_path(zotero_path: &Path, attachment_key: &str, path: &str) -> Option<PathBuf> {
+    if let Some(file_name) = path.strip_prefix("storage:") {
+        Some(zotero_path.join("storage").join(attachment_key).join(file_name))
+    } else if path.starts_with("attachments:") {
+        None
+    } else {
+        Some(PathBuf::from(path))
+    }
+}
+
+/// Layout of serialized search parameters and matches, stored with saved
+/// searches, saved result sets and JSON exports so later versions of the app
+/// can tell how to read them. Bump `CURRENT` when a field is renamed or changes
+/// meaning, and convert data with an older version where it's loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(transparent)]
+pub struct SchemaVersion(pub u32);
+
+impl SchemaVersion {
+    pub const CURRENT: SchemaVersion = SchemaVersion(1);
+
+    // Data saved before the layout was versioned, and requests that don't say,
+    // use the first layout
+    fn unversioned() -> Self {
+        SchemaVersion(1)
+    }
+
+    /// Note data written by a newer version of the app, whose added fields are
+    /// dropped on reading
+    pub fn warn_if_newer(self, source: &str) {
+        if self > Self::CURRENT {
+            eprintln!(
+                "Warning: {} was saved in schema version {}, newer than this app's {}; fields it added are ignored",
+                source,
+                self.0,
+                Self::CURRENT.0
+            );
+        }
+    }
+}
+
+impl Default for SchemaVersion {
+    fn default() -> Self {
+        Self::CURRENT
+    }
+}
+
+// Whatever version was read, the fields written are the current layout's
+impl Serialize for SchemaVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(Self::CURRENT.0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchMatch {
+    #[serde(default = "SchemaVersion::unversioned")]
+    pub schema_version: SchemaVersion,
+    // The document matched: its path, name, source and Zotero item
+    #[serde(flatten)]
+    pub item: LibraryItem,
     pub page_number: usize,
     pub context_before: String,
     pub matched_text: String,
     pub context_after: String,
-    pub zotero_link: Option<String>,
-    pub zotero_metadata: Option<ZoteroMetadata>,
+    // The query that produced this match
+    #[serde(default)]
+    pub query: Option<String>,
+    // Byte offset of the match within the page's normalized text (raw text for raw_text queries)
+    #[serde(default)]
+    pub offset: Option<usize>,
+    // Logical page label from the PDF (e.g. "xiv", "A-3"), when it defines one
+    #[serde(default)]
+    pub page_label: Option<String>,
+    // Other queries' hits inside this match's context
+    #[serde(default)]
+    pub highlights: Vec<ContextHighlight>,
+    // Values of the regex query's named capture groups, by group name
+    #[serde(default)]
+    pub captures: BTreeMap<String, String>,
+    // Where the match lies in the page's original text, which normalization may have changed
+    #[serde(default)]
+    pub source_span: Option<SourceSpan>,
+    // The match came from cached text and the PDF itself isn't present
+    #[serde(default)]
+    pub source_unavailable: bool,
+    // "<search ID>:<position>", assigned once a search's results are final
+    #[serde(default)]
+    pub match_id: Option<String>,
+    // The PDF forbids text extraction and was searched because the search overrode that
+    #[serde(default)]
+    pub extraction_restricted: bool,
+    // The match is in text recognized by OCR, which may misread words
+    #[serde(default)]
+    pub ocr: bool,
+    // Where the match is drawn on the page, one rectangle per line it spans, in PDF
+    // user space (points from the bottom-left corner); only when coordinates were asked for
+    #[serde(default)]
+    pub bounding_boxes: Option<Vec<Rect>>,
+    // The same boxes from the top-left corner of the page as viewers show it, after
+    // its crop box and /Rotate, for overlays on a rendered page; boxes outside the
+    // crop box are left out
+    #[serde(default)]
+    pub display_boxes: Option<Vec<Rect>>,
+    // The page's crop box and rotation, alongside the boxes
+    #[serde(default)]
+    pub page_geometry: Option<PageGeometry>,
+    // Character offsets of the match within the page's extracted text, alongside the boxes
+    #[serde(default)]
+    pub char_range: Option<Range<usize>>,
+    // Highlight color of the query that produced this match
+    #[serde(default)]
+    pub color: String,
+    // The nearest heading before the match, from a tagged PDF's structure tree
+    #[serde(default)]
+    pub section: Option<String>,
+    // The named corpus the document is in, when the search read several
+    #[serde(default)]
+    pub corpus: Option<String>,
 }
 
+/// Where another query's term occurs within a match's context. Offsets are in
+/// UTF-16 code units so they index JavaScript strings directly.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PdfListItem {
-    pub file_path: String,
-    pub file_name: String,
-    pub zotero_metadata: Option<ZoteroMetadata>,
+pub struct ContextHighlight {
+    pub query: String,
+    pub color: String,
+    // "before" or "after": which context string the range falls in
+    pub context: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A match located in the page's extracted text after NFC composition. Offsets
+/// are UTF-16 code units so the viewer can highlight the original characters,
+/// e.g. "Café-au-lait" for a diacritic- and hyphen-insensitive "cafe au lait".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceSpan {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -44,68 +298,549 @@ pub struct ListPdfsParams {
     pub zotero_path: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ZoteroLinkReportParams {
+    pub directory: String,
+    pub zotero_path: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ZoteroLinkReport {
+    pub files_scanned: usize,
+    pub linked_files: usize,
+    pub unmatched_files: Vec<String>,
+    pub missing_attachments: Vec<MissingAttachment>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MissingAttachment {
+    pub file_name: String,
+    pub attachment_key: Option<String>,
+    pub citekey: String,
+    pub title: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryItem {
     pub query: String,
     pub use_regex: bool,
     #[serde(default = "default_query_type")]
     pub query_type: String, // "parallel" or "filter"
-    #[serde(default = "default_color")]
-    pub color: String, // hex color for highlighting
+    // Hex color for highlighting; left empty, one is assigned from the palette
+    #[serde(default)]
+    pub color: String,
+    #[serde(default = "default_weight")]
+    pub weight: f64, // contribution of each hit to a document's score
+    // Match against the extracted text as is, skipping normalization, so regex
+    // anchors, whitespace classes and hyphens see the original layout
+    #[serde(default)]
+    pub raw_text: bool,
+    #[serde(default)]
+    pub regex_flags: RegexFlags,
+    // For literal multi-word queries: match the words in order with at most this
+    // many other words between consecutive ones, instead of as an exact phrase
+    #[serde(default)]
+    pub max_word_gap: Option<usize>,
+    // Parse the query as a boolean expression: AND, OR, NOT, "quoted phrases",
+    // parentheses and NEAR/n, evaluated per page. Ignored for regex queries.
+    #[serde(default)]
+    pub boolean: bool,
+    // Boolean queries: terms only match whole words
+    #[serde(default)]
+    pub whole_word: bool,
+    // Boolean queries: None follows the search's case_fold normalization setting
+    #[serde(default)]
+    pub case_sensitive: Option<bool>,
+}
+
+/// Flags applied when compiling a regex query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegexFlags {
+    // None follows the search's case_fold normalization setting
+    pub case_insensitive: Option<bool>,
+    // ^ and $ match at line boundaries, not just at the ends of the text
+    pub multi_line: bool,
+    // . also matches \n
+    pub dot_matches_new_line: bool,
+    // Unicode-aware \w, \d, \s and case folding. Without it, patterns that
+    // could match partial UTF-8 (like a bare `.`) are rejected
+    pub unicode: bool,
+}
+
+impl Default for RegexFlags {
+    fn default() -> Self {
+        RegexFlags {
+            case_insensitive: None,
+            multi_line: false,
+            dot_matches_new_line: false,
+            unicode: true,
+        }
+    }
+}
+
+impl QueryItem {
+    /// A literal, parallel query whose highlight color is assigned from the palette
+    pub fn plain(query: String) -> Self {
+        QueryItem {
+            query,
+            use_regex: false,
+            query_type: default_query_type(),
+            color: String::new(),
+            weight: default_weight(),
+            raw_text: false,
+            regex_flags: RegexFlags::default(),
+            max_word_gap: None,
+            boolean: false,
+            whole_word: false,
+            case_sensitive: None,
+        }
+    }
+
+    /// Whether the query is a boolean expression rather than a plain or regex query
+    pub fn is_boolean(&self) -> bool {
+        self.boolean && !self.use_regex
+    }
+
+    // The word gap when ordered proximity applies: literal queries of two or more words
+    fn proximity(&self) -> Option<usize> {
+        self.max_word_gap
+            .filter(|_| !self.use_regex && !self.boolean && self.query.split_whitespace().nth(1).is_some())
+    }
+}
+
+fn default_match_method() -> String {
+    "filename".to_string()
 }
 
 fn default_query_type() -> String {
     "parallel".to_string()
 }
 
-fn default_color() -> String {
-    "#ffff00".to_string() // yellow default
+fn default_weight() -> f64 {
+    1.0
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct SearchParams {
+    #[serde(default = "SchemaVersion::unversioned")]
+    pub schema_version: SchemaVersion,
     pub queries: Vec<QueryItem>,
     pub directory: String,
     pub context_words: usize,
     pub zotero_path: Option<String>,
     pub start_page: Option<usize>,
     pub end_page: Option<usize>,
+    // Stop scheduling further files once this many matches have been found
+    #[serde(default)]
+    pub max_results: Option<usize>,
+    // Only search files modified within this many days
+    #[serde(default)]
+    pub mtime_within_days: Option<u64>,
+    // Only search files first seen by the index within this many days
+    #[serde(default)]
+    pub added_within_days: Option<u64>,
+    // Let context run across block/paragraph boundaries (the old behavior)
+    #[serde(default)]
+    pub context_across_blocks: bool,
+    // Where the word budget cuts context off mid-sentence, trim it back to the
+    // nearest sentence boundary inside the budget
+    #[serde(default)]
+    pub sentence_context: bool,
+    // Drop a query's matches from a file where it hits fewer than this many times
+    #[serde(default)]
+    pub min_matches_per_file: Option<usize>,
+    // "score" orders documents by relevance score; otherwise by path
+    #[serde(default)]
+    pub sort_by: Option<String>,
+    // Return only a seeded random sample of the matches
+    #[serde(default)]
+    pub sample: Option<SampleParams>,
+    #[serde(default, flatten)]
+    pub walk: WalkOptions,
+    // "zotero" searches the attachments of the Zotero library at `zotero_path`
+    // instead of walking `directory`; "archive" searches the index's cached text
+    // of the files under `directory`, whether or not they're present
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub normalization: NormalizationOptions,
+    // Skip documents screened out in a review
+    #[serde(default)]
+    pub screening: Option<ScreeningFilter>,
+    // Search only the documents of this named corpus subset; see `subset`
+    #[serde(default)]
+    pub subset: Option<String>,
+    // Search these named corpora together instead of `directory`, labeling each
+    // match with its corpus; see `corpora`
+    #[serde(default)]
+    pub corpora: Vec<NamedCorpus>,
+    // Approximate ceiling for extraction buffers and held results; past it the
+    // search narrows its parallelism and moves results to a temporary store
+    #[serde(default)]
+    pub memory_limit_mb: Option<u64>,
+    // Once a search has found more matches than this, they're written to a spool
+    // on disk as files finish, and served a page at a time instead of returned
+    #[serde(default)]
+    pub stream_threshold: Option<usize>,
+    // Ask Spotlight or the Windows Search index which files contain the queries'
+    // words and skip the rest; see `native_index`
+    #[serde(default)]
+    pub native_prefilter: bool,
+    // Search PDFs whose permissions forbid text extraction instead of reporting them
+    #[serde(default)]
+    pub ignore_extraction_permissions: bool,
+    // Take the text of Zotero attachments from Zotero's own full-text index
+    // instead of extracting it again; see `zotero_fulltext`
+    #[serde(default)]
+    pub zotero_fulltext: bool,
+    // Read pages that have no usable text layer, such as scans, with OCR; see `ocr`
+    #[serde(default)]
+    pub use_ocr: bool,
+    // Give matches their bounding boxes on the page, for the viewer to highlight.
+    // Reads the PDF itself rather than cached text, so meant for single-file searches.
+    #[serde(default)]
+    pub page_coordinates: bool,
+    // Documents the reviewer excluded from every search; filled in from the settings
+    #[serde(default)]
+    pub excluded_documents: Vec<ExcludedDocument>,
+    // Search only one of each set of byte-identical files, listing the other
+    // copies' paths on its matches instead of showing every hit again
+    #[serde(default)]
+    pub collapse_duplicates: bool,
+    // Search-as-you-type: read only the index's cached text of the files under
+    // `directory`, narrowed by its term index, and return what was found within
+    // this many milliseconds. The outcome isn't `complete`, and the full search
+    // is run when the query is submitted.
+    #[serde(default)]
+    pub latency_budget_ms: Option<u64>,
+    // Stop searching a file once it has this many matches; set by previews
+    #[serde(skip)]
+    pub file_match_limit: Option<usize>,
 }
 
-pub fn find_pdf_files(directory: &Path) -> Result<Vec<PathBuf>> {
-    let mut pdf_files = Vec::new();
+/// A document the reviewer took out of the results, and why
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExcludedDocument {
+    pub file_path: String,
+    pub reason: String,
+    pub excluded_at: u64,
+    // Matches it had in the result set it was removed from
+    #[serde(default)]
+    pub matches_removed: usize,
+}
 
-    for entry in WalkDir::new(directory)
-        .follow_links(true)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        let path = entry.path();
-        if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("pdf") {
-            pdf_files.push(path.to_path_buf());
+/// Text normalization steps applied to both the page text and the queries
+/// before matching. The defaults are the long-standing behavior; each step
+/// helps some corpora and hurts others.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NormalizationOptions {
+    // Remove spaces and line breaks, so words split or run together by the PDF still match
+    pub strip_whitespace: bool,
+    // Remove hyphens, so words broken across lines still match
+    pub strip_hyphens: bool,
+    // Expand typographic ligatures (ﬁ, ﬂ, ...) into their letters
+    pub expand_ligatures: bool,
+    pub case_fold: bool,
+    // Match "café" with "cafe"
+    pub strip_diacritics: bool,
+}
+
+impl Default for NormalizationOptions {
+    fn default() -> Self {
+        NormalizationOptions {
+            strip_whitespace: true,
+            strip_hyphens: true,
+            expand_ligatures: false,
+            case_fold: true,
+            strip_diacritics: false,
         }
     }
+}
 
-    Ok(pdf_files)
+/// A file that couldn't be searched, reported alongside the matches from the rest
+#[derive(Debug, Clone, Serialize)]
+pub struct FileError {
+    pub file_path: String,
+    pub error: AppError,
+}
+
+/// Per-document summary of a search, scored as the weighted sum of its hits
+#[derive(Debug, Clone, Serialize)]
+pub struct DocumentScore {
+    pub file_path: String,
+    pub file_name: String,
+    pub match_count: usize,
+    pub score: f64,
+}
+
+/// How many documents each stage of a search kept or excluded, for audit reports
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SearchStats {
+    pub files_found: usize,
+    pub excluded_by_modified_date: usize,
+    pub excluded_by_date_added: usize,
+    pub excluded_by_screening: usize,
+    pub excluded_by_subset: usize,
+    pub excluded_by_native_index: usize,
+    pub excluded_by_reviewer: usize,
+    // Identical copies of other files, searched through the copy kept
+    pub collapsed_copies: usize,
+    pub files_searched: usize,
+    pub excluded_by_filter_queries: usize,
+    pub excluded_by_min_matches: usize,
+    pub files_with_errors: usize,
+    pub files_with_matches: usize,
+    pub query_document_counts: Vec<QueryDocumentCount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryDocumentCount {
+    pub query: String,
+    pub documents: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SearchOutcome {
+    pub matches: Vec<SearchMatch>,
+    pub documents: Vec<DocumentScore>,
+    pub file_errors: Vec<FileError>,
+    pub stats: SearchStats,
+    pub walk: WalkDiagnostics,
+    // The search was cancelled before every file was searched; the matches are from the files that were
+    pub partial: bool,
+    // Every file was searched in full; false for a cancelled search and for any
+    // search with a latency budget, whose results stand in until the full search
+    pub complete: bool,
+    pub memory: MemoryDiagnostics,
+    // Set when the search found nothing
+    pub no_results: Option<NoResultsExplanation>,
+    // Matches in `spool` when the search passed its stream threshold; `matches`
+    // is then empty
+    pub streamed_matches: Option<usize>,
+    #[serde(skip)]
+    pub spool: Option<ResultSpool>,
+}
+
+/// Why a search found nothing, so the user can act instead of guessing
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NoResultsExplanation {
+    pub files_found: usize,
+    // Left out before searching by date, screening or platform index filters
+    pub files_excluded: usize,
+    pub files_searched: usize,
+    pub files_extracted: usize,
+    // Scans without a text layer
+    pub image_only: usize,
+    // Password protected, or forbidding text extraction
+    pub encrypted: usize,
+    pub other_errors: usize,
+    // Had hits, but failed a filter query or the minimum matches per file
+    pub excluded_by_query_rules: usize,
+    pub query_notes: Vec<QueryNote>,
+}
+
+/// How normalization changed a query, when that may be why it didn't match
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryNote {
+    pub query: String,
+    // The query as it's compared with the normalized text
+    pub normalized: String,
+    pub note: String,
+}
+
+// Why a searched document contributed no matches despite containing hits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Exclusion {
+    FilterQuery,
+    MinMatches,
+}
+
+// Matches from one document, or the reason it was excluded
+#[derive(Default)]
+struct FileMatches {
+    matches: Vec<SearchMatch>,
+    exclusion: Option<Exclusion>,
+    // The matches are in the search's spill store rather than `matches`
+    spilled: bool,
+    // The matches went to the search's spool; what's needed of them for the totals
+    streamed: Option<StreamedFile>,
+}
+
+struct StreamedFile {
+    match_count: usize,
+    documents: Vec<DocumentScore>,
+    query_documents: Vec<QueryDocumentCount>,
+}
+
+impl StreamedFile {
+    fn new(matches: &[SearchMatch], queries: &[QueryItem]) -> Self {
+        StreamedFile {
+            match_count: matches.len(),
+            documents: score_documents(matches, queries),
+            query_documents: query_document_counts(matches, queries),
+        }
+    }
+}
+
+/// Shared state a search runs with, beyond its parameters
+pub struct SearchContext<'a> {
+    pub cancel: &'a AtomicBool,
+    pub index: Option<&'a Index>,
+    // Called from the worker threads as each file is done
+    pub progress: Option<&'a (dyn Fn(SearchProgress) + Sync)>,
+}
+
+/// How far a search has got, reported after each file
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchProgress {
+    pub files_scanned: usize,
+    pub total_files: usize,
+    pub current_file: String,
+    pub matches_so_far: usize,
+    // The file's matches, before match IDs are assigned
+    pub matches: Vec<SearchMatch>,
+}
+
+pub fn find_pdf_files(directory: &Path) -> Result<Vec<PathBuf>> {
+    walk_pdfs(directory, &WalkOptions::default()).map(|(pdf_files, _)| pdf_files)
 }
 
-// Build a map of PDF filenames to Zotero metadata
-fn build_zotero_map(zotero_path: &Path) -> Result<HashMap<String, ZoteroMetadata>> {
+// Open a temporary copy of the Zotero database to avoid file lock issues.
+// Returns the connection and the copy's path, which the caller removes when done.
+pub fn open_zotero_database(zotero_path: &Path, purpose: &str) -> Result<(Connection, PathBuf)> {
+    // The database is copied before it's read, which mobile storage has no room or
+    // access for; the web API serves the library there instead
+    if platform::MOBILE {
+        return Err(AppError::Zotero {
+            message: "Zotero's database can't be read on this platform".to_string(),
+        }
+        .into());
+    }
     let db_path = zotero_path.join("zotero.sqlite");
-    let bbt_db_path = zotero_path.join("better-bibtex.sqlite");
 
     if !db_path.exists() {
-        return Err(anyhow::anyhow!("Zotero database not found at {:?}", db_path));
+        return Err(AppError::Zotero {
+            message: format!("Zotero database not found at {}", db_path.display()),
+        }
+        .into());
     }
 
-    // Create a temporary copy of the Zotero database to avoid file lock issues
-    let temp_dir = std::env::temp_dir();
-    let temp_db_path = temp_dir.join(format!("zotero_temp_{}.sqlite", std::process::id()));
+    let temp_db_path = std::env::temp_dir().join(format!("zotero_temp_{}_{}.sqlite", purpose, std::process::id()));
     std::fs::copy(&db_path, &temp_db_path)
         .context("Failed to create temporary copy of Zotero database")?;
 
     let conn = Connection::open(&temp_db_path)
         .context("Failed to open Zotero database")?;
+    Ok((conn, temp_db_path))
+}
+
+// Title, date and language of every item that has them
+#[derive(Default)]
+struct ItemFields {
+    title: Option<String>,
+    date: Option<String>,
+    language: Option<String>,
+}
+
+fn item_fields(conn: &Connection) -> Result<HashMap<i32, ItemFields>> {
+    let mut stmt = conn.prepare(
+        "SELECT itemData.itemID, fields.fieldName, itemDataValues.value
+         FROM itemData
+         JOIN fields ON itemData.fieldID = fields.fieldID
+         JOIN itemDataValues ON itemData.valueID = itemDataValues.valueID
+         WHERE fields.fieldName IN ('title', 'date', 'language')",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+    })?;
+
+    let mut fields: HashMap<i32, ItemFields> = HashMap::new();
+    for (item_id, field_name, value) in rows.flatten() {
+        let entry = fields.entry(item_id).or_default();
+        match field_name.as_str() {
+            "title" => entry.title = Some(value),
+            "language" => entry.language = Some(value),
+            _ => entry.date = Some(value),
+        }
+    }
+    Ok(fields)
+}
+
+// Every item's creators as "First Last, First Last", in their Zotero order
+#[derive(Default)]
+struct ItemCreators {
+    all: Vec<String>,
+    editors: Vec<String>,
+}
+
+fn item_creators(conn: &Connection) -> Result<HashMap<i32, ItemCreators>> {
+    let mut stmt = conn.prepare(
+        "SELECT itemCreators.itemID, creators.firstName, creators.lastName, creatorTypes.creatorType
+         FROM creators
+         JOIN itemCreators ON creators.creatorID = itemCreators.creatorID
+         LEFT JOIN creatorTypes ON itemCreators.creatorTypeID = creatorTypes.creatorTypeID
+         ORDER BY itemCreators.itemID, itemCreators.orderIndex",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i32>(0)?,
+            row.get::<_, Option<String>>(1)?, // firstName (can be null)
+            row.get::<_, Option<String>>(2)?, // lastName
+            row.get::<_, Option<String>>(3)?, // creatorType
+        ))
+    })?;
+
+    let mut creators: HashMap<i32, ItemCreators> = HashMap::new();
+    for (item_id, first_name, last_name, creator_type) in rows.flatten() {
+        let name = match (first_name, last_name) {
+            (Some(first), Some(last)) => format!("{} {}", first, last),
+            (None, Some(last)) => last,
+            (Some(first), None) => first,
+            (None, None) => continue,
+        };
+        let entry = creators.entry(item_id).or_default();
+        if creator_type.as_deref() == Some("editor") {
+            entry.editors.push(name.clone());
+        }
+        entry.all.push(name);
+    }
+    Ok(creators)
+}
+
+// Better BibTeX citation keys by item key
+fn better_bibtex_citekeys(conn: &Connection) -> Result<HashMap<String, String>> {
+    let mut stmt = conn.prepare("SELECT itemKey, citationKey FROM citationkey")?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+    Ok(rows.flatten().collect())
+}
+
+// Conditions on `items` joined with `itemAttachments` that leave out attachments
+// in the trash or whose parent item is, and optionally those of the items in the
+// user's own "My Publications"
+fn attachment_exclusions(skip_publications: bool) -> String {
+    let mut conditions = "items.itemID NOT IN (SELECT itemID FROM deletedItems)
+           AND (itemAttachments.parentItemID IS NULL
+                OR itemAttachments.parentItemID NOT IN (SELECT itemID FROM deletedItems))"
+        .to_string();
+    if skip_publications {
+        conditions.push_str(
+            "
+           AND COALESCE(itemAttachments.parentItemID, items.itemID) NOT IN (SELECT itemID FROM publicationsItems)",
+        );
+    }
+    conditions
+}
+
+/// Map Zotero attachments to the metadata of their items, leaving out trashed
+/// items and, with `skip_publications`, those in My Publications. The library is
+/// read with a few queries over whole tables, joined here, so a large library takes
+/// about as long as copying its database.
+pub fn build_zotero_map(zotero_path: &Path, skip_publications: bool) -> Result<ZoteroMap> {
+    let bbt_db_path = zotero_path.join("better-bibtex.sqlite");
+    let temp_dir = std::env::temp_dir();
+    let (conn, temp_db_path) = open_zotero_database(zotero_path, "map")?;
 
     // Open Better BibTeX database if it exists (also create temp copy)
     let (bbt_conn, temp_bbt_db_path) = if bbt_db_path.exists() {
@@ -121,15 +856,17 @@ fn build_zotero_map(zotero_path: &Path) -> Result<HashMap<String, ZoteroMetadata
 
     // First, query to get basic item info and attachment paths
     // We need both the attachment item and the parent item
-    let mut stmt = conn.prepare(
-        "SELECT items.itemID, items.key, itemAttachments.path, itemAttachments.parentItemID, parent.key
+    let mut stmt = conn.prepare(&format!(
+        "SELECT items.itemID, items.key, itemAttachments.path, itemAttachments.parentItemID, parent.key,
+                itemAttachments.contentType
          FROM items
          JOIN itemAttachments ON items.itemID = itemAttachments.itemID
          LEFT JOIN items AS parent ON itemAttachments.parentItemID = parent.itemID
-         WHERE itemAttachments.path IS NOT NULL"
-    )?;
+         WHERE itemAttachments.path IS NOT NULL
+           AND {}",
+        attachment_exclusions(skip_publications)
+    ))?;
 
-    let mut map = HashMap::new();
     let rows = stmt.query_map([], |row| {
         Ok((
             row.get::<_, i32>(0)?,           // attachment itemID
@@ -137,54 +874,121 @@ fn build_zotero_map(zotero_path: &Path) -> Result<HashMap<String, ZoteroMetadata
             row.get::<_, String>(2)?,        // attachment path
             row.get::<_, Option<i32>>(3)?,   // parent itemID (null if no parent)
             row.get::<_, Option<String>>(4)?, // parent key (null if no parent)
+            row.get::<_, Option<String>>(5)?, // content type
         ))
     })?;
 
-    for row in rows {
-        if let Ok((attachment_id, attachment_key, path, parent_id, parent_key)) = row {
-            // Extract filename from path (could be "storage:filename.pdf" or just "filename.pdf")
-            let filename = if let Some(colon_pos) = path.rfind(':') {
-                &path[colon_pos + 1..]
-            } else {
-                path.rsplit('/').next().unwrap_or(&path)
-            };
+    let fields = item_fields(&conn)?;
+    let creators = item_creators(&conn)?;
+    // Without readable Better BibTeX keys, items fall back to their Zotero key
+    let citekeys = match bbt_conn {
+        Some(ref bbt_conn) => better_bibtex_citekeys(bbt_conn).unwrap_or_else(|e| {
+            eprintln!("Warning: Failed to read Better BibTeX citation keys: {}", e);
+            HashMap::new()
+        }),
+        None => HashMap::new(),
+    };
 
-            // Store attachment key for later use
-            let pdf_attachment_key = attachment_key.clone();
+    // Collect attachments first so siblings of the same parent item can be linked to each other
+    struct AttachmentRow {
+        attachment_key: String,
+        // Where the file is, or the stored path where that's unknown
+        path: String,
+        file_name: String,
+        title: Option<String>,
+        is_pdf: bool,
+        item_id: i32,
+        item_key: String,
+    }
 
-            // Use parent item if available, otherwise use attachment item itself
-            let (item_id, item_key) = if let (Some(pid), Some(pkey)) = (parent_id, parent_key) {
-                (pid, pkey)
-            } else {
-                (attachment_id, attachment_key)
-            };
+    let mut attachments = Vec::new();
+    for (attachment_id, attachment_key, path, parent_id, parent_key, content_type) in rows.flatten() {
+        // Extract filename from path (could be "storage:filename.pdf" or just "filename.pdf")
+        let file_name = attachment_file_name(&path).to_string();
+        let path = attachment_path(zotero_path, &attachment_key, &path)
+            .map_or(path, |resolved| resolved.to_string_lossy().to_string());
 
-            // Query for title, date, and creators from the parent item
-            let title = get_item_field(&conn, item_id, "title").ok().flatten();
-            let date = get_item_field(&conn, item_id, "date").ok().flatten();
-            let year = extract_year(&date);
-            let authors = get_item_creators(&conn, item_id).ok().flatten();
+        let is_pdf = content_type.as_deref() == Some("application/pdf")
+            || file_name.to_lowercase().ends_with(".pdf");
 
-            // Try to get the BibTeX citation key from Better BibTeX database
-            let bibtex_citekey = if let Some(ref bbt_conn) = bbt_conn {
-                get_better_bibtex_citekey(bbt_conn, &item_key).ok().flatten()
-            } else {
-                None
-            };
-            let citekey = bibtex_citekey.unwrap_or_else(|| item_key.clone());
-
-            map.insert(
-                filename.to_string(),
-                ZoteroMetadata {
-                    citekey: citekey.clone(),
-                    title,
-                    year,
-                    authors,
-                    zotero_link: format!("zotero://select/library/items/{}", item_key),
-                    pdf_attachment_key: Some(pdf_attachment_key),
-                },
-            );
-        }
+        // The attachment's own title usually distinguishes versions ("Submitted Version", "Full Text PDF")
+        let title = fields.get(&attachment_id).and_then(|f| f.title.clone());
+
+        // Use parent item if available, otherwise use attachment item itself
+        let (item_id, item_key) = if let (Some(pid), Some(pkey)) = (parent_id, parent_key) {
+            (pid, pkey)
+        } else {
+            (attachment_id, attachment_key.clone())
+        };
+
+        attachments.push(AttachmentRow {
+            attachment_key,
+            path,
+            file_name,
+            title,
+            is_pdf,
+            item_id,
+            item_key,
+        });
+    }
+
+    let mut pdfs_by_item: HashMap<i32, Vec<ZoteroAttachment>> = HashMap::new();
+    for attachment in attachments.iter().filter(|a| a.is_pdf) {
+        pdfs_by_item
+            .entry(attachment.item_id)
+            .or_default()
+            .push(ZoteroAttachment {
+                attachment_key: attachment.attachment_key.clone(),
+                file_name: attachment.file_name.clone(),
+                title: attachment.title.clone(),
+                open_pdf_link: open_pdf_link(&attachment.attachment_key),
+            });
+    }
+
+    let mut map = HashMap::new();
+    for attachment in attachments {
+        let item_id = attachment.item_id;
+        let item_key = attachment.item_key;
+
+        // Title, date, and creators come from the parent item
+        let item = fields.get(&item_id);
+        let title = item.and_then(|f| f.title.clone());
+        let year = extract_year(&item.and_then(|f| f.date.clone()));
+        let item_creators = creators.get(&item_id);
+        let authors = item_creators.map(|c| c.all.join(", "));
+        let editors = item_creators.filter(|c| !c.editors.is_empty()).map(|c| c.editors.join(", "));
+        let language = item.and_then(|f| f.language.clone());
+
+        // Prefer the Better BibTeX citation key
+        let citekey = citekeys.get(&item_key).cloned().unwrap_or_else(|| item_key.clone());
+
+        let sibling_attachments = pdfs_by_item
+            .get(&item_id)
+            .map(|pdfs| {
+                pdfs.iter()
+                    .filter(|a| a.attachment_key != attachment.attachment_key)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        map.insert(
+            attachment.path,
+            ZoteroMetadata {
+                citekey,
+                title,
+                year,
+                authors,
+                zotero_link: format!("zotero://select/library/items/{}", item_key),
+                match_method: default_match_method(),
+                match_confidence: None,
+                open_pdf_link: Some(open_pdf_link(&attachment.attachment_key)),
+                pdf_attachment_key: Some(attachment.attachment_key),
+                sibling_attachments,
+                language,
+                editors,
+            },
+        );
     }
 
     // Clean up temporary database files
@@ -193,74 +997,62 @@ fn build_zotero_map(zotero_path: &Path) -> Result<HashMap<String, ZoteroMetadata
         let _ = std::fs::remove_file(&temp_bbt_path);
     }
 
-    Ok(map)
+    Ok(ZoteroMap::from(map))
 }
 
-// Helper function to get item field value by field name
-fn get_item_field(conn: &Connection, item_id: i32, field_name: &str) -> Result<Option<String>> {
-    let mut stmt = conn.prepare(
-        "SELECT itemDataValues.value
-         FROM itemData
-         JOIN fields ON itemData.fieldID = fields.fieldID
-         JOIN itemDataValues ON itemData.valueID = itemDataValues.valueID
-         WHERE itemData.itemID = ? AND fields.fieldName = ?"
-    )?;
-
-    let value = stmt.query_row([item_id.to_string(), field_name.to_string()], |row| {
-        row.get::<_, String>(0)
-    }).ok();
+/// PDF attachments of the Zotero library that exist on disk: files in Zotero's
+/// storage directory plus linked files with absolute paths
+fn zotero_library_files(zotero_path: &Path, skip_publications: bool) -> Result<Vec<PathBuf>> {
+    let (conn, temp_db_path) = open_zotero_database(zotero_path, "files")?;
 
-    Ok(value)
-}
-
-// Helper function to get item creators (authors)
-fn get_item_creators(conn: &Connection, item_id: i32) -> Result<Option<String>> {
-    let mut stmt = conn.prepare(
-        "SELECT creators.firstName, creators.lastName
-         FROM creators
-         JOIN itemCreators ON creators.creatorID = itemCreators.creatorID
-         WHERE itemCreators.itemID = ?
-         ORDER BY itemCreators.orderIndex"
-    )?;
+    let paths: Vec<(String, String)> = {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT items.key, itemAttachments.path
+             FROM items
+             JOIN itemAttachments ON items.itemID = itemAttachments.itemID
+             WHERE itemAttachments.path IS NOT NULL
+               AND (itemAttachments.contentType = 'application/pdf' OR lower(itemAttachments.path) LIKE '%.pdf')
+               AND {}",
+            attachment_exclusions(skip_publications)
+        ))?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.flatten().collect()
+    };
+    drop(conn);
+    let _ = std::fs::remove_file(&temp_db_path);
 
-    let mut creators = Vec::new();
-    let rows = stmt.query_map([item_id], |row| {
-        Ok((
-            row.get::<_, Option<String>>(0)?,  // firstName (can be null)
-            row.get::<_, Option<String>>(1)?,  // lastName
-        ))
-    })?;
+    let mut pdf_files = Vec::new();
+    for (attachment_key, path) in paths {
+        let Some(file_path) = attachment_path(zotero_path, &attachment_key, &path) else {
+            eprintln!("Warning: Skipping attachment relative to base directory: {}", path);
+            continue;
+        };
 
-    for row in rows {
-        if let Ok((first_name, last_name)) = row {
-            let name = match (first_name, last_name) {
-                (Some(first), Some(last)) => format!("{} {}", first, last),
-                (None, Some(last)) => last,
-                (Some(first), None) => first,
-                (None, None) => continue,
-            };
-            creators.push(name);
+        if file_path.is_file() {
+            pdf_files.push(file_path);
         }
     }
 
-    if creators.is_empty() {
-        Ok(None)
+    pdf_files.sort();
+    pdf_files.dedup();
+    Ok(pdf_files)
+}
+
+// Classify failures while reading the Zotero database, keeping errors that are already specific
+fn zotero_error(err: anyhow::Error) -> anyhow::Error {
+    if err.is::<AppError>() {
+        err
     } else {
-        Ok(Some(creators.join(", ")))
+        AppError::Zotero {
+            message: format!("{:#}", err),
+        }
+        .into()
     }
 }
 
-// Helper function to get Better BibTeX citation key
-fn get_better_bibtex_citekey(conn: &Connection, item_key: &str) -> Result<Option<String>> {
-    let mut stmt = conn.prepare(
-        "SELECT citationKey FROM citationkey WHERE itemKey = ?"
-    )?;
-
-    let citekey = stmt.query_row([item_key], |row| {
-        row.get::<_, String>(0)
-    }).ok();
-
-    Ok(citekey)
+// Link that opens a specific attachment in Zotero's PDF reader
+fn open_pdf_link(attachment_key: &str) -> String {
+    format!("zotero://open-pdf/library/items/{}", attachment_key)
 }
 
 // Helper function to extract year from date field
@@ -271,7 +1063,7 @@ fn extract_year(date: &Option<String>) -> Option<String> {
         for part in date_str.split(|c: char| !c.is_numeric()) {
             if part.len() == 4 {
                 if let Ok(year) = part.parse::<i32>() {
-                    if year >= 1000 && year <= 9999 {
+                    if (1000..=9999).contains(&year) {
                         return Some(year.to_string());
                     }
                 }
@@ -281,344 +1073,3012 @@ fn extract_year(date: &Option<String>) -> Option<String> {
     None
 }
 
-fn extract_text_from_pdf(pdf_path: &Path) -> Result<Vec<(usize, String)>> {
-    let doc = Document::load(pdf_path)
-        .context(format!("Failed to load PDF: {}", pdf_path.display()))?;
+// Minimum normalized title similarity for a heuristic Zotero match
+const TITLE_MATCH_THRESHOLD: f64 = 0.85;
+
+// Look up Zotero metadata by attachment path or file name, falling back to
+// fuzzy-matching the PDF's title against Zotero item titles for renamed files
+fn lookup_zotero_metadata(map: &ZoteroMap, pdf_path: &Path, title_candidates: &[String]) -> Option<ZoteroMetadata> {
+    if let Some(metadata) = map.get(pdf_path) {
+        return Some(metadata.clone());
+    }
+
+    let mut best: Option<(f64, &ZoteroMetadata)> = None;
+    for candidate in title_candidates {
+        let candidate = normalize_title(candidate);
+        if candidate.is_empty() {
+            continue;
+        }
+
+        for metadata in map.values() {
+            let Some(ref title) = metadata.title else { continue };
+            let title = normalize_title(title);
+
+            // Skip titles whose length difference alone rules out a match
+            let (a, b) = (candidate.chars().count(), title.chars().count());
+            if (a.min(b) as f64) < (a.max(b) as f64) * TITLE_MATCH_THRESHOLD {
+                continue;
+            }
+
+            let similarity = title_similarity(&candidate, &title);
+            if similarity >= TITLE_MATCH_THRESHOLD && best.is_none_or(|(score, _)| similarity > score) {
+                best = Some((similarity, metadata));
+            }
+        }
+    }
+
+    best.map(|(score, metadata)| ZoteroMetadata {
+        match_method: "heuristic".to_string(),
+        match_confidence: Some(score),
+        ..metadata.clone()
+    })
+}
+
+// Lowercase and collapse everything but letters and digits so punctuation and
+// spacing differences don't count against a title match
+fn normalize_title(title: &str) -> String {
+    title
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Normalized Levenshtein similarity in [0, 1]
+fn title_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let max_len = a.len().max(b.len());
+    if max_len == 0 {
+        return 1.0;
+    }
+
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        curr[0] = i;
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            curr[j] = (prev[j] + 1).min(curr[j - 1] + 1).min(prev[j - 1] + cost);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    1.0 - prev[b.len()] as f64 / max_len as f64
+}
+
+// Read the Title entry from the PDF's Info dictionary
+fn pdf_info_title(doc: &Document) -> Option<String> {
+    let info = doc.trailer.get(b"Info").ok()?;
+    let (_, info) = doc.dereference(info).ok()?;
+    let title = info.as_dict().ok()?.get(b"Title").ok()?;
+    let (_, title) = doc.dereference(title).ok()?;
+    let title = decode_pdf_string(title.as_str().ok()?);
+    let title = title.trim();
+
+    if title.is_empty() {
+        None
+    } else {
+        Some(title.to_string())
+    }
+}
+
+// PDF text strings are either UTF-16BE with a byte order mark or PDFDocEncoding,
+// which matches Latin-1 for the characters that matter here
+pub fn decode_pdf_string(bytes: &[u8]) -> String {
+    if bytes.starts_with(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = bytes[2..]
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        bytes.iter().map(|&b| b as char).collect()
+    }
+}
+
+// One range of the PageLabels number tree: pages from `start` on are labelled
+// `prefix` followed by a counter in `style`, beginning at `first_number`
+struct PageLabelRange {
+    start: usize,
+    style: Option<Vec<u8>>,
+    prefix: String,
+    first_number: i64,
+}
+
+// Resolve the logical label of every page from the catalog's PageLabels tree.
+// Returns one entry per page, all None when the PDF doesn't define labels.
+fn page_labels(doc: &Document, page_count: usize) -> Vec<Option<String>> {
+    let mut ranges = Vec::new();
+    if let Some(root) = doc.catalog().ok().and_then(|catalog| catalog.get(b"PageLabels").ok()) {
+        collect_page_label_ranges(doc, root, &mut ranges, 0);
+    }
+    ranges.sort_by_key(|range| range.start);
+
+    let mut labels = Vec::with_capacity(page_count);
+    for index in 0..page_count {
+        let label = ranges.iter().rev().find(|range| range.start <= index).map(|range| {
+            let number = range.first_number + (index - range.start) as i64;
+            let counter = match range.style.as_deref() {
+                Some(b"D") => number.to_string(),
+                Some(b"R") => to_roman(number).to_uppercase(),
+                Some(b"r") => to_roman(number),
+                Some(b"A") => to_alphabetic(number).to_uppercase(),
+                Some(b"a") => to_alphabetic(number),
+                _ => String::new(),
+            };
+            format!("{}{}", range.prefix, counter)
+        });
+        labels.push(label);
+    }
+    labels
+}
+
+// Walk a number tree node, following Kids down to the leaves' Nums arrays
+fn collect_page_label_ranges(doc: &Document, node: &Object, ranges: &mut Vec<PageLabelRange>, depth: usize) {
+    // Guard against malformed trees that reference themselves
+    if depth > 32 {
+        return;
+    }
+    let Some(node) = doc.dereference(node).ok().and_then(|(_, node)| node.as_dict().ok()) else {
+        return;
+    };
+
+    if let Ok(kids) = node.get(b"Kids").and_then(Object::as_array) {
+        for kid in kids {
+            collect_page_label_ranges(doc, kid, ranges, depth + 1);
+        }
+    }
+
+    let Ok(nums) = node.get(b"Nums").and_then(Object::as_array) else {
+        return;
+    };
+    for pair in nums.chunks_exact(2) {
+        let Ok(start) = pair[0].as_i64() else { continue };
+        let Some(label) = doc.dereference(&pair[1]).ok().and_then(|(_, label)| label.as_dict().ok()) else {
+            continue;
+        };
+
+        ranges.push(PageLabelRange {
+            start: start.max(0) as usize,
+            style: label.get(b"S").and_then(Object::as_name).ok().map(<[u8]>::to_vec),
+            prefix: label
+                .get(b"P")
+                .and_then(Object::as_str)
+                .map(decode_pdf_string)
+                .unwrap_or_default(),
+            first_number: label.get(b"St").and_then(Object::as_i64).unwrap_or(1),
+        });
+    }
+}
+
+fn to_roman(mut number: i64) -> String {
+    const NUMERALS: [(i64, &str); 13] = [
+        (1000, "m"), (900, "cm"), (500, "d"), (400, "cd"), (100, "c"), (90, "xc"),
+        (50, "l"), (40, "xl"), (10, "x"), (9, "ix"), (5, "v"), (4, "iv"), (1, "i"),
+    ];
+
+    let mut roman = String::new();
+    for (value, numeral) in NUMERALS {
+        while number >= value {
+            roman.push_str(numeral);
+            number -= value;
+        }
+    }
+    roman
+}
+
+// Letter counters run a..z, then aa..zz, then aaa..zzz, and so on
+fn to_alphabetic(number: i64) -> String {
+    if number < 1 {
+        return String::new();
+    }
+    let letter = (b'a' + ((number - 1) % 26) as u8) as char;
+    letter.to_string().repeat(((number - 1) / 26 + 1) as usize)
+}
+
+/// Text of one page, with block boundaries when the extractor knows the layout
+struct PageText {
+    page_number: usize,
+    label: Option<String>,
+    text: String,
+    // Paragraphs/blocks within `text` from a tagged PDF's structure; None for unstructured extraction
+    blocks: Option<Vec<Block>>,
+    // The heading in force where the page starts; see `carry_sections`
+    section: Option<String>,
+    // The text was recognized from the page image
+    ocr: bool,
+    // Where each character was drawn, when coordinates were asked for
+    glyphs: Option<Vec<GlyphBox>>,
+    // How the page is shown, alongside the glyphs
+    geometry: Option<PageGeometry>,
+}
+
+struct ExtractedPdf {
+    pages: Vec<PageText>,
+    title: Option<String>,
+    // Text came from the index because the file itself is missing
+    source_unavailable: bool,
+    // Text came from the index's cache rather than the PDF
+    from_cache: bool,
+    // The PDF's permissions forbid text extraction, which was overridden
+    extraction_restricted: bool,
+    // Text came from Zotero's full-text index, which isn't copied into ours
+    from_zotero: bool,
+    // Pages without usable text were OCR'd
+    ocr: bool,
+}
+
+/// How a file's text may be read
+#[derive(Default, Clone, Copy)]
+struct TextOptions<'a> {
+    zotero_fulltext: Option<&'a ZoteroFulltext>,
+    ignore_permissions: bool,
+    use_ocr: bool,
+    // Record where each character is drawn; see `layout`
+    coordinates: bool,
+    // Set when the search is cancelled; extraction stops at the next page
+    cancel: Option<&'a AtomicBool>,
+    // Take the cached text as it is, without checking the file or extracting it
+    cached_only: bool,
+}
+
+// A file's extraction or matching stopped partway because the search was
+// cancelled. The file then counts as not searched rather than as failed.
+#[derive(Debug)]
+struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The search was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+// Checked once per page or match, so a long file stops within one of them
+fn check_cancelled(cancel: Option<&AtomicBool>) -> Result<()> {
+    if cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+        return Err(Cancelled.into());
+    }
+    Ok(())
+}
+
+impl ExtractedPdf {
+    // Titles to try when the filename doesn't match a Zotero attachment:
+    // the embedded title, then the first line of page 1 that looks like a title
+    fn title_candidates(&self) -> Vec<String> {
+        let mut candidates: Vec<String> = self.title.iter().cloned().collect();
+
+        if let Some(first_page) = self.pages.first() {
+            if let Some(line) = first_page
+                .text
+                .lines()
+                .map(str::trim)
+                .find(|line| line.split_whitespace().count() >= 3)
+            {
+                candidates.push(line.to_string());
+            }
+        }
+
+        candidates
+    }
+}
+
+impl PageText {
+    // Tagged headings on the page, with where each starts in the text
+    fn headings(&self) -> impl Iterator<Item = (usize, String)> + '_ {
+        self.blocks.iter().flatten().filter(|block| block.is_heading()).map(|block| {
+            let heading = self.text[block.range.clone()].split_whitespace().collect::<Vec<_>>().join(" ");
+            (block.range.start, heading)
+        })
+    }
+
+    // The nearest heading at or before a byte offset of the text
+    fn section_at(&self, offset: usize) -> Option<String> {
+        self.headings()
+            .take_while(|(start, _)| *start <= offset)
+            .last()
+            .map(|(_, heading)| heading)
+            .or_else(|| self.section.clone())
+    }
+}
+
+// Give each page the last heading of the pages before it, so a match early on a
+// page is attributed to the section it continues
+fn carry_sections(pages: &mut [PageText]) {
+    let mut section = None;
+    for page in pages {
+        page.section = section.clone();
+        if let Some((_, heading)) = page.headings().last() {
+            section = Some(heading);
+        }
+    }
+}
+
+// Whether an encrypted PDF's permissions (bit 5 of /P) allow copying or extracting its text
+fn text_extraction_permitted(doc: &Document) -> bool {
+    let Ok(encryption) = doc.get_encrypted() else {
+        return true;
+    };
+    encryption
+        .get(b"P")
+        .and_then(Object::as_i64)
+        .map_or(true, |permissions| permissions & 0x10 != 0)
+}
+
+fn extract_text_from_pdf(pdf_path: &Path, options: &TextOptions) -> Result<ExtractedPdf> {
+    let mut doc = match Document::load(pdf_path) {
+        Ok(doc) => doc,
+        Err(e) => {
+            // A missing or unreadable file isn't a problem with the PDF
+            if !matches!(e, lopdf::Error::IO(_)) {
+                problem_corpus::record(pdf_path, FailureCategory::LoadFailed, Some(e.to_string()));
+            }
+            return Err(anyhow::Error::new(e).context(format!("Failed to load PDF: {}", pdf_path.display())));
+        }
+    };
+
+    // Read before decrypting, which leaves the permissions behind
+    let extraction_restricted = !text_extraction_permitted(&doc);
+    if extraction_restricted && !options.ignore_permissions {
+        return Err(AppError::ExtractionNotPermitted {
+            file: pdf_path.to_string_lossy().to_string(),
+        }
+        .into());
+    }
+
+    // Many encrypted PDFs only restrict permissions and open with an empty user password
+    if doc.is_encrypted() && doc.decrypt("").is_err() {
+        problem_corpus::record(pdf_path, FailureCategory::Encrypted, None);
+        return Err(AppError::Encrypted {
+            file: pdf_path.to_string_lossy().to_string(),
+        }
+        .into());
+    }
+
+    let mut pages = Vec::new();
+    let page_ids = doc.get_pages();
+    let page_count = page_ids.len();
+    let structure = StructureTree::read(&doc);
+    let mut labels = page_labels(&doc, page_count).into_iter();
+    let mut successful_extractions = 0;
+    let mut total_chars = 0;
+
+    let mut extracted: Vec<(u32, ObjectId, String, bool)> = Vec::with_capacity(page_count);
+    for (page_num, page_id) in page_ids {
+        check_cancelled(options.cancel)?;
+        let text = doc.extract_text(&[page_num]).unwrap_or_else(|e| {
+            eprintln!("Warning: Failed to extract text from page {} of {}: {}",
+                     page_num, pdf_path.display(), e);
+            String::new()
+        });
+        extracted.push((page_num, page_id, text, false));
+    }
+    report_text_problems(pdf_path, &extracted);
+
+    // Pages without usable text are read a batch at a time
+    if options.use_ocr {
+        let mut scanned: Vec<_> = extracted.iter_mut().filter(|(_, _, text, _)| ocr::needs_ocr(text)).collect();
+        for batch in scanned.chunks_mut(ocr::batch_size()) {
+            check_cancelled(options.cancel)?;
+            batch.par_iter_mut().for_each(|(page_num, page_id, text, ocr)| {
+                // Pages of the batch not yet started are skipped once cancelled
+                if check_cancelled(options.cancel).is_err() {
+                    return;
+                }
+                match ocr::ocr_page(&doc, *page_id) {
+                    Ok(Some(recognized)) => {
+                        *text = recognized;
+                        *ocr = true;
+                    }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("Warning: OCR failed on page {} of {}: {:#}", page_num, pdf_path.display(), e),
+                }
+            });
+        }
+        // A batch cut short leaves pages unread, which mustn't be cached
+        check_cancelled(options.cancel)?;
+    }
+
+    for (page_num, page_id, text, ocr) in extracted {
+        check_cancelled(options.cancel)?;
+        let label = labels.next().flatten();
+        // Boxes and marked content only line up with the text when the walk reproduced it exactly
+        let page_layout = if !ocr && (options.coordinates || structure.is_some()) {
+            layout::page_layout(&doc, page_id).filter(|page_layout| page_layout.text == text)
+        } else {
+            None
+        };
+        // Tagged pages are read in their structure's order, in blocks
+        let tagged = structure
+            .as_ref()
+            .zip(page_layout.as_ref())
+            .and_then(|(structure, page_layout)| structure.arrange(page_id, page_layout));
+        let (text, glyphs, blocks) = match tagged {
+            Some(tagged) => (tagged.layout.text, Some(tagged.layout.glyphs), Some(tagged.blocks)),
+            None => (text, page_layout.map(|page_layout| page_layout.glyphs), None),
+        };
+        let glyphs = glyphs.filter(|_| options.coordinates);
+        let geometry = glyphs.as_ref().and_then(|_| layout::page_geometry(&doc, page_id));
+        let char_count = text.len();
+        if char_count > 0 {
+            successful_extractions += 1;
+            total_chars += char_count;
+        }
+        // Without a structure tree, lopdf only marks the end of each text object,
+        // which isn't a reliable paragraph boundary
+        pages.push(PageText {
+            page_number: page_num as usize,
+            label,
+            text,
+            blocks,
+            section: None,
+            ocr,
+            glyphs,
+            geometry,
+        });
+    }
+
+    // Check if we extracted ANY text at all
+    if total_chars == 0 && page_count > 0 {
+        return Err(AppError::NoExtractableText {
+            file: pdf_path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+        }
+        .into());
+    }
+
+    eprintln!("Extracted text from {}/{} pages ({} total chars) in {}",
+             successful_extractions, page_count, total_chars,
+             pdf_path.file_name().unwrap_or_default().to_string_lossy());
+
+    Ok(ExtractedPdf {
+        pages,
+        title: pdf_info_title(&doc),
+        source_unavailable: false,
+        from_cache: false,
+        extraction_restricted,
+        from_zotero: false,
+        ocr: options.use_ocr,
+    })
+}
+
+// Record a PDF whose own text is missing or mostly garbled in the problem corpus,
+// before OCR stands in for it
+fn report_text_problems(pdf_path: &Path, extracted: &[(u32, ObjectId, String, bool)]) {
+    if extracted.is_empty() {
+        return;
+    }
+    let with_text: Vec<&str> = extracted
+        .iter()
+        .map(|(_, _, text, _)| text.as_str())
+        .filter(|text| ocr::has_text(text))
+        .collect();
+    let garbled = with_text.iter().filter(|text| ocr::garbled(text)).count();
+    if with_text.is_empty() {
+        problem_corpus::record(
+            pdf_path,
+            FailureCategory::NoText,
+            Some(format!("no text on any of {} pages", extracted.len())),
+        );
+    } else if garbled * 2 >= with_text.len() {
+        problem_corpus::record(
+            pdf_path,
+            FailureCategory::Garbled,
+            Some(format!("{} of {} pages with text are garbled", garbled, with_text.len())),
+        );
+    }
+}
+
+// Zotero's text of an attachment it has indexed, when there is one to use, and
+// otherwise the PDF's own. Zotero's text carries no page labels or title, and is
+// passed over when OCR is wanted since Zotero doesn't OCR scans, and when
+// coordinates are, since they come from the PDF's content.
+fn extract_or_reuse(pdf_path: &Path, options: &TextOptions) -> Result<ExtractedPdf> {
+    let zotero_pages = options
+        .zotero_fulltext
+        .filter(|_| !options.use_ocr && !options.coordinates)
+        .and_then(|fulltext| fulltext.pages(pdf_path));
+    let Some(pages) = zotero_pages else {
+        return extract_text_from_pdf(pdf_path, options);
+    };
+    Ok(ExtractedPdf {
+        pages: pages
+            .into_iter()
+            .enumerate()
+            .map(|(i, text)| PageText {
+                page_number: i + 1,
+                label: None,
+                text,
+                blocks: None,
+                section: None,
+                ocr: false,
+                glyphs: None,
+                geometry: None,
+            })
+            .collect(),
+        title: None,
+        source_unavailable: false,
+        from_cache: false,
+        extraction_restricted: false,
+        from_zotero: true,
+        ocr: false,
+    })
+}
+
+// Extract a PDF's text, going through the index's text cache when there is one.
+// A cached entry is used while the file's size and modification time are unchanged,
+// or when only the modification time changed but the contents hash the same
+// (files copied to another machine, or imported with a corpus bundle).
+//
+// Text of PDFs that forbid extraction is never cached, so a later search that
+// doesn't override the permission can't read it from the cache.
+//
+// With OCR on, text cached without it is extracted again if any page lacks usable text.
+// Coordinates aren't cached, so asking for them always reads the PDF.
+fn load_text(pdf_path: &Path, index: Option<&Index>, options: &TextOptions) -> Result<ExtractedPdf> {
+    let Some(index) = index.filter(|_| !options.coordinates) else {
+        return extract_or_reuse(pdf_path, options);
+    };
+    let path = pdf_path.to_string_lossy();
+
+    if options.cached_only {
+        let cached = index
+            .cached_text(&path)?
+            .ok_or_else(|| anyhow::anyhow!("No cached text for {}", path))?;
+        return Ok(cached.into());
+    }
+
+    // Archived corpora can be searched away from the original files
+    if !pdf_path.exists() {
+        if let Ok(Some(cached)) = index.cached_text(&path) {
+            let mut extracted = ExtractedPdf::from(cached);
+            extracted.source_unavailable = true;
+            return Ok(extracted);
+        }
+    }
+
+    let size = pdf_path.metadata().map(|m| m.len()).unwrap_or(0);
+    let mtime = index::file_mtime(pdf_path).unwrap_or(0);
+
+    let missing_ocr = |cached: &CachedText| {
+        options.use_ocr && !cached.ocr && cached.pages.iter().any(|page| ocr::needs_ocr(&page.text))
+    };
+    match index.cached_text(&path) {
+        Ok(Some(cached)) if missing_ocr(&cached) => {}
+        Ok(Some(cached)) if cached.size == size => {
+            if cached.mtime == mtime {
+                return Ok(cached.into());
+            }
+            if index::file_sha256(pdf_path).is_ok_and(|sha256| sha256 == cached.sha256) {
+                if let Err(e) = index.touch_cached_text(&path, mtime) {
+                    eprintln!("Warning: Failed to update text cache for {}: {}", path, e);
+                }
+                return Ok(cached.into());
+            }
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("Warning: Failed to read text cache for {}: {}", path, e),
+    }
+
+    let extracted = extract_or_reuse(pdf_path, options)?;
+    if extracted.extraction_restricted || extracted.from_zotero {
+        return Ok(extracted);
+    }
+    cache_text(index, pdf_path, size, mtime, &extracted);
+    Ok(extracted)
+}
+
+fn cache_text(index: &Index, pdf_path: &Path, size: u64, mtime: u64, extracted: &ExtractedPdf) {
+    let path = pdf_path.to_string_lossy();
+    if let Ok(sha256) = index::file_sha256(pdf_path) {
+        let cached = CachedText {
+            size,
+            mtime,
+            sha256,
+            title: extracted.title.clone(),
+            ocr: extracted.ocr,
+            pages: extracted
+                .pages
+                .iter()
+                .map(|page| CachedPage {
+                    page_number: page.page_number,
+                    label: page.label.clone(),
+                    text: page.text.clone(),
+                    ocr: page.ocr,
+                    blocks: page.blocks.clone(),
+                })
+                .collect(),
+        };
+        if let Err(e) = index.store_text(&path, &cached) {
+            eprintln!("Warning: Failed to cache text for {}: {}", path, e);
+        }
+    }
+}
+
+/// What building or updating the index did
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IndexReport {
+    pub files: usize,
+    // New, changed or, when rebuilding, every file that could be extracted
+    pub extracted: usize,
+    // Already in the index with the same contents
+    pub unchanged: usize,
+    pub failed: Vec<FileError>,
+}
+
+/// Extract the text of `pdf_files` into the index's text cache, so searches read
+/// it from there instead of parsing the PDFs. Only files that are new or whose size,
+/// modification time and hash changed are extracted, unless `rebuild` is set.
+/// Cached text of files that have since disappeared is kept for archive searches.
+pub fn index_files(pdf_files: &[PathBuf], index: &Index, rebuild: bool) -> IndexReport {
+    let results: Vec<(String, Result<bool>)> = pdf_files
+        .par_iter()
+        .map(|pdf_path| {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                if !rebuild {
+                    return load_text(pdf_path, Some(index), &TextOptions::default())
+                        .map(|extracted| !extracted.from_cache);
+                }
+                let extracted = extract_text_from_pdf(pdf_path, &TextOptions::default())?;
+                let size = pdf_path.metadata().map(|m| m.len()).unwrap_or(0);
+                let mtime = index::file_mtime(pdf_path).unwrap_or(0);
+                cache_text(index, pdf_path, size, mtime, &extracted);
+                Ok(true)
+            }));
+            let file_path = pdf_path.to_string_lossy().to_string();
+            // A malformed file that panics the parser is reported like any other failure
+            let result = result.unwrap_or_else(|payload| {
+                let message = panic_message(payload.as_ref());
+                problem_corpus::record(pdf_path, FailureCategory::Crashed, Some(message.clone()));
+                Err(AppError::Internal {
+                    message,
+                    file: file_path.clone(),
+                }
+                .into())
+            });
+            (file_path, result)
+        })
+        .collect();
+
+    let mut report = IndexReport {
+        files: pdf_files.len(),
+        ..Default::default()
+    };
+    for (file_path, result) in results {
+        match result {
+            Ok(true) => report.extracted += 1,
+            Ok(false) => report.unchanged += 1,
+            Err(e) => report.failed.push(FileError {
+                file_path,
+                error: e.into(),
+            }),
+        }
+    }
+    eprintln!(
+        "Indexed {} files: {} extracted, {} unchanged, {} failed",
+        report.files,
+        report.extracted,
+        report.unchanged,
+        report.failed.len()
+    );
+    report
+}
+
+impl From<CachedText> for ExtractedPdf {
+    fn from(cached: CachedText) -> Self {
+        ExtractedPdf {
+            pages: cached
+                .pages
+                .into_iter()
+                .map(|page| PageText {
+                    page_number: page.page_number,
+                    label: page.label,
+                    text: page.text,
+                    blocks: page.blocks,
+                    section: None,
+                    ocr: page.ocr,
+                    glyphs: None,
+                    geometry: None,
+                })
+                .collect(),
+            title: cached.title,
+            source_unavailable: false,
+            from_cache: true,
+            extraction_restricted: false,
+            from_zotero: false,
+            ocr: cached.ocr,
+        }
+    }
+}
+
+fn split_into_words(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+// Abbreviations whose period doesn't end a sentence
+const ABBREVIATIONS: &[&str] = &[
+    "al", "cf", "ch", "dr", "ed", "eds", "eg", "etc", "fig", "ie", "mr", "mrs", "ms", "no", "p", "pp", "prof",
+    "st", "vol", "vs",
+];
+
+// Whether a word ends a sentence: it ends in . ! ? or …, before any closing
+// quotes or brackets, and isn't an initial or a common abbreviation
+fn ends_sentence(word: &str) -> bool {
+    let word = word.trim_end_matches(['"', '\'', '”', '’', ')', ']', '»']);
+    if word.ends_with(['!', '?', '…']) {
+        return true;
+    }
+    let Some(stem) = word.strip_suffix('.') else {
+        return false;
+    };
+    let letters = stem.trim_start_matches(['"', '\'', '“', '‘', '(', '[', '«']);
+    !(letters.contains('.')
+        || letters.chars().count() == 1
+        || ABBREVIATIONS.contains(&letters.to_lowercase().as_str()))
+}
+
+// The words of a context before a match, starting after the first sentence end,
+// so a sentence cut off by the word budget is dropped. Unchanged without one.
+fn whole_sentences_before<S: AsRef<str>>(words: &[S]) -> &[S] {
+    match words.iter().position(|word| ends_sentence(word.as_ref())) {
+        Some(i) => &words[i + 1..],
+        None => words,
+    }
+}
+
+// The words of a context after a match, up to the last sentence end
+fn whole_sentences_after<S: AsRef<str>>(words: &[S]) -> &[S] {
+    match words.iter().rposition(|word| ends_sentence(word.as_ref())) {
+        Some(i) => &words[..=i],
+        None => words,
+    }
+}
+
+/// Normalize text for searching. By default this removes whitespace and common
+/// separators, which handles PDFs that don't have proper word spacing.
+fn normalize_text(text: &str, options: &NormalizationOptions) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    for c in text.chars() {
+        options.push_normalized(c, &mut normalized);
+    }
+    normalized
+}
+
+impl NormalizationOptions {
+    // Append what a single source char normalizes to, possibly nothing or several chars
+    fn push_normalized(&self, c: char, out: &mut String) {
+        match c {
+            ' ' | '\t' | '\n' | '\r' | '\u{00A0}' | '\u{2007}' | '\u{202F}' if self.strip_whitespace => {}
+            // Hyphens and soft hyphens
+            '-' | '\u{00AD}' | '\u{2010}' | '\u{2011}' if self.strip_hyphens => {}
+            'ﬀ' if self.expand_ligatures => out.push_str("ff"),
+            'ﬁ' if self.expand_ligatures => out.push_str("fi"),
+            'ﬂ' if self.expand_ligatures => out.push_str("fl"),
+            'ﬃ' if self.expand_ligatures => out.push_str("ffi"),
+            'ﬄ' if self.expand_ligatures => out.push_str("ffl"),
+            'ﬅ' | 'ﬆ' if self.expand_ligatures => out.push_str("st"),
+            _ if self.strip_diacritics => {
+                // Decompose, then drop the combining accents
+                out.extend(std::iter::once(c).nfd().filter(|d| !is_combining_mark(*d)));
+            }
+            _ => out.push(c),
+        }
+    }
+
+    fn normalized_len(&self, c: char) -> usize {
+        let mut buffer = String::new();
+        self.push_normalized(c, &mut buffer);
+        buffer.len()
+    }
+}
+
+// Translate block byte ranges in the raw page text into ranges in its normalized form
+fn normalized_block_ranges(text: &str, blocks: &[Range<usize>], options: &NormalizationOptions) -> Vec<Range<usize>> {
+    let mut boundaries: Vec<usize> = blocks.iter().flat_map(|b| [b.start, b.end]).collect();
+    boundaries.sort_unstable();
+    boundaries.dedup();
+
+    // Normalized length of the text preceding each boundary
+    let mut normalized_at = HashMap::new();
+    let mut pending = boundaries.iter().peekable();
+    let mut kept = 0;
+    for (i, c) in text.char_indices() {
+        while let Some(&&boundary) = pending.peek() {
+            if boundary > i {
+                break;
+            }
+            normalized_at.insert(boundary, kept);
+            pending.next();
+        }
+        kept += options.normalized_len(c);
+    }
+    for &boundary in pending {
+        normalized_at.insert(boundary, kept);
+    }
+
+    blocks
+        .iter()
+        .map(|b| normalized_at[&b.start]..normalized_at[&b.end])
+        .filter(|b| !b.is_empty())
+        .collect()
+}
+
+/// Normalized copy of a page's text that remembers which source char each byte
+/// came from, like `CaseFolded`, so hits can be mapped back to the original text
+struct NormalizedText {
+    text: String,
+    source_ranges: Vec<(usize, usize)>,
+}
+
+impl NormalizedText {
+    fn new(source: &str, options: &NormalizationOptions) -> Self {
+        let mut text = String::with_capacity(source.len());
+        let mut source_ranges = Vec::with_capacity(source.len());
+
+        for (start, c) in source.char_indices() {
+            let before = text.len();
+            options.push_normalized(c, &mut text);
+            source_ranges.extend(std::iter::repeat_n((start, start + c.len_utf8()), text.len() - before));
+        }
+
+        NormalizedText { text, source_ranges }
+    }
+
+    fn source_range(&self, start: usize, end: usize) -> (usize, usize) {
+        (self.source_ranges[start].0, self.source_ranges[end - 1].1)
+    }
+}
+
+// The source span of a hit in NFC coordinates. Combining marks right after the
+// hit belong to its last letter, even though stripping diacritics dropped them.
+fn source_span(text: &str, start: usize, mut end: usize) -> SourceSpan {
+    end += text[end..]
+        .chars()
+        .take_while(|c| is_combining_mark(*c))
+        .map(char::len_utf8)
+        .sum::<usize>();
+
+    let before: String = text[..start].nfc().collect();
+    let matched: String = text[start..end].nfc().collect();
+    let start = utf16_len(&before);
+    SourceSpan {
+        start,
+        end: start + utf16_len(&matched),
+        text: matched,
+    }
+}
+
+/// Lowercased copy of a string that remembers which source char each byte came from,
+/// so offsets found in the folded text always map back to valid char boundaries
+struct CaseFolded {
+    text: String,
+    // For every byte of `text`, the byte range of the source char it was folded from
+    source_ranges: Vec<(usize, usize)>,
+}
+
+impl CaseFolded {
+    fn new(source: &str) -> Self {
+        let mut text = String::with_capacity(source.len());
+        let mut source_ranges = Vec::with_capacity(source.len());
+
+        for (start, c) in source.char_indices() {
+            let end = start + c.len_utf8();
+            for lower in c.to_lowercase() {
+                // Final sigma is the same letter as sigma for matching purposes
+                let lower = if lower == 'ς' { 'σ' } else { lower };
+                text.push(lower);
+                source_ranges.extend(std::iter::repeat_n((start, end), lower.len_utf8()));
+            }
+        }
+
+        CaseFolded { text, source_ranges }
+    }
+
+    // Map a non-empty byte range of the folded text to the source chars it covers
+    fn source_range(&self, start: usize, end: usize) -> (usize, usize) {
+        (self.source_ranges[start].0, self.source_ranges[end - 1].1)
+    }
+}
+
+// Occurrences of the query's words in order, each within `max_gap` words of the
+// previous one. Hits span from the first word to the last in the unnormalized text.
+fn find_ordered_words(text: &str, query: &str, max_gap: usize, normalization: &NormalizationOptions) -> Vec<Hit> {
+    let words = word_spans(text);
+    let keys: Vec<String> = words.iter().map(|word| word_key(&text[word.clone()], normalization)).collect();
+    let query_keys: Vec<String> = query
+        .split_whitespace()
+        .map(|word| word_key(word, normalization))
+        .filter(|key| !key.is_empty())
+        .collect();
+
+    let mut hits = Vec::new();
+    let Some(first) = query_keys.first() else {
+        return hits;
+    };
+
+    let mut dead_ends = HashSet::new();
+    let mut position = 0;
+    while position < keys.len() {
+        if keys[position] == *first {
+            if let Some(last) = match_rest(&keys, &query_keys, 0, position, max_gap, &mut dead_ends) {
+                hits.push(Hit {
+                    start: words[position].start,
+                    end: words[last].end,
+                    captures: BTreeMap::new(),
+                });
+                position = last + 1;
+                continue;
+            }
+        }
+        position += 1;
+    }
+
+    hits
+}
+
+// Index of the word matching the last query word, given query word `matched` at
+// word `previous`. Every placement within the gap is tried, since the nearest one
+// isn't always the one that lets later words fit. Whether the rest fits depends
+// only on (`matched`, `previous`), so placements that failed are recorded in
+// `dead_ends` and never tried again, which keeps repetitive pages linear.
+fn match_rest(
+    keys: &[String],
+    query_keys: &[String],
+    matched: usize,
+    previous: usize,
+    max_gap: usize,
+    dead_ends: &mut HashSet<(usize, usize)>,
+) -> Option<usize> {
+    let Some(next) = query_keys.get(matched + 1) else {
+        return Some(previous);
+    };
+    if dead_ends.contains(&(matched, previous)) {
+        return None;
+    }
+    let window_end = (previous + max_gap + 2).min(keys.len());
+    let last = (previous + 1..window_end)
+        .filter(|&candidate| keys[candidate] == *next)
+        .find_map(|candidate| match_rest(keys, query_keys, matched + 1, candidate, max_gap, dead_ends));
+    if last.is_none() {
+        dead_ends.insert((matched, previous));
+    }
+    last
+}
+
+// Byte ranges of whitespace-separated words, without surrounding punctuation
+fn word_spans(text: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    for (index, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+        match (c.is_whitespace(), start) {
+            (true, Some(word_start)) => {
+                let word = &text[word_start..index];
+                let trimmed = word.trim_start_matches(|c: char| !c.is_alphanumeric());
+                let leading = word.len() - trimmed.len();
+                let trimmed = trimmed.trim_end_matches(|c: char| !c.is_alphanumeric());
+                if !trimmed.is_empty() {
+                    spans.push(word_start + leading..word_start + leading + trimmed.len());
+                }
+                start = None;
+            }
+            (false, None) => start = Some(index),
+            _ => {}
+        }
+    }
+    spans
+}
+
+// A word as compared for ordered proximity, after the search's normalization
+fn word_key(word: &str, normalization: &NormalizationOptions) -> String {
+    let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+    let normalized = normalize_text(word, normalization);
+    if normalization.case_fold {
+        CaseFolded::new(&normalized).text
+    } else {
+        normalized
+    }
+}
+
+// A single hit within one page's text
+struct PageMatch {
+    context_before: String,
+    matched_text: String,
+    context_after: String,
+    // Byte offset of the match within the page's normalized text (raw text for raw_text queries)
+    offset: usize,
+    captures: BTreeMap<String, String>,
+    source_span: SourceSpan,
+    // Byte range of the match in the page's original text
+    source_range: Range<usize>,
+}
+
+// Byte range of one occurrence of a query, with its named capture groups for regex queries
+struct Hit {
+    start: usize,
+    end: usize,
+    captures: BTreeMap<String, String>,
+}
+
+// Case-insensitive unless the query's flags say otherwise or case folding is off
+fn compile_regex(pattern: &str, flags: &RegexFlags, case_fold: bool) -> Result<Regex> {
+    Ok(RegexBuilder::new(pattern)
+        .case_insensitive(flags.case_insensitive.unwrap_or(case_fold))
+        .multi_line(flags.multi_line)
+        .dot_matches_new_line(flags.dot_matches_new_line)
+        .unicode(flags.unicode)
+        .build()?)
+}
+
+// Byte ranges of every non-empty occurrence of an already normalized query in normalized text
+fn find_hits(normalized_text: &str, normalized_query: &str, query_item: &QueryItem, case_fold: bool) -> Result<Vec<Hit>> {
+    let mut hits = Vec::new();
+
+    if query_item.use_regex {
+        let pattern = compile_regex(normalized_query, &query_item.regex_flags, case_fold)?;
+        let group_names: Vec<&str> = pattern.capture_names().flatten().collect();
+
+        for captures in pattern.captures_iter(normalized_text) {
+            let whole = captures.get(0).expect("group 0 is always the whole match");
+            // Patterns like `a*` can match the empty string everywhere
+            if whole.is_empty() {
+                continue;
+            }
+            // Groups that didn't participate in the match are left out
+            let named = group_names
+                .iter()
+                .filter_map(|name| Some((name.to_string(), captures.name(name)?.as_str().to_string())))
+                .collect();
+            hits.push(Hit {
+                start: whole.start(),
+                end: whole.end(),
+                captures: named,
+            });
+        }
+    } else {
+        hits.extend(
+            find_literal(normalized_text, normalized_query, case_fold)
+                .into_iter()
+                .map(|range| Hit {
+                    start: range.start,
+                    end: range.end,
+                    captures: BTreeMap::new(),
+                }),
+        );
+    }
+
+    Ok(hits)
+}
+
+// Byte ranges of every occurrence of a literal, already normalized query
+fn find_literal(normalized_text: &str, normalized_query: &str, case_fold: bool) -> Vec<Range<usize>> {
+    if !case_fold {
+        if normalized_query.is_empty() {
+            return Vec::new();
+        }
+        return normalized_text
+            .match_indices(normalized_query)
+            .map(|(start, matched)| start..start + matched.len())
+            .collect();
+    }
+
+    // Case-insensitive search by default. Lowercasing can change byte lengths,
+    // so matches are found in a folded copy and mapped back to the normalized text.
+    let search_query = CaseFolded::new(normalized_query).text;
+    if search_query.is_empty() {
+        return Vec::new();
+    }
+
+    // Search the full normalized text instead of word-by-word
+    // This catches multi-word queries that span across "words" in the original text
+    let folded_text = CaseFolded::new(normalized_text);
+
+    let mut ranges = Vec::new();
+    let mut search_start = 0;
+    while let Some(match_pos) = folded_text.text[search_start..].find(&search_query) {
+        let folded_start = search_start + match_pos;
+        let folded_end = folded_start + search_query.len();
+        let (start, end) = folded_text.source_range(folded_start, folded_end);
+        ranges.push(start..end);
+
+        // Move past this match to find the next one
+        search_start = folded_end;
+    }
+    ranges
+}
+
+/// A parsed boolean query. Operators are upper case, so "and" is searched as a word.
+/// NOT binds tighter than AND, which binds tighter than OR; terms side by side are
+/// ANDed, and NEAR/n joins two terms, phrases or parenthesized groups.
+#[derive(Debug, PartialEq)]
+enum BooleanQuery {
+    // A word, or a quoted phrase
+    Term(String),
+    And(Vec<BooleanQuery>),
+    Or(Vec<BooleanQuery>),
+    Not(Box<BooleanQuery>),
+    // Both sides within this many words of each other, in either order
+    Near(Box<BooleanQuery>, Box<BooleanQuery>, usize),
+}
+
+#[derive(Debug, PartialEq)]
+enum BooleanToken {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Near(usize),
+    Term(String),
+}
+
+fn boolean_query_error(query: &str, problem: &str) -> anyhow::Error {
+    AppError::invalid_input(format!("Invalid boolean query \"{}\": {}", query, problem)).into()
+}
+
+fn tokenize_boolean_query(query: &str) -> Result<Vec<BooleanToken>> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(BooleanToken::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(BooleanToken::Close);
+            }
+            '"' => {
+                chars.next();
+                let mut phrase = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '"' {
+                        closed = true;
+                        break;
+                    }
+                    phrase.push(c);
+                }
+                if !closed {
+                    return Err(boolean_query_error(query, "a quote isn't closed"));
+                }
+                if phrase.trim().is_empty() {
+                    return Err(boolean_query_error(query, "a quoted phrase is empty"));
+                }
+                tokens.push(BooleanToken::Term(phrase));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | '"') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(match word.as_str() {
+                    "AND" => BooleanToken::And,
+                    "OR" => BooleanToken::Or,
+                    "NOT" => BooleanToken::Not,
+                    "NEAR" => return Err(boolean_query_error(query, "NEAR needs a distance, as in NEAR/10")),
+                    _ => match word.strip_prefix("NEAR/") {
+                        Some(distance) => BooleanToken::Near(
+                            distance
+                                .parse()
+                                .map_err(|_| boolean_query_error(query, "NEAR needs a distance, as in NEAR/10"))?,
+                        ),
+                        None => BooleanToken::Term(word),
+                    },
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parse a boolean query, reporting syntax errors as invalid input
+fn parse_boolean_query(query: &str) -> Result<BooleanQuery> {
+    let tokens = tokenize_boolean_query(query)?;
+    if tokens.is_empty() {
+        return Err(boolean_query_error(query, "there's nothing to search for"));
+    }
+    let mut parser = BooleanParser { query, tokens, position: 0 };
+    let parsed = parser.or()?;
+    if parser.position < parser.tokens.len() {
+        return Err(boolean_query_error(query, "a closing parenthesis has no opening one"));
+    }
+    Ok(parsed)
+}
+
+struct BooleanParser<'a> {
+    query: &'a str,
+    tokens: Vec<BooleanToken>,
+    position: usize,
+}
+
+impl BooleanParser<'_> {
+    fn peek(&self) -> Option<&BooleanToken> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&BooleanToken> {
+        self.position += 1;
+        self.tokens.get(self.position - 1)
+    }
+
+    fn or(&mut self) -> Result<BooleanQuery> {
+        let mut operands = vec![self.and()?];
+        while self.peek() == Some(&BooleanToken::Or) {
+            self.next();
+            operands.push(self.and()?);
+        }
+        Ok(if operands.len() == 1 { operands.remove(0) } else { BooleanQuery::Or(operands) })
+    }
+
+    // Explicit AND, or operands side by side
+    fn and(&mut self) -> Result<BooleanQuery> {
+        let mut operands = vec![self.not()?];
+        loop {
+            match self.peek() {
+                Some(BooleanToken::And) => {
+                    self.next();
+                }
+                Some(BooleanToken::Open | BooleanToken::Not | BooleanToken::Term(_)) => {}
+                _ => break,
+            }
+            operands.push(self.not()?);
+        }
+        Ok(if operands.len() == 1 { operands.remove(0) } else { BooleanQuery::And(operands) })
+    }
+
+    fn not(&mut self) -> Result<BooleanQuery> {
+        if self.peek() == Some(&BooleanToken::Not) {
+            self.next();
+            return Ok(BooleanQuery::Not(Box::new(self.not()?)));
+        }
+        self.near()
+    }
+
+    fn near(&mut self) -> Result<BooleanQuery> {
+        let mut left = self.primary()?;
+        while let Some(&BooleanToken::Near(distance)) = self.peek() {
+            self.next();
+            let right = self.primary()?;
+            left = BooleanQuery::Near(Box::new(left), Box::new(right), distance);
+        }
+        Ok(left)
+    }
+
+    fn primary(&mut self) -> Result<BooleanQuery> {
+        let query = self.query;
+        match self.next() {
+            Some(BooleanToken::Term(term)) => Ok(BooleanQuery::Term(term.clone())),
+            Some(BooleanToken::Open) => {
+                let inner = self.or()?;
+                match self.next() {
+                    Some(BooleanToken::Close) => Ok(inner),
+                    _ => Err(boolean_query_error(query, "a parenthesis isn't closed")),
+                }
+            }
+            Some(BooleanToken::Near(_)) => Err(boolean_query_error(query, "NEAR needs a term on each side")),
+            Some(_) => Err(boolean_query_error(query, "an operator is missing a term")),
+            None => Err(boolean_query_error(query, "the query ends with an operator")),
+        }
+    }
+}
+
+// A page's text prepared for evaluating boolean queries against it
+struct BooleanPage {
+    words: Vec<Range<usize>>,
+    // Word keys for whole-word matching, None when terms match anywhere
+    word_keys: Option<Vec<String>>,
+    normalized: NormalizedText,
+    normalization: NormalizationOptions,
+}
+
+impl BooleanPage {
+    fn new(text: &str, query_item: &QueryItem, normalization: &NormalizationOptions) -> Self {
+        // Raw-text queries compare the text as extracted, apart from case
+        let mut normalization = if query_item.raw_text {
+            NormalizationOptions {
+                strip_whitespace: false,
+                strip_hyphens: false,
+                expand_ligatures: false,
+                case_fold: normalization.case_fold,
+                strip_diacritics: false,
+            }
+        } else {
+            normalization.clone()
+        };
+        if let Some(case_sensitive) = query_item.case_sensitive {
+            normalization.case_fold = !case_sensitive;
+        }
+
+        let words = word_spans(text);
+        let word_keys = query_item
+            .whole_word
+            .then(|| words.iter().map(|word| word_key(&text[word.clone()], &normalization)).collect());
+        BooleanPage {
+            words,
+            word_keys,
+            normalized: NormalizedText::new(text, &normalization),
+            normalization,
+        }
+    }
+
+    // Source byte ranges of the term's occurrences
+    fn term_hits(&self, term: &str) -> Vec<Range<usize>> {
+        let Some(ref word_keys) = self.word_keys else {
+            let normalized_term = normalize_text(term, &self.normalization);
+            return find_literal(&self.normalized.text, &normalized_term, self.normalization.case_fold)
+                .into_iter()
+                .map(|range| {
+                    let (start, end) = self.normalized.source_range(range.start, range.end);
+                    start..end
+                })
+                .collect();
+        };
+
+        let term_keys: Vec<String> = term
+            .split_whitespace()
+            .map(|word| word_key(word, &self.normalization))
+            .filter(|key| !key.is_empty())
+            .collect();
+        if term_keys.is_empty() {
+            return Vec::new();
+        }
+        word_keys
+            .windows(term_keys.len())
+            .enumerate()
+            .filter(|(_, keys)| *keys == term_keys.as_slice())
+            .map(|(first, _)| self.words[first].start..self.words[first + term_keys.len() - 1].end)
+            .collect()
+    }
+
+    // Indices of the first and last word a hit covers
+    fn word_range(&self, hit: &Range<usize>) -> (usize, usize) {
+        let first = self.words.partition_point(|word| word.end <= hit.start);
+        let last = self.words.partition_point(|word| word.start < hit.end);
+        (first, last.saturating_sub(1).max(first))
+    }
+
+    // None when the expression doesn't hold on the page; otherwise the hits that
+    // make it hold, which is empty for NOT
+    fn evaluate(&self, query: &BooleanQuery) -> Option<Vec<Range<usize>>> {
+        match query {
+            BooleanQuery::Term(term) => Some(self.term_hits(term)).filter(|hits| !hits.is_empty()),
+            BooleanQuery::And(operands) => operands
+                .iter()
+                .map(|operand| self.evaluate(operand))
+                .collect::<Option<Vec<_>>>()
+                .map(|hits| hits.concat()),
+            BooleanQuery::Or(operands) => {
+                let held: Vec<Vec<Range<usize>>> = operands.iter().filter_map(|operand| self.evaluate(operand)).collect();
+                (!held.is_empty()).then(|| held.concat())
+            }
+            BooleanQuery::Not(operand) => match self.evaluate(operand) {
+                Some(_) => None,
+                None => Some(Vec::new()),
+            },
+            BooleanQuery::Near(left, right, distance) => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+                let mut hits = Vec::new();
+                for a in &left {
+                    let (a_first, a_last) = self.word_range(a);
+                    for b in &right {
+                        let (b_first, b_last) = self.word_range(b);
+                        // Words strictly between the two, zero when they touch or overlap
+                        let between = b_first.saturating_sub(a_last + 1).max(a_first.saturating_sub(b_last + 1));
+                        if between <= *distance {
+                            hits.push(a.start.min(b.start)..a.end.max(b.end));
+                        }
+                    }
+                }
+                Some(hits).filter(|hits| !hits.is_empty())
+            }
+        }
+    }
+}
+
+// Source byte ranges of a boolean query's hits on a page, in order and without repeats
+fn find_boolean_hits(text: &str, query_item: &QueryItem, normalization: &NormalizationOptions) -> Result<Vec<Hit>> {
+    let query = parse_boolean_query(&query_item.query)?;
+    let page = BooleanPage::new(text, query_item, normalization);
+    let mut ranges = page.evaluate(&query).unwrap_or_default();
+    ranges.sort_by_key(|range| (range.start, range.end));
+    ranges.dedup();
+    Ok(ranges
+        .into_iter()
+        .map(|range| Hit {
+            start: range.start,
+            end: range.end,
+            captures: BTreeMap::new(),
+        })
+        .collect())
+}
+
+/// Text every match of a query must contain, as checked by the first, cheap pass
+/// of a search. It's looked up in the page's scan text, which is coarser than
+/// any normalization the precise matchers apply, so a page that fails it can't
+/// have a match.
+#[derive(Debug, PartialEq)]
+enum Prefilter {
+    // Nothing cheap to check; the page is searched
+    Pass,
+    // Scan-folded text
+    Contains(String),
+    All(Vec<Prefilter>),
+    Any(Vec<Prefilter>),
+}
+
+impl Prefilter {
+    fn contains(text: &str) -> Self {
+        let folded = scan_text(text);
+        if folded.is_empty() {
+            Prefilter::Pass
+        } else {
+            Prefilter::Contains(folded)
+        }
+    }
+
+    fn all(parts: Vec<Prefilter>) -> Self {
+        let mut parts: Vec<Prefilter> = parts.into_iter().filter(|part| *part != Prefilter::Pass).collect();
+        match parts.len() {
+            0 => Prefilter::Pass,
+            1 => parts.pop().unwrap(),
+            _ => Prefilter::All(parts),
+        }
+    }
+
+    fn any(parts: Vec<Prefilter>) -> Self {
+        if parts.is_empty() || parts.contains(&Prefilter::Pass) {
+            Prefilter::Pass
+        } else {
+            Prefilter::Any(parts)
+        }
+    }
+
+    // Words must each occur, though not necessarily side by side: word matching
+    // skips the punctuation between them
+    fn words(text: &str) -> Self {
+        Prefilter::all(
+            text.split_whitespace()
+                .map(|word| Prefilter::contains(word.trim_matches(|c: char| !c.is_alphanumeric())))
+                .collect(),
+        )
+    }
+
+    fn for_query(query_item: &QueryItem, normalization: &NormalizationOptions) -> Self {
+        if query_item.is_boolean() {
+            return parse_boolean_query(&query_item.query).map_or(Prefilter::Pass, |query| Prefilter::for_boolean(&query));
+        }
+        if query_item.proximity().is_some() {
+            return Prefilter::words(&query_item.query);
+        }
+        if !query_item.use_regex {
+            return Prefilter::contains(&query_item.query);
+        }
+
+        // Patterns are normalized like the text they're matched against. Case is
+        // left to the scan text's folding, so literals stay literals.
+        let pattern = if query_item.raw_text {
+            query_item.query.clone()
+        } else {
+            normalize_text(&query_item.query, normalization)
+        };
+        let flags = &query_item.regex_flags;
+        regex_syntax::ParserBuilder::new()
+            .multi_line(flags.multi_line)
+            .dot_matches_new_line(flags.dot_matches_new_line)
+            .unicode(flags.unicode)
+            .build()
+            .parse(&pattern)
+            .map_or(Prefilter::Pass, |hir| Prefilter::for_regex(&hir))
+    }
+
+    fn for_boolean(query: &BooleanQuery) -> Self {
+        match query {
+            BooleanQuery::Term(term) => Prefilter::words(term),
+            BooleanQuery::And(operands) => Prefilter::all(operands.iter().map(Prefilter::for_boolean).collect()),
+            BooleanQuery::Or(operands) => Prefilter::any(operands.iter().map(Prefilter::for_boolean).collect()),
+            BooleanQuery::Not(_) => Prefilter::Pass,
+            BooleanQuery::Near(left, right, _) => Prefilter::all(vec![Prefilter::for_boolean(left), Prefilter::for_boolean(right)]),
+        }
+    }
+
+    fn for_regex(hir: &regex_syntax::hir::Hir) -> Self {
+        use regex_syntax::hir::HirKind;
+        match hir.kind() {
+            HirKind::Literal(literal) => std::str::from_utf8(&literal.0).map_or(Prefilter::Pass, Prefilter::contains),
+            HirKind::Capture(capture) => Prefilter::for_regex(&capture.sub),
+            HirKind::Repetition(repetition) if repetition.min > 0 => Prefilter::for_regex(&repetition.sub),
+            HirKind::Concat(parts) => Prefilter::all(parts.iter().map(Prefilter::for_regex).collect()),
+            HirKind::Alternation(parts) => Prefilter::any(parts.iter().map(Prefilter::for_regex).collect()),
+            _ => Prefilter::Pass,
+        }
+    }
+
+    fn holds(&self, scan: &str) -> bool {
+        match self {
+            Prefilter::Pass => true,
+            Prefilter::Contains(text) => scan.contains(text.as_str()),
+            Prefilter::All(parts) => parts.iter().all(|part| part.holds(scan)),
+            Prefilter::Any(parts) => parts.iter().any(|part| part.holds(scan)),
+        }
+    }
+}
+
+// Every normalization step at once, for the scan text
+const SCAN_NORMALIZATION: NormalizationOptions = NormalizationOptions {
+    strip_whitespace: true,
+    strip_hyphens: true,
+    expand_ligatures: true,
+    case_fold: true,
+    strip_diacritics: true,
+};
+
+// A page's text as the first pass of a search looks at it: normalized every way,
+// then case-folded through upper case so letters regex case folding pairs up
+// ("ſ" and "s", "ϐ" and "β") fold alike. Char by char, so whatever a query's
+// match contains, the scan text of the match contains in scan form.
+fn scan_text(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut scan = String::with_capacity(text.len());
+    for c in text.chars() {
+        normalized.clear();
+        SCAN_NORMALIZATION.push_normalized(c, &mut normalized);
+        for folded in normalized.chars().flat_map(char::to_uppercase).flat_map(char::to_lowercase) {
+            let folded = if folded == 'ς' { 'σ' } else { folded };
+            scan.extend(std::iter::once(folded).nfd().filter(|d| !is_combining_mark(*d) && !d.is_whitespace()));
+        }
+    }
+    scan
+}
+
+fn search_in_page(
+    page: &PageText,
+    query_item: &QueryItem,
+    params: &SearchParams,
+    cancel: Option<&AtomicBool>,
+) -> Result<Vec<PageMatch>> {
+    let mut matches = Vec::new();
+    let normalization = &params.normalization;
+    let context_words = params.context_words;
+
+    // Ordered proximity and boolean queries match words, so they work on the text's own word breaks
+    let proximity = query_item.proximity();
+    let boolean = query_item.is_boolean();
+    let raw_coordinates = query_item.raw_text || proximity.is_some() || boolean;
+
+    // Normalize both query and page text to handle PDFs with inconsistent spacing,
+    // unless the query asks for the raw extracted text
+    let (normalized_query, normalized) = if raw_coordinates {
+        (Cow::Borrowed(query_item.query.as_str()), None)
+    } else {
+        (
+            Cow::Owned(normalize_text(&query_item.query, normalization)),
+            Some(NormalizedText::new(&page.text, normalization)),
+        )
+    };
+    let normalized_page = normalized.as_ref().map_or(page.text.as_str(), |n| n.text.as_str());
+
+    // Block boundaries in the searched text's coordinates, used to keep context inside the match's block
+    let blocks = match page.blocks {
+        Some(ref blocks) if !params.context_across_blocks => {
+            let ranges: Vec<Range<usize>> = blocks.iter().map(|block| block.range.clone()).collect();
+            if raw_coordinates {
+                ranges
+            } else {
+                normalized_block_ranges(&page.text, &ranges, normalization)
+            }
+        }
+        _ => Vec::new(),
+    };
+
+    // Byte ranges of each hit within the normalized page
+    let hits = match proximity {
+        _ if boolean => find_boolean_hits(&page.text, query_item, normalization)?,
+        Some(max_gap) => find_ordered_words(&page.text, &query_item.query, max_gap, normalization),
+        None => find_hits(normalized_page, &normalized_query, query_item, normalization.case_fold)?,
+    };
+
+    for hit in hits {
+        check_cancelled(cancel)?;
+        let (match_start, match_end) = (hit.start, hit.end);
+        // Context stops at the edges of the block containing the match, when known
+        let context_start = blocks
+            .iter()
+            .find(|b| b.contains(&match_start))
+            .map_or(0, |b| b.start);
+        let context_end = blocks
+            .iter()
+            .find(|b| b.contains(&(match_end - 1)))
+            .map_or(normalized_page.len(), |b| b.end);
+
+        let before_words = split_into_words(&normalized_page[context_start..match_start]);
+        let after_words = split_into_words(&normalized_page[match_end..context_end]);
+
+        let mut before_window = &before_words[before_words.len().saturating_sub(context_words)..];
+        let mut after_window = &after_words[..after_words.len().min(context_words)];
+        // Only a side the budget cut short can end mid-sentence
+        if params.sentence_context {
+            if before_words.len() > context_words {
+                before_window = whole_sentences_before(before_window);
+            }
+            if after_words.len() > context_words {
+                after_window = whole_sentences_after(after_window);
+            }
+        }
+        let context_before = before_window.join(" ");
+        let context_after = after_window.join(" ");
+
+        let (source_start, source_end) = normalized
+            .as_ref()
+            .map_or((match_start, match_end), |n| n.source_range(match_start, match_end));
+
+        matches.push(PageMatch {
+            context_before,
+            matched_text: normalized_page[match_start..match_end].to_string(),
+            context_after,
+            offset: match_start,
+            captures: hit.captures,
+            source_span: source_span(&page.text, source_start, source_end),
+            source_range: source_start..source_end,
+        });
+    }
+
+    Ok(matches)
+}
+
+fn search_pdf_with_queries(
+    pdf_path: &Path,
+    params: &SearchParams,
+    zotero_map: Option<&ZoteroMap>,
+    index: Option<&Index>,
+    zotero_fulltext: Option<&ZoteroFulltext>,
+    cancel: Option<&AtomicBool>,
+) -> Result<FileMatches> {
+    let queries = &params.queries;
+    let corpus = corpora::corpus_of(&params.corpora, pdf_path).map(str::to_string);
+
+    let options = TextOptions {
+        zotero_fulltext,
+        ignore_permissions: params.ignore_extraction_permissions,
+        use_ocr: params.use_ocr,
+        coordinates: params.page_coordinates,
+        cancel,
+        cached_only: params.latency_budget_ms.is_some(),
+    };
+    let extracted = load_text(pdf_path, index, &options)?;
+    let title_candidates = extracted.title_candidates();
+    let source_unavailable = extracted.source_unavailable;
+    let extraction_restricted = extracted.extraction_restricted;
+    let info_title = extracted.title.clone();
+    let first_page = extracted.pages.first().filter(|page| page.page_number == 1).map(|page| page.text.clone());
+    let mut pages = extracted.pages;
+    // Before the page range drops the pages whose headings open it
+    carry_sections(&mut pages);
+
+    // Filter pages by page range if specified
+    if let (Some(start), Some(end)) = (params.start_page, params.end_page) {
+        pages.retain(|page| page.page_number >= start && page.page_number <= end);
+    }
+
+    // The document and its Zotero item, if available
+    let source = if params.source.as_deref() == Some("zotero") {
+        ItemSource::Zotero
+    } else {
+        ItemSource::File
+    };
+    let mut item = LibraryItem::new(pdf_path, source);
+    let zotero_metadata = zotero_map.and_then(|map| lookup_zotero_metadata(map, pdf_path, &title_candidates));
+    item = item.with_zotero(zotero_metadata);
+
+    // Two passes: each page is scanned once for the text every query's matches
+    // must contain, and the precise matchers only run on the pages that have it.
+    // A single literal query is already a plain scan, so it's searched directly.
+    let prefilters: Vec<Prefilter> = queries
+        .iter()
+        .map(|q| Prefilter::for_query(q, &params.normalization))
+        .collect();
+    let single_literal = queries.len() == 1 && !queries[0].use_regex && queries[0].proximity().is_none() && !queries[0].is_boolean();
+    let scans: Vec<Option<String>> = if single_literal || prefilters.iter().all(|p| *p == Prefilter::Pass) {
+        vec![None; pages.len()]
+    } else {
+        pages.iter().map(|page| Some(scan_text(&page.text))).collect()
+    };
+    let candidate = |query_index: usize, page_index: usize| {
+        scans[page_index].as_deref().is_none_or(|scan| prefilters[query_index].holds(scan))
+    };
+
+    // Separate queries into parallel and filter types
+    let parallel_queries: Vec<usize> = (0..queries.len())
+        .filter(|&i| queries[i].query_type == "parallel")
+        .collect();
+    let filter_queries: Vec<usize> = (0..queries.len())
+        .filter(|&i| queries[i].query_type == "filter")
+        .collect();
+
+    // First, check if the PDF contains ALL filter queries (anywhere in the document)
+    // Filter queries act as document-level filters
+    for query_index in filter_queries {
+        let mut found_in_pdf = false;
+
+        for (page_index, page) in pages.iter().enumerate() {
+            check_cancelled(cancel)?;
+            if !candidate(query_index, page_index) {
+                continue;
+            }
+            let matches = search_in_page(page, &queries[query_index], params, cancel)?;
+
+            if !matches.is_empty() {
+                found_in_pdf = true;
+                break;
+            }
+        }
+
+        if !found_in_pdf {
+            // This PDF doesn't contain this filter query anywhere, so skip the entire PDF
+            return Ok(FileMatches {
+                matches: Vec::new(),
+                exclusion: Some(Exclusion::FilterQuery),
+                ..Default::default()
+            });
+        }
+    }
+
+    // If we get here, the PDF passes all filters
+    // Now collect matches from ALL parallel queries
+    let mut final_results = Vec::new();
+
+    // If there are no parallel queries, use the first query as parallel
+    let queries_to_search: Vec<usize> = if parallel_queries.is_empty() && !queries.is_empty() {
+        vec![0]
+    } else {
+        parallel_queries
+    };
+
+    'queries: for query_index in queries_to_search {
+        let query_item = &queries[query_index];
+        for (page_index, page) in pages.iter().enumerate() {
+            check_cancelled(cancel)?;
+            if !candidate(query_index, page_index) {
+                continue;
+            }
+            let matches = search_in_page(page, query_item, params, cancel)?;
+
+            for page_match in matches {
+                let highlights = context_highlights(&page_match, queries, query_item, &params.normalization)?;
+                let (bounding_boxes, char_range) = match page.glyphs {
+                    Some(ref glyphs) => {
+                        let range = &page_match.source_range;
+                        let start = page.text[..range.start].chars().count();
+                        let end = start + page.text[range.clone()].chars().count();
+                        (Some(layout::boxes_for_range(glyphs, range)), Some(start..end))
+                    }
+                    None => (None, None),
+                };
+                let display_boxes = bounding_boxes.as_ref().zip(page.geometry).map(|(boxes, geometry)| {
+                    boxes.iter().filter_map(|rect| geometry.to_display(rect)).collect()
+                });
+                final_results.push(SearchMatch {
+                    schema_version: SchemaVersion::CURRENT,
+                    item: item.clone(),
+                    page_number: page.page_number,
+                    page_label: page.label.clone(),
+                    context_before: page_match.context_before,
+                    matched_text: page_match.matched_text,
+                    context_after: page_match.context_after,
+                    offset: Some(page_match.offset),
+                    query: Some(query_item.query.clone()),
+                    color: query_item.color.clone(),
+                    highlights,
+                    captures: page_match.captures,
+                    source_span: Some(page_match.source_span),
+                    source_unavailable,
+                    extraction_restricted,
+                    ocr: page.ocr,
+                    bounding_boxes,
+                    display_boxes,
+                    page_geometry: page.geometry,
+                    char_range,
+                    match_id: None,
+                    section: page.section_at(page_match.source_range.start),
+                    corpus: corpus.clone(),
+                });
+                if params.file_match_limit.is_some_and(|limit| final_results.len() >= limit) {
+                    break 'queries;
+                }
+            }
+        }
+    }
+
+    // Passing mentions: a query that only hits a few times doesn't count for this file
+    if let Some(min_matches) = params.min_matches_per_file {
+        let mut hits_per_query: HashMap<Option<String>, usize> = HashMap::new();
+        for m in &final_results {
+            *hits_per_query.entry(m.query.clone()).or_default() += 1;
+        }
+        let had_matches = !final_results.is_empty();
+        final_results.retain(|m| hits_per_query[&m.query] >= min_matches);
+
+        if had_matches && final_results.is_empty() {
+            return Ok(FileMatches {
+                matches: final_results,
+                exclusion: Some(Exclusion::MinMatches),
+                ..Default::default()
+            });
+        }
+    }
+
+    // Cited by what its first page says it is rather than by its file name
+    if !final_results.is_empty() && item.zotero_metadata.is_none() {
+        let from_file = !source_unavailable && !options.cached_only;
+        let fallback = guess_fallback_metadata(pdf_path, index, from_file, info_title.as_deref(), first_page.as_deref());
+        for m in &mut final_results {
+            m.item.fallback_metadata = fallback.clone();
+        }
+    }
+
+    Ok(FileMatches {
+        matches: final_results,
+        ..Default::default()
+    })
+}
+
+// The title, authors and year of a document outside Zotero, as guessed before if
+// the index has them for the file as it is. The file is laid out again only when
+// `from_file`; otherwise page 1's text is all there is to go on.
+fn guess_fallback_metadata(
+    pdf_path: &Path,
+    index: Option<&Index>,
+    from_file: bool,
+    info_title: Option<&str>,
+    first_page: Option<&str>,
+) -> Option<FallbackMetadata> {
+    let path = pdf_path.to_string_lossy();
+    let mtime = index::file_mtime(pdf_path);
+    if let (Some(index), Some(mtime)) = (index, mtime) {
+        if let Ok(Some(json)) = index.fallback_metadata(&path, mtime) {
+            return serde_json::from_str(&json).unwrap_or_default();
+        }
+    }
+
+    let guessed = from_file
+        .then(|| Document::load(pdf_path).ok())
+        .flatten()
+        .and_then(|doc| fallback_metadata::guess(&doc))
+        .or_else(|| fallback_metadata::guess_from_text(info_title, first_page?));
+    // Stored even when nothing was found, so the file isn't laid out again
+    if let (Some(index), Some(mtime), true) = (index, mtime, from_file) {
+        let stored = serde_json::to_string(&guessed)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| index.store_fallback_metadata(&path, mtime, &json));
+        if let Err(e) = stored {
+            eprintln!("Warning: Failed to cache the metadata guessed for {}: {}", path, e);
+        }
+    }
+    guessed
+}
+
+/// One file's extraction and matching, timed separately
+pub struct FileTiming {
+    pub pages: usize,
+    pub matches: usize,
+    pub extraction: Duration,
+    pub matching: Duration,
+    pub from_cache: bool,
+}
+
+/// Extract a file, from the PDF itself or through `index`'s text cache, and run
+/// every query over each page. Filter queries and result limits don't apply.
+pub fn time_file(pdf_path: &Path, params: &SearchParams, index: Option<&Index>) -> Result<FileTiming> {
+    let started = Instant::now();
+    let options = TextOptions {
+        ignore_permissions: params.ignore_extraction_permissions,
+        use_ocr: params.use_ocr,
+        ..Default::default()
+    };
+    let extracted = load_text(pdf_path, index, &options)?;
+    let extraction = started.elapsed();
+
+    let started = Instant::now();
+    let mut matches = 0;
+    for query_item in &params.queries {
+        for page in &extracted.pages {
+            matches += search_in_page(page, query_item, params, None)?.len();
+        }
+    }
+
+    Ok(FileTiming {
+        pages: extracted.pages.len(),
+        matches,
+        extraction,
+        matching: started.elapsed(),
+        from_cache: extracted.from_cache,
+    })
+}
+
+// Find where the other queries occur in a match's context, so co-occurring
+// terms show up in one snippet
+fn context_highlights(
+    page_match: &PageMatch,
+    queries: &[QueryItem],
+    own_query: &QueryItem,
+    normalization: &NormalizationOptions,
+) -> Result<Vec<ContextHighlight>> {
+    let mut highlights = Vec::new();
+
+    for query_item in queries {
+        if std::ptr::eq(query_item, own_query) {
+            continue;
+        }
+        let normalized_query = if query_item.raw_text {
+            query_item.query.clone()
+        } else {
+            normalize_text(&query_item.query, normalization)
+        };
+
+        for (context, text) in [("before", &page_match.context_before), ("after", &page_match.context_after)] {
+            let hits = match query_item.proximity() {
+                _ if query_item.is_boolean() => find_boolean_hits(text, query_item, normalization)?,
+                Some(max_gap) => find_ordered_words(text, &query_item.query, max_gap, normalization),
+                None => find_hits(text, &normalized_query, query_item, normalization.case_fold)?,
+            };
+            for Hit { start, end, .. } in hits {
+                highlights.push(ContextHighlight {
+                    query: query_item.query.clone(),
+                    color: query_item.color.clone(),
+                    context: context.to_string(),
+                    start: utf16_len(&text[..start]),
+                    end: utf16_len(&text[..end]),
+                });
+            }
+        }
+    }
+
+    Ok(highlights)
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.chars().map(char::len_utf16).sum()
+}
+
+pub fn search_pdfs(params: SearchParams, ctx: &SearchContext) -> Result<SearchOutcome> {
+    let directory = PathBuf::from(&params.directory);
+
+    if params.queries.is_empty() {
+        return Ok(SearchOutcome {
+            complete: true,
+            ..Default::default()
+        });
+    }
+
+    let (pdf_files, walk) = if params.source.as_deref() == Some("zotero") {
+        if !params.corpora.is_empty() {
+            return Err(AppError::invalid_input("Named corpora are folders, which a Zotero library search doesn't read").into());
+        }
+        let zotero_path = params
+            .zotero_path
+            .as_ref()
+            .ok_or_else(|| AppError::invalid_input("Searching the Zotero library requires a Zotero data directory"))?;
+        let pdf_files = zotero_library_files(Path::new(zotero_path), zotero_cache::skip_publications())
+            .map_err(zotero_error)?;
+        (pdf_files, WalkDiagnostics::default())
+    } else if !params.corpora.is_empty() {
+        // A folder in several corpora, or under another's folder, is read once
+        let mut pdf_files = Vec::new();
+        let mut seen = HashSet::new();
+        let mut walk = WalkDiagnostics::default();
+        for directory in params.corpora.iter().flat_map(|corpus| &corpus.directories) {
+            let (files, diagnostics) = directory_files(Path::new(directory), &params, ctx)?;
+            pdf_files.extend(files.into_iter().filter(|path| seen.insert(path.clone())));
+            walk.merge(diagnostics);
+        }
+        (pdf_files, walk)
+    } else {
+        directory_files(&directory, &params, ctx)?
+    };
+
+    let mut outcome = search_pdf_paths(&pdf_files, &params, ctx)?;
+    outcome.walk = walk;
+    Ok(outcome)
+}
+
+// The PDFs a search of `directory` reads: the index's cached text for archive
+// and search-as-you-type searches, a subset's files, or else the walked folder
+fn directory_files(directory: &Path, params: &SearchParams, ctx: &SearchContext) -> Result<(Vec<PathBuf>, WalkDiagnostics)> {
+    if params.source.as_deref() == Some("archive") {
+        let index = ctx
+            .index
+            .ok_or_else(|| AppError::invalid_input("Searching an archived corpus requires the search index"))?;
+        Ok((corpus::archived_files(index, directory)?, WalkDiagnostics::default()))
+    } else if let Some(ref name) = params.subset {
+        // The subset already names the files, so the directory isn't walked
+        let index = ctx.index.ok_or_else(|| AppError::invalid_input("Searching a corpus subset requires the search index"))?;
+        Ok((subset::files_under(index, name, directory)?, WalkDiagnostics::default()))
+    } else if params.latency_budget_ms.is_some() {
+        // Walking the directory alone can take longer than the budget
+        let index = ctx.index.ok_or_else(|| AppError::invalid_input("Searching as you type requires the search index"))?;
+        Ok((corpus::archived_files(index, directory)?, WalkDiagnostics::default()))
+    } else {
+        walk_pdfs(directory, &params.walk)
+    }
+}
+
+// Narrow the candidate files by modification time and by when the index first saw them
+fn apply_scope_filters(
+    mut pdf_files: Vec<PathBuf>,
+    params: &SearchParams,
+    index: Option<&Index>,
+    stats: &mut SearchStats,
+) -> Result<Vec<PathBuf>> {
+    // Files a search walks are registered too, in case the library was never listed or indexed
+    let first_seen = match index {
+        Some(index) => Some(index.register_files(&pdf_files)?),
+        None => None,
+    };
+
+    if let Some(days) = params.mtime_within_days {
+        let before = pdf_files.len();
+        pdf_files = index::modified_within(pdf_files, days);
+        stats.excluded_by_modified_date = before - pdf_files.len();
+    }
+
+    if let Some(days) = params.added_within_days {
+        let first_seen = first_seen
+            .ok_or_else(|| AppError::invalid_input("Filtering by date added requires the search index"))?;
+        let cutoff = index::unix_now().saturating_sub(days * 86_400);
+        let before = pdf_files.len();
+        pdf_files.retain(|path| first_seen.get(path).is_some_and(|seen| *seen >= cutoff));
+        stats.excluded_by_date_added = before - pdf_files.len();
+    }
+
+    if let Some(ref filter) = params.screening {
+        let index = index.ok_or_else(|| AppError::invalid_input("Filtering by screening status requires the search index"))?;
+        let before = pdf_files.len();
+        pdf_files = screening::filter_files(index, pdf_files, filter)?;
+        stats.excluded_by_screening = before - pdf_files.len();
+    }
+
+    if let Some(ref name) = params.subset {
+        let index = index.ok_or_else(|| AppError::invalid_input("Searching a corpus subset requires the search index"))?;
+        let before = pdf_files.len();
+        pdf_files = subset::filter_files(index, pdf_files, name)?;
+        stats.excluded_by_subset = before - pdf_files.len();
+    }
+
+    if !params.excluded_documents.is_empty() {
+        let excluded: HashSet<&Path> = params.excluded_documents.iter().map(|d| Path::new(&d.file_path)).collect();
+        let before = pdf_files.len();
+        pdf_files.retain(|path| !excluded.contains(path.as_path()));
+        stats.excluded_by_reviewer = before - pdf_files.len();
+    }
+
+    // The term index does this job for a latency-budget search, without leaving the process
+    if params.latency_budget_ms.is_some() {
+        let index = index.ok_or_else(|| AppError::invalid_input("Searching as you type requires the search index"))?;
+        pdf_files = cached_candidates(pdf_files, &params.queries, index)?;
+    } else if params.native_prefilter {
+        let scope = Some(Path::new(&params.directory)).filter(|dir| dir.is_dir());
+        // Without the platform index the search simply opens every file
+        match native_index::prefilter(pdf_files.clone(), &params.queries, scope) {
+            Ok(candidates) => {
+                stats.excluded_by_native_index = pdf_files.len() - candidates.len();
+                pdf_files = candidates;
+            }
+            Err(e) => eprintln!("Warning: Platform search index unavailable: {}", e),
+        }
+    }
+
+    Ok(pdf_files)
+}
+
+// Narrow `pdf_files` to those with cached text that the term index says may
+// match, as `native_index::prefilter` does with the platform's index: every
+// literal filter query's words must be present, and when all parallel queries
+// are literal, one of them must be. Each word only has to start a word of the
+// document, so that the word still being typed matches; a query word found
+// only inside a longer word, or broken across lines, is left to the full search.
+fn cached_candidates(pdf_files: Vec<PathBuf>, queries: &[QueryItem], index: &Index) -> Result<Vec<PathBuf>> {
+    // The term index splits a query into words as it split the text
+    let words = |query: &QueryItem| -> Option<Vec<String>> {
+        let literal = !query.use_regex && !query.boolean && query.query.chars().any(char::is_alphanumeric);
+        literal.then(|| vec![query.query.clone()])
+    };
+    let (filters, parallels): (Vec<&QueryItem>, Vec<&QueryItem>) =
+        queries.iter().partition(|q| q.query_type == "filter");
+
+    let mut required: Vec<HashSet<String>> = vec![index.cached_text_paths()?.into_iter().collect()];
+    for words in filters.into_iter().filter_map(words) {
+        required.push(index.documents_with_prefixes(&words)?);
+    }
+    let parallel_words: Option<Vec<Vec<String>>> = parallels.iter().map(|q| words(q)).collect();
+    if let Some(parallel_words) = parallel_words.filter(|words| !words.is_empty()) {
+        let mut any = HashSet::new();
+        for words in parallel_words {
+            any.extend(index.documents_with_prefixes(&words)?);
+        }
+        required.push(any);
+    }
+
+    Ok(pdf_files
+        .into_iter()
+        .filter(|path| {
+            let path = path.to_string_lossy();
+            required.iter().all(|files| files.contains(path.as_ref()))
+        })
+        .collect())
+}
+
+// Below each of these sizes files are read before the larger ones (in bytes)
+const SIZE_CLASSES: [u64; 3] = [1 << 20, 10 << 20, 50 << 20];
+
+// Keep the first copy by path of each set of identical files, and map it to
+// the paths of the copies left out
+fn collapse_copies(mut pdf_files: Vec<PathBuf>, stats: &mut SearchStats) -> (Vec<PathBuf>, HashMap<PathBuf, Vec<String>>) {
+    let mut copies = HashMap::new();
+    let mut left_out = HashSet::new();
+    for (_, mut paths) in duplicates::identical_files(&pdf_files) {
+        paths.sort();
+        let kept = paths.remove(0);
+        copies.insert(kept, paths.iter().map(|path| path.to_string_lossy().to_string()).collect());
+        left_out.extend(paths);
+    }
+    pdf_files.retain(|path| !left_out.contains(path));
+    stats.collapsed_copies = left_out.len();
+    (pdf_files, copies)
+}
+
+// Words of a query that could turn up in a file name or title
+fn name_words(queries: &[QueryItem]) -> Vec<String> {
+    const OPERATORS: &[&str] = &["and", "or", "not", "near"];
+    queries
+        .iter()
+        .filter(|q| !q.use_regex)
+        .flat_map(|q| q.query.split(|c: char| !c.is_alphanumeric()))
+        .map(str::to_lowercase)
+        .filter(|word| word.chars().count() >= 3 && !OPERATORS.contains(&word.as_str()))
+        .collect()
+}
+
+// The order to read files in so the first results come within seconds: text
+// already in the index, then files whose name or Zotero title has a query word,
+// then small files before large ones and, among similar sizes, recently modified
+// first. Results are still reported in the files' own order.
+fn processing_order(
+    pdf_files: &[PathBuf],
+    params: &SearchParams,
+    index: Option<&Index>,
+    zotero_map: Option<&ZoteroMap>,
+) -> Vec<usize> {
+    let cached: HashSet<String> = index
+        .and_then(|index| index.cached_text_paths().ok())
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+    let words = name_words(&params.queries);
+
+    let mut keys: Vec<_> = pdf_files
+        .iter()
+        .enumerate()
+        .map(|(file_index, path)| {
+            let size = path.metadata().map_or(0, |m| m.len());
+            let mtime = index::file_mtime(path).unwrap_or(0);
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            let title = zotero_map
+                .and_then(|map| map.get(path))
+                .and_then(|metadata| metadata.title.as_deref())
+                .unwrap_or_default();
+            let names = format!("{} {}", file_name, title).to_lowercase();
+            let name_hit = words.iter().any(|word| names.contains(word.as_str()));
+            let size_class = SIZE_CLASSES.iter().take_while(|&&limit| size >= limit).count();
+            let uncached = !cached.contains(path.to_string_lossy().as_ref());
+            ((uncached, !name_hit, size_class, std::cmp::Reverse(mtime)), file_index)
+        })
+        .collect();
+    keys.sort();
+    keys.into_iter().map(|(_, file_index)| file_index).collect()
+}
+
+/// Expand a list of dropped files and folders into the PDFs they contain
+pub fn expand_dropped_paths(paths: &[String], options: &WalkOptions) -> Result<(Vec<PathBuf>, WalkDiagnostics)> {
+    let mut pdf_files = Vec::new();
+    let mut diagnostics = WalkDiagnostics::default();
+
+    for path in paths.iter().map(PathBuf::from) {
+        if path.is_dir() {
+            let (found, walk) = walk_pdfs(&path, options)?;
+            pdf_files.extend(found);
+            diagnostics.merge(walk);
+        } else if path.is_file()
+            && path
+                .extension()
+                .and_then(|s| s.to_str())
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+        {
+            pdf_files.push(path);
+        }
+    }
+
+    // A file dropped alongside its own folder shouldn't be searched twice
+    pdf_files.sort();
+    pdf_files.dedup();
+
+    Ok((pdf_files, diagnostics))
+}
+
+/// Search an explicit list of PDFs in parallel with the given query set
+pub fn search_pdf_paths(
+    pdf_files: &[PathBuf],
+    params: &SearchParams,
+    ctx: &SearchContext,
+) -> Result<SearchOutcome> {
+    let cancel = ctx.cancel;
+    let deadline = params
+        .latency_budget_ms
+        .map(|budget| Instant::now() + Duration::from_millis(budget));
+
+    if params.queries.is_empty() {
+        return Ok(SearchOutcome {
+            complete: true,
+            ..Default::default()
+        });
+    }
+    // A malformed boolean query fails the search once, not once per file
+    for query_item in params.queries.iter().filter(|q| q.is_boolean()) {
+        parse_boolean_query(&query_item.query)?;
+    }
+    if pdf_files.is_empty() {
+        return Ok(SearchOutcome {
+            no_results: Some(explain_no_results(&SearchStats::default(), &[], params)),
+            complete: deadline.is_none(),
+            ..Default::default()
+        });
+    }
+
+    let mut stats = SearchStats {
+        files_found: pdf_files.len(),
+        ..Default::default()
+    };
+    let pdf_files = apply_scope_filters(pdf_files.to_vec(), params, ctx.index, &mut stats)?;
+    let (pdf_files, copies) = if params.collapse_duplicates {
+        collapse_copies(pdf_files, &mut stats)
+    } else {
+        (pdf_files, HashMap::new())
+    };
+
+    // Build Zotero map if path is provided
+    let zotero_map = if let Some(ref zotero_path) = params.zotero_path {
+        let path = PathBuf::from(zotero_path);
+        match zotero_cache::zotero_map(&path) {
+            Ok(map) => Some(map),
+            Err(e) => {
+                eprintln!("Warning: Failed to load Zotero database: {}", e);
+                None
+            }
+        }
+    } else if let Some(index) = ctx.index {
+        // Fall back to the snapshot that came with an imported corpus
+        corpus::zotero_snapshot_for(index, Path::new(&params.directory)).unwrap_or_else(|e| {
+            eprintln!("Warning: Failed to load Zotero snapshot: {}", e);
+            None
+        })
+    } else {
+        None
+    };
+
+    let zotero_fulltext = match params.zotero_path {
+        Some(ref zotero_path) if params.zotero_fulltext => ZoteroFulltext::load(Path::new(zotero_path))
+            .map_err(|e| eprintln!("Warning: Failed to read Zotero's full-text index: {}", e))
+            .ok(),
+        _ => None,
+    };
+
+    let budget = MemoryBudget::new(params.memory_limit_mb);
+    // Only a search with a memory limit can need somewhere to put results
+    let spill = match params.memory_limit_mb {
+        Some(_) => Some(ResultSpill::create()?),
+        None => None,
+    };
+    // A sample is drawn from all the matches at once, so sampled searches aren't streamed
+    let spool = match params.stream_threshold {
+        Some(_) if params.sample.is_none() => Some(ResultSpool::create()?),
+        _ => None,
+    };
+    let stream_threshold = params.stream_threshold.unwrap_or(usize::MAX);
+
+    let match_count = AtomicUsize::new(0);
+    let files_scanned = AtomicUsize::new(0);
+    let limit_reached = || {
+        params
+            .max_results
+            .is_some_and(|max| match_count.load(Ordering::Relaxed) >= max)
+    };
+
+    // Search all PDFs in parallel, applying all queries to each PDF. Threads take
+    // the next file in order of priority as they free up.
+    let order = processing_order(&pdf_files, params, ctx.index, zotero_map.as_ref());
+    let mut results: Vec<(usize, Result<FileMatches, FileError>)> = order
+        .into_iter()
+        .par_bridge()
+        .filter_map(|file_index| {
+            let pdf_path = &pdf_files[file_index];
+            // Skip remaining files once the search has been cancelled or is out of time
+            if cancel.load(Ordering::Relaxed) || limit_reached() || deadline.is_some_and(|d| Instant::now() >= d) {
+                return None;
+            }
+
+            let file_size = pdf_path.metadata().map_or(0, |m| m.len() as usize);
+            let reservation = budget.reserve(file_size);
+
+            // A panic on one malformed file must not take down the whole search
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                search_pdf_with_queries(
+                    pdf_path,
+                    params,
+                    zotero_map.as_ref(),
+                    ctx.index,
+                    zotero_fulltext.as_ref(),
+                    Some(cancel),
+                )
+            }));
+            drop(reservation);
+            // A file the cancel stopped partway is left out, like the files not yet started
+            if matches!(result, Ok(Err(ref e)) if e.is::<Cancelled>()) {
+                return None;
+            }
+
+            let file_path = pdf_path.to_string_lossy().to_string();
+            let mut progress_matches = Vec::new();
+            let result = match result {
+                Ok(Ok(mut file)) => {
+                    if let Some(other_copies) = copies.get(pdf_path) {
+                        for m in &mut file.matches {
+                            m.item.other_copies = other_copies.clone();
+                        }
+                    }
+                    let found = match_count.fetch_add(file.matches.len(), Ordering::Relaxed) + file.matches.len();
+                    if ctx.progress.is_some() {
+                        progress_matches = file.matches.clone();
+                    }
+                    if let Some(ref spool) = spool {
+                        if found > stream_threshold && !file.matches.is_empty() {
+                            match spool.append(file_index, &file.matches) {
+                                Ok(()) => {
+                                    file.streamed = Some(StreamedFile::new(&file.matches, &params.queries));
+                                    file.matches = Vec::new();
+                                }
+                                Err(e) => eprintln!("Warning: Failed to spool results: {}", e),
+                            }
+                        }
+                    }
+                    if let (Some(ref spill), None) = (&spill, &file.streamed) {
+                        if !budget.hold_results(&file.matches) {
+                            match spill.store(file_index, &file.matches) {
+                                Ok(()) => {
+                                    file.matches = Vec::new();
+                                    file.spilled = true;
+                                }
+                                // Keeping the matches in memory beats losing them
+                                Err(e) => eprintln!("Warning: Failed to spill results: {}", e),
+                            }
+                        }
+                    }
+                    Ok(file)
+                }
+                Ok(Err(e)) => Err(FileError {
+                    file_path: file_path.clone(),
+                    error: e.into(),
+                }),
+                Err(payload) => {
+                    let message = panic_message(payload.as_ref());
+                    problem_corpus::record(pdf_path, FailureCategory::Crashed, Some(message.clone()));
+                    Err(FileError {
+                        error: AppError::Internal {
+                            message,
+                            file: file_path.clone(),
+                        },
+                        file_path: file_path.clone(),
+                    })
+                }
+            };
+
+            if let Some(progress) = ctx.progress {
+                progress(SearchProgress {
+                    files_scanned: files_scanned.fetch_add(1, Ordering::Relaxed) + 1,
+                    total_files: pdf_files.len(),
+                    current_file: file_path,
+                    matches_so_far: match_count.load(Ordering::Relaxed),
+                    matches: progress_matches,
+                });
+            }
+            Some((file_index, result))
+        })
+        .collect();
+    results.sort_by_key(|(file_index, _)| *file_index);
+
+    // A cancelled search still returns what it found so far
+    let mut outcome = SearchOutcome {
+        partial: cancel.load(Ordering::Relaxed),
+        ..Default::default()
+    };
+    outcome.complete = !outcome.partial && deadline.is_none();
+    stats.files_searched = results.len();
+    // Once some files' matches are in the spool, the rest join them there
+    let streaming = results.iter().any(|(_, result)| result.as_ref().is_ok_and(|file| file.streamed.is_some()));
+    let mut streamed_files = Vec::new();
+    for (file_index, result) in results {
+        match result {
+            Ok(mut file) => {
+                if file.spilled {
+                    if let Some(ref spill) = spill {
+                        file.matches = spill.take(file_index)?;
+                    }
+                }
+                if let (true, Some(spool)) = (streaming, &spool) {
+                    if file.streamed.is_none() && !file.matches.is_empty() {
+                        spool.append(file_index, &file.matches)?;
+                        file.streamed = Some(StreamedFile::new(&file.matches, &params.queries));
+                        file.matches = Vec::new();
+                    }
+                }
+                let has_matches = !file.matches.is_empty() || file.streamed.as_ref().is_some_and(|s| s.match_count > 0);
+                match file.exclusion {
+                    Some(Exclusion::FilterQuery) => stats.excluded_by_filter_queries += 1,
+                    Some(Exclusion::MinMatches) => stats.excluded_by_min_matches += 1,
+                    None if has_matches => stats.files_with_matches += 1,
+                    None => {}
+                }
+                streamed_files.extend(file.streamed);
+                outcome.matches.extend(file.matches);
+            }
+            Err(file_error) => outcome.file_errors.push(file_error),
+        }
+    }
+    stats.files_with_errors = outcome.file_errors.len();
+    outcome.memory = budget.diagnostics(spill.as_ref().map_or(0, ResultSpill::count));
+
+    if let (true, Some(spool)) = (streaming, spool) {
+        stats.query_document_counts = merge_query_document_counts(&streamed_files, &params.queries);
+        outcome.stats = stats;
+        let mut documents: Vec<DocumentScore> = streamed_files.into_iter().flat_map(|file| file.documents).collect();
+        documents.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+        if params.sort_by.as_deref() == Some("score") {
+            documents.sort_by(|a, b| b.score.total_cmp(&a.score));
+        }
+        let order: Vec<String> = match params.sort_by.as_deref() {
+            Some("score") => documents.iter().map(|doc| doc.file_path.clone()).collect(),
+            _ => Vec::new(),
+        };
+        spool.seal(&order, params.max_results)?;
+        outcome.documents = documents;
+        outcome.streamed_matches = Some(spool.len()?);
+        outcome.spool = Some(spool);
+        return Ok(outcome);
+    }
+
+    stats.query_document_counts = query_document_counts(&outcome.matches, &params.queries);
+    outcome.stats = stats;
+
+    if let Some(max) = params.max_results {
+        outcome.matches.truncate(max);
+    }
+
+    if let Some(ref sample) = params.sample {
+        outcome.matches = sample_matches(std::mem::take(&mut outcome.matches), sample);
+    }
+
+    if outcome.matches.is_empty() {
+        outcome.no_results = Some(explain_no_results(&outcome.stats, &outcome.file_errors, params));
+    }
+
+    outcome.documents = score_documents(&outcome.matches, &params.queries);
+    if params.sort_by.as_deref() == Some("score") {
+        outcome.documents.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        // Keep matches in the same document order so the results list follows the ranking
+        let rank: HashMap<&str, usize> = outcome
+            .documents
+            .iter()
+            .enumerate()
+            .map(|(rank, doc)| (doc.file_path.as_str(), rank))
+            .collect();
+        let mut matches = std::mem::take(&mut outcome.matches);
+        matches.sort_by_key(|m| rank[m.item.file_path.as_str()]);
+        outcome.matches = matches;
+    }
 
-    let mut pages = Vec::new();
-    let page_count = doc.get_pages().len();
-    let mut successful_extractions = 0;
-    let mut total_chars = 0;
+    Ok(outcome)
+}
 
-    for page_num in 1..=page_count {
-        match doc.extract_text(&[page_num as u32]) {
-            Ok(text) => {
-                let char_count = text.len();
-                if char_count > 0 {
-                    successful_extractions += 1;
-                    total_chars += char_count;
-                }
-                pages.push((page_num, text));
-            }
-            Err(e) => {
-                eprintln!("Warning: Failed to extract text from page {} of {}: {}",
-                         page_num, pdf_path.display(), e);
-                pages.push((page_num, String::new()));
-            }
+// Why a search found nothing, from its counts, failed files and queries
+fn explain_no_results(stats: &SearchStats, file_errors: &[FileError], params: &SearchParams) -> NoResultsExplanation {
+    let mut explanation = NoResultsExplanation {
+        files_found: stats.files_found,
+        files_excluded: stats.excluded_by_modified_date
+            + stats.excluded_by_date_added
+            + stats.excluded_by_screening
+            + stats.excluded_by_subset
+            + stats.excluded_by_native_index
+            + stats.excluded_by_reviewer,
+        files_searched: stats.files_searched,
+        files_extracted: stats.files_searched.saturating_sub(file_errors.len()),
+        excluded_by_query_rules: stats.excluded_by_filter_queries + stats.excluded_by_min_matches,
+        ..Default::default()
+    };
+    for file_error in file_errors {
+        match file_error.error {
+            AppError::NoExtractableText { .. } => explanation.image_only += 1,
+            AppError::Encrypted { .. } | AppError::ExtractionNotPermitted { .. } => explanation.encrypted += 1,
+            _ => explanation.other_errors += 1,
         }
     }
+    explanation.query_notes = params
+        .queries
+        .iter()
+        .filter_map(|query_item| query_note(query_item, &params.normalization))
+        .collect();
+    explanation
+}
 
-    // Check if we extracted ANY text at all
-    if total_chars == 0 && page_count > 0 {
-        return Err(anyhow::anyhow!(
-            "Unable to extract text from this PDF. This may be due to: unsupported text encoding (common in Adobe PDFs), \
-             scanned images without OCR, or encryption. File: {}",
-            pdf_path.file_name().unwrap_or_default().to_string_lossy()
-        ));
+// Whitespace is stripped from the text as well as literal queries, so only other
+// changes are worth pointing out
+fn query_note(query_item: &QueryItem, normalization: &NormalizationOptions) -> Option<QueryNote> {
+    if query_item.raw_text || query_item.proximity().is_some() || query_item.is_boolean() {
+        return None;
     }
+    let query = &query_item.query;
+    let normalized = normalize_text(query, normalization);
 
-    eprintln!("Extracted text from {}/{} pages ({} total chars) in {}",
-             successful_extractions, page_count, total_chars,
-             pdf_path.file_name().unwrap_or_default().to_string_lossy());
+    let note = if normalized.trim().is_empty() {
+        i18n::message("note-removes-everything", &[])
+    } else if query_item.use_regex {
+        if normalization.strip_whitespace && query.contains("\\s") {
+            i18n::message("note-whitespace-regex", &[])
+        } else if normalized != *query {
+            i18n::message("note-rewritten-regex", &[])
+        } else {
+            return None;
+        }
+    } else {
+        let removed: Vec<String> = query
+            .chars()
+            .filter(|c| !c.is_whitespace() && normalization.normalized_len(*c) == 0)
+            .map(|c| format!("'{}'", c))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let unspaced: String = query.chars().filter(|c| !c.is_whitespace()).collect();
+        if !removed.is_empty() {
+            i18n::message("note-ignored-characters", &[("characters", removed.join(", ").into())])
+        } else if normalized != unspaced {
+            i18n::message("note-changed-characters", &[])
+        } else {
+            return None;
+        }
+    };
 
-    Ok(pages)
+    Some(QueryNote {
+        query: query.clone(),
+        normalized,
+        note,
+    })
 }
 
-fn split_into_words(text: &str) -> Vec<String> {
-    text.split_whitespace()
-        .map(|s| s.to_string())
+// Number of distinct documents each query matched in. Filter queries produce no
+// matches of their own, so they're only counted when they were searched as parallel.
+fn query_document_counts(matches: &[SearchMatch], queries: &[QueryItem]) -> Vec<QueryDocumentCount> {
+    queries
+        .iter()
+        .filter(|q| q.query_type != "filter" || matches.iter().any(|m| m.query.as_deref() == Some(q.query.as_str())))
+        .map(|query_item| {
+            let documents: HashSet<&str> = matches
+                .iter()
+                .filter(|m| m.query.as_deref() == Some(query_item.query.as_str()))
+                .map(|m| m.item.file_path.as_str())
+                .collect();
+            QueryDocumentCount {
+                query: query_item.query.clone(),
+                documents: documents.len(),
+            }
+        })
         .collect()
 }
 
-/// Normalize text for searching by removing whitespace and common separators
-/// This handles cases where PDFs don't have proper word spacing
-fn normalize_text(text: &str) -> String {
-    text.chars()
-        .filter_map(|c| {
-            match c {
-                // Remove all whitespace
-                ' ' | '\t' | '\n' | '\r' | '\u{00A0}' | '\u{2007}' | '\u{202F}' => None,
-                // Remove hyphens and soft hyphens
-                '-' | '\u{00AD}' | '\u{2010}' | '\u{2011}' => None,
-                // Keep everything else
-                _ => Some(c),
-            }
+// `query_document_counts` over the files whose matches were streamed, from each
+// file's own counts
+fn merge_query_document_counts(files: &[StreamedFile], queries: &[QueryItem]) -> Vec<QueryDocumentCount> {
+    queries
+        .iter()
+        .filter_map(|query_item| {
+            let documents: usize = files
+                .iter()
+                .flat_map(|file| &file.query_documents)
+                .filter(|count| count.query == query_item.query)
+                .map(|count| count.documents)
+                .sum();
+            (query_item.query_type != "filter" || documents > 0).then(|| QueryDocumentCount {
+                query: query_item.query.clone(),
+                documents,
+            })
         })
         .collect()
 }
 
-fn search_in_page(
-    page_text: &str,
-    query: &str,
-    context_words: usize,
-    use_regex: bool,
-) -> Result<Vec<(String, String, String)>> {
-    let mut matches = Vec::new();
+// Group matches by document and score each one as the sum of its hits' query weights
+fn score_documents(matches: &[SearchMatch], queries: &[QueryItem]) -> Vec<DocumentScore> {
+    let weight_of = |query: &Option<String>| {
+        queries
+            .iter()
+            .find(|q| Some(&q.query) == query.as_ref())
+            .map_or(default_weight(), |q| q.weight)
+    };
 
-    // Normalize both query and page text to handle PDFs with inconsistent spacing
-    let normalized_query = normalize_text(query);
-    let normalized_page = normalize_text(page_text);
+    let mut documents: BTreeMap<&str, DocumentScore> = BTreeMap::new();
+    for m in matches {
+        let doc = documents.entry(&m.item.file_path).or_insert_with(|| DocumentScore {
+            file_path: m.item.file_path.clone(),
+            file_name: m.item.file_name.clone(),
+            match_count: 0,
+            score: 0.0,
+        });
+        doc.match_count += 1;
+        doc.score += weight_of(&m.query);
+    }
 
-    if use_regex {
-        // Case-insensitive regex by default
-        let pattern = Regex::new(&format!("(?i){}", normalized_query))?;
+    documents.into_values().collect()
+}
 
-        for regex_match in pattern.find_iter(&normalized_page) {
-            let match_start = regex_match.start();
-            let match_end = regex_match.end();
-            let matched_text = regex_match.as_str().to_string();
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
 
-            let before_text = &normalized_page[..match_start];
-            let after_text = &normalized_page[match_end..];
+pub fn search_single_pdf(params: SearchParams) -> Result<Vec<SearchMatch>> {
+    // In this case, params.directory is actually the file path
+    let pdf_path = PathBuf::from(&params.directory);
 
-            let before_words: Vec<String> = split_into_words(before_text);
-            let after_words: Vec<String> = split_into_words(after_text);
+    if params.queries.is_empty() {
+        return Ok(Vec::new());
+    }
 
-            let context_before = before_words
-                .iter()
-                .rev()
-                .take(context_words)
-                .rev()
-                .cloned()
-                .collect::<Vec<_>>()
-                .join(" ");
-
-            let context_after = after_words
-                .iter()
-                .take(context_words)
-                .cloned()
-                .collect::<Vec<_>>()
-                .join(" ");
+    if !pdf_path.exists() || !pdf_path.is_file() {
+        return Err(AppError::NotFound {
+            file: pdf_path.to_string_lossy().to_string(),
+        }
+        .into());
+    }
 
-            matches.push((context_before, matched_text, context_after));
+    // Build Zotero map if path is provided
+    let zotero_map = if let Some(ref zotero_path) = params.zotero_path {
+        let path = PathBuf::from(zotero_path);
+        match zotero_cache::zotero_map(&path) {
+            Ok(map) => Some(map),
+            Err(e) => {
+                eprintln!("Warning: Failed to load Zotero database: {}", e);
+                None
+            }
         }
     } else {
-        // Case-insensitive search by default
-        let search_query = normalized_query.to_lowercase();
-
-        // Search the full normalized text instead of word-by-word
-        // This catches multi-word queries that span across "words" in the original text
-        let normalized_page_lower = normalized_page.to_lowercase();
-
-        let mut search_start = 0;
-        while let Some(match_pos) = normalized_page_lower[search_start..].find(&search_query) {
-            let absolute_pos = search_start + match_pos;
-            let match_end = absolute_pos + search_query.len();
+        None
+    };
 
-            // Extract matched text from normalized page
-            let matched_text = normalized_page[absolute_pos..match_end].to_string();
+    // Search the single PDF
+    Ok(search_pdf_with_queries(&pdf_path, &params, zotero_map.as_ref(), None, None, None)?.matches)
+}
 
-            // Get context from normalized text
-            let before_text = &normalized_page[..absolute_pos];
-            let after_text = &normalized_page[match_end..];
+// Hover previews should never hold up the library list
+const PREVIEW_TIMEOUT: Duration = Duration::from_secs(2);
+const PREVIEW_CONTEXT_WORDS: usize = 12;
 
-            let before_words: Vec<String> = split_into_words(before_text);
-            let after_words: Vec<String> = split_into_words(after_text);
+/// Search a single PDF for a quick preview, stopping at `limit` matches. Fails,
+/// and cancels the search, if it doesn't finish within a short timeout.
+pub fn preview_pdf_matches(file_path: &Path, queries: Vec<QueryItem>, limit: usize) -> Result<Vec<SearchMatch>> {
+    if queries.is_empty() || limit == 0 {
+        return Ok(Vec::new());
+    }
 
-            let context_before = before_words
-                .iter()
-                .rev()
-                .take(context_words)
-                .rev()
-                .cloned()
-                .collect::<Vec<_>>()
-                .join(" ");
-
-            let context_after = after_words
-                .iter()
-                .take(context_words)
-                .cloned()
-                .collect::<Vec<_>>()
-                .join(" ");
+    if !file_path.is_file() {
+        return Err(AppError::NotFound {
+            file: file_path.to_string_lossy().to_string(),
+        }
+        .into());
+    }
 
-            matches.push((context_before, matched_text, context_after));
+    let (sender, receiver) = mpsc::channel();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let worker_cancel = Arc::clone(&cancel);
+    let pdf_path = file_path.to_path_buf();
+    let params = SearchParams {
+        queries,
+        context_words: PREVIEW_CONTEXT_WORDS,
+        file_match_limit: Some(limit),
+        ..Default::default()
+    };
+    std::thread::spawn(move || {
+        let result = search_pdf_with_queries(&pdf_path, &params, None, None, None, Some(&worker_cancel))
+            .map(|file| file.matches);
+        let _ = sender.send(result);
+    });
 
-            // Move past this match to find the next one
-            search_start = match_end;
+    let mut matches = match receiver.recv_timeout(PREVIEW_TIMEOUT) {
+        Ok(result) => result?,
+        Err(_) => {
+            // Stops the worker at its next page
+            cancel.store(true, Ordering::Relaxed);
+            return Err(anyhow::anyhow!(
+                "Preview timed out after {} seconds: {}",
+                PREVIEW_TIMEOUT.as_secs(),
+                file_path.display()
+            ))
         }
-    }
+    };
 
+    matches.truncate(limit);
     Ok(matches)
 }
 
-fn search_pdf_with_queries(
-    pdf_path: &Path,
-    queries: &[QueryItem],
-    context_words: usize,
-    zotero_map: Option<&HashMap<String, ZoteroMetadata>>,
-    start_page: Option<usize>,
-    end_page: Option<usize>,
-) -> Result<Vec<SearchMatch>> {
-    let mut pages = extract_text_from_pdf(pdf_path)?;
-
-    // Filter pages by page range if specified
-    if let (Some(start), Some(end)) = (start_page, end_page) {
-        pages.retain(|(page_num, _)| *page_num >= start && *page_num <= end);
+/// Give each match an ID that stays valid after the search: the search ID, under
+/// which its results may also be saved, and the match's position in them
+pub fn assign_match_ids(search_id: u64, matches: &mut [SearchMatch]) {
+    for (position, m) in matches.iter_mut().enumerate() {
+        m.match_id = Some(match_id(search_id, position));
     }
+}
 
-    // Get filename and lookup Zotero metadata if available
-    let file_name = pdf_path
-        .file_name()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .to_string();
+/// The ID of the match at `position` in a search's results
+pub fn match_id(search_id: u64, position: usize) -> String {
+    format!("{}:{}", search_id, position)
+}
 
-    let (zotero_link, zotero_metadata) = zotero_map
-        .and_then(|map| map.get(&file_name))
-        .map(|metadata| (
-            Some(metadata.zotero_link.clone()),
-            Some(metadata.clone()),
-        ))
-        .unwrap_or((None, None));
+/// The search ID a match ID was assigned under
+pub fn match_search_id(match_id: &str) -> Result<u64> {
+    match_id
+        .split_once(':')
+        .and_then(|(search_id, _)| search_id.parse().ok())
+        .ok_or_else(|| AppError::invalid_input(format!("Invalid match ID: {}", match_id)).into())
+}
 
-    // Separate queries into parallel and filter types
-    let parallel_queries: Vec<&QueryItem> = queries.iter()
-        .filter(|q| q.query_type == "parallel")
-        .collect();
-    let filter_queries: Vec<&QueryItem> = queries.iter()
-        .filter(|q| q.query_type == "filter")
-        .collect();
+/// Re-derive a match's snippet with more words of context on either side, from
+/// the page's cached text where possible. `params` are the parameters of the
+/// search that produced the match.
+pub fn expand_context(
+    m: &SearchMatch,
+    params: &SearchParams,
+    extra_words_before: usize,
+    extra_words_after: usize,
+    index: Option<&Index>,
+) -> Result<SearchMatch> {
+    let query_item = params
+        .queries
+        .iter()
+        .find(|q| m.query.as_deref() == Some(q.query.as_str()))
+        .ok_or_else(|| AppError::invalid_input("The match's query isn't part of its search"))?;
+    let stale = || AppError::invalid_input(format!("The document changed since it was searched: {}", m.item.file_path));
 
-    // First, check if the PDF contains ALL filter queries (anywhere in the document)
-    // Filter queries act as document-level filters
-    for query_item in &filter_queries {
-        let mut found_in_pdf = false;
+    let options = TextOptions {
+        ignore_permissions: params.ignore_extraction_permissions,
+        use_ocr: params.use_ocr || m.ocr,
+        ..Default::default()
+    };
+    let extracted = load_text(Path::new(&m.item.file_path), index, &options)?;
+    let page = extracted
+        .pages
+        .iter()
+        .find(|page| page.page_number == m.page_number)
+        .ok_or_else(stale)?;
 
-        for (_page_num, page_text) in &pages {
-            let matches = search_in_page(page_text, &query_item.query, context_words, query_item.use_regex)?;
+    let words_before = params.context_words + extra_words_before;
+    let words_after = params.context_words + extra_words_after;
+    // One word more than needed on each side tells whether the budget cut the context short
+    let wider = SearchParams {
+        context_words: words_before.max(words_after) + 1,
+        sentence_context: false,
+        ..params.clone()
+    };
+    let mut page_match = search_in_page(page, query_item, &wider, None)?
+        .into_iter()
+        .find(|pm| Some(pm.offset) == m.offset)
+        .ok_or_else(stale)?;
 
-            if !matches.is_empty() {
-                found_in_pdf = true;
-                break;
-            }
+    let before: Vec<&str> = page_match.context_before.split_whitespace().collect();
+    let after: Vec<&str> = page_match.context_after.split_whitespace().collect();
+    let mut before_window = &before[before.len().saturating_sub(words_before)..];
+    let mut after_window = &after[..after.len().min(words_after)];
+    if params.sentence_context {
+        if before.len() > words_before {
+            before_window = whole_sentences_before(before_window);
         }
-
-        if !found_in_pdf {
-            // This PDF doesn't contain this filter query anywhere, so skip the entire PDF
-            return Ok(Vec::new());
+        if after.len() > words_after {
+            after_window = whole_sentences_after(after_window);
         }
     }
+    page_match.context_before = before_window.join(" ");
+    page_match.context_after = after_window.join(" ");
 
-    // If we get here, the PDF passes all filters
-    // Now collect matches from ALL parallel queries
-    let mut final_results = Vec::new();
-
-    // If there are no parallel queries, use the first query as parallel
-    let queries_to_search: Vec<&QueryItem> = if parallel_queries.is_empty() && !queries.is_empty() {
-        vec![&queries[0]]
-    } else {
-        parallel_queries
-    };
-
-    for query_item in queries_to_search {
-        for (page_num, page_text) in &pages {
-            let matches = search_in_page(page_text, &query_item.query, context_words, query_item.use_regex)?;
-
-            for (context_before, matched_text, context_after) in matches {
-                final_results.push(SearchMatch {
-                    file_path: pdf_path.to_string_lossy().to_string(),
-                    file_name: file_name.clone(),
-                    page_number: *page_num,
-                    context_before,
-                    matched_text,
-                    context_after,
-                    zotero_link: zotero_link.clone(),
-                    zotero_metadata: zotero_metadata.clone(),
-                });
-            }
-        }
-    }
+    let highlights = context_highlights(&page_match, &params.queries, query_item, &params.normalization)?;
+    Ok(SearchMatch {
+        context_before: page_match.context_before,
+        context_after: page_match.context_after,
+        highlights,
+        ..m.clone()
+    })
+}
 
-    Ok(final_results)
+/// A match's neighbours among the hits in the same document, in reading order
+#[derive(Debug, Serialize)]
+pub struct MatchNeighbors {
+    pub previous: Option<SearchMatch>,
+    pub next: Option<SearchMatch>,
+    // 1-based position of the match among the document's hits
+    pub position: usize,
+    pub total: usize,
 }
 
-pub fn search_pdfs(params: SearchParams) -> Result<Vec<SearchMatch>> {
-    let directory = PathBuf::from(&params.directory);
+/// Find the hits before and after `match_id` in its document. Matches are ordered
+/// by page, then by where they start in the page text; source spans put every
+/// query's hits in the same coordinates, while normalized offsets may not.
+pub fn match_neighbors(results: Vec<SearchMatch>, match_id: &str) -> Result<MatchNeighbors> {
+    let file_path = results
+        .iter()
+        .find(|m| m.match_id.as_deref() == Some(match_id))
+        .map(|m| m.item.file_path.clone())
+        .ok_or_else(|| AppError::invalid_input(format!("No match with ID {}", match_id)))?;
 
-    if params.queries.is_empty() {
-        return Ok(Vec::new());
-    }
+    let mut in_document: Vec<SearchMatch> = results.into_iter().filter(|m| m.item.file_path == file_path).collect();
+    in_document.sort_by_key(|m| {
+        let start = m.source_span.as_ref().map(|span| span.start).or(m.offset).unwrap_or(0);
+        (m.page_number, start)
+    });
 
-    let pdf_files = find_pdf_files(&directory)?;
+    let index = in_document
+        .iter()
+        .position(|m| m.match_id.as_deref() == Some(match_id))
+        .unwrap_or_default();
+    Ok(MatchNeighbors {
+        previous: index.checked_sub(1).map(|i| in_document[i].clone()),
+        next: in_document.get(index + 1).cloned(),
+        position: index + 1,
+        total: in_document.len(),
+    })
+}
 
-    if pdf_files.is_empty() {
-        return Ok(Vec::new());
-    }
+/// Hits per page of one document, for a scrollbar heatmap of where matches concentrate
+#[derive(Debug, Serialize)]
+pub struct PageHitCounts {
+    // counts[i] is the number of hits on page i + 1
+    pub counts: Vec<u32>,
+    pub max: u32,
+}
 
-    // Build Zotero map if path is provided
-    let zotero_map = if let Some(ref zotero_path) = params.zotero_path {
-        let path = PathBuf::from(zotero_path);
-        match build_zotero_map(&path) {
-            Ok(map) => Some(map),
-            Err(e) => {
-                eprintln!("Warning: Failed to load Zotero database: {}", e);
-                None
-            }
-        }
-    } else {
-        None
+/// Count every query's hits on each page of a PDF, using the index's text cache when available
+pub fn page_hit_counts(
+    file_path: &Path,
+    queries: Vec<QueryItem>,
+    normalization: NormalizationOptions,
+    index: Option<&Index>,
+) -> Result<PageHitCounts> {
+    let extracted = load_text(file_path, index, &TextOptions::default())?;
+    let params = SearchParams {
+        queries,
+        normalization,
+        ..Default::default()
     };
 
-    // Search all PDFs in parallel, applying all queries to each PDF
-    let all_matches: Vec<SearchMatch> = pdf_files
-        .par_iter()
-        .filter_map(|pdf_path| {
-            match search_pdf_with_queries(
-                pdf_path,
-                &params.queries,
-                params.context_words,
-                zotero_map.as_ref(),
-                params.start_page,
-                params.end_page,
-            ) {
-                Ok(matches) => Some(matches),
-                Err(_) => None,
-            }
-        })
-        .flatten()
-        .collect();
+    let page_count = extracted.pages.iter().map(|page| page.page_number).max().unwrap_or(0);
+    let mut counts = vec![0u32; page_count];
+    for page in &extracted.pages {
+        for query_item in &params.queries {
+            counts[page.page_number - 1] += search_in_page(page, query_item, &params, None)?.len() as u32;
+        }
+    }
 
-    Ok(all_matches)
+    let max = counts.iter().copied().max().unwrap_or(0);
+    Ok(PageHitCounts { counts, max })
 }
 
-pub fn search_single_pdf(params: SearchParams) -> Result<Vec<SearchMatch>> {
-    // In this case, params.directory is actually the file path
-    let pdf_path = PathBuf::from(&params.directory);
+/// A text selection from the viewer, located in the document and cited
+#[derive(Debug, Serialize)]
+pub struct CitedSelection {
+    pub file_path: String,
+    pub page_number: usize,
+    pub page_label: Option<String>,
+    // Where the selection lies in the page's text, in the same terms as a match's
+    pub source_span: SourceSpan,
+    pub zotero_metadata: Option<ZoteroMetadata>,
+    // "Authors (Year). Title, p. 12"
+    pub citation: String,
+    // The selected text in quotes, followed by the citation
+    pub quotation: String,
+}
 
-    if params.queries.is_empty() {
-        return Ok(Vec::new());
+/// Find a selection made in the viewer in the PDF's extracted text and cite it.
+/// The viewer's text layer breaks lines and hyphenates differently from our
+/// extraction, so both sides are compared without whitespace, hyphens, ligatures
+/// or accents. `page_hint` is tried first; the selection can be on any page.
+pub fn cite_selection(
+    file_path: &Path,
+    selection: &str,
+    page_hint: Option<usize>,
+    zotero_map: Option<&ZoteroMap>,
+    index: Option<&Index>,
+) -> Result<CitedSelection> {
+    let options = NormalizationOptions {
+        strip_whitespace: true,
+        strip_hyphens: true,
+        expand_ligatures: true,
+        case_fold: false,
+        strip_diacritics: true,
+    };
+    let wanted = NormalizedText::new(selection, &options).text;
+    if wanted.is_empty() {
+        return Err(AppError::invalid_input("The selection has no text to cite").into());
     }
 
-    if !pdf_path.exists() || !pdf_path.is_file() {
-        return Err(anyhow::anyhow!("File does not exist or is not a file: {:?}", pdf_path));
+    let extracted = load_text(file_path, index, &TextOptions::default())?;
+    let mut pages: Vec<&PageText> = extracted.pages.iter().collect();
+    if let Some(hint) = page_hint {
+        pages.sort_by_key(|page| page.page_number != hint);
     }
 
-    // Build Zotero map if path is provided
-    let zotero_map = if let Some(ref zotero_path) = params.zotero_path {
-        let path = PathBuf::from(zotero_path);
-        match build_zotero_map(&path) {
-            Ok(map) => Some(map),
-            Err(e) => {
-                eprintln!("Warning: Failed to load Zotero database: {}", e);
-                None
-            }
-        }
-    } else {
-        None
+    let found = pages.into_iter().find_map(|page| {
+        let normalized = NormalizedText::new(&page.text, &options);
+        let start = normalized.text.find(&wanted)?;
+        let (start, end) = normalized.source_range(start, start + wanted.len());
+        Some((page, source_span(&page.text, start, end)))
+    });
+    let Some((page, span)) = found else {
+        return Err(AppError::invalid_input(format!(
+            "The selection wasn't found in the text of {}",
+            file_path.display()
+        ))
+        .into());
     };
 
-    // Search the single PDF
-    search_pdf_with_queries(
-        &pdf_path,
-        &params.queries,
-        params.context_words,
-        zotero_map.as_ref(),
-        params.start_page,
-        params.end_page,
-    )
+    let zotero_metadata =
+        zotero_map.and_then(|map| lookup_zotero_metadata(map, file_path, &extracted.title_candidates()));
+    let mut item = LibraryItem::new(file_path, ItemSource::File).with_zotero(zotero_metadata.clone());
+    if zotero_metadata.is_none() {
+        let first_page = extracted.pages.first().filter(|page| page.page_number == 1);
+        item.fallback_metadata = guess_fallback_metadata(
+            file_path,
+            index,
+            !extracted.source_unavailable,
+            extracted.title.as_deref(),
+            first_page.map(|page| page.text.as_str()),
+        );
+    }
+    let page_cited = page.label.clone().unwrap_or_else(|| page.page_number.to_string());
+    let citation = format!("{}, p. {}", export::item_heading(&item), page_cited);
+    let quoted = span.text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    Ok(CitedSelection {
+        file_path: file_path.to_string_lossy().to_string(),
+        page_number: page.page_number,
+        page_label: page.label.clone(),
+        source_span: span,
+        zotero_metadata,
+        quotation: format!("\"{}\" ({})", quoted, citation),
+        citation,
+    })
 }
 
-pub fn list_pdfs(params: ListPdfsParams) -> Result<Vec<PdfListItem>> {
+pub fn list_pdfs(params: ListPdfsParams, index: Option<&Index>) -> Result<Vec<LibraryItem>> {
     let directory = PathBuf::from(&params.directory);
 
     let pdf_files = find_pdf_files(&directory)?;
@@ -627,10 +4087,17 @@ pub fn list_pdfs(params: ListPdfsParams) -> Result<Vec<PdfListItem>> {
         return Ok(Vec::new());
     }
 
+    // A file listed in the library has been seen, whether or not a search has found it yet
+    if let Some(index) = index {
+        if let Err(e) = index.register_files(&pdf_files) {
+            eprintln!("Warning: Failed to record the listed files in the index: {}", e);
+        }
+    }
+
     // Build Zotero map if path is provided
     let zotero_map = if let Some(ref zotero_path) = params.zotero_path {
         let path = PathBuf::from(zotero_path);
-        match build_zotero_map(&path) {
+        match zotero_cache::zotero_map(&path) {
             Ok(map) => Some(map),
             Err(e) => {
                 eprintln!("Warning: Failed to load Zotero database: {}", e);
@@ -642,25 +4109,12 @@ pub fn list_pdfs(params: ListPdfsParams) -> Result<Vec<PdfListItem>> {
     };
 
     // Create list items with metadata
-    let mut items: Vec<PdfListItem> = pdf_files
+    let mut items: Vec<LibraryItem> = pdf_files
         .iter()
         .map(|pdf_path| {
-            let file_name = pdf_path
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string();
-
-            let zotero_metadata = zotero_map
-                .as_ref()
-                .and_then(|map| map.get(&file_name))
-                .cloned();
-
-            PdfListItem {
-                file_path: pdf_path.to_string_lossy().to_string(),
-                file_name,
-                zotero_metadata,
-            }
+            let item = LibraryItem::new(pdf_path, ItemSource::File);
+            let zotero_metadata = zotero_map.as_ref().and_then(|map| map.get(pdf_path)).cloned();
+            item.with_zotero(zotero_metadata)
         })
         .collect();
 
@@ -710,33 +4164,451 @@ pub fn list_pdfs(params: ListPdfsParams) -> Result<Vec<PdfListItem>> {
     }
 
     // Sort alphabetically by filename
-    items.sort_by(|a, b| a.file_name.to_lowercase().cmp(&b.file_name.to_lowercase()));
+    items.sort_by_key(|item| item.file_name.to_lowercase());
 
     Ok(items)
 }
 
-pub fn export_to_markdown(matches: &[SearchMatch]) -> String {
-    let mut markdown = String::from("# PDF Search Results\n\n");
-    markdown.push_str(&format!("Total matches found: {}\n\n", matches.len()));
+/// Compare the PDFs in a directory against the Zotero library so users can see
+/// which files aren't linked and which library attachments aren't on disk
+pub fn build_zotero_link_report(params: ZoteroLinkReportParams) -> Result<ZoteroLinkReport> {
+    let directory = PathBuf::from(&params.directory);
+    let pdf_files = find_pdf_files(&directory)?;
+    let zotero_map = zotero_cache::zotero_map(Path::new(&params.zotero_path)).map_err(zotero_error)?;
+
+    let mut files_on_disk = std::collections::HashSet::new();
+    let mut unmatched_files = Vec::new();
+
+    for pdf_path in &pdf_files {
+        let file_name = pdf_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        if !zotero_map.contains(pdf_path) {
+            unmatched_files.push(pdf_path.to_string_lossy().to_string());
+        }
+        files_on_disk.insert(file_name);
+    }
+
+    // Only PDF attachments are expected to show up in the searched directory
+    let mut missing_attachments: Vec<MissingAttachment> = zotero_map
+        .iter()
+        .map(|(path, metadata)| (attachment_file_name(path), metadata))
+        .filter(|(file_name, _)| file_name.to_lowercase().ends_with(".pdf"))
+        .filter(|(file_name, _)| !files_on_disk.contains(*file_name))
+        .map(|(file_name, metadata)| MissingAttachment {
+            file_name: file_name.to_string(),
+            attachment_key: metadata.pdf_attachment_key.clone(),
+            citekey: metadata.citekey.clone(),
+            title: metadata.title.clone(),
+        })
+        .collect();
+
+    unmatched_files.sort_by_key(|path| path.to_lowercase());
+    missing_attachments.sort_by_key(|a| a.file_name.to_lowercase());
+
+    Ok(ZoteroLinkReport {
+        files_scanned: pdf_files.len(),
+        linked_files: pdf_files.len() - unmatched_files.len(),
+        unmatched_files,
+        missing_attachments,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boolean(query: &str) -> QueryItem {
+        QueryItem {
+            boolean: true,
+            ..QueryItem::plain(query.to_string())
+        }
+    }
+
+    fn term(word: &str) -> BooleanQuery {
+        BooleanQuery::Term(word.to_string())
+    }
+
+    // Start and end of each hit that makes the query hold, None when it doesn't
+    fn evaluate(text: &str, query: &str) -> Option<Vec<(usize, usize)>> {
+        let page = BooleanPage::new(text, &boolean(query), &NormalizationOptions::default());
+        let hits = page.evaluate(&parse_boolean_query(query).unwrap())?;
+        Some(hits.into_iter().map(|hit| (hit.start, hit.end)).collect())
+    }
+
+    fn boolean_hits(text: &str, query: &str) -> Vec<(usize, usize)> {
+        find_boolean_hits(text, &boolean(query), &NormalizationOptions::default())
+            .unwrap()
+            .into_iter()
+            .map(|hit| (hit.start, hit.end))
+            .collect()
+    }
+
+    #[test]
+    fn boolean_precedence() {
+        assert_eq!(
+            parse_boolean_query("a OR b AND c").unwrap(),
+            BooleanQuery::Or(vec![term("a"), BooleanQuery::And(vec![term("b"), term("c")])])
+        );
+        assert_eq!(
+            parse_boolean_query("a b OR c").unwrap(),
+            BooleanQuery::Or(vec![BooleanQuery::And(vec![term("a"), term("b")]), term("c")])
+        );
+        assert_eq!(
+            parse_boolean_query("NOT a b").unwrap(),
+            BooleanQuery::And(vec![BooleanQuery::Not(Box::new(term("a"))), term("b")])
+        );
+        assert_eq!(
+            parse_boolean_query("a NEAR/3 b OR c").unwrap(),
+            BooleanQuery::Or(vec![BooleanQuery::Near(Box::new(term("a")), Box::new(term("b")), 3), term("c")])
+        );
+        assert_eq!(
+            parse_boolean_query("(a OR b) \"c d\"").unwrap(),
+            BooleanQuery::And(vec![BooleanQuery::Or(vec![term("a"), term("b")]), term("c d")])
+        );
+        // Lower case operators are words
+        assert_eq!(
+            parse_boolean_query("cats and dogs").unwrap(),
+            BooleanQuery::And(vec![term("cats"), term("and"), term("dogs")])
+        );
+    }
+
+    #[test]
+    fn boolean_syntax_errors() {
+        for query in [
+            "",
+            "\"unclosed",
+            "a \"b",
+            "\"\"",
+            "(a OR b",
+            "((a)",
+            "a)",
+            "()",
+            "a AND",
+            "OR a",
+            "NOT",
+            "NEAR a",
+            "a NEAR b",
+            "a NEAR/x b",
+            "a NEAR/2",
+            "NEAR/2 b",
+        ] {
+            assert!(parse_boolean_query(query).is_err(), "{:?} should be rejected", query);
+        }
+    }
+
+    #[test]
+    fn boolean_not() {
+        assert_eq!(evaluate("cats and mice", "NOT dogs"), Some(Vec::new()));
+        assert_eq!(evaluate("cats and dogs", "NOT dogs"), None);
+        assert_eq!(evaluate("cats and mice", "cats AND NOT dogs"), Some(vec![(0, 4)]));
+        assert_eq!(evaluate("cats and dogs", "cats AND NOT dogs"), None);
+        assert_eq!(evaluate("mice and dogs", "cats AND NOT dogs"), None);
+    }
+
+    #[test]
+    fn boolean_near_boundaries() {
+        let text = "alpha one two beta";
+        assert!(evaluate(text, "alpha NEAR/2 beta").is_some());
+        assert!(evaluate(text, "alpha NEAR/1 beta").is_none());
+        // Either order
+        assert!(evaluate(text, "beta NEAR/2 alpha").is_some());
+        assert!(evaluate(text, "beta NEAR/1 alpha").is_none());
+        // Adjacent words have none between them
+        assert!(evaluate("alpha beta", "alpha NEAR/0 beta").is_some());
+        assert!(evaluate("alpha x beta", "alpha NEAR/0 beta").is_none());
+        // A hit spans both sides
+        assert_eq!(boolean_hits(text, "alpha NEAR/2 beta"), vec![(0, 18)]);
+    }
+
+    #[test]
+    fn boolean_hit_offsets_after_normalization() {
+        // Hyphens and line breaks are normalized away, and hits map back to the source
+        let text = "The data-\nbase grew";
+        assert_eq!(boolean_hits(text, "database"), vec![(4, 14)]);
+        assert_eq!(&text[4..14], "data-\nbase");
+
+        // Case folding across multi-byte chars
+        let text = "Die Überschrift über alles";
+        assert_eq!(boolean_hits(text, "ÜBER"), vec![(4, 9), (17, 22)]);
+        assert_eq!(&text[17..22], "über");
+
+        // Hits from both sides of an OR, in order and without repeats
+        assert_eq!(boolean_hits("b a b", "b OR a OR b"), vec![(0, 1), (2, 3), (4, 5)]);
+    }
+
+    fn page(text: &str) -> PageText {
+        PageText {
+            page_number: 1,
+            label: None,
+            text: text.to_string(),
+            blocks: None,
+            section: None,
+            ocr: false,
+            glyphs: None,
+            geometry: None,
+        }
+    }
+
+    fn regex(pattern: &str) -> QueryItem {
+        QueryItem {
+            use_regex: true,
+            ..QueryItem::plain(pattern.to_string())
+        }
+    }
+
+    fn with_ligatures() -> NormalizationOptions {
+        NormalizationOptions {
+            expand_ligatures: true,
+            ..NormalizationOptions::default()
+        }
+    }
+
+    // Whether the query matches the text, and whether the scan let the page through
+    fn match_and_prefilter(text: &str, query_item: &QueryItem, normalization: &NormalizationOptions) -> (bool, bool) {
+        let params = SearchParams {
+            normalization: normalization.clone(),
+            ..SearchParams::default()
+        };
+        let matched = !search_in_page(&page(text), query_item, &params, None).unwrap().is_empty();
+        let passed = Prefilter::for_query(query_item, normalization).holds(&scan_text(text));
+        (matched, passed)
+    }
+
+    #[test]
+    fn prefilter_passes_every_page_that_matches() {
+        let plain = NormalizationOptions::default();
+        let near = |query: &str, gap| QueryItem {
+            max_word_gap: Some(gap),
+            ..QueryItem::plain(query.to_string())
+        };
+        let case_sensitive = |pattern: &str| QueryItem {
+            regex_flags: RegexFlags {
+                case_insensitive: Some(false),
+                ..RegexFlags::default()
+            },
+            ..regex(pattern)
+        };
+        let cases = [
+            // Regex literals with escapes
+            ("see e.g. chapter 2", regex(r"e\.g\.\s*chapter"), &plain),
+            ("costs $40 today", regex(r"\$\d+"), &plain),
+            ("a (parenthetical) remark", regex(r"\(paren\w+\)"), &plain),
+            ("tabs\tand\nlines", regex(r"and\\?lines"), &plain),
+            // Case-insensitive patterns, whatever the flags say
+            ("Some DATA here", regex("(?i)data"), &plain),
+            ("Some DATA here", case_sensitive("(?i)data"), &plain),
+            ("Some data here", case_sensitive("(?i:DA)ta"), &plain),
+            ("Some DATA here", regex("DATA|nothing"), &plain),
+            // Ligatures, in the text or the query
+            ("the ﬁnal word", QueryItem::plain("final".to_string()), &with_ligatures()),
+            ("the final word", QueryItem::plain("ﬁnal".to_string()), &with_ligatures()),
+            ("the ﬁnal word", QueryItem::plain("ﬁnal".to_string()), &plain),
+            ("the eﬄuent", regex("effl?uent"), &with_ligatures()),
+            // Words broken by hyphens or split by whitespace
+            ("the data-\nbase grew", QueryItem::plain("database".to_string()), &plain),
+            ("the database grew", QueryItem::plain("data-base".to_string()), &plain),
+            ("the da ta base grew", QueryItem::plain("database".to_string()), &plain),
+            ("the data base grew", regex("database"), &plain),
+            ("the data-base grew", regex(r"data\w*base"), &plain),
+            ("long-standing, well known results", near("longstanding results", 2), &plain),
+            ("well-known results", boolean("known AND results"), &plain),
+            // Case folding beyond ASCII
+            ("İstanbul", QueryItem::plain("İSTANBUL".to_string()), &plain),
+            ("ISTANBUL", QueryItem::plain("istanbul".to_string()), &plain),
+            ("\u{212A}elvin", QueryItem::plain("kelvin".to_string()), &plain),
+            ("\u{212A}ELVIN", regex("(?i)kelvin"), &plain),
+            ("kelvin", regex("(?i)\u{212A}ELVIN"), &plain),
+            ("ſtraße", regex("(?i)STRASSE|straße"), &plain),
+            ("ΟΔΟΣ", QueryItem::plain("οδος".to_string()), &plain),
+            ("Ünïcödé", QueryItem::plain("ÜNÏCÖDÉ".to_string()), &plain),
+        ];
+        for (text, query_item, normalization) in cases {
+            let (matched, passed) = match_and_prefilter(text, &query_item, normalization);
+            assert!(matched, "{:?} should match {:?}", query_item.query, text);
+            assert!(passed, "the prefilter for {:?} rejected {:?}", query_item.query, text);
+        }
+    }
+
+    #[test]
+    fn prefilter_rejects_pages_without_the_literals() {
+        let plain = NormalizationOptions::default();
+        let cases = [
+            ("nothing here", regex(r"e\.g\."), &plain),
+            ("costs 40 today", regex(r"\$\d+"), &plain),
+            ("the file", QueryItem::plain("final".to_string()), &with_ligatures()),
+            ("data only", boolean("data AND base"), &plain),
+            ("Kelvin", QueryItem::plain("celsius".to_string()), &plain),
+        ];
+        for (text, query_item, normalization) in cases {
+            let (matched, passed) = match_and_prefilter(text, &query_item, normalization);
+            assert!(!matched && !passed, "the prefilter for {:?} let {:?} through", query_item.query, text);
+        }
+    }
+
+    // Every byte of the folded or normalized text maps to a whole source char
+    fn assert_char_boundaries(source: &str, source_ranges: &[(usize, usize)]) {
+        for &(start, end) in source_ranges {
+            assert!(start < end && source.is_char_boundary(start) && source.is_char_boundary(end));
+        }
+    }
 
-    let mut current_file = String::new();
+    #[test]
+    fn case_folded_offsets_cover_whole_chars() {
+        // "İ" lowercases to "i" and a combining dot, three bytes from two
+        let folded = CaseFolded::new("İx");
+        assert_eq!(folded.text, "i\u{307}x");
+        assert_eq!(folded.source_ranges, vec![(0, 2), (0, 2), (0, 2), (2, 3)]);
+        assert_eq!(folded.source_range(0, 1), (0, 2));
+        assert_eq!(folded.source_range(3, 4), (2, 3));
 
-    for (idx, m) in matches.iter().enumerate() {
-        if m.file_path != current_file {
-            current_file = m.file_path.clone();
-            markdown.push_str(&format!("\n## File: `{}`\n", m.file_path));
-            markdown.push_str(&format!("**Filename:** {}\n\n", m.file_name));
+        for source in ["İstanbul İZMİR", "ȺȺa", "ẞtraße", "\u{212A}elvin", "ΟΔΟΣ", "ǅemal"] {
+            let folded = CaseFolded::new(source);
+            assert_eq!(folded.source_ranges.len(), folded.text.len());
+            assert_char_boundaries(source, &folded.source_ranges);
         }
+    }
+
+    #[test]
+    fn literal_hits_map_back_through_length_changing_case() {
+        // "Ⱥ" is two bytes and its lower case three
+        let text = "ȺȺa";
+        let hits = find_literal(text, "ⱥa", true);
+        assert_eq!(hits, vec![2..5]);
+        assert_eq!(&text[2..5], "Ⱥa");
+
+        let text = "xİSTANBUL";
+        let hits = find_literal(text, "İstanbul", true);
+        assert_eq!(hits, vec![1..10]);
+        assert_eq!(&text[1..10], "İSTANBUL");
+
+        // A plain "i" only matches the first byte of "İ"'s folding, and still covers all of it
+        let text = "İx";
+        assert_eq!(find_literal(text, "i", true), vec![0..2]);
+    }
+
+    #[test]
+    fn normalized_offsets_cover_whole_chars() {
+        let options = NormalizationOptions {
+            expand_ligatures: true,
+            strip_diacritics: true,
+            ..NormalizationOptions::default()
+        };
+        let source = "eﬃcient café, co-op İ";
+        let normalized = NormalizedText::new(source, &options);
+        assert_eq!(normalized.text, "efficientcafe,coopI");
+        assert_eq!(normalized.source_ranges.len(), normalized.text.len());
+        assert_char_boundaries(source, &normalized.source_ranges);
+
+        // Part of a ligature is the whole ligature
+        assert_eq!(normalized.source_range(2, 4), (1, 4));
+        // The accent-less letter is the accented one
+        let e = normalized.text.find("cafe").unwrap() + 3;
+        let (start, end) = normalized.source_range(e, e + 1);
+        assert_eq!(&source[start..end], "é");
+        // A stripped hyphen is inside the range of the letters around it
+        let oo = normalized.text.find("oo").unwrap();
+        let (start, end) = normalized.source_range(oo, oo + 2);
+        assert_eq!(&source[start..end], "o-o");
+    }
+
+    #[test]
+    fn context_highlights_stay_on_char_boundaries() {
+        let page_match = PageMatch {
+            context_before: "İstanbul and ȺȺa".to_string(),
+            matched_text: "x".to_string(),
+            context_after: "\u{212A}elvin İ".to_string(),
+            offset: 0,
+            captures: BTreeMap::new(),
+            source_span: SourceSpan {
+                start: 0,
+                end: 1,
+                text: "x".to_string(),
+            },
+            source_range: 0..1,
+        };
+        let queries = vec![
+            QueryItem::plain("x".to_string()),
+            QueryItem::plain("i".to_string()),
+            QueryItem::plain("ⱥa".to_string()),
+            QueryItem::plain("kelvin".to_string()),
+        ];
+        let highlights = context_highlights(&page_match, &queries, &queries[0], &NormalizationOptions::default()).unwrap();
+        let spans: Vec<(&str, &str, usize, usize)> = highlights
+            .iter()
+            .map(|h| (h.query.as_str(), h.context.as_str(), h.start, h.end))
+            .collect();
+        // Offsets are UTF-16 units within the context
+        assert_eq!(
+            spans,
+            vec![
+                ("i", "before", 0, 1),
+                ("i", "after", 4, 5),
+                ("i", "after", 7, 8),
+                ("ⱥa", "before", 14, 16),
+                ("kelvin", "after", 0, 6),
+            ]
+        );
+    }
+
+    fn ordered_words<'a>(text: &'a str, query: &str, max_gap: usize) -> Vec<&'a str> {
+        find_ordered_words(text, query, max_gap, &NormalizationOptions::default())
+            .into_iter()
+            .map(|hit| &text[hit.start..hit.end])
+            .collect()
+    }
 
-        markdown.push_str(&format!("### Match {} (Page {})\n\n", idx + 1, m.page_number));
-        markdown.push_str(&format!("**Page:** {}\n\n", m.page_number));
-        markdown.push_str("**Context:**\n\n");
-        markdown.push_str(&format!(
-            "...{} **{}** {}...\n\n",
-            m.context_before, m.matched_text, m.context_after
-        ));
-        markdown.push_str("---\n\n");
+    #[test]
+    fn ordered_words_gap_boundary() {
+        let text = "climate one two three change";
+        assert_eq!(ordered_words(text, "climate change", 3), vec![text]);
+        assert!(ordered_words(text, "climate change", 2).is_empty());
+        // In order only
+        assert!(ordered_words(text, "change climate", 3).is_empty());
+        assert_eq!(ordered_words("climate change", "climate change", 0), vec!["climate change"]);
+        assert!(ordered_words("climate, not change", "climate change", 0).is_empty());
     }
 
-    markdown
+    #[test]
+    fn ordered_words_repeated_words() {
+        // A later placement of the middle word can be the one that lets the last fit
+        let text = "a b x x b x c";
+        assert_eq!(ordered_words(text, "a b c", 3), vec![text]);
+        assert!(ordered_words(text, "a b c", 2).is_empty());
+
+        // A query repeating a word needs it twice
+        assert_eq!(ordered_words("very very good", "very very good", 0), vec!["very very good"]);
+        assert!(ordered_words("very good", "very very good", 1).is_empty());
+
+        // Hits don't overlap; the next starts after the last one's end
+        assert_eq!(ordered_words("the cat the cat", "the cat", 0), vec!["the cat", "the cat"]);
+        assert_eq!(ordered_words("the the cat", "the cat", 1), vec!["the the cat"]);
+    }
+
+    #[test]
+    fn ordered_words_long_repetitive_page() {
+        // Every placement of the repeated word fits the gap and none leads to the
+        // last word, which used to take exponential time
+        let text = "word ".repeat(5000);
+        let started = Instant::now();
+        assert!(ordered_words(&text, "word word word word word word word word word word end", 10).is_empty());
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        let text = format!("{}end", text);
+        assert_eq!(ordered_words(&text, "word word end", 10).len(), 1);
+    }
+
+    #[test]
+    fn ordered_words_match_whole_words() {
+        // "cat" inside "concatenate" or "cats" isn't the word
+        assert!(ordered_words("concatenate the dogs", "cat dogs", 2).is_empty());
+        assert!(ordered_words("cats and dogs", "cat dogs", 2).is_empty());
+        // Surrounding punctuation isn't part of the word
+        assert_eq!(ordered_words("(cat), and \"dogs\".", "cat dogs", 1), vec!["cat), and \"dogs"]);
+        // Case and hyphens are normalized like the rest of the search
+        assert_eq!(ordered_words("Well-Known CATS", "wellknown cats", 0), vec!["Well-Known CATS"]);
+    }
 }
@@ -0,0 +1,138 @@
+use crate::{native_index, ocr, pandoc};
+use serde::Serialize;
+use std::path::PathBuf;
+
+// The same crate runs on desktops and, through Tauri's mobile entry point, on
+// phones and tablets. Some of what a desktop search leans on isn't there on a
+// phone: other programs can't be run, Zotero's database isn't on the device,
+// and folders outside the app's own storage can't be listed. Searching a
+// document on every core at once also drains the battery and gets the app
+// throttled. So on mobile targets searches run on a single thread within the
+// app's storage, and the frontend asks which subsystems there are instead of
+// offering what can't work.
+
+/// Whether this is a build for Android or iOS
+pub const MOBILE: bool = cfg!(any(target_os = "android", target_os = "ios"));
+
+// Threads searches use on mobile targets
+const MOBILE_THREADS: usize = 1;
+
+/// One part of the app and whether it works on this platform
+#[derive(Debug, Clone, Serialize)]
+pub struct Subsystem {
+    // "parallel_search", "zotero_database", "zotero_web_api", "native_index",
+    // "ocr", "pandoc", "network_mount_detection" or "text_cache"
+    pub name: &'static str,
+    pub available: bool,
+    // Why it isn't, or what it's limited to
+    pub note: Option<String>,
+}
+
+/// What the platform the app runs on supports, for the frontend to offer only that
+#[derive(Debug, Clone, Serialize)]
+pub struct PlatformReport {
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub mobile: bool,
+    // Threads a search reads documents on
+    pub search_threads: usize,
+    // On mobile, the folders searches are confined to; empty on desktops, where the
+    // settings and the user's choices decide
+    pub storage_roots: Vec<String>,
+    pub subsystems: Vec<Subsystem>,
+}
+
+/// Size the thread pool searches run on for the platform. Called once at
+/// startup, before the first search builds the pool on its own.
+pub fn configure() {
+    if !MOBILE {
+        return;
+    }
+    if let Err(e) = rayon::ThreadPoolBuilder::new().num_threads(MOBILE_THREADS).build_global() {
+        eprintln!("Warning: Failed to limit search threads: {}", e);
+    }
+}
+
+pub fn search_threads() -> usize {
+    if MOBILE {
+        MOBILE_THREADS
+    } else {
+        rayon::current_num_threads()
+    }
+}
+
+fn subsystem(name: &'static str, available: bool, note: Option<&str>) -> Subsystem {
+    Subsystem {
+        name,
+        available,
+        note: note.map(str::to_string),
+    }
+}
+
+/// Detect the subsystems there are. `storage_roots` are the app's own folders,
+/// which confine searches on mobile. OCR and Pandoc are looked for by running
+/// them, so this isn't for every search.
+pub fn report(storage_roots: &[PathBuf]) -> PlatformReport {
+    let no_processes = "Other programs can't be run on this platform";
+    let parallel = search_threads() > 1;
+    let native_index = native_index::available();
+    let network_mounts = cfg!(any(target_os = "linux", target_os = "macos"));
+    let ocr = !MOBILE && ocr::available();
+    let pandoc = !MOBILE && pandoc::available().is_some();
+
+    let subsystems = vec![
+        subsystem(
+            "parallel_search",
+            parallel,
+            (!parallel).then_some("Documents are read one at a time"),
+        ),
+        subsystem(
+            "zotero_database",
+            !MOBILE,
+            MOBILE.then_some("Zotero's database isn't on this device; use the Zotero web API"),
+        ),
+        subsystem("zotero_web_api", true, None),
+        subsystem(
+            "native_index",
+            native_index,
+            (!native_index).then_some("Only Spotlight and Windows Search are asked"),
+        ),
+        subsystem(
+            "ocr",
+            ocr,
+            match (MOBILE, ocr) {
+                (true, _) => Some(no_processes),
+                (false, false) => Some("Tesseract wasn't found"),
+                (false, true) => None,
+            },
+        ),
+        subsystem(
+            "pandoc",
+            pandoc,
+            match (MOBILE, pandoc) {
+                (true, _) => Some(no_processes),
+                (false, false) => Some("Pandoc wasn't found"),
+                (false, true) => None,
+            },
+        ),
+        subsystem(
+            "network_mount_detection",
+            network_mounts,
+            (!network_mounts).then_some("Only UNC paths are recognized as network locations"),
+        ),
+        subsystem("text_cache", true, None),
+    ];
+
+    PlatformReport {
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        mobile: MOBILE,
+        search_threads: search_threads(),
+        storage_roots: if MOBILE {
+            storage_roots.iter().map(|root| root.to_string_lossy().to_string()).collect()
+        } else {
+            Vec::new()
+        },
+        subsystems,
+    }
+}
@@ -0,0 +1,237 @@
+use crate::index;
+use crate::settings::AppSettings;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// PDFs whose text couldn't be extracted, or came out unusable, collected when the
+// user opts in so they can be sent along with a bug report. Each file is listed
+// once in a manifest in the app data folder, with what went wrong; in copy mode the
+// PDF itself is copied beside it, so the report survives the original moving.
+
+const CORPUS_DIR: &str = "problem_corpus";
+const MANIFEST_FILE: &str = "problems.json";
+const FILES_DIR: &str = "files";
+
+// Larger files are only referenced, even in copy mode
+const MAX_COPY_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Whether problem PDFs are collected, and how
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CollectionMode {
+    #[default]
+    Off,
+    // Record the file's path and hash
+    Reference,
+    // Record it and copy the PDF into the corpus
+    Copy,
+}
+
+/// What went wrong with a file's text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureCategory {
+    // lopdf couldn't parse the file
+    LoadFailed,
+    // Encrypted with a password other than the empty one
+    Encrypted,
+    // No page had any text, as with scans
+    NoText,
+    // Most pages with text had mostly symbols rather than letters, as with
+    // fonts lacking a Unicode mapping
+    Garbled,
+    // Extraction panicked
+    Crashed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProblemReport {
+    pub file_path: String,
+    pub sha256: Option<String>,
+    pub size: u64,
+    pub category: FailureCategory,
+    // The error, or what was counted
+    pub detail: Option<String>,
+    // Name of the copy in the corpus's files folder
+    pub copy: Option<String>,
+    pub first_seen: u64,
+    pub last_seen: u64,
+    // Extractions that hit the problem
+    pub occurrences: usize,
+}
+
+/// The collected reports and where they're kept
+#[derive(Debug, Serialize)]
+pub struct ProblemCorpus {
+    pub dir: String,
+    pub mode: CollectionMode,
+    pub reports: Vec<ProblemReport>,
+}
+
+struct CollectorConfig {
+    mode: CollectionMode,
+    data_dir: Option<PathBuf>,
+}
+
+static CONFIG: RwLock<CollectorConfig> = RwLock::new(CollectorConfig {
+    mode: CollectionMode::Off,
+    data_dir: None,
+});
+
+// Serializes read-modify-write of the manifest across search threads
+static MANIFEST_LOCK: Mutex<()> = Mutex::new(());
+
+/// Take the collection mode from the settings; reports go under `data_dir`
+pub fn configure(settings: &AppSettings, data_dir: Option<&Path>) {
+    *CONFIG.write().unwrap() = CollectorConfig {
+        mode: settings.problem_corpus,
+        data_dir: data_dir.map(Path::to_path_buf),
+    };
+}
+
+pub fn corpus_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join(CORPUS_DIR)
+}
+
+fn load_manifest(dir: &Path) -> Result<Vec<ProblemReport>> {
+    let path = dir.join(MANIFEST_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = fs::read_to_string(&path).context(format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&contents).context(format!("Failed to parse {}", path.display()))
+}
+
+fn save_manifest(dir: &Path, reports: &[ProblemReport]) -> Result<()> {
+    fs::create_dir_all(dir).context(format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(MANIFEST_FILE);
+    fs::write(&path, serde_json::to_string_pretty(reports)?).context(format!("Failed to write {}", path.display()))
+}
+
+/// Note a problem with a file's text, when collection is on. Failures to record
+/// are only logged, since they mustn't fail the extraction.
+pub fn record(pdf_path: &Path, category: FailureCategory, detail: Option<String>) {
+    let (mode, data_dir) = {
+        let config = CONFIG.read().unwrap();
+        (config.mode, config.data_dir.clone())
+    };
+    let Some(data_dir) = data_dir.filter(|_| mode != CollectionMode::Off) else {
+        return;
+    };
+    if let Err(e) = add_report(&corpus_dir(&data_dir), pdf_path, category, detail, mode) {
+        eprintln!("Warning: Failed to record problem PDF {}: {:#}", pdf_path.display(), e);
+    }
+}
+
+fn add_report(
+    dir: &Path,
+    pdf_path: &Path,
+    category: FailureCategory,
+    detail: Option<String>,
+    mode: CollectionMode,
+) -> Result<()> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let file_path = pdf_path.to_string_lossy().to_string();
+    let size = pdf_path.metadata().map_or(0, |m| m.len());
+    // Hashed outside the lock; it's the slow part
+    let sha256 = index::file_sha256(pdf_path).ok();
+
+    let _guard = MANIFEST_LOCK.lock().unwrap();
+    let mut reports = load_manifest(dir)?;
+    let position = match reports.iter().position(|r| r.file_path == file_path) {
+        Some(position) => {
+            let report = &mut reports[position];
+            // A changed file is a new case of whatever it now does
+            if report.sha256 != sha256 {
+                report.copy = None;
+                report.first_seen = now;
+                report.occurrences = 0;
+            }
+            report.sha256 = sha256;
+            report.size = size;
+            report.category = category;
+            report.detail = detail;
+            report.last_seen = now;
+            report.occurrences += 1;
+            position
+        }
+        None => {
+            reports.push(ProblemReport {
+                file_path,
+                sha256,
+                size,
+                category,
+                detail,
+                copy: None,
+                first_seen: now,
+                last_seen: now,
+                occurrences: 1,
+            });
+            reports.len() - 1
+        }
+    };
+
+    let report = &mut reports[position];
+    if mode == CollectionMode::Copy && report.copy.is_none() && size <= MAX_COPY_BYTES {
+        if let Some(ref sha256) = report.sha256 {
+            let name = format!(
+                "{}-{}",
+                &sha256[..16],
+                pdf_path.file_name().unwrap_or_default().to_string_lossy()
+            );
+            let files = dir.join(FILES_DIR);
+            fs::create_dir_all(&files).context(format!("Failed to create {}", files.display()))?;
+            match fs::copy(pdf_path, files.join(&name)) {
+                Ok(_) => report.copy = Some(name),
+                Err(e) => eprintln!("Warning: Failed to copy problem PDF {}: {}", pdf_path.display(), e),
+            }
+        }
+    }
+
+    save_manifest(dir, &reports)
+}
+
+/// The reports, most recently seen first
+pub fn list(data_dir: &Path) -> Result<ProblemCorpus> {
+    let dir = corpus_dir(data_dir);
+    let mut reports = {
+        let _guard = MANIFEST_LOCK.lock().unwrap();
+        load_manifest(&dir)?
+    };
+    reports.sort_by(|a, b| b.last_seen.cmp(&a.last_seen).then_with(|| a.file_path.cmp(&b.file_path)));
+    Ok(ProblemCorpus {
+        dir: dir.to_string_lossy().to_string(),
+        mode: CONFIG.read().unwrap().mode,
+        reports,
+    })
+}
+
+/// Drop a file's report and its copy; returns whether it was listed
+pub fn remove(data_dir: &Path, file_path: &str) -> Result<bool> {
+    let dir = corpus_dir(data_dir);
+    let _guard = MANIFEST_LOCK.lock().unwrap();
+    let mut reports = load_manifest(&dir)?;
+    let Some(position) = reports.iter().position(|r| r.file_path == file_path) else {
+        return Ok(false);
+    };
+    let report = reports.remove(position);
+    if let Some(copy) = report.copy {
+        let _ = fs::remove_file(dir.join(FILES_DIR).join(copy));
+    }
+    save_manifest(&dir, &reports)?;
+    Ok(true)
+}
+
+/// Delete every report and copy
+pub fn clear(data_dir: &Path) -> Result<()> {
+    let dir = corpus_dir(data_dir);
+    let _guard = MANIFEST_LOCK.lock().unwrap();
+    if dir.exists() {
+        fs::remove_dir_all(&dir).context(format!("Failed to remove {}", dir.display()))?;
+    }
+    Ok(())
+}
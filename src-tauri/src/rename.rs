@@ -0,0 +1,343 @@
+use crate::error::AppError;
+use crate::pdf_metadata;
+use crate::index::Index;
+use crate::pdf_search::find_pdf_files;
+use crate::zotero_cache::zotero_linker;
+use anyhow::Result;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Tidying a folder of downloads ("1-s2.0-S0048733318.pdf", "download (3).pdf")
+// into names that say what each paper is. Nothing is renamed until the reviewed
+// suggestions are passed to `apply_renames`. Zotero's own attachments are left
+// alone, since Zotero finds them by path.
+
+pub const DEFAULT_PATTERN: &str = "{author}_{title}";
+
+const PLACEHOLDERS: &[&str] = &["author", "title"];
+
+// Longer titles are cut at a word boundary
+const MAX_TITLE_CHARS: usize = 60;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RenameSuggestion {
+    pub file_path: String,
+    pub file_name: String,
+    // None when there's no metadata to name the file by, the name wouldn't
+    // change or the file is a Zotero attachment
+    pub suggested_name: Option<String>,
+    // "pdf_metadata"
+    pub source: Option<String>,
+    // Placeholders of the pattern with no value, left out of the name
+    pub missing: Vec<String>,
+    // Zotero links to the file, so renaming it would break the attachment
+    pub zotero_linked: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileRename {
+    pub file_path: String,
+    // A file name in the same directory
+    pub new_name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RenameOutcome {
+    pub file_path: String,
+    pub new_path: String,
+    // "renamed", "would_rename" on a dry run, or "skipped" with the reason in `error`
+    pub status: String,
+    pub error: Option<AppError>,
+}
+
+#[derive(Default)]
+struct NameFields {
+    author: Option<String>,
+    title: Option<String>,
+}
+
+impl NameFields {
+    fn get(&self, placeholder: &str) -> Option<&str> {
+        match placeholder {
+            "author" => self.author.as_deref(),
+            _ => self.title.as_deref(),
+        }
+    }
+}
+
+fn placeholder_regex() -> Regex {
+    Regex::new(r"\{([a-z]+)\}").expect("valid regex")
+}
+
+fn validate_pattern(pattern: &str) -> Result<()> {
+    let placeholders: Vec<String> = placeholder_regex()
+        .captures_iter(pattern)
+        .map(|captures| captures[1].to_string())
+        .collect();
+    if placeholders.is_empty() {
+        return Err(AppError::invalid_input(format!(
+            "The file name pattern needs at least one of {}",
+            PLACEHOLDERS.iter().map(|p| format!("{{{}}}", p)).collect::<Vec<_>>().join(", ")
+        ))
+        .into());
+    }
+    if let Some(unknown) = placeholders.iter().find(|p| !PLACEHOLDERS.contains(&p.as_str())) {
+        return Err(AppError::invalid_input(format!("Unknown placeholder in file name pattern: {{{}}}", unknown)).into());
+    }
+    if pattern.contains(['/', '\\']) {
+        return Err(AppError::invalid_input("The file name pattern can't contain path separators").into());
+    }
+    Ok(())
+}
+
+// The first author's family name. The Info dictionary's Author is free text,
+// often "Last, First; Last, First".
+fn first_author(authors: &str) -> Option<String> {
+    let first = authors.split([';', '&']).next()?.split(" and ").next()?.trim();
+    let last = match first.split_once(',') {
+        Some((last, _)) => last.trim(),
+        None => first.split_whitespace().last()?,
+    };
+    Some(last.to_string())
+}
+
+// Keep what's allowed in file names on every platform, with `_` between words
+fn clean_component(text: &str, max_chars: Option<usize>) -> Option<String> {
+    let cleaned: String = text
+        .chars()
+        .map(|c| if c.is_control() || "/\\:*?\"<>|".contains(c) { ' ' } else { c })
+        .collect();
+    let mut words = Vec::new();
+    let mut length = 0;
+    for word in cleaned.split_whitespace() {
+        let word = word.trim_matches(['.', ',', ';']);
+        if word.is_empty() {
+            continue;
+        }
+        let word_length = word.chars().count();
+        if max_chars.is_some_and(|max| length + word_length > max) && !words.is_empty() {
+            break;
+        }
+        length += word_length + 1;
+        words.push(word);
+    }
+    let component = words.join("_");
+    (!component.is_empty()).then_some(component)
+}
+
+fn name_fields(path: &Path) -> (NameFields, Option<&'static str>) {
+    match pdf_metadata::read_pdf_info(path) {
+        Ok(info) if info.title.is_some() || info.author.is_some() => {
+            let fields = NameFields {
+                author: info.author.as_deref().and_then(first_author),
+                title: info.title,
+            };
+            (fields, Some("pdf_metadata"))
+        }
+        _ => (NameFields::default(), None),
+    }
+}
+
+// The pattern filled in. An empty placeholder is left out together with the
+// separator before it, so "{title} - {author}" without an author gives "Title".
+fn render_name(pattern: &str, fields: &NameFields) -> (Option<String>, Vec<String>) {
+    let mut missing = Vec::new();
+    // Text before the first placeholder is always kept
+    let mut end = placeholder_regex().find(pattern).map_or(0, |m| m.start());
+    let mut name = pattern[..end].to_string();
+    let mut filled = false;
+    let mut last_filled = false;
+    for captures in placeholder_regex().captures_iter(pattern) {
+        let whole = captures.get(0).expect("group 0 always matches");
+        let separator = &pattern[end..whole.start()];
+        end = whole.end();
+
+        let placeholder = &captures[1];
+        let max_chars = (placeholder == "title").then_some(MAX_TITLE_CHARS);
+        match fields.get(placeholder).and_then(|value| clean_component(value, max_chars)) {
+            Some(value) => {
+                if filled {
+                    name.push_str(separator);
+                }
+                name.push_str(&value);
+                filled = true;
+                last_filled = true;
+            }
+            None => {
+                missing.push(placeholder.to_string());
+                last_filled = false;
+            }
+        }
+    }
+
+    if !filled {
+        return (None, missing);
+    }
+    // Text after the last placeholder goes with it, like a closing bracket
+    if last_filled {
+        name.push_str(&pattern[end..]);
+    }
+    (Some(format!("{}.pdf", name.trim())), missing)
+}
+
+/// Propose a name following `pattern` (`{author}`, `{title}`) for each PDF
+/// under `directory`, from its embedded title and author. Zotero attachments
+/// get no suggestion and are marked `zotero_linked`. Suggestions that would
+/// collide with another file get a numeric suffix.
+pub fn suggest_filenames(
+    directory: &Path,
+    pattern: Option<&str>,
+    zotero_path: Option<&Path>,
+) -> Result<Vec<RenameSuggestion>> {
+    let pattern = pattern.map(str::trim).filter(|p| !p.is_empty()).unwrap_or(DEFAULT_PATTERN);
+    validate_pattern(pattern)?;
+
+    let zotero_linked = zotero_linker(zotero_path);
+
+    let pdf_files = find_pdf_files(directory)?;
+    let mut suggestions: Vec<RenameSuggestion> = pdf_files
+        .par_iter()
+        .map(|path| {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let linked = zotero_linked(path);
+            let (fields, source) = if linked { (NameFields::default(), None) } else { name_fields(path) };
+            let (suggested_name, missing) = match source {
+                Some(_) => render_name(pattern, &fields),
+                None => (None, Vec::new()),
+            };
+            RenameSuggestion {
+                file_path: path.to_string_lossy().to_string(),
+                file_name,
+                suggested_name,
+                source: source.map(str::to_string),
+                missing,
+                zotero_linked: linked,
+            }
+        })
+        .collect();
+
+    // Names already taken in each directory, compared case-insensitively for
+    // the file systems that do
+    let mut taken: HashSet<(PathBuf, String)> = pdf_files
+        .iter()
+        .map(|path| (parent(path), path.file_name().unwrap_or_default().to_string_lossy().to_lowercase()))
+        .collect();
+    for suggestion in &mut suggestions {
+        let Some(name) = suggestion.suggested_name.take() else {
+            continue;
+        };
+        if name == suggestion.file_name {
+            continue;
+        }
+        let directory = parent(Path::new(&suggestion.file_path));
+        let stem = name.trim_end_matches(".pdf");
+        let mut candidate = name.clone();
+        let mut counter = 2;
+        while taken.contains(&(directory.clone(), candidate.to_lowercase()))
+            && candidate.to_lowercase() != suggestion.file_name.to_lowercase()
+        {
+            candidate = format!("{}_{}.pdf", stem, counter);
+            counter += 1;
+        }
+        taken.insert((directory, candidate.to_lowercase()));
+        suggestion.suggested_name = Some(candidate);
+    }
+
+    Ok(suggestions)
+}
+
+fn parent(path: &Path) -> PathBuf {
+    path.parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+fn check_rename(
+    rename: &FileRename,
+    planned: &HashSet<PathBuf>,
+    zotero_linked: impl Fn(&Path) -> bool,
+) -> Result<PathBuf, AppError> {
+    let from = Path::new(&rename.file_path);
+    let new_name = rename.new_name.trim();
+    if new_name.is_empty()
+        || new_name.contains(['/', '\\'])
+        || new_name == "."
+        || new_name == ".."
+        || !new_name.to_lowercase().ends_with(".pdf")
+    {
+        return Err(AppError::invalid_input(format!("Not a PDF file name: {}", rename.new_name)));
+    }
+    if !from.is_file() {
+        return Err(AppError::NotFound { file: rename.file_path.clone() });
+    }
+    if zotero_linked(from) {
+        return Err(AppError::invalid_input(format!(
+            "{} is a Zotero attachment, which would lose its file if renamed",
+            from.display()
+        )));
+    }
+
+    let to = from.with_file_name(new_name);
+    if to == from {
+        return Err(AppError::invalid_input("The file already has this name"));
+    }
+    // A change of case only is the same file on case-insensitive file systems
+    let case_change = to.to_string_lossy().to_lowercase() == from.to_string_lossy().to_lowercase();
+    if (to.exists() && !case_change) || planned.contains(&to) {
+        return Err(AppError::invalid_input(format!("{} already exists", to.display())));
+    }
+    Ok(to)
+}
+
+/// Rename PDFs within their directories, never replacing an existing file or
+/// renaming a Zotero attachment. The index moves the renamed files' cached text,
+/// catalog entries and screening decisions to their new paths. With `dry_run`,
+/// only checks each rename and reports what would happen.
+pub fn apply_renames(
+    index: Option<&Index>,
+    renames: &[FileRename],
+    zotero_path: Option<&Path>,
+    dry_run: bool,
+) -> Vec<RenameOutcome> {
+    let zotero_linked = zotero_linker(zotero_path);
+    let mut planned = HashSet::new();
+    renames
+        .iter()
+        .map(|rename| {
+            let checked = check_rename(rename, &planned, &zotero_linked).and_then(|to| {
+                if !dry_run {
+                    fs::rename(&rename.file_path, &to).map_err(|e| AppError::io(e, &rename.file_path))?;
+                    if let Some(index) = index {
+                        let new_path = to.to_string_lossy();
+                        if let Err(e) = index.move_file(&rename.file_path, &new_path) {
+                            eprintln!("Warning: Failed to update the index for {}: {}", rename.file_path, e);
+                        }
+                    }
+                }
+                Ok(to)
+            });
+            match checked {
+                Ok(to) => {
+                    planned.insert(to.clone());
+                    RenameOutcome {
+                        file_path: rename.file_path.clone(),
+                        new_path: to.to_string_lossy().to_string(),
+                        status: if dry_run { "would_rename" } else { "renamed" }.to_string(),
+                        error: None,
+                    }
+                }
+                Err(error) => RenameOutcome {
+                    file_path: rename.file_path.clone(),
+                    new_path: Path::new(&rename.file_path)
+                        .with_file_name(rename.new_name.trim())
+                        .to_string_lossy()
+                        .to_string(),
+                    status: "skipped".to_string(),
+                    error: Some(error),
+                },
+            }
+        })
+        .collect()
+}
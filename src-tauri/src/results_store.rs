@@ -0,0 +1,104 @@
+use crate::pdf_search::{SearchMatch, SearchParams};
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+const RESULTS_DIR: &str = "saved_results";
+
+/// Summary of a persisted result set, stored next to the compressed matches
+/// so listing doesn't have to decompress every set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedResultsInfo {
+    pub search_id: u64,
+    pub name: Option<String>,
+    pub saved_at: u64,
+    pub match_count: usize,
+    pub params: SearchParams,
+    // Saved from a search that was interrupted before it finished
+    #[serde(default)]
+    pub partial: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedResults {
+    #[serde(flatten)]
+    pub info: SavedResultsInfo,
+    pub matches: Vec<SearchMatch>,
+}
+
+fn results_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join(RESULTS_DIR)
+}
+
+pub fn save_results(data_dir: &Path, info: SavedResultsInfo, matches: Vec<SearchMatch>) -> Result<SavedResultsInfo> {
+    let dir = results_dir(data_dir);
+    fs::create_dir_all(&dir)
+        .context(format!("Failed to create results directory: {}", dir.display()))?;
+
+    let matches_path = dir.join(format!("{}.json.gz", info.search_id));
+    let file = File::create(&matches_path)
+        .context(format!("Failed to create saved results: {}", matches_path.display()))?;
+    let saved = SavedResults { info, matches };
+    let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+    serde_json::to_writer(&mut encoder, &saved)?;
+    encoder
+        .finish()
+        .context(format!("Failed to write saved results: {}", matches_path.display()))?;
+
+    // Written last, so a listed set always has its matches on disk
+    let info_path = dir.join(format!("{}.json", saved.info.search_id));
+    fs::write(&info_path, serde_json::to_string_pretty(&saved.info)?)
+        .context(format!("Failed to write saved results summary: {}", info_path.display()))?;
+
+    Ok(saved.info)
+}
+
+pub fn load_results(data_dir: &Path, search_id: u64) -> Result<SavedResults> {
+    let path = results_dir(data_dir).join(format!("{}.json.gz", search_id));
+    let file = File::open(&path)
+        .context(format!("Failed to open saved results: {}", path.display()))?;
+    let saved: SavedResults = serde_json::from_reader(GzDecoder::new(BufReader::new(file)))
+        .context(format!("Failed to parse saved results: {}", path.display()))?;
+    let version = saved
+        .matches
+        .iter()
+        .map(|m| m.schema_version)
+        .fold(saved.info.params.schema_version, Ord::max);
+    version.warn_if_newer(&path.display().to_string());
+    Ok(saved)
+}
+
+/// Whether the results of search `search_id` were saved
+pub fn has_results(data_dir: &Path, search_id: u64) -> bool {
+    results_dir(data_dir).join(format!("{}.json.gz", search_id)).exists()
+}
+
+/// Every persisted result set, most recently saved first
+pub fn list_saved_results(data_dir: &Path) -> Result<Vec<SavedResultsInfo>> {
+    let dir = results_dir(data_dir);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut saved = Vec::new();
+    for entry in fs::read_dir(&dir)?.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        // A corrupt summary shouldn't hide the other saved sets
+        match fs::read_to_string(&path).map(|contents| serde_json::from_str::<SavedResultsInfo>(&contents)) {
+            Ok(Ok(info)) => saved.push(info),
+            _ => eprintln!("Warning: Skipping unreadable saved results: {}", path.display()),
+        }
+    }
+
+    saved.sort_by_key(|info| Reverse(info.saved_at));
+    Ok(saved)
+}
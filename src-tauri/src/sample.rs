@@ -0,0 +1,58 @@
+use crate::pdf_search::SearchMatch;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Reproducible random subset of the results, e.g. for double-coding
+/// a share of the matches in a systematic review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SampleParams {
+    // Matches to keep from each group
+    pub size: usize,
+    // "query" samples within each query's matches, "document" within each file's
+    #[serde(default = "default_sample_by")]
+    pub per: String,
+    pub seed: u64,
+}
+
+fn default_sample_by() -> String {
+    "query".to_string()
+}
+
+/// Keep at most `size` randomly chosen matches per group. The same seed over
+/// the same matches always selects the same subset, whatever order the parallel
+/// search produced them in.
+pub fn sample_matches(mut matches: Vec<SearchMatch>, params: &SampleParams) -> Vec<SearchMatch> {
+    matches.sort_by(|a, b| {
+        (&a.item.file_path, a.page_number, a.offset, &a.query).cmp(&(&b.item.file_path, b.page_number, b.offset, &b.query))
+    });
+
+    let mut groups: BTreeMap<String, Vec<SearchMatch>> = BTreeMap::new();
+    for m in matches {
+        let key = if params.per == "document" {
+            m.item.file_path.clone()
+        } else {
+            m.query.clone().unwrap_or_default()
+        };
+        groups.entry(key).or_default().push(m);
+    }
+
+    let mut rng = fastrand::Rng::with_seed(params.seed);
+    let mut sampled = Vec::new();
+    for group in groups.into_values() {
+        if group.len() <= params.size {
+            sampled.extend(group);
+            continue;
+        }
+
+        let mut picked: Vec<usize> = (0..group.len()).collect();
+        rng.shuffle(&mut picked);
+        picked.truncate(params.size);
+        // Present the sample in reading order rather than draw order
+        picked.sort_unstable();
+
+        let mut group: Vec<Option<SearchMatch>> = group.into_iter().map(Some).collect();
+        sampled.extend(picked.into_iter().filter_map(|i| group[i].take()));
+    }
+
+    sampled
+}
@@ -0,0 +1,150 @@
+use crate::directory_config;
+use crate::error::AppError;
+use crate::index::{self, Index, SavedSearchRow};
+use crate::palette;
+use crate::pdf_search::{search_pdfs, FileError, SearchContext, SearchMatch, SearchParams};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// A stored search definition that can be re-run as a standing alert
+#[derive(Debug, Serialize)]
+pub struct SavedSearch {
+    pub id: i64,
+    pub name: String,
+    pub params: SearchParams,
+    pub created: u64,
+    pub last_run: Option<u64>,
+}
+
+// What the previous run found, to diff the next run against
+#[derive(Default, Serialize, Deserialize)]
+struct RunSnapshot {
+    // Modification time of each file that had matches
+    files: HashMap<String, u64>,
+    matches: HashSet<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RerunResult {
+    pub search_id: i64,
+    pub first_run: bool,
+    pub total_matches: usize,
+    // Matches the previous run didn't have
+    pub new_matches: Vec<SearchMatch>,
+    // Files with matches for the first time
+    pub new_files: Vec<String>,
+    // Files that matched before and have been modified since
+    pub changed_files: Vec<String>,
+    pub file_errors: Vec<FileError>,
+}
+
+impl TryFrom<SavedSearchRow> for SavedSearch {
+    type Error = anyhow::Error;
+
+    fn try_from(row: SavedSearchRow) -> Result<Self> {
+        Ok(SavedSearch {
+            id: row.id,
+            name: row.name,
+            params: serde_json::from_str(&row.params)?,
+            created: row.created,
+            last_run: row.last_run,
+        })
+    }
+}
+
+/// Store a search definition. Queries without a color get one from the palette,
+/// keeping the colors they had in an earlier saved search of the same name, and
+/// are stored with it so every re-run highlights them the same way.
+pub fn save_search(index: &Index, name: &str, params: &SearchParams) -> Result<i64> {
+    let previous = list_saved_searches(index)?
+        .into_iter()
+        .rev()
+        .find(|search| search.name == name)
+        .map(|search| search.params.queries)
+        .unwrap_or_default();
+    let mut params = params.clone();
+    palette::assign_colors(&mut params.queries, &previous);
+    index.save_search(name, &serde_json::to_string(&params)?)
+}
+
+pub fn list_saved_searches(index: &Index) -> Result<Vec<SavedSearch>> {
+    index.saved_searches()?.into_iter().map(SavedSearch::try_from).collect()
+}
+
+// Identity of a match across runs
+fn match_key(m: &SearchMatch) -> String {
+    format!(
+        "{}\u{0}{}\u{0}{}\u{0}{}\u{0}{}",
+        m.item.file_path,
+        m.page_number,
+        m.offset.unwrap_or(0),
+        m.query.as_deref().unwrap_or_default(),
+        m.matched_text
+    )
+}
+
+/// Run a saved search again and report what's new since its previous run.
+/// The first run reports every match as new.
+pub fn rerun_search(search_id: i64, ctx: &SearchContext) -> Result<RerunResult> {
+    let index = ctx
+        .index
+        .ok_or_else(|| AppError::invalid_input("Re-running saved searches requires the search index"))?;
+    let row = index
+        .saved_search(search_id)?
+        .ok_or_else(|| AppError::invalid_input(format!("No saved search with ID {}", search_id)))?;
+
+    let first_run = row.snapshot.is_none();
+    let previous: RunSnapshot = match row.snapshot {
+        Some(ref snapshot) => serde_json::from_str(snapshot).unwrap_or_default(),
+        None => RunSnapshot::default(),
+    };
+    let mut params: SearchParams = serde_json::from_str(&row.params)?;
+    params.schema_version.warn_if_newer(&format!("Saved search {}", search_id));
+    directory_config::apply_directory_config(&mut params)?;
+    // The run is compared match by match with the last one, so it's kept in memory
+    params.stream_threshold = None;
+
+    let outcome = search_pdfs(params, ctx)?;
+
+    let mut snapshot = RunSnapshot::default();
+    for m in &outcome.matches {
+        snapshot.matches.insert(match_key(m));
+        snapshot
+            .files
+            .entry(m.item.file_path.clone())
+            .or_insert_with(|| index::file_mtime(Path::new(&m.item.file_path)).unwrap_or(0));
+    }
+
+    let mut new_files = Vec::new();
+    let mut changed_files = Vec::new();
+    for (file_path, mtime) in &snapshot.files {
+        match previous.files.get(file_path) {
+            None => new_files.push(file_path.clone()),
+            Some(previous_mtime) if previous_mtime != mtime => changed_files.push(file_path.clone()),
+            Some(_) => {}
+        }
+    }
+    new_files.sort();
+    changed_files.sort();
+
+    let total_matches = outcome.matches.len();
+    let new_matches = outcome
+        .matches
+        .into_iter()
+        .filter(|m| !previous.matches.contains(&match_key(m)))
+        .collect();
+
+    index.record_search_run(search_id, &serde_json::to_string(&snapshot)?)?;
+
+    Ok(RerunResult {
+        search_id,
+        first_run,
+        total_matches,
+        new_matches,
+        new_files,
+        changed_files,
+        file_errors: outcome.file_errors,
+    })
+}
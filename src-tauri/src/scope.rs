@@ -0,0 +1,90 @@
+use crate::error::AppError;
+use crate::settings::AppSettings;
+use std::path::{Component, Path, PathBuf};
+use std::sync::RwLock;
+
+// Commands take paths from the webview, which shouldn't be able to read or
+// write anywhere the user hasn't pointed the app at. A path is allowed when it
+// lies under a root: the default and Zotero directories from the settings,
+// folders chosen in the app's dialogs (remembered in the settings), and, for
+// the current session, saved files chosen in a dialog, files dropped onto the
+// window and files a search returned.
+
+// Roots are canonical, so symlinks and `..` can't be used to step outside them
+#[derive(Default)]
+pub struct PathScope {
+    settings_roots: RwLock<Vec<PathBuf>>,
+    session_roots: RwLock<Vec<PathBuf>>,
+}
+
+// The canonical form of `path`, which needn't exist yet: its nearest existing
+// ancestor is resolved and the rest appended, as long as the rest doesn't step
+// back up with `..`
+fn resolve(path: &Path) -> Option<PathBuf> {
+    if !path.is_absolute() {
+        return None;
+    }
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return Some(rest.iter().rev().fold(canonical, |path, part| path.join(part)));
+        }
+        match existing.components().next_back()? {
+            Component::Normal(part) => rest.push(part.to_os_string()),
+            _ => return None,
+        }
+        existing = existing.parent()?;
+    }
+}
+
+impl PathScope {
+    /// Replace the roots that come from the settings, keeping the session's
+    pub fn set_settings_roots(&self, settings: &AppSettings) {
+        *self.settings_roots.write().unwrap() = settings
+            .default_directory
+            .iter()
+            .chain(settings.zotero_path.iter())
+            .chain(settings.allowed_roots.iter())
+            .filter_map(|root| resolve(Path::new(root)))
+            .collect();
+    }
+
+    /// Confine the settings roots to `roots`, whatever the settings name, as on
+    /// mobile targets where only the app's own storage can be read
+    pub fn set_storage_roots(&self, roots: &[PathBuf]) {
+        *self.settings_roots.write().unwrap() = roots.iter().filter_map(|root| resolve(root)).collect();
+    }
+
+    /// Let commands use `path` and everything below it until the app exits
+    pub fn allow(&self, path: &Path) {
+        let Some(root) = resolve(path) else {
+            return;
+        };
+        let mut roots = self.session_roots.write().unwrap();
+        if !roots.contains(&root) {
+            roots.push(root);
+        }
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        let Some(path) = resolve(path) else {
+            return false;
+        };
+        let under = |roots: &RwLock<Vec<PathBuf>>| roots.read().unwrap().iter().any(|root| path.starts_with(root));
+        under(&self.settings_roots) || under(&self.session_roots)
+    }
+
+    /// Fail with `OutsideScope` unless `path` is under an allowed root
+    pub fn check(&self, path: &str) -> Result<(), AppError> {
+        if self.contains(Path::new(path)) {
+            Ok(())
+        } else {
+            Err(AppError::OutsideScope { file: path.to_string() })
+        }
+    }
+
+    pub fn check_all<'a>(&self, paths: impl IntoIterator<Item = &'a str>) -> Result<(), AppError> {
+        paths.into_iter().try_for_each(|path| self.check(path))
+    }
+}
@@ -0,0 +1,132 @@
+use crate::error::AppError;
+use crate::index::{Index, ScreeningRow};
+use crate::pdf_search::SearchMatch;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScreeningStatus {
+    Include,
+    Exclude,
+    Maybe,
+}
+
+impl ScreeningStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            ScreeningStatus::Include => "include",
+            ScreeningStatus::Exclude => "exclude",
+            ScreeningStatus::Maybe => "maybe",
+        }
+    }
+
+    fn parse(status: &str) -> Option<Self> {
+        match status {
+            "include" => Some(ScreeningStatus::Include),
+            "exclude" => Some(ScreeningStatus::Exclude),
+            "maybe" => Some(ScreeningStatus::Maybe),
+            _ => None,
+        }
+    }
+}
+
+/// A reviewer's decision on one document. Decisions are grouped into named
+/// reviews, so one corpus can be screened separately for different questions.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScreeningDecision {
+    pub file_path: String,
+    pub status: ScreeningStatus,
+    pub reason: Option<String>,
+    pub updated: u64,
+}
+
+/// Which documents a search or export keeps, based on a review's decisions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScreeningFilter {
+    pub review: String,
+    // Keep only documents with one of these statuses; None drops excluded documents
+    #[serde(default)]
+    pub statuses: Option<Vec<ScreeningStatus>>,
+    // Whether documents without a decision yet are kept
+    #[serde(default = "default_include_unscreened")]
+    pub include_unscreened: bool,
+}
+
+fn default_include_unscreened() -> bool {
+    true
+}
+
+impl TryFrom<ScreeningRow> for ScreeningDecision {
+    type Error = anyhow::Error;
+
+    fn try_from(row: ScreeningRow) -> Result<Self> {
+        let status = ScreeningStatus::parse(&row.status)
+            .ok_or_else(|| anyhow::anyhow!("Unknown screening status: {}", row.status))?;
+        Ok(ScreeningDecision {
+            file_path: row.path,
+            status,
+            reason: row.reason,
+            updated: row.updated,
+        })
+    }
+}
+
+/// Record a decision; `None` clears the document's decision
+pub fn set_screening(
+    index: &Index,
+    review: &str,
+    file_path: &str,
+    status: Option<ScreeningStatus>,
+    reason: Option<&str>,
+) -> Result<()> {
+    if review.trim().is_empty() {
+        return Err(AppError::invalid_input("Screening decisions need a review name").into());
+    }
+    match status {
+        Some(status) => index.set_screening(review, file_path, status.as_str(), reason),
+        None => index.clear_screening(review, file_path),
+    }
+}
+
+pub fn list_screening(index: &Index, review: &str) -> Result<Vec<ScreeningDecision>> {
+    index.screening(review)?.into_iter().map(ScreeningDecision::try_from).collect()
+}
+
+// Statuses by file path, for filtering
+fn decisions(index: &Index, review: &str) -> Result<HashMap<String, ScreeningStatus>> {
+    Ok(list_screening(index, review)?
+        .into_iter()
+        .map(|decision| (decision.file_path, decision.status))
+        .collect())
+}
+
+impl ScreeningFilter {
+    fn keeps(&self, status: Option<&ScreeningStatus>) -> bool {
+        match (status, &self.statuses) {
+            (None, _) => self.include_unscreened,
+            (Some(status), Some(statuses)) => statuses.contains(status),
+            (Some(status), None) => *status != ScreeningStatus::Exclude,
+        }
+    }
+}
+
+/// Drop the files the filter's review screened out
+pub fn filter_files(index: &Index, files: Vec<PathBuf>, filter: &ScreeningFilter) -> Result<Vec<PathBuf>> {
+    let decisions = decisions(index, &filter.review)?;
+    Ok(files
+        .into_iter()
+        .filter(|path| filter.keeps(decisions.get(path.to_string_lossy().as_ref())))
+        .collect())
+}
+
+/// Drop the matches from documents the filter's review screened out
+pub fn filter_matches(index: &Index, matches: Vec<SearchMatch>, filter: &ScreeningFilter) -> Result<Vec<SearchMatch>> {
+    let decisions = decisions(index, &filter.review)?;
+    Ok(matches
+        .into_iter()
+        .filter(|m| filter.keeps(decisions.get(&m.item.file_path)))
+        .collect())
+}
@@ -0,0 +1,353 @@
+use crate::ocr;
+use crate::pdf_search::{search_single_pdf, NormalizationOptions, QueryItem, SearchMatch, SearchParams};
+use anyhow::{Context, Result};
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, Object, Stream, StringFormat};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+// A check of the install: small PDFs exercising what real documents need of the
+// extraction stack are written to a temporary folder, then searched the way the
+// app searches. Each capability passes when the search finds the text it should.
+
+const CONTEXT_WORDS: usize = 5;
+
+// A 5×7 bitmap of each letter the OCR page spells, "#" for ink
+const GLYPHS: [(char, [&str; 7]); 3] = [
+    ('T', ["#####", "..#..", "..#..", "..#..", "..#..", "..#..", "..#.."]),
+    ('E', ["#####", "#....", "#....", "####.", "#....", "#....", "#####"]),
+    ('S', [".####", "#....", "#....", ".###.", "....#", "....#", "####."]),
+];
+
+// Pixels per bitmap dot; Tesseract wants letters a few dozen pixels tall
+const GLYPH_SCALE: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+    // The capability isn't set up, as when Tesseract isn't installed
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestCheck {
+    pub capability: String,
+    pub status: CheckStatus,
+    // What was found, or why it failed or was skipped
+    pub detail: String,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    pub platform: String,
+    pub checks: Vec<SelfTestCheck>,
+    // No check failed; skipped ones don't count
+    pub passed: bool,
+}
+
+// One page of a test PDF: its content, and an image for it to draw
+struct TestPage {
+    operations: Vec<Operation>,
+    image: Option<Stream>,
+}
+
+// A check's outcome before it's timed
+type Outcome = Result<(CheckStatus, String)>;
+
+// Writes its PDF into the folder and searches it
+type Check = fn(&Path) -> Outcome;
+
+/// Write the test PDFs, search them and report on each capability
+pub fn run_self_test() -> Result<SelfTestReport> {
+    let dir = std::env::temp_dir().join(format!("pdf-search-self-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).context(format!("Failed to create {}", dir.display()))?;
+
+    let checks: [(&str, Check); 6] = [
+        ("text", check_text),
+        ("two_column", check_two_column),
+        ("hyphenation", check_hyphenation),
+        ("accented_text", check_accented_text),
+        ("cid_font", check_cid_font),
+        ("ocr", check_ocr),
+    ];
+    let checks: Vec<SelfTestCheck> = checks
+        .into_iter()
+        .map(|(capability, check)| {
+            let started = Instant::now();
+            let (status, detail) = check(&dir).unwrap_or_else(|e| (CheckStatus::Fail, format!("{:#}", e)));
+            SelfTestCheck {
+                capability: capability.to_string(),
+                status,
+                detail,
+                elapsed_ms: started.elapsed().as_millis() as u64,
+            }
+        })
+        .collect();
+    let _ = fs::remove_dir_all(&dir);
+
+    Ok(SelfTestReport {
+        platform: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+        passed: checks.iter().all(|check| check.status != CheckStatus::Fail),
+        checks,
+    })
+}
+
+fn check_text(dir: &Path) -> Outcome {
+    let path = dir.join("text.pdf");
+    write_pdf(
+        &path,
+        vec![text_page(&[(50.0, &["The quick brown fox jumps over the lazy dog"])])],
+    )?;
+    let matches = search(&path, "lazy dog", NormalizationOptions::default(), false)?;
+    expect(&matches, "lazy dog", |m| m.context_before.contains("fox"))
+}
+
+// Columns drawn one after the other must come out one after the other, so a
+// sentence running down the left column isn't interleaved with the right one
+fn check_two_column(dir: &Path) -> Outcome {
+    let path = dir.join("two_column.pdf");
+    write_pdf(
+        &path,
+        vec![text_page(&[
+            (50.0, &["Left column opens the first", "argument and carries it", "down the page."]),
+            (320.0, &["Right column holds a", "separate second thread", "of its own."]),
+        ])],
+    )?;
+    let matches = search(&path, "first argument and carries", NormalizationOptions::default(), false)?;
+    let outcome = expect(&matches, "a sentence across the left column's lines", |_| true)?;
+    // Read row by row, the columns' first lines would run together
+    let interleaved = search(&path, "opens the first right column", NormalizationOptions::default(), false)?;
+    if !interleaved.is_empty() {
+        return Ok((CheckStatus::Fail, "The columns' lines were read interleaved".to_string()));
+    }
+    Ok(outcome)
+}
+
+fn check_hyphenation(dir: &Path) -> Outcome {
+    let path = dir.join("hyphenation.pdf");
+    write_pdf(
+        &path,
+        vec![text_page(&[(50.0, &["Searching the text of scientific docu-", "ments across a line break."])])],
+    )?;
+    let matches = search(&path, "documents", NormalizationOptions::default(), false)?;
+    expect(&matches, "a word hyphenated across lines", |_| true)
+}
+
+// A simple font's WinAnsi encoding, which covers Western European letters
+fn check_accented_text(dir: &Path) -> Outcome {
+    let path = dir.join("accented_text.pdf");
+    write_pdf(
+        &path,
+        vec![text_page(&[(50.0, &["A naïve café on the Straße near the façade"])])],
+    )?;
+    let exact = search(&path, "Straße", NormalizationOptions::default(), false)?;
+    if let (CheckStatus::Fail, detail) = expect(&exact, "Straße", |_| true)? {
+        return Ok((CheckStatus::Fail, detail));
+    }
+    let folded = NormalizationOptions {
+        strip_diacritics: true,
+        ..Default::default()
+    };
+    let matches = search(&path, "naive cafe", folded, false)?;
+    expect(&matches, "Straße, and \"naive cafe\" without accents", |_| true)
+}
+
+// A composite font whose strings are UTF-16, as many non-Latin PDFs use
+fn check_cid_font(dir: &Path) -> Outcome {
+    let path = dir.join("cid_font.pdf");
+    let text: Vec<u8> = "λόγος 中文检索".encode_utf16().flat_map(u16::to_be_bytes).collect();
+    let operations = vec![
+        Operation::new("BT", vec![]),
+        Operation::new("Tf", vec!["F2".into(), 12.into()]),
+        Operation::new("Td", vec![50.into(), 700.into()]),
+        Operation::new("Tj", vec![Object::String(text, StringFormat::Hexadecimal)]),
+        Operation::new("ET", vec![]),
+    ];
+    write_pdf(&path, vec![TestPage { operations, image: None }])?;
+    let matches = search(&path, "中文检索", NormalizationOptions::default(), false)?;
+    expect(&matches, "中文检索", |_| true)
+}
+
+// A page that's only an image of text, read by Tesseract. The first page has
+// text, as a partly scanned document would.
+fn check_ocr(dir: &Path) -> Outcome {
+    if !ocr::available() {
+        return Ok((CheckStatus::Skipped, "The OCR command isn't installed".to_string()));
+    }
+    let path = dir.join("ocr.pdf");
+    let (width, height, pixels) = word_image("TEST");
+    let image = Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => width as i64,
+            "Height" => height as i64,
+            "ColorSpace" => "DeviceGray",
+            "BitsPerComponent" => 8,
+        },
+        pixels,
+    );
+    let drawn = vec![
+        Operation::new("q", vec![]),
+        Operation::new(
+            "cm",
+            vec![(width as f32 / 2.0).into(), 0.into(), 0.into(), (height as f32 / 2.0).into(), 50.into(), 600.into()],
+        ),
+        Operation::new("Do", vec!["Im1".into()]),
+        Operation::new("Q", vec![]),
+    ];
+    write_pdf(
+        &path,
+        vec![
+            text_page(&[(50.0, &["This page has a text layer; the next is scanned."])]),
+            TestPage {
+                operations: drawn,
+                image: Some(image),
+            },
+        ],
+    )?;
+    let matches = search(&path, "test", NormalizationOptions::default(), true)?;
+    expect(&matches, "the word on the scanned page", |m| m.ocr)
+}
+
+// Pass when a match satisfies `check`
+fn expect(matches: &[SearchMatch], what: &str, check: impl Fn(&SearchMatch) -> bool) -> Outcome {
+    Ok(match matches.iter().find(|m| check(m)) {
+        Some(_) => (CheckStatus::Pass, format!("Found {}", what)),
+        None if matches.is_empty() => (CheckStatus::Fail, format!("Didn't find {}", what)),
+        None => (
+            CheckStatus::Fail,
+            format!(
+                "Found {}, but with the wrong text around it: \"{} [{}] {}\"",
+                what, matches[0].context_before, matches[0].matched_text, matches[0].context_after
+            ),
+        ),
+    })
+}
+
+fn search(path: &Path, query: &str, normalization: NormalizationOptions, use_ocr: bool) -> Result<Vec<SearchMatch>> {
+    search_single_pdf(SearchParams {
+        queries: vec![QueryItem::plain(query.to_string())],
+        directory: path.to_string_lossy().to_string(),
+        context_words: CONTEXT_WORDS,
+        normalization,
+        use_ocr,
+        ..Default::default()
+    })
+}
+
+// A page of lines in Helvetica, one block of lines per column starting at its x
+fn text_page(columns: &[(f32, &[&str])]) -> TestPage {
+    let mut operations = Vec::new();
+    for (x, lines) in columns {
+        operations.push(Operation::new("BT", vec![]));
+        operations.push(Operation::new("Tf", vec!["F1".into(), 12.into()]));
+        operations.push(Operation::new("TL", vec![14.into()]));
+        operations.push(Operation::new("Td", vec![(*x).into(), 700.into()]));
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                operations.push(Operation::new("T*", vec![]));
+            }
+            let bytes = Document::encode_text(Some("WinAnsiEncoding"), line);
+            operations.push(Operation::new("Tj", vec![Object::String(bytes, StringFormat::Literal)]));
+        }
+        operations.push(Operation::new("ET", vec![]));
+    }
+    TestPage { operations, image: None }
+}
+
+// A grayscale image of `word` in the bitmap letters, black on white
+fn word_image(word: &str) -> (usize, usize, Vec<u8>) {
+    let margin = 2;
+    let columns = margin * 2 + word.chars().count() * 6 - 1;
+    let rows = margin * 2 + 7;
+    let (width, height) = (columns * GLYPH_SCALE, rows * GLYPH_SCALE);
+    let mut pixels = vec![255u8; width * height];
+    for (i, letter) in word.chars().enumerate() {
+        let Some((_, bitmap)) = GLYPHS.iter().find(|(c, _)| *c == letter) else {
+            continue;
+        };
+        for (row, line) in bitmap.iter().enumerate() {
+            for (column, dot) in line.chars().enumerate() {
+                if dot != '#' {
+                    continue;
+                }
+                let (x0, y0) = ((margin + i * 6 + column) * GLYPH_SCALE, (margin + row) * GLYPH_SCALE);
+                for y in y0..y0 + GLYPH_SCALE {
+                    pixels[y * width + x0..y * width + x0 + GLYPH_SCALE].fill(0);
+                }
+            }
+        }
+    }
+    (width, height, pixels)
+}
+
+fn write_pdf(path: &Path, pages: Vec<TestPage>) -> Result<()> {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let helvetica = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+        "Encoding" => "WinAnsiEncoding",
+    });
+    // Adobe's standard Chinese font, which viewers substitute; lopdf reads the
+    // UCS-2 encoding's strings as UTF-16
+    let cid_font = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type0",
+        "BaseFont" => "STSong-Light",
+        "Encoding" => "UniGB-UCS2-H",
+        "DescendantFonts" => vec![dictionary! {
+            "Type" => "Font",
+            "Subtype" => "CIDFontType0",
+            "BaseFont" => "STSong-Light",
+            "CIDSystemInfo" => dictionary! {
+                "Registry" => Object::string_literal("Adobe"),
+                "Ordering" => Object::string_literal("GB1"),
+                "Supplement" => 2,
+            },
+        }.into()],
+    });
+
+    let mut kids = Vec::new();
+    for page in pages {
+        let content = Content {
+            operations: page.operations,
+        };
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode()?));
+        let mut resources = dictionary! {
+            "Font" => dictionary! { "F1" => helvetica, "F2" => cid_font },
+        };
+        if let Some(image) = page.image {
+            let image_id = doc.add_object(image);
+            resources.set("XObject", dictionary! { "Im1" => image_id });
+        }
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+            "Resources" => resources,
+        });
+        kids.push(page_id.into());
+    }
+    let count = kids.len() as i64;
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => count,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(path).context(format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
@@ -0,0 +1,325 @@
+use crate::export::SearchAudit;
+use crate::pdf_search::{self, ExcludedDocument, SearchMatch};
+use crate::spool::ResultSpool;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Search state owned by a single app window
+#[derive(Default)]
+struct WindowSearch {
+    search_id: u64,
+    cancel: Arc<AtomicBool>,
+    running: bool,
+    results: Vec<SearchMatch>,
+    // Holds the results instead, for searches that streamed them to disk
+    spool: Option<Arc<ResultSpool>>,
+    audit: Option<SearchAudit>,
+    // ID of the search that `results` came from
+    completed_id: u64,
+}
+
+impl WindowSearch {
+    // The completed search's matches as they can be read once the sessions' lock
+    // is released: a handle to the spool, or a copy of the returned matches
+    fn result_set(&self) -> ResultSet {
+        match self.spool {
+            Some(ref spool) => ResultSet::Spooled {
+                search_id: self.completed_id,
+                spool: spool.clone(),
+            },
+            None => ResultSet::Returned(self.results.clone()),
+        }
+    }
+}
+
+/// A completed search's matches, taken out of the sessions so reading them
+/// doesn't hold up other windows. A streamed search's stay in its spool, read
+/// as far as they're needed.
+pub enum ResultSet {
+    Spooled { search_id: u64, spool: Arc<ResultSpool> },
+    Returned(Vec<SearchMatch>),
+}
+
+impl ResultSet {
+    /// Every match, which reads a spool whole
+    pub fn all(self) -> Result<Vec<SearchMatch>> {
+        match self {
+            ResultSet::Spooled { search_id, spool } => spool.all(search_id),
+            ResultSet::Returned(matches) => Ok(matches),
+        }
+    }
+
+    /// The match with this ID
+    pub fn find(&self, match_id: &str) -> Result<Option<SearchMatch>> {
+        match self {
+            ResultSet::Spooled { search_id, spool } => {
+                let position = match_id.split_once(':').and_then(|(_, position)| position.parse().ok());
+                match position {
+                    Some(position) => spool.get(*search_id, position),
+                    None => Ok(None),
+                }
+            }
+            ResultSet::Returned(matches) => Ok(matches.iter().find(|m| m.match_id.as_deref() == Some(match_id)).cloned()),
+        }
+    }
+
+    /// The matches of the documents `file_paths`, in result order
+    pub fn documents(&self, file_paths: &[String]) -> Result<Vec<SearchMatch>> {
+        match self {
+            ResultSet::Spooled { search_id, spool } => spool.documents(*search_id, file_paths),
+            ResultSet::Returned(matches) => {
+                let wanted: HashSet<&str> = file_paths.iter().map(String::as_str).collect();
+                Ok(matches.iter().filter(|m| wanted.contains(m.item.file_path.as_str())).cloned().collect())
+            }
+        }
+    }
+
+    /// Number of documents with matches
+    pub fn document_count(&self) -> Result<usize> {
+        match self {
+            ResultSet::Spooled { spool, .. } => spool.document_count(),
+            ResultSet::Returned(matches) => Ok(matches.iter().map(|m| &m.item.file_path).collect::<HashSet<_>>().len()),
+        }
+    }
+}
+
+/// A slice of a window's results, for lists that load them as they scroll
+#[derive(Debug, Serialize)]
+pub struct ResultsPage {
+    pub search_id: u64,
+    // Matches in the whole result set
+    pub total: usize,
+    pub offset: usize,
+    pub matches: Vec<SearchMatch>,
+}
+
+/// The results of a window's last completed search, with how they were produced
+pub struct CompletedSearch {
+    pub search_id: u64,
+    pub results: ResultSet,
+    pub audit: SearchAudit,
+}
+
+/// Per-window search state, so each window can run, cancel, and keep the
+/// results of its own search independently of the others
+pub struct SearchSessions {
+    windows: Mutex<HashMap<String, WindowSearch>>,
+    next_id: AtomicU64,
+}
+
+impl Default for SearchSessions {
+    // IDs start from the launch time so they stay unique across launches,
+    // which lets persisted result sets be keyed by search ID
+    fn default() -> Self {
+        let launched_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        SearchSessions {
+            windows: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(launched_at),
+        }
+    }
+}
+
+impl SearchSessions {
+    /// Start a new search for a window, cancelling any search it already has running.
+    /// Returns the new search ID and the cancellation flag workers should check.
+    pub fn begin(&self, window_label: &str) -> (u64, Arc<AtomicBool>) {
+        let search_id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let mut windows = self.windows.lock().unwrap();
+        let state = windows.entry(window_label.to_string()).or_default();
+        state.cancel.store(true, Ordering::Relaxed);
+        state.search_id = search_id;
+        state.cancel = cancel.clone();
+        state.running = true;
+
+        (search_id, cancel)
+    }
+
+    /// Store the results of a finished search, along with how they were produced;
+    /// a streamed search's are in its spool. Results from a search that has since
+    /// been superseded in the same window are dropped.
+    pub fn finish(
+        &self,
+        window_label: &str,
+        search_id: u64,
+        results: &[SearchMatch],
+        spool: Option<ResultSpool>,
+        audit: SearchAudit,
+    ) {
+        let mut windows = self.windows.lock().unwrap();
+        if let Some(state) = windows.get_mut(window_label) {
+            if state.search_id == search_id {
+                state.running = false;
+                state.results = results.to_vec();
+                state.spool = spool.map(Arc::new);
+                state.audit = Some(audit);
+                state.completed_id = search_id;
+            }
+        }
+    }
+
+    /// Mark a search as ended without replacing the window's previous results
+    pub fn abandon(&self, window_label: &str, search_id: u64) {
+        let mut windows = self.windows.lock().unwrap();
+        if let Some(state) = windows.get_mut(window_label) {
+            if state.search_id == search_id {
+                state.running = false;
+            }
+        }
+    }
+
+    /// Request cancellation of the window's running search. Returns false if
+    /// there was nothing to cancel.
+    pub fn cancel(&self, window_label: &str) -> bool {
+        let windows = self.windows.lock().unwrap();
+        match windows.get(window_label) {
+            Some(state) if state.running => {
+                state.cancel.store(true, Ordering::Relaxed);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn results(&self, window_label: &str) -> Vec<SearchMatch> {
+        let windows = self.windows.lock().unwrap();
+        let Some(results) = windows.get(window_label).map(WindowSearch::result_set) else {
+            return Vec::new();
+        };
+        drop(windows);
+        results.all().unwrap_or_else(|e| {
+            eprintln!("Warning: Failed to read spooled results: {}", e);
+            Vec::new()
+        })
+    }
+
+    /// Up to `limit` of the window's results from `offset` on, read from disk for
+    /// streamed searches. None when the window has no completed search.
+    pub fn results_page(&self, window_label: &str, offset: usize, limit: usize) -> Option<ResultsPage> {
+        let windows = self.windows.lock().unwrap();
+        let state = windows.get(window_label).filter(|state| state.audit.is_some())?;
+        let search_id = state.completed_id;
+        let (total, matches) = match state.spool {
+            Some(ref spool) => {
+                let spool = spool.clone();
+                drop(windows);
+                let read = spool.len().and_then(|total| Ok((total, spool.page(search_id, offset, limit)?)));
+                read.unwrap_or_else(|e| {
+                    eprintln!("Warning: Failed to read spooled results: {}", e);
+                    (0, Vec::new())
+                })
+            }
+            None => (
+                state.results.len(),
+                state.results.iter().skip(offset).take(limit).cloned().collect(),
+            ),
+        };
+        Some(ResultsPage {
+            search_id,
+            total,
+            offset,
+            matches,
+        })
+    }
+
+    /// A match of the window's last completed search, and the search's audit,
+    /// without reading a streamed result set whole
+    pub fn find_match(&self, window_label: &str, match_id: &str) -> Option<(SearchMatch, SearchAudit)> {
+        let windows = self.windows.lock().unwrap();
+        let state = windows.get(window_label)?;
+        let audit = state.audit.clone()?;
+        if pdf_search::match_search_id(match_id).ok()? != state.completed_id {
+            return None;
+        }
+        let m = match state.spool {
+            Some(ref spool) => {
+                let position = match_id.split_once(':')?.1.parse().ok()?;
+                spool.get(state.completed_id, position).ok().flatten()?
+            }
+            None => state.results.iter().find(|m| m.match_id.as_deref() == Some(match_id))?.clone(),
+        };
+        Some((m, audit))
+    }
+
+    pub fn audit(&self, window_label: &str) -> Option<SearchAudit> {
+        let windows = self.windows.lock().unwrap();
+        windows.get(window_label).and_then(|state| state.audit.clone())
+    }
+
+    pub fn completed(&self, window_label: &str) -> Option<CompletedSearch> {
+        let windows = self.windows.lock().unwrap();
+        let state = windows.get(window_label)?;
+        Some(CompletedSearch {
+            search_id: state.completed_id,
+            results: state.result_set(),
+            audit: state.audit.clone()?,
+        })
+    }
+
+    /// Remove a document's matches from the window's last completed search and
+    /// record the exclusion in its audit trail. Returns the number of matches
+    /// removed, or None when the window has no completed search.
+    pub fn exclude_document(&self, window_label: &str, mut exclusion: ExcludedDocument) -> Option<usize> {
+        let mut windows = self.windows.lock().unwrap();
+        let state = windows.get_mut(window_label)?;
+        let audit = state.audit.as_mut()?;
+
+        let before = state.results.len();
+        state.results.retain(|m| m.item.file_path != exclusion.file_path);
+        exclusion.matches_removed = before - state.results.len();
+        if let Some(ref spool) = state.spool {
+            exclusion.matches_removed += spool.remove_document(&exclusion.file_path).unwrap_or_else(|e| {
+                eprintln!("Warning: Failed to update spooled results: {}", e);
+                0
+            });
+        }
+        let removed = exclusion.matches_removed;
+        audit.excluded_documents.retain(|d| d.file_path != exclusion.file_path);
+        audit.excluded_documents.push(exclusion);
+        Some(removed)
+    }
+
+    /// Rewrite the matches and audit trail of the window's last completed search,
+    /// streamed or not, as long as it's `search_id`. Returns false when it isn't.
+    pub fn update_completed(
+        &self,
+        window_label: &str,
+        search_id: u64,
+        change_match: impl Fn(&mut SearchMatch),
+        change_audit: impl FnOnce(&mut SearchAudit),
+    ) -> Result<bool> {
+        let mut windows = self.windows.lock().unwrap();
+        let Some(state) = windows.get_mut(window_label) else {
+            return Ok(false);
+        };
+        if state.completed_id != search_id {
+            return Ok(false);
+        }
+        let Some(audit) = state.audit.as_mut() else {
+            return Ok(false);
+        };
+        change_audit(audit);
+        state.results.iter_mut().for_each(&change_match);
+        if let Some(spool) = state.spool.clone() {
+            drop(windows);
+            spool.update(change_match)?;
+        }
+        Ok(true)
+    }
+
+    /// Drop a closed window's state, cancelling its search if one is still running
+    pub fn remove(&self, window_label: &str) {
+        let mut windows = self.windows.lock().unwrap();
+        if let Some(state) = windows.remove(window_label) {
+            state.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+}
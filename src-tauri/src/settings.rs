@@ -0,0 +1,141 @@
+use crate::compute::ComputeDevice;
+use crate::corpora::NamedCorpus;
+use crate::error::AppError;
+use crate::export::QuoteStyle;
+use crate::i18n;
+use crate::pdf_search::ExcludedDocument;
+use crate::problem_corpus::CollectionMode;
+use crate::walk;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// User settings persisted by the backend so commands can run without the
+/// frontend assembling every parameter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    pub default_directory: Option<String>,
+    pub default_context_words: usize,
+    pub zotero_path: Option<String>,
+    pub quick_search_limit: usize,
+    // Directory names skipped while walking for PDFs
+    pub skip_dirs: Vec<String>,
+    // Zotero web API credentials, for writing tags back to the library
+    pub zotero_user_id: Option<String>,
+    pub zotero_api_key: Option<String>,
+    // Documents left out of every search until restored
+    pub excluded_documents: Vec<ExcludedDocument>,
+    // Language of error messages, diagnostics and exports, e.g. "de" or "es"
+    pub language: String,
+    // Folders chosen in the app's dialogs, which commands may read and write
+    pub allowed_roots: Vec<String>,
+    // How long Zotero metadata is reused while the library is unchanged; 0 reads it for every command
+    pub zotero_cache_ttl_secs: u64,
+    // Reuse Zotero metadata, whatever its age, until the library's database changes
+    pub zotero_refresh_on_change_only: bool,
+    // Leave the items in Zotero's My Publications out of searches and metadata
+    pub zotero_skip_my_publications: bool,
+    // Tesseract executable used for OCR, by name on the PATH or as a full path
+    pub ocr_command: String,
+    // Tesseract language codes for OCR, e.g. "eng" or "deu+eng"
+    pub ocr_language: String,
+    // Where Tesseract runs: "auto", "cpu", or a GPU ("metal", "cuda") through an
+    // OpenCL build; see `compute`
+    pub ocr_device: ComputeDevice,
+    // Pages of a document read at once, each by its own Tesseract process
+    pub ocr_batch_size: usize,
+    // How matches are set off in exported context: ellipses, quotes, dashes, markup
+    pub export_quote_style: QuoteStyle,
+    // Folder of CSL locale files (locales-xx-XX.xml) adding or overriding the
+    // languages citations are localized in; see `csl_locale`
+    pub csl_locales_dir: Option<String>,
+    // Pandoc executable for DOCX and ODT exports, by name on the PATH or as a full path
+    pub pandoc_command: String,
+    // Word or LibreOffice document whose styles those exports take
+    pub pandoc_reference_doc: Option<String>,
+    // Poppler's pdftoppm, which renders match images for exports
+    pub pdftoppm_command: String,
+    // Collect PDFs whose text extraction fails or comes out garbled, for bug
+    // reports: "off", "reference", or "copy"; see `problem_corpus`
+    pub problem_corpus: CollectionMode,
+    // Folders grouped under a name, which a search can read several of at once; see `corpora`
+    pub corpora: Vec<NamedCorpus>,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        AppSettings {
+            default_directory: None,
+            default_context_words: 100,
+            zotero_path: None,
+            quick_search_limit: 50,
+            skip_dirs: walk::default_skip_dirs(),
+            zotero_user_id: None,
+            zotero_api_key: None,
+            excluded_documents: Vec::new(),
+            language: i18n::DEFAULT_LANGUAGE.to_string(),
+            allowed_roots: Vec::new(),
+            zotero_cache_ttl_secs: 300,
+            zotero_refresh_on_change_only: false,
+            zotero_skip_my_publications: false,
+            ocr_command: "tesseract".to_string(),
+            ocr_language: "eng".to_string(),
+            ocr_device: ComputeDevice::Auto,
+            ocr_batch_size: 1,
+            export_quote_style: QuoteStyle::default(),
+            csl_locales_dir: None,
+            pandoc_command: "pandoc".to_string(),
+            pandoc_reference_doc: None,
+            pdftoppm_command: "pdftoppm".to_string(),
+            problem_corpus: CollectionMode::Off,
+            corpora: Vec::new(),
+        }
+    }
+}
+
+/// Reject external tools that aren't a program name to find on the PATH or an
+/// existing file, so the settings can't name a command line to run
+pub fn check_tool_commands(settings: &AppSettings) -> Result<()> {
+    let tools = [
+        ("OCR", &settings.ocr_command),
+        ("Pandoc", &settings.pandoc_command),
+        ("pdftoppm", &settings.pdftoppm_command),
+    ];
+    for (tool, command) in tools {
+        if !is_program_name(command) && !Path::new(command).is_file() {
+            return Err(AppError::invalid_input(format!("{} command is not a program or file: {}", tool, command)).into());
+        }
+    }
+    Ok(())
+}
+
+// A bare name like "tesseract", without a path, arguments or a leading option dash
+fn is_program_name(command: &str) -> bool {
+    !command.is_empty()
+        && !command.starts_with('-')
+        && !command.chars().any(|c| c.is_whitespace() || matches!(c, '/' | '\\' | ':'))
+}
+
+pub fn load_settings(config_dir: &Path) -> Result<AppSettings> {
+    let path = config_dir.join(SETTINGS_FILE);
+    if !path.exists() {
+        return Ok(AppSettings::default());
+    }
+
+    let contents = fs::read_to_string(&path)
+        .context(format!("Failed to read settings: {}", path.display()))?;
+    serde_json::from_str(&contents)
+        .context(format!("Failed to parse settings: {}", path.display()))
+}
+
+pub fn save_settings(config_dir: &Path, settings: &AppSettings) -> Result<()> {
+    fs::create_dir_all(config_dir)
+        .context(format!("Failed to create settings directory: {}", config_dir.display()))?;
+    let path = config_dir.join(SETTINGS_FILE);
+    fs::write(&path, serde_json::to_string_pretty(settings)?)
+        .context(format!("Failed to write settings: {}", path.display()))
+}
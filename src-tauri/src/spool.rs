@@ -0,0 +1,248 @@
+use crate::pdf_search::{self, SearchMatch};
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+// Matches of a very large search, kept on disk as the files finish instead of in
+// memory, and read back a page at a time. Unlike the memory budget's spill, which
+// only parks matches until the search returns them all, a spool outlives the
+// search: it stands in for the window's result set until the next search.
+
+static NEXT_SPOOL: AtomicU64 = AtomicU64::new(0);
+
+// Matches rewritten per read by `update`
+const UPDATE_BATCH: usize = 1000;
+
+/// Temporary SQLite store of a search's matches. Files' matches are appended as
+/// they finish, then `seal` puts them in the order the search would have
+/// returned them, which numbers them for match IDs. The file is deleted on drop.
+#[derive(Debug)]
+pub struct ResultSpool {
+    conn: Mutex<Connection>,
+    path: PathBuf,
+}
+
+impl ResultSpool {
+    pub fn create() -> Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "pdf-search-spool-{}-{}.sqlite",
+            std::process::id(),
+            NEXT_SPOOL.fetch_add(1, Ordering::Relaxed)
+        ));
+        let conn = Connection::open(&path)
+            .context(format!("Failed to create result spool: {}", path.display()))?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = OFF;
+             PRAGMA synchronous = OFF;
+             CREATE TABLE matches (
+                 file_index INTEGER NOT NULL,
+                 position INTEGER NOT NULL,
+                 file_path TEXT NOT NULL,
+                 query TEXT,
+                 json TEXT NOT NULL,
+                 PRIMARY KEY (file_index, position)
+             );
+             CREATE TABLE results (
+                 -- The match's position in the results, from 1
+                 seq INTEGER PRIMARY KEY,
+                 file_path TEXT NOT NULL,
+                 query TEXT,
+                 json TEXT NOT NULL
+             );
+             CREATE INDEX results_file_path ON results (file_path);",
+        )?;
+        Ok(ResultSpool {
+            conn: Mutex::new(conn),
+            path,
+        })
+    }
+
+    /// Add one file's matches; `file_index` is the file's place in the search
+    pub fn append(&self, file_index: usize, matches: &[SearchMatch]) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT OR REPLACE INTO matches (file_index, position, file_path, query, json) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for (position, m) in matches.iter().enumerate() {
+                insert.execute(params![
+                    file_index as i64,
+                    position as i64,
+                    m.item.file_path,
+                    m.query,
+                    serde_json::to_string(m)?
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Number the matches in result order: documents in `document_order` first,
+    /// as a score-sorted search lists them, then the rest in file order. At most
+    /// `max` are kept.
+    pub fn seal(&self, document_order: &[String], max: Option<usize>) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute("CREATE TEMP TABLE document_order (file_path TEXT PRIMARY KEY, rank INTEGER NOT NULL)", [])?;
+        {
+            let mut insert = tx.prepare("INSERT OR REPLACE INTO document_order (file_path, rank) VALUES (?1, ?2)")?;
+            for (rank, file_path) in document_order.iter().enumerate() {
+                insert.execute(params![file_path, rank as i64])?;
+            }
+        }
+        // Rows are numbered as they're inserted
+        tx.execute(
+            "INSERT INTO results (file_path, query, json)
+             SELECT m.file_path, m.query, m.json
+             FROM matches m LEFT JOIN document_order d ON d.file_path = m.file_path
+             ORDER BY d.rank IS NULL, d.rank, m.file_index, m.position LIMIT ?1",
+            [max.map_or(-1, |max| max as i64)],
+        )?;
+        tx.execute_batch("DROP TABLE document_order; DELETE FROM matches;")?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> Result<usize> {
+        let count: i64 = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM results", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    /// Up to `limit` matches from the `offset`th on, with IDs under `search_id`
+    pub fn page(&self, search_id: u64, offset: usize, limit: usize) -> Result<Vec<SearchMatch>> {
+        let conn = self.conn.lock().unwrap();
+        let mut select = conn.prepare("SELECT seq, json FROM results ORDER BY seq LIMIT ?1 OFFSET ?2")?;
+        let rows = select.query_map(params![limit.min(i64::MAX as usize) as i64, offset as i64], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut matches = Vec::new();
+        for row in rows {
+            let (seq, json) = row?;
+            matches.push(decode(search_id, seq, &json)?);
+        }
+        Ok(matches)
+    }
+
+    /// The matches of the documents `file_paths`, in result order, reading only their rows
+    pub fn documents(&self, search_id: u64, file_paths: &[String]) -> Result<Vec<SearchMatch>> {
+        let conn = self.conn.lock().unwrap();
+        let mut select = conn.prepare("SELECT seq, json FROM results WHERE file_path = ?")?;
+        let mut rows = Vec::new();
+        for file_path in file_paths {
+            let found = select.query_map([file_path], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+            rows.extend(found.collect::<rusqlite::Result<Vec<_>>>()?);
+        }
+        rows.sort_unstable_by_key(|(seq, _)| *seq);
+        rows.dedup_by_key(|(seq, _)| *seq);
+        rows.iter().map(|(seq, json)| decode(search_id, *seq, json)).collect()
+    }
+
+    /// Number of documents with matches
+    pub fn document_count(&self) -> Result<usize> {
+        let count: i64 = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT COUNT(DISTINCT file_path) FROM results", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    /// Every match, for the commands that work on a whole result set
+    pub fn all(&self, search_id: u64) -> Result<Vec<SearchMatch>> {
+        self.page(search_id, 0, usize::MAX)
+    }
+
+    /// The match at this position of the results, from 0 as in match IDs
+    pub fn get(&self, search_id: u64, position: usize) -> Result<Option<SearchMatch>> {
+        let conn = self.conn.lock().unwrap();
+        let json: Option<String> = conn
+            .query_row("SELECT json FROM results WHERE seq = ?", [position as i64 + 1], |row| row.get(0))
+            .optional()?;
+        json.map(|json| decode(search_id, position as i64 + 1, &json)).transpose()
+    }
+
+    /// The documents with matches
+    pub fn file_paths(&self) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut select = conn.prepare("SELECT DISTINCT file_path FROM results")?;
+        let paths = select.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+        Ok(paths)
+    }
+
+    /// Matches per query
+    pub fn query_counts(&self) -> Result<HashMap<String, usize>> {
+        let conn = self.conn.lock().unwrap();
+        let mut select = conn.prepare("SELECT query, COUNT(*) FROM results WHERE query IS NOT NULL GROUP BY query")?;
+        let counts = select
+            .query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(counts)
+    }
+
+    /// Rewrite every match in place, keeping their positions. Matches are read
+    /// `UPDATE_BATCH` at a time, so the result set is never in memory whole.
+    pub fn update(&self, mut change: impl FnMut(&mut SearchMatch)) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        {
+            let mut select = tx.prepare("SELECT seq, json FROM results WHERE seq > ?1 ORDER BY seq LIMIT ?2")?;
+            let mut update = tx.prepare("UPDATE results SET query = ?2, json = ?3 WHERE seq = ?1")?;
+            let mut last_seq = 0;
+            loop {
+                let batch = select
+                    .query_map(params![last_seq, UPDATE_BATCH as i64], |row| {
+                        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                let Some(&(last, _)) = batch.last() else {
+                    break;
+                };
+                for (seq, json) in batch {
+                    let mut m: SearchMatch = serde_json::from_str(&json)?;
+                    change(&mut m);
+                    update.execute(params![seq, m.query, serde_json::to_string(&m)?])?;
+                }
+                last_seq = last;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Drop a document's matches, keeping the others' positions; returns how
+    /// many there were
+    pub fn remove_document(&self, file_path: &str) -> Result<usize> {
+        let removed = self
+            .conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM results WHERE file_path = ?", [file_path])?;
+        Ok(removed)
+    }
+}
+
+// A stored match with its ID; `seq` is its position in the results, from 1
+fn decode(search_id: u64, seq: i64, json: &str) -> Result<SearchMatch> {
+    let mut m: SearchMatch = serde_json::from_str(json)?;
+    m.match_id = Some(pdf_search::match_id(search_id, seq as usize - 1));
+    Ok(m)
+}
+
+impl Drop for ResultSpool {
+    fn drop(&mut self) {
+        // Close the connection first; Windows won't delete an open file
+        if let (Ok(conn), Ok(placeholder)) = (self.conn.get_mut(), Connection::open_in_memory()) {
+            drop(std::mem::replace(conn, placeholder));
+        }
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
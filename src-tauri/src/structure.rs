@@ -0,0 +1,282 @@
+use crate::layout::{GlyphBox, MarkedRun, PageLayout};
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+// Tagged PDFs carry a structure tree: elements such as paragraphs, headings and
+// list items, in reading order, each pointing at the marked-content sequences of
+// the page content that hold its text. For those PDFs the page text is put
+// together from the tree instead of in content stream order, which for
+// multi-column layouts, sidebars and footnotes is often not the order it's read
+// in, and the elements' types give the text's blocks and headings.
+
+// Guard against malformed trees that reference themselves
+const MAX_DEPTH: usize = 64;
+
+// Structure types whose content is read as one block. An element of one of these
+// inside another (a paragraph in a list item) starts a block of its own.
+const BLOCK_ROLES: &[&str] = &[
+    "P", "H", "H1", "H2", "H3", "H4", "H5", "H6", "Title", "LI", "TD", "TH", "Caption", "BlockQuote", "Note",
+    "TOCI", "Figure", "Formula", "Code",
+];
+
+/// A block of a tagged page's text and the structure type it was tagged with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Block {
+    // Byte range of the block in the page text
+    pub range: Range<usize>,
+    // Standard structure type, after the document's role map: "P", "H2", "LI", ...
+    pub role: String,
+}
+
+impl Block {
+    pub fn is_heading(&self) -> bool {
+        self.role == "H" || self.role.strip_prefix('H').is_some_and(|level| level.parse::<u8>().is_ok())
+    }
+}
+
+// One block-level element's content on one page, as marked-content IDs in reading order
+#[derive(Debug)]
+struct TaggedBlock {
+    role: String,
+    mcids: Vec<i64>,
+}
+
+/// A page's text in the order its structure tree reads it
+pub struct TaggedLayout {
+    pub layout: PageLayout,
+    pub blocks: Vec<Block>,
+}
+
+/// The content of a tagged PDF's structure tree, by page
+pub struct StructureTree {
+    pages: HashMap<ObjectId, Vec<TaggedBlock>>,
+}
+
+impl StructureTree {
+    /// The document's structure tree, or None when it isn't tagged
+    pub fn read(doc: &Document) -> Option<StructureTree> {
+        let catalog = doc.catalog().ok()?;
+        let (_, root) = doc.dereference(catalog.get(b"StructTreeRoot").ok()?).ok()?;
+        let root = root.as_dict().ok()?;
+        let role_map = root
+            .get(b"RoleMap")
+            .ok()
+            .and_then(|role_map| doc.dereference(role_map).ok())
+            .and_then(|(_, role_map)| role_map.as_dict().ok());
+
+        let mut walker = Walker {
+            doc,
+            role_map,
+            pages: HashMap::new(),
+            continuing: None,
+            visited: HashSet::new(),
+        };
+        walker.kids(root.get(b"K").ok()?, None, None, 0);
+        let tree = StructureTree { pages: walker.pages };
+        (!tree.pages.is_empty()).then_some(tree)
+    }
+
+    /// The page's text rearranged into the tree's reading order, one block per
+    /// line. Artifacts such as running heads are left out, and text the tree
+    /// doesn't reach follows the blocks in content order. None when the tree
+    /// has nothing for the page, which is then read as it's drawn.
+    pub fn arrange(&self, page_id: ObjectId, layout: &PageLayout) -> Option<TaggedLayout> {
+        let tagged = self.pages.get(&page_id)?;
+
+        let mut runs_by_mcid: HashMap<i64, Vec<&MarkedRun>> = HashMap::new();
+        for run in layout.marked.iter().filter(|run| !run.artifact) {
+            if let Some(mcid) = run.mcid {
+                runs_by_mcid.entry(mcid).or_default().push(run);
+            }
+        }
+
+        let mut arranged = Arrangement::new(layout);
+        let mut used = HashSet::new();
+        for block in tagged {
+            let start = arranged.layout.text.len();
+            for mcid in &block.mcids {
+                for run in runs_by_mcid.get(mcid).into_iter().flatten() {
+                    arranged.copy(&run.range);
+                }
+                used.insert(*mcid);
+            }
+            arranged.end_block(start, Some(&block.role));
+        }
+        if arranged.blocks.is_empty() {
+            return None;
+        }
+
+        for run in &layout.marked {
+            if run.artifact || run.mcid.is_some_and(|mcid| used.contains(&mcid)) {
+                continue;
+            }
+            let start = arranged.layout.text.len();
+            arranged.copy(&run.range);
+            arranged.end_block(start, None);
+        }
+
+        Some(TaggedLayout {
+            layout: arranged.layout,
+            blocks: arranged.blocks,
+        })
+    }
+}
+
+// Page text being copied run by run into a new order, along with its glyph boxes
+struct Arrangement<'a> {
+    source: &'a PageLayout,
+    layout: PageLayout,
+    blocks: Vec<Block>,
+}
+
+impl<'a> Arrangement<'a> {
+    fn new(source: &'a PageLayout) -> Self {
+        Arrangement {
+            source,
+            layout: PageLayout {
+                text: String::new(),
+                glyphs: Vec::new(),
+                marked: Vec::new(),
+            },
+            blocks: Vec::new(),
+        }
+    }
+
+    fn copy(&mut self, range: &Range<usize>) {
+        let offset = self.layout.text.len();
+        self.layout.text.push_str(&self.source.text[range.clone()]);
+        // Glyphs are recorded in text order
+        let glyphs = &self.source.glyphs;
+        let first = glyphs.partition_point(|glyph| glyph.range.start < range.start);
+        for glyph in glyphs[first..].iter().take_while(|glyph| glyph.range.end <= range.end) {
+            self.layout.glyphs.push(GlyphBox {
+                range: glyph.range.start - range.start + offset..glyph.range.end - range.start + offset,
+                rect: glyph.rect,
+            });
+        }
+    }
+
+    // Finish the text copied since `start` as a block of `role`, or as untagged
+    // text when there's none, and start the next on a new line
+    fn end_block(&mut self, start: usize, role: Option<&str>) {
+        let text = &self.layout.text[start..];
+        if text.trim().is_empty() {
+            self.layout.text.truncate(start);
+            self.layout.glyphs.retain(|glyph| glyph.range.end <= start);
+            return;
+        }
+        let trimmed_end = start + text.trim_end().len();
+        let leading = text.len() - text.trim_start().len();
+        if let Some(role) = role {
+            self.blocks.push(Block {
+                range: start + leading..trimmed_end,
+                role: role.to_string(),
+            });
+        }
+        if !self.layout.text.ends_with('\n') {
+            self.layout.text.push('\n');
+        }
+    }
+}
+
+struct Walker<'a> {
+    doc: &'a Document,
+    role_map: Option<&'a Dictionary>,
+    pages: HashMap<ObjectId, Vec<TaggedBlock>>,
+    // The block element content is currently added to, until another block starts or ends
+    continuing: Option<(ObjectId, usize)>,
+    visited: HashSet<ObjectId>,
+}
+
+impl<'a> Walker<'a> {
+    // Walk an element's /K: a structure element, a marked-content ID or
+    // reference, an object reference, or an array of those
+    fn kids(&mut self, kids: &'a Object, page: Option<ObjectId>, role: Option<&str>, depth: usize) {
+        if depth > MAX_DEPTH {
+            return;
+        }
+        match kids {
+            Object::Integer(mcid) => self.content(page, *mcid, role),
+            Object::Array(array) => {
+                for kid in array {
+                    self.kids(kid, page, role, depth + 1);
+                }
+            }
+            Object::Reference(id) if self.visited.insert(*id) => {
+                if let Ok(object) = self.doc.get_object(*id) {
+                    self.kids(object, page, role, depth + 1);
+                }
+            }
+            // /Type is optional on all three kinds of dictionary, so they're told apart by their keys
+            Object::Dictionary(dict) if dict.has(b"MCID") => {
+                let page = dict.get(b"Pg").and_then(Object::as_reference).ok().or(page);
+                // Content in form XObjects (/Stm) isn't in the page text
+                if dict.has(b"Stm") {
+                    return;
+                }
+                if let Ok(mcid) = dict.get(b"MCID").and_then(Object::as_i64) {
+                    self.content(page, mcid, role);
+                }
+            }
+            Object::Dictionary(dict) if dict.has(b"S") => self.element(dict, page, role, depth),
+            // Annotations and other objects (/OBJR) have no text in the content stream
+            _ => {}
+        }
+    }
+
+    fn element(&mut self, element: &'a Dictionary, page: Option<ObjectId>, role: Option<&str>, depth: usize) {
+        let Ok(kids) = element.get(b"K") else {
+            return;
+        };
+        let page = element.get(b"Pg").and_then(Object::as_reference).ok().or(page);
+        let own_role = element
+            .get(b"S")
+            .and_then(Object::as_name_str)
+            .ok()
+            .map(|name| self.standard_role(name))
+            .filter(|name| BLOCK_ROLES.iter().any(|role| role == name));
+
+        match own_role {
+            Some(own_role) => {
+                self.continuing = None;
+                self.kids(kids, page, Some(own_role), depth + 1);
+                self.continuing = None;
+            }
+            None => self.kids(kids, page, role, depth + 1),
+        }
+    }
+
+    // Follow the role map from a custom structure type to a standard one
+    fn standard_role(&self, mut name: &'a str) -> &'a str {
+        for _ in 0..8 {
+            let mapped = self
+                .role_map
+                .and_then(|role_map| role_map.get(name.as_bytes()).and_then(Object::as_name_str).ok());
+            match mapped {
+                Some(mapped) if mapped != name => name = mapped,
+                _ => break,
+            }
+        }
+        name
+    }
+
+    fn content(&mut self, page: Option<ObjectId>, mcid: i64, role: Option<&str>) {
+        let Some(page) = page else {
+            return;
+        };
+        let blocks = self.pages.entry(page).or_default();
+        match self.continuing {
+            // A block split across pages continues as a new block on the next
+            Some((continuing_page, index)) if continuing_page == page => blocks[index].mcids.push(mcid),
+            _ => {
+                blocks.push(TaggedBlock {
+                    role: role.unwrap_or("P").to_string(),
+                    mcids: vec![mcid],
+                });
+                self.continuing = Some((page, blocks.len() - 1));
+            }
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::error::AppError;
+use crate::index::{Index, SubsetRow};
+use crate::pdf_search::SearchMatch;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+// The documents a search matched can be kept as a named corpus subset and given
+// as a later search's scope (`SearchParams.subset`), so analyses chain: within
+// the documents that mention X, how do they discuss Y? A subset is a fixed list
+// of files; documents added to the corpus afterwards aren't in it.
+
+/// A named set of documents taken from a result set
+#[derive(Debug, Clone, Serialize)]
+pub struct CorpusSubset {
+    pub name: String,
+    pub created: u64,
+    // The search whose results it was made from; `load_results` opens them once saved
+    pub search_id: u64,
+    // That search's queries, to show what the subset stands for
+    pub queries: Vec<String>,
+    pub documents: usize,
+}
+
+impl TryFrom<SubsetRow> for CorpusSubset {
+    type Error = anyhow::Error;
+
+    fn try_from(row: SubsetRow) -> Result<Self> {
+        Ok(CorpusSubset {
+            name: row.name,
+            created: row.created,
+            search_id: row.search_id,
+            queries: serde_json::from_str(&row.queries)?,
+            documents: row.documents,
+        })
+    }
+}
+
+/// Store the documents of `matches` as the subset `name`, replacing any subset
+/// of that name
+pub fn register_subset(
+    index: &Index,
+    name: &str,
+    search_id: u64,
+    queries: &[String],
+    matches: &[SearchMatch],
+) -> Result<CorpusSubset> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::invalid_input("A corpus subset needs a name").into());
+    }
+    let paths: BTreeSet<&str> = matches.iter().map(|m| m.item.file_path.as_str()).collect();
+    if paths.is_empty() {
+        return Err(AppError::invalid_input("The result set has no documents to keep as a subset").into());
+    }
+    let paths: Vec<String> = paths.into_iter().map(str::to_string).collect();
+    index.save_subset(name, search_id, &serde_json::to_string(queries)?, &paths)?;
+    list_subsets(index)?
+        .into_iter()
+        .find(|subset| subset.name == name)
+        .ok_or_else(|| anyhow::anyhow!("Corpus subset {} wasn't saved", name))
+}
+
+pub fn list_subsets(index: &Index) -> Result<Vec<CorpusSubset>> {
+    index.subsets()?.into_iter().map(CorpusSubset::try_from).collect()
+}
+
+fn subset_paths(index: &Index, name: &str) -> Result<HashSet<String>> {
+    index
+        .subset_paths(name)?
+        .ok_or_else(|| AppError::invalid_input(format!("No corpus subset named {}", name)).into())
+}
+
+/// The subset's documents under `root` that are still there, in path order,
+/// which a search of `root` reads instead of walking it
+pub fn files_under(index: &Index, name: &str, root: &Path) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = subset_paths(index, name)?
+        .into_iter()
+        .map(PathBuf::from)
+        .filter(|path| path.starts_with(root) && path.is_file())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Drop the files outside the subset
+pub fn filter_files(index: &Index, files: Vec<PathBuf>, name: &str) -> Result<Vec<PathBuf>> {
+    let paths = subset_paths(index, name)?;
+    Ok(files
+        .into_iter()
+        .filter(|path| paths.contains(path.to_string_lossy().as_ref()))
+        .collect())
+}
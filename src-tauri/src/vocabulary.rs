@@ -0,0 +1,320 @@
+use crate::corpus;
+use crate::index::Index;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+// Suggestions only come from the text the index has cached, i.e. files that
+// have been searched at least once; nothing here opens a PDF.
+
+const STOPWORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "an", "and", "any", "are", "as", "at", "be", "been", "but", "by",
+    "can", "could", "did", "do", "does", "each", "et", "for", "from", "had", "has", "have", "he", "her",
+    "his", "how", "i", "if", "in", "into", "is", "it", "its", "may", "more", "most", "no", "not", "of",
+    "on", "one", "only", "or", "other", "our", "she", "should", "so", "some", "such", "than", "that",
+    "the", "their", "them", "then", "there", "these", "they", "this", "those", "through", "to", "two",
+    "up", "was", "we", "were", "what", "when", "where", "which", "while", "who", "will", "with", "would",
+    "you", "your",
+];
+
+/// Queries found in at most this many documents get suggestions
+pub const LOW_YIELD_DOCUMENTS: usize = 2;
+// N-grams containing a query word that are worth suggesting
+const MAX_PHRASES: usize = 5;
+// Similar spellings and variants suggested per query word
+const MAX_SIMILAR: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionKind {
+    // A corpus term within a small edit distance, e.g. a spelling variant
+    SimilarTerm,
+    // A frequent phrase containing a query word
+    Phrase,
+    // The same word hyphenated, unhyphenated or split in two
+    Hyphenation,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuerySuggestion {
+    pub query: String,
+    pub suggestion: String,
+    pub kind: SuggestionKind,
+    pub occurrences: usize,
+    pub documents: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TermCounts {
+    pub occurrences: usize,
+    pub documents: usize,
+}
+
+/// Lowercased words of a page, with line-break hyphenation ("informa-\ntion")
+/// kept as a hyphenated word and other hyphens and apostrophes inside words kept
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c.is_alphanumeric() {
+            word.extend(c.to_lowercase());
+            continue;
+        }
+        if (c == '-' || c == '\'' || c == '’') && !word.is_empty() {
+            // A hyphen at the end of a line joins the word's two halves
+            if c == '-' {
+                while chars.next_if(|next| *next == '\n' || *next == '\r').is_some() {}
+            }
+            if chars.peek().is_some_and(|next| next.is_alphanumeric()) {
+                word.push(if c == '’' { '\'' } else { c });
+                continue;
+            }
+        }
+        if !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+pub fn is_stopword(word: &str) -> bool {
+    STOPWORDS.binary_search(&word).is_ok()
+}
+
+/// Call `visit` with the words of each cached document under `root`, all pages together
+pub fn for_each_document(index: &Index, root: &Path, mut visit: impl FnMut(&[String])) -> Result<usize> {
+    let mut documents = 0;
+    for path in corpus::archived_files(index, root)? {
+        let Some(cached) = index.cached_text(&path.to_string_lossy())? else {
+            continue;
+        };
+        let words: Vec<String> = cached.pages.iter().flat_map(|page| tokenize(&page.text)).collect();
+        visit(&words);
+        documents += 1;
+    }
+    Ok(documents)
+}
+
+// Add one document's occurrences of each key to `counts`
+fn count_document<'a>(counts: &mut HashMap<String, TermCounts>, keys: impl Iterator<Item = &'a str>) {
+    let mut seen = HashSet::new();
+    for key in keys {
+        let entry = counts.entry(key.to_string()).or_default();
+        entry.occurrences += 1;
+        if seen.insert(key) {
+            entry.documents += 1;
+        }
+    }
+}
+
+/// Propose alternatives for queries that found little: corpus terms a small edit
+/// away from a query word, frequent phrases containing one, and hyphenation
+/// variants of it, all drawn from the cached text under `root`
+pub fn suggest_queries(index: &Index, root: &Path, queries: &[String]) -> Result<Vec<QuerySuggestion>> {
+    let query_words: HashSet<String> = queries.iter().flat_map(|query| tokenize(query)).collect();
+    let unhyphenated_words: HashSet<String> = query_words.iter().map(|word| word.replace('-', "")).collect();
+
+    let mut terms: HashMap<String, TermCounts> = HashMap::new();
+    let mut phrases: HashMap<String, TermCounts> = HashMap::new();
+    for_each_document(index, root, |words| {
+        count_document(&mut terms, words.iter().map(String::as_str));
+
+        // Bigrams and trigrams around query words, without leading or trailing
+        // stopwords, and query words split in two ("e mail")
+        let mut found = Vec::new();
+        for n in 2..=3 {
+            for gram in words.windows(n) {
+                let useful = gram.iter().any(|word| query_words.contains(word))
+                    && !is_stopword(&gram[0])
+                    && !is_stopword(&gram[n - 1]);
+                let split_word = n == 2 && unhyphenated_words.contains(&gram.concat().replace('-', ""));
+                if useful || split_word {
+                    found.push(gram.join(" "));
+                }
+            }
+        }
+        count_document(&mut phrases, found.iter().map(String::as_str));
+    })?;
+
+    let mut suggestions = Vec::new();
+    for query in queries {
+        let words = tokenize(query);
+        for word in &words {
+            let replace = |term: &str| {
+                if words.len() == 1 {
+                    term.to_string()
+                } else {
+                    words.iter().map(|w| if w == word { term } else { w.as_str() }).collect::<Vec<_>>().join(" ")
+                }
+            };
+            let mut word_suggestions = Vec::new();
+
+            // "e-mail", "email" and "e mail" are the same word
+            let unhyphenated = word.replace('-', "");
+            let mut variants: Vec<(&String, &TermCounts)> = terms
+                .iter()
+                .chain(phrases.iter())
+                .filter(|(term, _)| *term != word && term.replace(['-', ' '], "") == unhyphenated)
+                .collect();
+            variants.sort_by(|a, b| b.1.documents.cmp(&a.1.documents).then_with(|| a.0.cmp(b.0)));
+            for (term, counts) in variants.into_iter().take(MAX_SIMILAR) {
+                word_suggestions.push((SuggestionKind::Hyphenation, replace(term), *counts));
+            }
+
+            let max_distance = if word.chars().count() <= 4 { 1 } else { 2 };
+            let mut similar: Vec<(&String, &TermCounts)> = terms
+                .iter()
+                .filter(|(term, _)| *term != word && term.replace('-', "") != unhyphenated && !is_stopword(term))
+                .filter(|(term, _)| within_distance(word, term, max_distance))
+                .collect();
+            similar.sort_by(|a, b| b.1.documents.cmp(&a.1.documents).then_with(|| a.0.cmp(b.0)));
+            for (term, counts) in similar.into_iter().take(MAX_SIMILAR) {
+                word_suggestions.push((SuggestionKind::SimilarTerm, replace(term), *counts));
+            }
+
+            suggestions.extend(word_suggestions.into_iter().map(|(kind, suggestion, counts)| QuerySuggestion {
+                query: query.clone(),
+                suggestion,
+                kind,
+                occurrences: counts.occurrences,
+                documents: counts.documents,
+            }));
+        }
+
+        let mut query_phrases: Vec<(&String, &TermCounts)> = phrases
+            .iter()
+            .filter(|(phrase, _)| phrase.split(' ').any(|w| words.contains(&w.to_string())))
+            .filter(|(phrase, _)| **phrase != words.join(" "))
+            // A phrase seen once is noise
+            .filter(|(_, counts)| counts.occurrences > 1)
+            .collect();
+        query_phrases.sort_by(|a, b| b.1.occurrences.cmp(&a.1.occurrences).then_with(|| a.0.cmp(b.0)));
+        suggestions.extend(query_phrases.into_iter().take(MAX_PHRASES).map(|(phrase, counts)| QuerySuggestion {
+            query: query.clone(),
+            suggestion: phrase.clone(),
+            kind: SuggestionKind::Phrase,
+            occurrences: counts.occurrences,
+            documents: counts.documents,
+        }));
+    }
+
+    Ok(suggestions)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VocabularyEntry {
+    pub term: String,
+    pub occurrences: usize,
+    pub documents: usize,
+}
+
+/// The most frequent words and word pairs of a corpus
+#[derive(Debug, Default, Serialize)]
+pub struct CorpusVocabulary {
+    // Cached documents the counts come from
+    pub documents: usize,
+    pub terms: Vec<VocabularyEntry>,
+    pub bigrams: Vec<VocabularyEntry>,
+}
+
+// Stopwords, numbers and single letters say nothing about a corpus
+fn is_content_word(word: &str) -> bool {
+    word.chars().count() > 1 && !word.chars().all(|c| c.is_numeric()) && !is_stopword(word)
+}
+
+/// Count the words and bigrams in the cached text under `root` and keep the
+/// `top_n` most frequent of each
+pub fn corpus_vocabulary(index: &Index, root: &Path, top_n: usize) -> Result<CorpusVocabulary> {
+    let mut terms: HashMap<String, TermCounts> = HashMap::new();
+    let mut bigrams: HashMap<String, TermCounts> = HashMap::new();
+    let documents = for_each_document(index, root, |words| {
+        count_document(&mut terms, words.iter().map(String::as_str).filter(|word| is_content_word(word)));
+
+        let pairs: Vec<String> = words
+            .windows(2)
+            .filter(|pair| is_content_word(&pair[0]) && is_content_word(&pair[1]))
+            .map(|pair| pair.join(" "))
+            .collect();
+        count_document(&mut bigrams, pairs.iter().map(String::as_str));
+    })?;
+
+    Ok(CorpusVocabulary {
+        documents,
+        terms: most_frequent(terms, top_n),
+        bigrams: most_frequent(bigrams, top_n),
+    })
+}
+
+fn most_frequent(counts: HashMap<String, TermCounts>, top_n: usize) -> Vec<VocabularyEntry> {
+    let mut entries: Vec<VocabularyEntry> = counts
+        .into_iter()
+        .map(|(term, counts)| VocabularyEntry {
+            term,
+            occurrences: counts.occurrences,
+            documents: counts.documents,
+        })
+        .collect();
+    entries.sort_by(|a, b| b.occurrences.cmp(&a.occurrences).then_with(|| a.term.cmp(&b.term)));
+    entries.truncate(top_n);
+    entries
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TermSuggestion {
+    pub term: String,
+    // Cached documents containing the term
+    pub documents: usize,
+}
+
+/// Terms starting with `prefix` across every cached document, from the index's
+/// full-text vocabulary. A prefix lookup doesn't scan any text, so this is fast
+/// enough to call on each keystroke.
+pub fn suggest_terms(index: &Index, prefix: &str, limit: usize) -> Result<Vec<TermSuggestion>> {
+    // Terms are indexed lowercased and without diacritics
+    let prefix: String = prefix
+        .trim()
+        .nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect();
+    if prefix.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    Ok(index
+        .terms_with_prefix(&prefix, limit)?
+        .into_iter()
+        .map(|(term, documents)| TermSuggestion { term, documents })
+        .collect())
+}
+
+// Levenshtein distance, giving up once every alignment exceeds `max`
+fn within_distance(a: &str, b: &str, max: usize) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return false;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        if current.iter().all(|distance| *distance > max) {
+            return false;
+        }
+        previous = current;
+    }
+    previous[b.len()] <= max
+}
@@ -0,0 +1,292 @@
+use anyhow::Result;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Listing a local directory takes milliseconds; anything slower is worth reporting
+const SLOW_DIRECTORY: Duration = Duration::from_secs(1);
+const DEFAULT_DIR_TIMEOUT_MS: u64 = 30_000;
+
+/// How to walk a directory tree, mostly for coping with slow network shares
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WalkOptions {
+    // Don't descend into SMB/NFS/etc. mounts at all
+    #[serde(default)]
+    pub skip_network_mounts: bool,
+    // Give up on a directory whose listing takes longer than this
+    #[serde(default)]
+    pub dir_timeout_ms: Option<u64>,
+    // Directory names never descended into; None uses DEFAULT_SKIP_DIRS
+    #[serde(default)]
+    pub skip_dirs: Option<Vec<String>>,
+    // Also walk hidden (dot) directories and files
+    #[serde(default)]
+    pub include_hidden: bool,
+    // Files and folders left out by path, e.g. from a corpus's .pdfsearch.toml
+    #[serde(default)]
+    pub exclude_paths: Vec<String>,
+}
+
+/// Trash, version control, dependency and OS metadata folders that only hold
+/// stale copies or nothing worth searching
+pub const DEFAULT_SKIP_DIRS: &[&str] = &[
+    ".Trash",
+    ".Trashes",
+    "$RECYCLE.BIN",
+    "System Volume Information",
+    "node_modules",
+    ".git",
+    "__MACOSX",
+    ".Spotlight-V100",
+    ".fseventsd",
+    ".DocumentRevisions-V100",
+    ".TemporaryItems",
+    "Backups.backupdb",
+    "@eaDir",
+    "#recycle",
+];
+
+pub fn default_skip_dirs() -> Vec<String> {
+    DEFAULT_SKIP_DIRS.iter().map(|dir| dir.to_string()).collect()
+}
+
+// Name-based exclusions applied to every entry below the walk's root
+struct SkipRules {
+    // Lowercased for case-insensitive file systems
+    dirs: HashSet<String>,
+    include_hidden: bool,
+    paths: HashSet<PathBuf>,
+}
+
+impl SkipRules {
+    fn new(options: &WalkOptions) -> Self {
+        let dirs = match options.skip_dirs {
+            Some(ref dirs) => dirs.iter().map(|dir| dir.to_lowercase()).collect(),
+            None => DEFAULT_SKIP_DIRS.iter().map(|dir| dir.to_lowercase()).collect(),
+        };
+        SkipRules {
+            dirs,
+            include_hidden: options.include_hidden,
+            paths: options.exclude_paths.iter().map(PathBuf::from).collect(),
+        }
+    }
+
+    fn skips_dir(&self, name: &str) -> bool {
+        self.dirs.contains(&name.to_lowercase()) || (!self.include_hidden && name.starts_with('.'))
+    }
+
+    // AppleDouble "._name.pdf" files are resource-fork metadata, never real PDFs
+    fn skips_file(&self, name: &str) -> bool {
+        name.starts_with("._") || (!self.include_hidden && name.starts_with('.'))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SlowPath {
+    pub path: String,
+    pub millis: u64,
+}
+
+/// What the walk ran into, so slow or missing parts of a share can be explained
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WalkDiagnostics {
+    pub directories_listed: usize,
+    pub slow_paths: Vec<SlowPath>,
+    // Directories abandoned because listing them exceeded the timeout
+    pub timed_out: Vec<String>,
+    pub skipped_network_mounts: Vec<String>,
+}
+
+impl WalkDiagnostics {
+    pub fn merge(&mut self, other: WalkDiagnostics) {
+        self.directories_listed += other.directories_listed;
+        self.slow_paths.extend(other.slow_paths);
+        self.timed_out.extend(other.timed_out);
+        self.skipped_network_mounts.extend(other.skipped_network_mounts);
+    }
+}
+
+enum Listing {
+    // `canonical` is None when the directory couldn't be resolved
+    Entries { canonical: Option<PathBuf>, dirs: Vec<PathBuf>, files: Vec<PathBuf>, elapsed: Duration },
+    TimedOut,
+    Failed,
+}
+
+/// Find every PDF under `root`. Directories are listed concurrently a level at
+/// a time, and each listing runs against a timeout so one hung share can't stall
+/// the whole walk.
+pub fn walk_pdfs(root: &Path, options: &WalkOptions) -> Result<(Vec<PathBuf>, WalkDiagnostics)> {
+    let mut diagnostics = WalkDiagnostics::default();
+    let network_mounts = if options.skip_network_mounts { network_mounts() } else { Vec::new() };
+    let timeout = Duration::from_millis(options.dir_timeout_ms.unwrap_or(DEFAULT_DIR_TIMEOUT_MS));
+    let rules = Arc::new(SkipRules::new(options));
+
+    // Symlinks are followed, so guard against cycles
+    let mut visited = HashSet::new();
+    let mut pdf_files = Vec::new();
+    let mut frontier = vec![root.to_path_buf()];
+
+    while !frontier.is_empty() {
+        let mut next = Vec::new();
+        frontier.retain(|dir| {
+            let on_network = options.skip_network_mounts && is_network_path(dir, &network_mounts);
+            if on_network {
+                diagnostics.skipped_network_mounts.push(dir.to_string_lossy().to_string());
+            }
+            !on_network
+        });
+
+        let listings: Vec<(PathBuf, Listing)> = frontier
+            .par_iter()
+            .map(|dir| (dir.clone(), list_directory(dir, timeout, &rules)))
+            .collect();
+
+        for (dir, listing) in listings {
+            match listing {
+                Listing::Entries { canonical, dirs, files, elapsed } => {
+                    // Resolved on the listing thread, since that can hang on a share too
+                    if canonical.is_some_and(|canonical| !visited.insert(canonical)) {
+                        continue;
+                    }
+                    diagnostics.directories_listed += 1;
+                    if elapsed >= SLOW_DIRECTORY {
+                        diagnostics.slow_paths.push(SlowPath {
+                            path: dir.to_string_lossy().to_string(),
+                            millis: elapsed.as_millis() as u64,
+                        });
+                    }
+                    pdf_files.extend(files);
+                    next.extend(dirs);
+                }
+                Listing::TimedOut => diagnostics.timed_out.push(dir.to_string_lossy().to_string()),
+                Listing::Failed => {}
+            }
+        }
+
+        frontier = next;
+    }
+
+    pdf_files.sort();
+    diagnostics.slow_paths.sort_by_key(|slow| std::cmp::Reverse(slow.millis));
+    Ok((pdf_files, diagnostics))
+}
+
+// Resolve and list one directory on a helper thread. On timeout the thread is left to
+// finish (or hang) on its own; its result is simply ignored.
+fn list_directory(dir: &Path, timeout: Duration, rules: &Arc<SkipRules>) -> Listing {
+    let (sender, receiver) = mpsc::channel();
+    let dir_owned = dir.to_path_buf();
+    let rules = Arc::clone(rules);
+    let started = Instant::now();
+
+    std::thread::spawn(move || {
+        let canonical = fs::canonicalize(&dir_owned).ok();
+        let _ = sender.send(read_entries(&dir_owned, &rules).map(|entries| (canonical, entries)));
+    });
+
+    match receiver.recv_timeout(timeout) {
+        Ok(Some((canonical, (dirs, files)))) => Listing::Entries { canonical, dirs, files, elapsed: started.elapsed() },
+        Ok(None) => Listing::Failed,
+        Err(_) => Listing::TimedOut,
+    }
+}
+
+fn read_entries(dir: &Path, rules: &SkipRules) -> Option<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(dir).ok()?.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if rules.paths.contains(&path) {
+            continue;
+        }
+        let Ok(mut file_type) = entry.file_type() else { continue };
+        // Follow symlinks to what they point at
+        if file_type.is_symlink() {
+            match fs::metadata(&path) {
+                Ok(metadata) => file_type = metadata.file_type(),
+                Err(_) => continue,
+            }
+        }
+
+        if file_type.is_dir() {
+            if !rules.skips_dir(&name) {
+                dirs.push(path);
+            }
+        } else if file_type.is_file()
+            && path.extension().and_then(|s| s.to_str()) == Some("pdf")
+            && !rules.skips_file(&name)
+        {
+            files.push(path);
+        }
+    }
+
+    Some((dirs, files))
+}
+
+const NETWORK_FS_TYPES: &[&str] = &[
+    "nfs", "nfs4", "cifs", "smb3", "smbfs", "afpfs", "webdav", "davfs", "fuse.sshfs", "ncpfs", "afs",
+];
+
+// Mount points of network file systems
+#[cfg(target_os = "linux")]
+fn network_mounts() -> Vec<PathBuf> {
+    let Ok(mounts) = fs::read_to_string("/proc/mounts") else {
+        return Vec::new();
+    };
+
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let mount_point = fields.nth(1)?;
+            let fs_type = fields.next()?;
+            NETWORK_FS_TYPES
+                .contains(&fs_type)
+                // Spaces in mount points are escaped as octal
+                .then(|| PathBuf::from(mount_point.replace("\\040", " ")))
+        })
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+fn network_mounts() -> Vec<PathBuf> {
+    // Lines look like: //user@server/share on /Volumes/share (smbfs, nodev, nosuid, mounted by user)
+    let Ok(output) = std::process::Command::new("mount").output() else {
+        return Vec::new();
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (_, rest) = line.split_once(" on ")?;
+            let (mount_point, options) = rest.rsplit_once(" (")?;
+            let fs_type = options.split(',').next()?.trim_end_matches(')');
+            NETWORK_FS_TYPES.contains(&fs_type).then(|| PathBuf::from(mount_point))
+        })
+        .collect()
+}
+
+// Elsewhere only UNC paths are recognized, see `is_network_path`
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn network_mounts() -> Vec<PathBuf> {
+    Vec::new()
+}
+
+fn is_network_path(dir: &Path, network_mounts: &[PathBuf]) -> bool {
+    if network_mounts.iter().any(|mount| dir.starts_with(mount)) {
+        return true;
+    }
+
+    // \\server\share or \\?\UNC\server\share, but not a verbatim local path like \\?\C:\
+    let path = dir.to_string_lossy();
+    cfg!(windows) && (path.starts_with(r"\\?\UNC\") || (path.starts_with(r"\\") && !path.starts_with(r"\\?\")))
+}
@@ -0,0 +1,164 @@
+use crate::error::AppError;
+use crate::pdf_search::SearchMatch;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashSet};
+
+// Zotero's local HTTP server (port 23119) can't modify existing items: its
+// local API is read-only and the connector endpoints only create new items.
+// Tags are therefore written through the web API and reach the desktop
+// library with its next sync.
+const API_BASE: &str = "https://api.zotero.org";
+// The web API accepts at most 50 items per read or write request
+const BATCH_SIZE: usize = 50;
+
+/// Credentials for the Zotero web API, created at zotero.org/settings/keys
+/// with write access to the personal library
+#[derive(Debug, Clone)]
+pub struct ZoteroApiCredentials {
+    pub user_id: String,
+    pub api_key: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagFailure {
+    pub item_key: String,
+    pub message: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct TagResult {
+    pub tagged: Vec<String>,
+    // Items that had the tag already
+    pub already_tagged: Vec<String>,
+    pub failed: Vec<TagFailure>,
+}
+
+#[derive(Deserialize)]
+struct ApiItem {
+    key: String,
+    version: u64,
+    data: ApiItemData,
+}
+
+#[derive(Deserialize)]
+struct ApiItemData {
+    #[serde(default)]
+    tags: Vec<Value>,
+}
+
+/// Keys of the Zotero items whose attachments matched, in a stable order
+pub fn matched_item_keys(matches: &[SearchMatch]) -> Vec<String> {
+    matches
+        .iter()
+        .filter_map(|m| m.item.zotero_metadata.as_ref())
+        .filter_map(|metadata| metadata.zotero_link.strip_prefix("zotero://select/library/items/"))
+        .map(str::to_string)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Add `tag` to each item, keeping its existing tags
+pub fn tag_items(credentials: &ZoteroApiCredentials, item_keys: &[String], tag: &str) -> Result<TagResult> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Err(AppError::invalid_input("The tag to apply is empty").into());
+    }
+
+    let mut result = TagResult::default();
+    for batch in item_keys.chunks(BATCH_SIZE) {
+        let items = fetch_items(credentials, batch)?;
+
+        let mut found = HashSet::new();
+        let mut updates = Vec::new();
+        for item in items {
+            found.insert(item.key.clone());
+            let has_tag = item
+                .data
+                .tags
+                .iter()
+                .any(|existing| existing.get("tag").and_then(Value::as_str) == Some(tag));
+            if has_tag {
+                result.already_tagged.push(item.key);
+                continue;
+            }
+
+            let mut tags = item.data.tags;
+            tags.push(json!({ "tag": tag }));
+            updates.push(json!({ "key": item.key, "version": item.version, "tags": tags }));
+        }
+
+        for key in batch.iter().filter(|key| !found.contains(*key)) {
+            result.failed.push(TagFailure {
+                item_key: key.clone(),
+                message: "Item not found in the Zotero web library".to_string(),
+            });
+        }
+
+        if !updates.is_empty() {
+            write_items(credentials, &updates, &mut result)?;
+        }
+    }
+
+    Ok(result)
+}
+
+fn fetch_items(credentials: &ZoteroApiCredentials, keys: &[String]) -> Result<Vec<ApiItem>> {
+    let url = format!("{}/users/{}/items", API_BASE, credentials.user_id);
+    let response = attohttpc::get(&url)
+        .param("itemKey", keys.join(","))
+        .param("limit", BATCH_SIZE.to_string())
+        .header("Zotero-API-Key", &credentials.api_key)
+        .header("Zotero-API-Version", "3")
+        .send()
+        .context("Failed to reach the Zotero web API")?;
+
+    if !response.is_success() {
+        return Err(api_error(response));
+    }
+    response.json().context("Unexpected response from the Zotero web API")
+}
+
+// Objects posted with a key and version are updated with PATCH semantics, so only
+// the tags change. The response reports success or failure per position in the batch.
+fn write_items(credentials: &ZoteroApiCredentials, updates: &[Value], result: &mut TagResult) -> Result<()> {
+    let url = format!("{}/users/{}/items", API_BASE, credentials.user_id);
+    let response = attohttpc::post(&url)
+        .header("Zotero-API-Key", &credentials.api_key)
+        .header("Zotero-API-Version", "3")
+        .json(&updates)?
+        .send()
+        .context("Failed to reach the Zotero web API")?;
+
+    if !response.is_success() {
+        return Err(api_error(response));
+    }
+    let body: Value = response.json().context("Unexpected response from the Zotero web API")?;
+
+    for (position, update) in updates.iter().enumerate() {
+        let key = update["key"].as_str().unwrap_or_default().to_string();
+        match body["failed"].get(position.to_string()) {
+            Some(failure) => result.failed.push(TagFailure {
+                item_key: key,
+                message: failure["message"].as_str().unwrap_or("Update rejected").to_string(),
+            }),
+            None => result.tagged.push(key),
+        }
+    }
+    Ok(())
+}
+
+fn api_error(response: attohttpc::Response) -> anyhow::Error {
+    let status = response.status();
+    let message = match status.as_u16() {
+        403 => "The Zotero API key was rejected or lacks write access".to_string(),
+        _ => format!(
+            "Zotero web API returned {}: {}",
+            status,
+            response.text().unwrap_or_default().trim()
+        ),
+    };
+    AppError::Zotero { message }.into()
+}
@@ -0,0 +1,192 @@
+use crate::error::AppError;
+use crate::index;
+use crate::pdf_search::{build_zotero_map, ZoteroMap};
+use crate::settings::AppSettings;
+use anyhow::Result;
+use serde::Serialize;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+// Reading the Zotero library takes a while on large libraries, and searches,
+// listings and citations all want it. The map is kept between commands and read
+// again when the policy in the settings says so. While Zotero holds a lock on its
+// database, the last map read is served instead and listeners are told it's stale.
+
+#[derive(Debug, Clone, Copy)]
+struct RefreshPolicy {
+    ttl: Duration,
+    change_only: bool,
+    // Not strictly about refreshing, but a map read with the other setting can't be reused
+    skip_publications: bool,
+}
+
+static POLICY: RwLock<RefreshPolicy> = RwLock::new(RefreshPolicy {
+    ttl: Duration::ZERO,
+    change_only: false,
+    skip_publications: false,
+});
+
+static CACHE: Mutex<Option<CachedMap>> = Mutex::new(None);
+
+type FreshnessListener = Box<dyn Fn(&ZoteroFreshness) + Send + Sync>;
+
+static LISTENER: OnceLock<FreshnessListener> = OnceLock::new();
+
+// Modification time and size of each database file, which change whenever Zotero
+// or Better BibTeX writes to it
+type Fingerprint = Vec<Option<(SystemTime, u64)>>;
+
+const DATABASE_FILES: &[&str] = &["zotero.sqlite", "zotero.sqlite-wal", "better-bibtex.sqlite"];
+
+struct CachedMap {
+    zotero_path: PathBuf,
+    map: ZoteroMap,
+    fingerprint: Fingerprint,
+    skip_publications: bool,
+    built: Instant,
+    built_at: u64,
+    served_stale: bool,
+}
+
+/// Sent to listeners when metadata was served from an earlier read because the
+/// database was locked, and again once it could be read afresh
+#[derive(Debug, Clone, Serialize)]
+pub struct ZoteroFreshness {
+    pub zotero_path: String,
+    // When the metadata served was read from the database (unix seconds)
+    pub built_at: u64,
+    pub stale: bool,
+    // Why the database couldn't be read, when stale
+    pub error: Option<AppError>,
+}
+
+/// Take the refresh policy from the settings
+pub fn set_policy(settings: &AppSettings) {
+    *POLICY.write().unwrap() = RefreshPolicy {
+        ttl: Duration::from_secs(settings.zotero_cache_ttl_secs),
+        change_only: settings.zotero_refresh_on_change_only,
+        skip_publications: settings.zotero_skip_my_publications,
+    };
+}
+
+/// Whether the items in the user's My Publications are left out of the library
+pub fn skip_publications() -> bool {
+    POLICY.read().unwrap().skip_publications
+}
+
+/// Call `listener` whenever the freshness of the metadata served changes. Only the
+/// first listener registered is kept.
+pub fn on_freshness(listener: impl Fn(&ZoteroFreshness) + Send + Sync + 'static) {
+    let _ = LISTENER.set(Box::new(listener));
+}
+
+fn notify(freshness: ZoteroFreshness) {
+    if let Some(listener) = LISTENER.get() {
+        listener(&freshness);
+    }
+}
+
+fn fingerprint(zotero_path: &Path) -> Fingerprint {
+    DATABASE_FILES
+        .iter()
+        .map(|name| {
+            let metadata = zotero_path.join(name).metadata().ok()?;
+            Some((metadata.modified().ok()?, metadata.len()))
+        })
+        .collect()
+}
+
+// Zotero locks its database while running: Windows refuses to copy the file, and
+// SQLite reports it busy or locked
+fn is_locked(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        if let Some(io_err) = cause.downcast_ref::<io::Error>() {
+            // ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION
+            return cfg!(windows) && matches!(io_err.raw_os_error(), Some(32 | 33));
+        }
+        if let Some(rusqlite::Error::SqliteFailure(sqlite_err, _)) = cause.downcast_ref::<rusqlite::Error>() {
+            return matches!(
+                sqlite_err.code,
+                rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked
+            );
+        }
+        false
+    })
+}
+
+/// The Zotero metadata map for the library at `zotero_path`, read again when the
+/// database has changed or, unless refreshing on changes only, when the cached
+/// map is older than the TTL. A locked database serves the last map read.
+pub fn zotero_map(zotero_path: &Path) -> Result<ZoteroMap> {
+    let policy = *POLICY.read().unwrap();
+    let fingerprint = fingerprint(zotero_path);
+
+    // Held while reading, so concurrent commands wait for one read rather than each doing their own
+    let mut cache = CACHE.lock().unwrap();
+    if let Some(cached) = cache.as_ref().filter(|cached| cached.zotero_path == zotero_path) {
+        let unchanged = cached.fingerprint == fingerprint && cached.skip_publications == policy.skip_publications;
+        if unchanged && (policy.change_only || cached.built.elapsed() < policy.ttl) {
+            return Ok(cached.map.clone());
+        }
+    }
+
+    match build_zotero_map(zotero_path, policy.skip_publications) {
+        Ok(map) => {
+            let built_at = index::unix_now();
+            let was_stale = cache
+                .as_ref()
+                .is_some_and(|cached| cached.zotero_path == zotero_path && cached.served_stale);
+            if was_stale {
+                notify(ZoteroFreshness {
+                    zotero_path: zotero_path.to_string_lossy().to_string(),
+                    built_at,
+                    stale: false,
+                    error: None,
+                });
+            }
+            *cache = Some(CachedMap {
+                zotero_path: zotero_path.to_path_buf(),
+                map: map.clone(),
+                fingerprint,
+                skip_publications: policy.skip_publications,
+                built: Instant::now(),
+                built_at,
+                served_stale: false,
+            });
+            Ok(map)
+        }
+        Err(e) if is_locked(&e) => {
+            let Some(cached) = cache.as_mut().filter(|cached| cached.zotero_path == zotero_path) else {
+                return Err(e);
+            };
+            eprintln!("Warning: Zotero database is locked, using metadata read earlier: {:#}", e);
+            cached.served_stale = true;
+            notify(ZoteroFreshness {
+                zotero_path: zotero_path.to_string_lossy().to_string(),
+                built_at: cached.built_at,
+                stale: true,
+                error: Some(AppError::Zotero { message: format!("{:#}", e) }),
+            });
+            Ok(cached.map.clone())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether a file is inside Zotero's storage folder or one of its attachments,
+/// which Zotero would lose track of if it were moved or renamed
+pub fn zotero_linker(zotero_path: Option<&Path>) -> impl Fn(&Path) -> bool {
+    let zotero_map = match zotero_path {
+        Some(zotero_path) => zotero_map(zotero_path)
+            .map_err(|e| eprintln!("Warning: Failed to load Zotero database: {}", e))
+            .ok(),
+        None => None,
+    };
+    let zotero_storage = zotero_path.map(|path| path.join("storage"));
+    move |path: &Path| {
+        zotero_storage.as_ref().is_some_and(|storage| path.starts_with(storage))
+            || zotero_map.as_ref().is_some_and(|map| map.contains(path))
+    }
+}
@@ -0,0 +1,84 @@
+use crate::pdf_search::open_zotero_database;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Zotero indexes the text of its PDF attachments and keeps it next to each file
+// in `storage/<key>/.zotero-ft-cache`, pages separated by form feeds. For a
+// library that's already indexed, reading that is much faster than extracting
+// the same PDFs again.
+
+const CACHE_FILE: &str = ".zotero-ft-cache";
+
+const PAGE_BREAK: char = '\x0c';
+
+/// Page counts of the attachments Zotero has indexed in full, by file path
+pub struct ZoteroFulltext {
+    total_pages: HashMap<PathBuf, usize>,
+}
+
+impl ZoteroFulltext {
+    /// Read which stored attachments of the library at `zotero_path` Zotero has
+    /// indexed every page of. Linked files keep no cache beside them and are left out.
+    pub fn load(zotero_path: &Path) -> Result<ZoteroFulltext> {
+        let (conn, temp_db_path) = open_zotero_database(zotero_path, "fulltext")?;
+
+        let rows: Vec<(String, String, usize)> = {
+            let mut stmt = conn.prepare(
+                "SELECT items.key, itemAttachments.path, fulltextItems.totalPages
+                 FROM fulltextItems
+                 JOIN items ON fulltextItems.itemID = items.itemID
+                 JOIN itemAttachments ON fulltextItems.itemID = itemAttachments.itemID
+                 WHERE itemAttachments.path LIKE 'storage:%'
+                   AND fulltextItems.totalPages > 0
+                   AND fulltextItems.indexedPages = fulltextItems.totalPages",
+            )?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, i64>(2)? as usize)))?;
+            rows.flatten().collect()
+        };
+        drop(conn);
+        let _ = fs::remove_file(&temp_db_path);
+
+        let total_pages = rows
+            .into_iter()
+            .filter_map(|(attachment_key, path, pages)| {
+                let file_name = path.strip_prefix("storage:")?;
+                Some((zotero_path.join("storage").join(attachment_key).join(file_name), pages))
+            })
+            .collect();
+        Ok(ZoteroFulltext { total_pages })
+    }
+
+    /// The text of each page of `pdf_path` as Zotero extracted it, or None when
+    /// Zotero's text can't stand in for extraction: the file isn't an indexed
+    /// attachment, the cache is older than the file or its pages don't add up,
+    /// or the PDF is encrypted and its permissions must be checked
+    pub fn pages(&self, pdf_path: &Path) -> Option<Vec<String>> {
+        let total_pages = *self.total_pages.get(pdf_path)?;
+        let cache_path = pdf_path.with_file_name(CACHE_FILE);
+        let cache_modified = cache_path.metadata().and_then(|m| m.modified()).ok()?;
+        if pdf_path.metadata().and_then(|m| m.modified()).ok()? > cache_modified {
+            return None;
+        }
+        if is_encrypted(pdf_path) {
+            return None;
+        }
+
+        let text = fs::read_to_string(&cache_path).ok()?;
+        let mut pages: Vec<String> = text.split(PAGE_BREAK).map(str::to_string).collect();
+        // The last page ends with a break too
+        if pages.len() == total_pages + 1 && pages.last().is_some_and(|page| page.trim().is_empty()) {
+            pages.pop();
+        }
+        (pages.len() == total_pages).then_some(pages)
+    }
+}
+
+// Much cheaper than parsing the PDF. A stray match only means the file is extracted as usual.
+fn is_encrypted(pdf_path: &Path) -> bool {
+    match fs::read(pdf_path) {
+        Ok(bytes) => bytes.windows(b"/Encrypt".len()).any(|window| window == b"/Encrypt"),
+        Err(_) => true,
+    }
+}
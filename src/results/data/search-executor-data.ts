@@ -1,5 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
-import type { SearchMatch, SearchParams } from '../../shared/data/types';
+import type { SearchMatch, SearchParams, SearchResponse } from '../../shared/data/types';
 import { getAllQueries } from '../../search/data/search-queries-data';
 import { saveSearchToHistory } from '../../search/data/search-history-data';
 import {
@@ -11,7 +11,7 @@ import {
   setCurrentResults,
   perPdfSearchQueries
 } from '../../shared/data/state';
-import { showStatus } from '../../shared/ui/status-message';
+import { showStatus, formatError } from '../../shared/ui/status-message';
 
 export async function performSearch(event: Event, renderResultsCallback: (results: SearchMatch[]) => void) {
   event.preventDefault();
@@ -45,7 +45,8 @@ export async function performSearch(event: Event, renderResultsCallback: (result
       zotero_path: zoteroMode.checked ? (zoteroPath.textContent || '').trim() || null : null,
     };
 
-    const results = await invoke<SearchMatch[]>('search_pdf_files', { params });
+    const response = await invoke<SearchResponse>('search_pdf_files', { params });
+    const results = response.matches;
     setCurrentResults(results);
 
     // Note: We don't update pinned results here anymore
@@ -53,12 +54,17 @@ export async function performSearch(event: Event, renderResultsCallback: (result
     // Current search results will be shown alongside them in the UI
 
     renderResultsCallback(results);
-    showStatus(`Found ${results.length} ${results.length === 1 ? 'match' : 'matches'}`, 'success');
+    const skipped = response.file_errors.length;
+    showStatus(
+      `Found ${results.length} ${results.length === 1 ? 'match' : 'matches'}` +
+        (skipped > 0 ? ` (${skipped} ${skipped === 1 ? 'file' : 'files'} could not be searched)` : ''),
+      'success'
+    );
 
     // Save to search history
     saveSearchToHistory(queries);
   } catch (error) {
-    showStatus(`Search failed: ${error}`, 'error');
+    showStatus(`Search failed: ${formatError(error)}`, 'error');
     resultsContainer.innerHTML = `
       <div class="empty-state">
         <p>Search failed. Please try again.</p>
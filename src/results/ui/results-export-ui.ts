@@ -37,14 +37,15 @@ export async function copyResults() {
 
         // Get unique pages and sort them
         const pages = Array.from(new Set(matches.map(m => m.page_number))).sort((a, b) => a - b);
+        const labels = pageLabels(matches);
 
-        // Page links
+        // Page links: cite the printed label, but open Zotero at the physical page
         pages.forEach(pageNum => {
           if (metadata.pdf_attachment_key) {
             const zoteroUrl = `zotero://open-pdf/library/items/${metadata.pdf_attachment_key}?page=${pageNum}`;
-            markdown += `- [Page ${pageNum}](${zoteroUrl})\n`;
+            markdown += `- [Page ${labels.get(pageNum)}](${zoteroUrl})\n`;
           } else {
-            markdown += `- Page ${pageNum}\n`;
+            markdown += `- Page ${labels.get(pageNum)}\n`;
           }
         });
       } else {
@@ -53,8 +54,9 @@ export async function copyResults() {
         markdown += '---\n';
 
         const pages = Array.from(new Set(matches.map(m => m.page_number))).sort((a, b) => a - b);
+        const labels = pageLabels(matches);
         pages.forEach(pageNum => {
-          markdown += `- Page ${pageNum}\n`;
+          markdown += `- Page ${labels.get(pageNum)}\n`;
         });
       }
 
@@ -68,3 +70,10 @@ export async function copyResults() {
     showStatus(`Copy failed: ${error}`, 'error');
   }
 }
+
+// Printed page label for each physical page, falling back to the page number
+function pageLabels(matches: SearchMatch[]): Map<number, string> {
+  const labels = new Map<number, string>();
+  matches.forEach(m => labels.set(m.page_number, m.page_label || String(m.page_number)));
+  return labels;
+}
@@ -16,6 +16,7 @@ import { togglePinResult } from '../data/pinned-results-data';
 import { loadPageImage, toggleCoverPage } from './pdf-viewer-ui';
 import { copyCitation, openInZotero } from '../../shared/ui/zotero-actions';
 import { escapeHtml } from '../../shared/ui/html-utils';
+import { formatError } from '../../shared/ui/status-message';
 import { getColorForQuery } from '../../shared/data/color-utils';
 
 export function renderFileGroup(filePath: string, fileMatches: SearchMatch[], isPinned: boolean, originalQueries?: QueryItem[], currentMatchCount?: number, originalMatchCount?: number, originalMatches?: SearchMatch[], currentMatches?: SearchMatch[]): string {
@@ -28,6 +29,8 @@ export function renderFileGroup(filePath: string, fileMatches: SearchMatch[], is
   const fileId = filePath.replace(/[^a-zA-Z0-9]/g, '_');
   const firstMatch = fileMatches[0];
   const zoteroMetadata = firstMatch.zotero_metadata;
+  // Identical copies the search collapsed into this one
+  const otherCopies = firstMatch.other_copies || [];
 
   // Determine pin button state
   const pinButtonClass = isPinned ? 'pin-btn pinned' : 'pin-btn';
@@ -98,6 +101,9 @@ export function renderFileGroup(filePath: string, fileMatches: SearchMatch[], is
             </div>
             <div class="result-file-path">${filePath}</div>
           `}
+          ${otherCopies.length ? `
+            <div class="result-file-copies">Also at: ${otherCopies.map(path => escapeHtml(path)).join(', ')}</div>
+          ` : ''}
         </div>
       </div>
       <div class="result-matches-filter" id="filter-${fileId}" data-filepath="${escapeHtml(filePath)}">
@@ -169,9 +175,13 @@ export function renderFileGroup(filePath: string, fileMatches: SearchMatch[], is
   // Render each page once with all its matches
   pageGroups.forEach((pageMatches, pageNumber) => {
     const pageId = `page-${fileId}-${pageNumber}`;
+    const label = pageMatches[0].page_label;
+    const pageName = label && label !== String(pageNumber)
+      ? `Page ${escapeHtml(label)} (PDF ${pageNumber})`
+      : `Page ${pageNumber}`;
     const pageHeader = zoteroMetadata && zoteroMetadata.pdf_attachment_key
-      ? `<a href="#" class="page-link" data-attachment-key="${escapeHtml(zoteroMetadata.pdf_attachment_key)}" data-page="${pageNumber}">Page ${pageNumber}</a> (${pageMatches.length} ${pageMatches.length === 1 ? 'match' : 'matches'})`
-      : `Page ${pageNumber} (${pageMatches.length} ${pageMatches.length === 1 ? 'match' : 'matches'})`;
+      ? `<a href="#" class="page-link" data-attachment-key="${escapeHtml(zoteroMetadata.pdf_attachment_key)}" data-page="${pageNumber}">${pageName}</a> (${pageMatches.length} ${pageMatches.length === 1 ? 'match' : 'matches'})`
+      : `${pageName} (${pageMatches.length} ${pageMatches.length === 1 ? 'match' : 'matches'})`;
     html += `
       <div class="result-match">
         <div class="result-match-header">${pageHeader}</div>
@@ -748,7 +758,7 @@ export function renderResults(matches: SearchMatch[]) {
             } catch (error) {
               console.error('Custom query search failed:', error);
               if (matchesContainer) {
-                matchesContainer.innerHTML = `<div class="page-preview-error">Search failed: ${error}</div>`;
+                matchesContainer.innerHTML = `<div class="page-preview-error">Search failed: ${escapeHtml(formatError(error))}</div>`;
               }
             }
           }
@@ -1078,7 +1088,7 @@ function setupPageRangeSliderListeners() {
         }
       } catch (error) {
         console.error('Page range search failed:', error);
-        matchesContainer.innerHTML = `<div class="page-preview-error">Search failed: ${error}</div>`;
+        matchesContainer.innerHTML = `<div class="page-preview-error">Search failed: ${escapeHtml(formatError(error))}</div>`;
       }
     });
   });
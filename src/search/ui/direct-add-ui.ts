@@ -2,7 +2,7 @@ import { invoke } from '@tauri-apps/api/core';
 import { directAddInput, directAddResults, directoryPath, zoteroMode, zoteroPath } from '../../shared/data/state';
 import type { PdfListItem, ListPdfsParams } from '../../shared/data/types';
 import { escapeHtml } from '../../shared/ui/html-utils';
-import { showStatus } from '../../shared/ui/status-message';
+import { showStatus, formatError } from '../../shared/ui/status-message';
 import { pinPdfDirectly } from './direct-add-pin';
 
 let searchTimeout: number | null = null;
@@ -70,7 +70,7 @@ async function performDirectSearch() {
     renderDirectAddResults(results);
   } catch (error) {
     console.error('Failed to search PDFs:', error);
-    showStatus(`Failed to search PDFs: ${error}`, 'error');
+    showStatus(`Failed to search PDFs: ${formatError(error)}`, 'error');
     directAddResults.classList.add('has-results');
     directAddResults.innerHTML = `
       <div class="direct-add-empty">Error searching PDFs</div>
@@ -1,12 +1,11 @@
-import { open } from "@tauri-apps/plugin-dialog";
+import { invoke } from "@tauri-apps/api/core";
 import { directoryPath, zoteroPath, zoteroMode, zoteroFolderGroup } from '../../shared/data/state';
 import { showStatus } from '../../shared/ui/status-message';
 
 export async function browseDirectory() {
   try {
-    const selected = await open({
-      directory: true,
-      multiple: false,
+    // Chosen through the backend, which only lets commands use folders picked this way
+    const selected = await invoke<string | null>('choose_directory', {
       title: 'Select PDF Directory',
     });
 
@@ -23,9 +22,7 @@ export async function browseDirectory() {
 
 export async function browseZoteroDirectory() {
   try {
-    const selected = await open({
-      directory: true,
-      multiple: false,
+    const selected = await invoke<string | null>('choose_directory', {
       title: 'Select Zotero Data Directory',
     });
 
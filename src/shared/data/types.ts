@@ -5,33 +5,469 @@ export interface ZoteroMetadata {
   authors: string | null;
   zotero_link: string;
   pdf_attachment_key: string | null;
+  match_method?: string; // "filename" or "heuristic"
+  match_confidence?: number | null;
+  open_pdf_link?: string | null;
+  sibling_attachments?: ZoteroAttachment[];
+  language?: string | null; // the item's language field as entered, e.g. "de" or "German"
+  editors?: string | null; // creators whose role is editor, also in authors
 }
 
-export interface SearchMatch {
+export interface ZoteroAttachment {
+  attachment_key: string;
+  file_name: string;
+  title: string | null;
+  open_pdf_link: string;
+}
+
+// A document in one of the searched libraries; matches and listings carry its fields
+// A best guess at a document's citation details, read from its first page
+export interface FallbackMetadata {
+  title: string | null;
+  authors: string | null;
+  year: string | null;
+  abstract: string | null;
+  title_source: 'font_size' | 'info' | 'first_line' | null;
+}
+
+export interface LibraryItem {
   file_path: string;
   file_name: string;
+  source?: 'file' | 'zotero'; // the library the document was found through
+  format?: 'pdf';
+  zotero_link: string | null;
+  zotero_metadata: ZoteroMetadata | null;
+  fallback_metadata?: FallbackMetadata | null; // guessed from the document when it has no Zotero item
+  other_copies?: string[]; // identical copies folded into this document by collapse_duplicates
+}
+
+export interface SearchMatch extends LibraryItem {
+  schema_version: number; // layout version of saved and exported matches
   page_number: number;
   context_before: string;
   matched_text: string;
   context_after: string;
-  zotero_link: string | null;
-  zotero_metadata: ZoteroMetadata | null;
+  query?: string | null; // the query that produced this match
+  offset?: number | null; // byte offset within the page's normalized text
+  page_label?: string | null; // logical page label from the PDF, e.g. "xiv"
+  highlights?: ContextHighlight[]; // other queries' hits inside the context
+  captures?: Record<string, string>; // named regex groups -> captured text
+  source_span?: SourceSpan | null; // the match in the page's original (NFC) text
+  source_unavailable?: boolean; // matched in cached text; the PDF isn't present locally
+  extraction_restricted?: boolean; // the PDF forbids text extraction; searched by override
+  ocr?: boolean; // matched in text recognized by OCR, which may misread words
+  bounding_boxes?: Rect[] | null; // one per line of the match, with page_coordinates
+  display_boxes?: Rect[] | null; // the same from the top-left of the page as displayed, after CropBox and /Rotate
+  page_geometry?: PageGeometry | null; // the page's crop box and rotation, alongside the boxes
+  char_range?: { start: number; end: number } | null; // character offsets in the page's extracted text
+  match_id?: string | null; // "<search_id>:<position>", for expand_context and other per-match commands
+  color?: string; // resolved highlight color of the match's query
+  section?: string | null; // nearest heading before the match, in tagged PDFs
+  corpus?: string | null; // named corpus the document is in, when the search read several
+}
+
+// PDF user space: points from the page's bottom-left corner
+// (display_boxes: from the top-left of the page as displayed)
+export interface Rect {
+  x0: number;
+  y0: number;
+  x1: number;
+  y1: number;
+}
+
+// How a page is shown: visible area in user space and clockwise quarter turns
+export interface PageGeometry {
+  visible: Rect; // crop box, else media box
+  rotate: number; // 0, 90, 180 or 270
+  width: number; // displayed size in points, after rotating
+  height: number;
+}
+
+export interface ContextHighlight {
+  query: string;
+  color: string;
+  context: 'before' | 'after';
+  start: number; // UTF-16 offsets into context_before/context_after
+  end: number;
+}
+
+export interface SourceSpan {
+  start: number; // UTF-16 offsets into the NFC page text
+  end: number;
+  text: string; // e.g. "Café-au-lait" for the query "cafe au lait"
+}
+
+// restyle_search_queries: a new color and/or name for one of a stored search's queries
+export interface QueryRestyle {
+  query: string; // as the search has it now
+  color?: string | null; // "#rgb" or "#rrggbb"
+  rename?: string | null;
 }
 
 export interface QueryItem {
   query: string;
   use_regex: boolean;
   query_type: string; // "parallel" or "filter"
-  color: string; // hex color for highlighting
+  color?: string; // hex color for highlighting; omit or leave empty to get one from the backend's palette
+  weight?: number; // contribution of each hit to a document's score (default 1)
+  raw_text?: boolean; // match against the unnormalized page text
+  regex_flags?: RegexFlags;
+  max_word_gap?: number | null; // literal multi-word queries: words in order, at most this many words between
+  boolean?: boolean; // parse as AND/OR/NOT, "phrases", parentheses and NEAR/n; ignored for regex queries
+  whole_word?: boolean; // boolean queries: terms match whole words only
+  case_sensitive?: boolean | null; // boolean queries: null follows the case_fold normalization setting
+}
+
+export interface RegexFlags {
+  case_insensitive?: boolean | null; // null follows normalization.case_fold
+  multi_line?: boolean;
+  dot_matches_new_line?: boolean;
+  unicode?: boolean; // default true
 }
 
 export interface SearchParams {
+  schema_version?: number; // omitted means the first layout
   queries: QueryItem[];
   directory: string;
   context_words: number;
   zotero_path: string | null;
   start_page?: number;
   end_page?: number;
+  max_results?: number | null;
+  mtime_within_days?: number | null;
+  added_within_days?: number | null;
+  context_across_blocks?: boolean; // let context run past the match's paragraph
+  sentence_context?: boolean; // trim context cut off by context_words back to a sentence boundary
+  min_matches_per_file?: number | null; // ignore queries that hit a file fewer times
+  sort_by?: 'score' | null; // order documents by relevance score instead of path
+  sample?: SampleParams | null; // return a seeded random subset of the matches
+  skip_network_mounts?: boolean; // don't walk SMB/NFS mounts
+  dir_timeout_ms?: number | null; // abandon a directory listing after this long (default 30s)
+  source?: 'zotero' | null; // search the Zotero library's attachments instead of `directory`
+  skip_dirs?: string[] | null; // directory names to skip; defaults to the saved settings
+  include_hidden?: boolean; // also walk dot directories and files
+  exclude_paths?: string[]; // absolute paths of files and folders to leave out
+  normalization?: Partial<NormalizationOptions>;
+  screening?: ScreeningFilter | null; // skip documents screened out in a review
+  subset?: string | null; // search only this corpus subset's documents
+  corpora?: NamedCorpus[]; // search these named corpora instead of directory; [{ name }] takes the folders from the settings
+  memory_limit_mb?: number | null; // throttle and spill results to disk near this ceiling
+  stream_threshold?: number | null; // past this many matches, keep them on disk and page with get_search_results_page
+  native_prefilter?: boolean; // skip files Spotlight/Windows Search says can't match
+  collapse_duplicates?: boolean; // search one of each set of identical files, listing the other copies
+  latency_budget_ms?: number | null; // search-as-you-type: cached text only, within this many ms
+  ignore_extraction_permissions?: boolean; // search PDFs that forbid text extraction
+  zotero_fulltext?: boolean; // reuse the text Zotero indexed for its attachments instead of extracting it
+  use_ocr?: boolean; // OCR pages without usable text; needs Tesseract installed
+  page_coordinates?: boolean; // add bounding_boxes to matches; for single-file searches
+}
+
+// A document the reviewer took out of the results; standing exclusions live in the settings
+export interface ExcludedDocument {
+  file_path: string;
+  reason: string;
+  excluded_at: number; // unix seconds
+  matches_removed: number; // matches it had in the result set it was removed from
+}
+
+// Text normalization applied before matching; omitted fields keep their defaults
+export interface NormalizationOptions {
+  strip_whitespace: boolean; // default true
+  strip_hyphens: boolean; // default true
+  expand_ligatures: boolean; // default false
+  case_fold: boolean; // default true
+  strip_diacritics: boolean; // default false
+}
+
+export interface WalkDiagnostics {
+  directories_listed: number;
+  slow_paths: { path: string; millis: number }[];
+  timed_out: string[];
+  skipped_network_mounts: string[];
+}
+
+export interface MemoryDiagnostics {
+  limit_mb: number | null;
+  peak_mb: number; // estimated peak of extraction buffers plus held results
+  spilled_matches: number; // written to a temporary store instead of held in memory
+  throttled_files: number; // waited for other extractions to stay under the limit
+}
+
+export interface SampleParams {
+  size: number; // matches kept per group
+  per?: 'query' | 'document';
+  seed: number;
+}
+
+export interface AppError {
+  kind: string;
+  message: string;
+  file: string | null;
+  hint: string | null;
+}
+
+export interface FileError {
+  file_path: string;
+  error: AppError;
+}
+
+// Returned by build_index and update_index
+export interface IndexReport {
+  files: number;
+  extracted: number; // new, changed or (rebuilding) every file
+  unchanged: number;
+  failed: FileError[];
+}
+
+// maintain_index
+export interface MaintenanceOptions {
+  keep_missing?: boolean; // keep text of files that are gone, for archive searches
+}
+
+export interface MaintenanceReport {
+  files_checked: number;
+  missing_removed: number; // cached text of files that no longer exist
+  changed_removed: number; // cached text of files whose contents changed
+  mtimes_updated: number; // touched files with the same contents
+  catalog_removed: number;
+  integrity_problems: string[]; // from SQLite's integrity check; empty when sound
+  bytes_before: number;
+  bytes_after: number;
+  bytes_reclaimed: number;
+}
+
+// Folders searched together under one name (AppSettings.corpora, SearchParams.corpora)
+export interface NamedCorpus {
+  name: string; // "Dissertation PDFs"
+  directories?: string[]; // left out in a search, taken from the settings by name
+}
+
+// register_corpus_subset / list_corpus_subsets
+export interface CorpusSubset {
+  name: string;
+  created: number; // unix seconds
+  search_id: number; // the search it was made from
+  queries: string[]; // that search's queries
+  documents: number;
+}
+
+// query_overlap: the documents that matched exactly these queries and none of the others
+export interface QueryOverlap {
+  label: string; // "q1 ∧ q2 ∧ ¬q3"
+  matched: string[];
+  unmatched: string[];
+  documents: OverlapDocument[];
+}
+
+export interface OverlapDocument {
+  file_path: string;
+  name: string; // citekey, else file name
+  hits: number[]; // per query in `matched`, same order
+}
+
+// export_query_summary with format "vega-lite", parsed; each is a Vega-Lite v5 spec with inline data
+export interface QueryCharts {
+  hits_by_year: Record<string, unknown>; // matched documents per year, by query
+  hits_per_document: Record<string, unknown>; // hits in the 20 documents with the most, stacked by query
+  query_cooccurrence: Record<string, unknown>; // documents matched by each pair of queries
+}
+
+// AppSettings.ocr_device
+export type ComputeDevice = 'auto' | 'cpu' | 'metal' | 'cuda';
+
+// compute_capabilities
+export interface Capabilities {
+  cpu_threads: number;
+  metal: boolean;
+  cuda_devices: string[]; // NVIDIA GPU names
+  ocr_engine: string | null; // Tesseract's version line
+  ocr_opencl: boolean; // Tesseract can use a GPU
+  ocr_devices: ComputeDevice[]; // what ocr_device can usefully be set to
+}
+
+// One part of the app and whether this platform has it (PlatformReport.subsystems)
+export interface Subsystem {
+  name:
+    | 'parallel_search'
+    | 'zotero_database'
+    | 'zotero_web_api'
+    | 'native_index'
+    | 'ocr'
+    | 'pandoc'
+    | 'network_mount_detection'
+    | 'text_cache';
+  available: boolean;
+  note: string | null; // why it isn't, or what it's limited to
+}
+
+export interface PlatformReport {
+  os: string; // "macos", "windows", "linux", "android", "ios"
+  arch: string;
+  mobile: boolean;
+  search_threads: number; // 1 on mobile
+  storage_roots: string[]; // on mobile, the only folders searches can read; empty on desktops
+  subsystems: Subsystem[];
+}
+
+// AppSettings.problem_corpus
+export type ProblemCollectionMode = 'off' | 'reference' | 'copy';
+
+export type ProblemCategory = 'load_failed' | 'encrypted' | 'no_text' | 'garbled' | 'crashed';
+
+export interface ProblemReport {
+  file_path: string;
+  sha256: string | null;
+  size: number;
+  category: ProblemCategory;
+  detail: string | null; // the error, or how many pages were affected
+  copy: string | null; // file name in the corpus's files/ folder
+  first_seen: number; // Unix seconds
+  last_seen: number;
+  occurrences: number;
+}
+
+// list_problem_pdfs
+export interface ProblemCorpus {
+  dir: string;
+  mode: ProblemCollectionMode;
+  reports: ProblemReport[]; // most recently seen first
+}
+
+// AppSettings.export_quote_style, or preview_export's options.quote_style
+export interface QuoteStyle {
+  ellipses?: boolean; // "..." where context was cut (default true)
+  smart_quotes?: boolean; // wrap the quotation in curly quotes
+  em_dashes?: boolean; // " — " between the match and its context
+  markup?: 'bold' | 'highlight' | 'none'; // **match**, <mark>match</mark> or plain
+}
+
+// export_results options; placeholders of match_template are listed in README-Technical.md
+export interface ExportOptions {
+  group_by_citekey?: boolean; // one heading per Zotero item, ordered by citekey
+  pandoc_citations?: boolean; // cite matches as [@citekey, p. N]
+  match_template?: string | null; // markdown per match, e.g. "> {quote} {citation}\n\n"
+  reference_doc?: string | null; // .docx/.odt whose styles export_results_document uses
+  match_images?: boolean; // render each match's region of the page for {image}
+  corpora?: string[]; // only matches from these named corpora; all when empty
+  group_by_corpus?: boolean; // markdown: a heading per named corpus above its sources
+}
+
+export type DocumentFormat = 'docx' | 'odt';
+
+// export_selected_documents: some of a search's documents, without sending its matches back
+export interface SelectionExportParams {
+  search_id: number;
+  file_paths: string[]; // documents whose matches are exported
+  output_path: string;
+  format: ExportFormat;
+  options?: ExportOptions;
+  screening?: ScreeningFilter | null;
+}
+
+// The `search` member of JSON and Web Annotation selection exports; markdown
+// gets it as front matter, CSV and BibTeX as comment lines
+export interface SearchHeader {
+  search_id: number;
+  params: SearchParams;
+  documents_selected: number;
+  documents_matched: number; // documents in the whole search
+}
+
+export type ExportFormat = 'markdown' | 'csv' | 'json' | 'bibtex' | 'webannotation';
+
+// send_match_to_obsidian: a heading needs Obsidian's Advanced URI plugin
+export interface ObsidianTarget {
+  vault: string;
+  file: string; // note path within the vault
+  heading?: string | null;
+  open_only?: boolean; // open the note without adding the quote
+}
+
+// send_match_to_logseq: quotes are appended to the page's file in the graph folder
+export interface LogseqTarget {
+  graph_path: string;
+  page: string;
+  open_only?: boolean;
+}
+
+// Returned by preview_export: the start of an export, rendered without writing it
+export interface ExportPreview {
+  text: string; // at most options.max_kb kilobytes (default 32)
+  truncated: boolean; // more of the export follows
+  total_matches: number;
+  total_documents: number;
+  documents_rendered: number;
+}
+
+export interface DocumentScore {
+  file_path: string;
+  file_name: string;
+  match_count: number;
+  score: number; // weighted sum of the document's hits
+}
+
+export interface SearchStats {
+  files_found: number;
+  excluded_by_modified_date: number;
+  excluded_by_date_added: number;
+  excluded_by_screening: number;
+  excluded_by_subset: number;
+  excluded_by_native_index: number; // not reported by Spotlight/Windows Search as containing the queries
+  excluded_by_reviewer: number; // standing exclusions from the settings
+  collapsed_copies: number; // identical copies searched through another copy
+  files_searched: number;
+  excluded_by_filter_queries: number;
+  excluded_by_min_matches: number;
+  files_with_errors: number;
+  files_with_matches: number;
+  query_document_counts: { query: string; documents: number }[];
+}
+
+export interface SearchResponse {
+  search_id: number;
+  directory_config: DirectoryConfig | null; // the corpus's .pdfsearch.toml, when it overrode settings
+  matches: SearchMatch[];
+  documents: DocumentScore[];
+  file_errors: FileError[];
+  stats: SearchStats;
+  walk: WalkDiagnostics;
+  partial: boolean; // cancelled mid-search; matches from the files searched so far
+  complete: boolean; // every file searched in full; false for search_as_you_type results
+  memory: MemoryDiagnostics;
+  no_results: NoResultsExplanation | null; // set when nothing matched
+  streamed_matches: number | null; // set when matches were streamed to disk; `matches` is then empty
+}
+
+// search_as_you_type: the outcome without a search session
+export type LiveSearchOutcome = Omit<SearchResponse, 'search_id' | 'directory_config'>;
+
+// get_search_results_page
+export interface ResultsPage {
+  search_id: number;
+  total: number;
+  offset: number;
+  matches: SearchMatch[];
+}
+
+// A corpus root's .pdfsearch.toml; its values override the search's own
+export interface DirectoryConfig {
+  context_words: number | null;
+  use_ocr: boolean | null;
+  normalization: { [K in keyof NormalizationOptions]: boolean | null };
+  exclude: string[]; // relative to the corpus root, with / separators
+  skip_dirs: string[]; // added to the settings' list
+}
+
+export interface NoResultsExplanation {
+  files_found: number;
+  files_excluded: number; // by date, screening or platform index filters
+  files_searched: number;
+  files_extracted: number;
+  image_only: number; // scans without a text layer
+  encrypted: number; // password protected or forbidding extraction
+  other_errors: number;
+  excluded_by_query_rules: number; // hits dropped by filter queries or minimum matches
+  query_notes: { query: string; normalized: string; note: string }[];
 }
 
 export interface SearchHistoryItem {
@@ -74,14 +510,239 @@ export interface NoteGroup {
   notes: Note[];
 }
 
-export interface PdfListItem {
-  file_path: string;
-  file_name: string;
-  zotero_metadata: ZoteroMetadata | null;
-}
+export type PdfListItem = LibraryItem;
 
 export interface ListPdfsParams {
   directory: string;
   search_query: string | null;
   zotero_path: string | null;
 }
+
+export interface SavedSearch {
+  id: number;
+  name: string;
+  params: SearchParams;
+  created: number; // unix seconds
+  last_run: number | null;
+}
+
+export interface RerunResult {
+  search_id: number;
+  first_run: boolean;
+  total_matches: number;
+  new_matches: SearchMatch[]; // matches the previous run didn't have
+  new_files: string[];
+  changed_files: string[]; // matched before and modified since
+  file_errors: FileError[];
+}
+
+// import_config_bundle
+export interface ConfigImportReport {
+  settings_applied: boolean;
+  searches_added: string[]; // saved search names
+  searches_unchanged: string[]; // already saved with the same definition
+  searches_skipped: { name: string; reason: string }[]; // e.g. outside the allowed folders
+}
+
+export type ScreeningStatus = 'include' | 'exclude' | 'maybe';
+
+export interface ScreeningDecision {
+  file_path: string;
+  status: ScreeningStatus;
+  reason: string | null;
+  updated: number; // unix seconds
+}
+
+export interface ScreeningFilter {
+  review: string;
+  statuses?: ScreeningStatus[] | null; // keep only these; null drops excluded documents
+  include_unscreened?: boolean; // default true
+}
+
+export interface SavedResultsInfo {
+  search_id: number;
+  name: string | null;
+  saved_at: number; // unix seconds
+  match_count: number;
+  params: SearchParams;
+  partial?: boolean; // saved automatically from an interrupted search
+}
+
+export interface SavedResults extends SavedResultsInfo {
+  matches: SearchMatch[];
+}
+
+export interface ShareResult {
+  summary_path: string; // full summary written to a temporary file
+  mailto_link: string | null;
+}
+
+export interface CorpusSummary {
+  documents: number;
+  skipped_stale: number; // cached text out of date, left out of the export
+  zotero_items: number | null; // items in the Zotero snapshot, when included
+}
+
+export interface SourceVerification {
+  unchanged: number;
+  modified: string[];
+  missing: string[];
+  new: string[]; // PDFs on disk with no recorded hash
+}
+
+export interface QuerySuggestion {
+  query: string;
+  suggestion: string;
+  kind: 'similar_term' | 'phrase' | 'hyphenation';
+  occurrences: number;
+  documents: number;
+}
+
+export interface VocabularyEntry {
+  term: string;
+  occurrences: number;
+  documents: number;
+}
+
+export interface CorpusVocabulary {
+  documents: number; // cached documents counted
+  terms: VocabularyEntry[];
+  bigrams: VocabularyEntry[];
+}
+
+export interface TermSuggestion {
+  term: string; // lowercased, without diacritics
+  documents: number;
+}
+
+export interface MatchNeighbors {
+  previous: SearchMatch | null;
+  next: SearchMatch | null;
+  position: number; // 1-based among the document's hits
+  total: number;
+}
+
+export interface CitedSelection {
+  file_path: string;
+  page_number: number;
+  page_label: string | null;
+  source_span: SourceSpan; // the selection in the page's extracted (NFC) text
+  zotero_metadata: ZoteroMetadata | null;
+  citation: string; // "Authors (Year). Title, p. 12", or the file name
+  quotation: string; // the selection in quotes, followed by the citation
+}
+
+// A PDF's Info dictionary entries; null where the file has none
+export interface PdfInfo {
+  title: string | null;
+  author: string | null;
+  keywords: string | null;
+}
+
+export interface RenameSuggestion {
+  file_path: string;
+  file_name: string;
+  suggested_name: string | null; // null when there's no metadata, the name wouldn't change or the file is a Zotero attachment
+  source: 'pdf_metadata' | null;
+  missing: string[]; // pattern placeholders with no value, left out of the name
+  zotero_linked: boolean; // a Zotero attachment, never renamed
+}
+
+// Passed to apply_renames; new_name is a file name in the same directory
+export interface FileRename {
+  file_path: string;
+  new_name: string;
+}
+
+export interface RenameOutcome {
+  file_path: string;
+  new_path: string;
+  status: 'renamed' | 'would_rename' | 'skipped';
+  error: AppError | null; // why a rename was skipped
+}
+
+export interface DuplicateFile {
+  file_path: string;
+  zotero_linked: boolean; // in Zotero's storage folder or named like one of its attachments
+}
+
+// Byte-identical copies; keep may be changed before passing the group to quarantine_duplicates
+export interface DuplicateGroup {
+  sha256: string;
+  size: number;
+  files: DuplicateFile[];
+  keep: string;
+}
+
+export interface QuarantineOutcome {
+  file_path: string;
+  kept_path: string;
+  quarantine_path: string;
+  status: 'quarantined' | 'would_quarantine' | 'skipped';
+  error: AppError | null; // why a copy was left in place
+}
+
+// Payload of the "search://progress" event, sent to the window running the search
+export interface SearchProgressEvent {
+  search_id: number;
+  files_scanned: number;
+  total_files: number;
+  current_file: string;
+  matches_so_far: number;
+  matches: SearchMatch[]; // found since the previous event; match_id not assigned yet
+}
+
+// Payload of the "zotero://metadata-freshness" event
+export interface ZoteroFreshness {
+  zotero_path: string;
+  built_at: number; // unix seconds when the metadata served was read
+  stale: boolean; // true while Zotero's locked database forces an earlier read to be used
+  error: AppError | null;
+}
+
+export interface PageHitCounts {
+  counts: number[]; // counts[i] = hits on page i + 1
+  max: number;
+}
+
+export interface BackendResult {
+  backend: string; // "lopdf", "text cache (first pass)" or "text cache (warm)"
+  files: number;
+  failed_files: number;
+  pages: number;
+  matches: number;
+  elapsed_ms: number;
+  extraction_ms: number; // summed over worker threads
+  matching_ms: number;
+  pages_per_sec: number;
+  matches_per_sec: number;
+  cache_hits: number; // files whose text came from the cache
+}
+
+export interface BenchmarkReport {
+  platform: string;
+  threads: number;
+  files: number;
+  backends: BackendResult[];
+  report: string; // plain-text table of the results
+}
+
+export interface SelfTestCheck {
+  capability: 'text' | 'two_column' | 'hyphenation' | 'accented_text' | 'cid_font' | 'ocr';
+  status: 'pass' | 'fail' | 'skipped'; // skipped when OCR isn't installed
+  detail: string;
+  elapsed_ms: number;
+}
+
+// run_self_test
+export interface SelfTestReport {
+  platform: string;
+  checks: SelfTestCheck[];
+  passed: boolean; // no check failed
+}
+
+export interface TagResult {
+  tagged: string[]; // Zotero item keys
+  already_tagged: string[];
+  failed: { item_key: string; message: string }[];
+}
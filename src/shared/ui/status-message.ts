@@ -1,4 +1,5 @@
 import { statusMessage } from '../data/state';
+import type { AppError } from '../data/types';
 
 export function showStatus(message: string, type: 'info' | 'error' | 'success') {
   statusMessage.textContent = message;
@@ -8,3 +9,12 @@ export function showStatus(message: string, type: 'info' | 'error' | 'success')
     statusMessage.textContent = '';
   }, 5000);
 }
+
+// Backend commands reject with an AppError; anything else is shown as-is
+export function formatError(error: unknown): string {
+  if (error && typeof error === 'object' && 'message' in error) {
+    const { message, hint } = error as AppError;
+    return hint ? `${message} ${hint}` : message;
+  }
+  return String(error);
+}